@@ -0,0 +1,62 @@
+use std::{net::TcpListener, path::PathBuf, thread};
+
+use sqldb_rs::{
+    database::Database, error::Result, pgwire::PgServer, server::Server, sql::engine::Engine,
+};
+
+const DEFAULT_ADDR: &str = "127.0.0.1:9605";
+const USAGE: &str = "Usage: sqldb-server [--listen ADDR] [--pg ADDR] [FILE]";
+
+// SQL server over TCP
+//
+// sqldb-server [--listen ADDR] [--pg ADDR] [FILE]
+//
+// serves the database in FILE, in memory if none is given, --pg also
+// accepts PostgreSQL clients such as psql on ADDR
+fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let mut addr = DEFAULT_ADDR.to_string();
+    let mut pg_addr = None;
+    let mut path = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-l" | "--listen" => addr = args.next().ok_or(USAGE)?,
+            "--pg" => pg_addr = Some(args.next().ok_or(USAGE)?),
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            _ if path.is_none() && !arg.starts_with('-') => path = Some(PathBuf::from(arg)),
+            _ => return Err(USAGE.into()),
+        }
+    }
+
+    let db = match path {
+        Some(path) => {
+            println!("Serving {}", path.display());
+            Database::open(path)?
+        }
+        None => {
+            println!("Serving a transient in-memory database");
+            Database::in_memory()
+        }
+    };
+    serve(db.engine(), &addr, pg_addr)?;
+    Ok(())
+}
+
+fn serve<E: Engine + Send + 'static>(engine: E, addr: &str, pg_addr: Option<String>) -> Result<()> {
+    let listener = TcpListener::bind(addr)?;
+    if let Some(pg_addr) = pg_addr {
+        let pg_listener = TcpListener::bind(&pg_addr)?;
+        let pg_server = PgServer::new(engine.clone());
+        println!("Listening for PostgreSQL clients on {}", pg_addr);
+        thread::spawn(move || {
+            if let Err(err) = pg_server.serve(pg_listener) {
+                eprintln!("[PgServer] {}", err);
+            }
+        });
+    }
+    println!("Listening on {}", addr);
+    Server::new(engine).serve(listener)
+}
@@ -0,0 +1,256 @@
+use std::{path::PathBuf, time::Instant};
+
+use rustyline::{error::ReadlineError, DefaultEditor};
+use sqldb_rs::{
+    database::Database,
+    error::Result,
+    sql::{
+        engine::{Engine, Session},
+        executor::ResultSet,
+        parser::ends_statement,
+        types::{Row, Value},
+    },
+};
+
+const HISTORY_FILE: &str = ".sqldb_history";
+
+// Interactive SQL shell
+//
+// sqldb [FILE]    open the database in FILE, in memory if none is given
+fn main() -> std::result::Result<(), Box<dyn std::error::Error>> {
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.as_slice() {
+        [] => {
+            println!("Connected to a transient in-memory database.");
+            Repl::new(Database::in_memory().session())?.run()
+        }
+        [arg] if arg == "-h" || arg == "--help" => {
+            println!("Usage: sqldb [FILE]");
+            Ok(())
+        }
+        [path] => {
+            let db = Database::open(path)?;
+            println!("Connected to {}.", path);
+            Repl::new(db.session())?.run()
+        }
+        _ => Err("Usage: sqldb [FILE]".into()),
+    }
+}
+
+struct Repl<E: Engine> {
+    session: Session<E>,
+    editor: DefaultEditor,
+    history: Option<PathBuf>,
+}
+
+impl<E: Engine> Repl<E> {
+    fn new(session: Session<E>) -> std::result::Result<Self, ReadlineError> {
+        let mut editor = DefaultEditor::new()?;
+        let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        if let Some(path) = &history {
+            // no history yet on the first run
+            let _ = editor.load_history(path);
+        }
+        Ok(Self {
+            session,
+            editor,
+            history,
+        })
+    }
+
+    fn run(mut self) -> std::result::Result<(), Box<dyn std::error::Error>> {
+        println!("Enter SQL statements terminated by a semicolon, \\q to quit.");
+        let mut input = String::new();
+        loop {
+            // the prompt shows the open transaction, if any, and a ! once
+            // it is aborted
+            let prompt = match self.session.version() {
+                Some(version) if self.session.is_aborted() => format!("sqldb:{}!> ", version),
+                Some(version) => format!("sqldb:{}> ", version),
+                None => "sqldb> ".to_string(),
+            };
+            let prompt = if input.is_empty() {
+                prompt
+            } else {
+                format!("{:>width$}", "...> ", width = prompt.len())
+            };
+            match self.editor.readline(&prompt) {
+                Ok(line) => {
+                    if input.is_empty() && line.trim() == "\\q" {
+                        break;
+                    }
+                    if !input.is_empty() {
+                        input.push('\n');
+                    }
+                    input.push_str(&line);
+                    // a statement may span several lines until its semicolon,
+                    // one in a string or a comment does not count
+                    if !ends_statement(&input) {
+                        if input.trim().is_empty() {
+                            input.clear();
+                        }
+                        continue;
+                    }
+                    self.editor.add_history_entry(input.as_str())?;
+                    self.execute(&input);
+                    input.clear();
+                }
+                // ctrl-c drops the statement being typed
+                Err(ReadlineError::Interrupted) => input.clear(),
+                Err(ReadlineError::Eof) => break,
+                Err(err) => return Err(err.into()),
+            }
+        }
+        if let Some(path) = &self.history {
+            self.editor.save_history(path)?;
+        }
+        Ok(())
+    }
+
+    // runs every statement of the input, up to the first failure
+    fn execute(&mut self, sql: &str) {
+        let start = Instant::now();
+        let (results, error) = match self.session.execute_batch(sql) {
+            Ok(results) => (results, None),
+            Err(err) => {
+                // the statement number only helps when several were entered
+                let message = match err.statement {
+                    1 => err.error.to_string(),
+                    _ => err.to_string(),
+                };
+                let span = err.error.span();
+                (err.results, Some((message, span)))
+            }
+        };
+        let elapsed = start.elapsed();
+        for result in results {
+            match format_result(result) {
+                Ok(output) => println!("{}", output),
+                Err(err) => println!("Error: {}", err),
+            }
+        }
+        match error {
+            Some((message, span)) => {
+                println!("Error: {}", message);
+                if let Some(span) = span {
+                    println!("{}", span.highlight(sql));
+                }
+            }
+            None => println!("Time: {:.3} ms", elapsed.as_secs_f64() * 1000.0),
+        }
+    }
+}
+
+fn format_result(result: ResultSet) -> Result<String> {
+    Ok(match result {
+        ResultSet::CreateTable { create_table } => format!("CREATE TABLE {}", create_table),
+        ResultSet::CreateIndex { index_name } => format!("CREATE INDEX {}", index_name),
+        ResultSet::DropIndex { index_name } => format!("DROP INDEX {}", index_name),
+        ResultSet::Insert { count } => format!("INSERT {}", count),
+        ResultSet::Update { count } => format!("UPDATE {}", count),
+        ResultSet::Delete { count } => format!("DELETE {}", count),
+        ResultSet::Explain { plan } => plan.trim_end().to_string(),
+        ResultSet::Begin { version } => format!("BEGIN {}", version),
+        ResultSet::Commit { version } => format!("COMMIT {}", version),
+        ResultSet::Rollback { version } => format!("ROLLBACK {}", version),
+        ResultSet::Select { columns, rows, .. } => {
+            format_table(&columns, &rows.collect::<Result<Vec<_>>>()?)
+        }
+    })
+}
+
+// rows as an aligned table, numbers are right aligned
+//
+//  id | name
+// ----+-------
+//   1 | Alice
+// (1 row)
+fn format_table(columns: &[String], rows: &[Row]) -> String {
+    let cells = rows
+        .iter()
+        .map(|row| row.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
+    let widths = columns
+        .iter()
+        .enumerate()
+        .map(|(i, col)| {
+            cells
+                .iter()
+                .map(|row| row[i].chars().count())
+                .chain([col.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+
+    let mut lines = Vec::with_capacity(rows.len() + 3);
+    lines.push(
+        columns
+            .iter()
+            .zip(&widths)
+            .map(|(col, width)| format!(" {:^width$} ", col, width = width))
+            .collect::<Vec<_>>()
+            .join("|"),
+    );
+    lines.push(
+        widths
+            .iter()
+            .map(|width| "-".repeat(width + 2))
+            .collect::<Vec<_>>()
+            .join("+"),
+    );
+    for (row, values) in cells.iter().zip(rows) {
+        lines.push(
+            row.iter()
+                .zip(values)
+                .zip(&widths)
+                .map(|((cell, value), width)| match value {
+                    Value::Integer(_) | Value::Float(_) | Value::Decimal(_) => {
+                        format!(" {:>width$} ", cell, width = width)
+                    }
+                    _ => format!(" {:<width$} ", cell, width = width),
+                })
+                .collect::<Vec<_>>()
+                .join("|"),
+        );
+    }
+    lines.push(match rows.len() {
+        1 => "(1 row)".to_string(),
+        n => format!("({} rows)", n),
+    });
+    lines
+        .into_iter()
+        .map(|line| line.trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::format_table;
+    use sqldb_rs::sql::types::Value;
+
+    #[test]
+    fn test_format_table() {
+        let columns = vec!["id".to_string(), "name".to_string()];
+        let rows = vec![
+            vec![Value::Integer(1), Value::String("Alice".to_string())],
+            vec![Value::Integer(100), Value::Null],
+        ];
+        assert_eq!(
+            format_table(&columns, &rows),
+            [
+                " id  | name",
+                "-----+-------",
+                "   1 | Alice",
+                " 100 | NULL",
+                "(2 rows)",
+            ]
+            .join("\n")
+        );
+        assert_eq!(
+            format_table(&columns, &[]),
+            " id | name\n----+------\n(0 rows)"
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::{
+    cell::RefCell,
+    io::{BufReader, BufWriter, Write},
+    net::{TcpStream, ToSocketAddrs},
+    rc::Rc,
+};
+
+use crate::{
+    error::{Error, Result},
+    server::{read_frame, write_frame, Request, Response},
+    sql::{executor::ResultSet, types::Row},
+};
+
+// client of a sqldb server, one connection is one session
+pub struct Client {
+    conn: Rc<RefCell<Connection>>,
+}
+
+struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    // rows of the last select have not all been read yet
+    pending: bool,
+    // number of statements sent, rows belong to the last one only
+    sent: u64,
+}
+
+impl Client {
+    pub fn connect(addr: impl ToSocketAddrs) -> Result<Self> {
+        let stream = TcpStream::connect(addr)?;
+        Ok(Self {
+            conn: Rc::new(RefCell::new(Connection {
+                reader: BufReader::new(stream.try_clone()?),
+                writer: BufWriter::new(stream),
+                pending: false,
+                sent: 0,
+            })),
+        })
+    }
+
+    // rows of a select are read from the connection as they are consumed,
+    // rows left unread are skipped by the next call
+    pub fn execute(&mut self, sql: &str) -> Result<ResultSet> {
+        let mut conn = self.conn.borrow_mut();
+        conn.skip_rows();
+        conn.sent += 1;
+        write_frame(&mut conn.writer, &Request::Execute(sql.to_string()))?;
+        conn.writer.flush()?;
+        match conn.receive()? {
+            Response::Result(ResultSet::Select { columns, types, .. }) => {
+                conn.pending = true;
+                Ok(ResultSet::Select {
+                    columns,
+                    types,
+                    rows: Box::new(RowStream {
+                        conn: self.conn.clone(),
+                        statement: conn.sent,
+                        done: false,
+                    }),
+                })
+            }
+            Response::Result(result) => Ok(result),
+            Response::Error(err) => Err(err),
+            response => Err(unexpected(response)),
+        }
+    }
+}
+
+impl Connection {
+    fn receive(&mut self) -> Result<Response> {
+        read_frame(&mut self.reader)?
+            .ok_or_else(|| Error::Io("[Client] Connection closed by server".to_string()))
+    }
+
+    // the next row of the pending select, none after its last row
+    fn next_row(&mut self) -> Result<Option<Row>> {
+        if !self.pending {
+            return Ok(None);
+        }
+        let response = self.receive();
+        if !matches!(response, Ok(Response::Row(_))) {
+            self.pending = false;
+        }
+        match response? {
+            Response::Row(row) => Ok(Some(row)),
+            Response::End => Ok(None),
+            Response::Error(err) => Err(err),
+            response => Err(unexpected(response)),
+        }
+    }
+
+    fn skip_rows(&mut self) {
+        while self.pending {
+            // an error ending the rows belongs to the abandoned select
+            let _ = self.next_row();
+        }
+    }
+}
+
+struct RowStream {
+    conn: Rc<RefCell<Connection>>,
+    statement: u64,
+    done: bool,
+}
+
+impl Iterator for RowStream {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut conn = self.conn.borrow_mut();
+        // the rows were skipped when a later statement was sent
+        if self.done || conn.sent != self.statement {
+            return None;
+        }
+        let row = conn.next_row().transpose();
+        self.done = !matches!(row, Some(Ok(_)));
+        row
+    }
+}
+
+fn unexpected(response: Response) -> Error {
+    Error::Internal(format!("[Client] Unexpected response {:?}", response))
+}
@@ -0,0 +1,477 @@
+use std::{ops::RangeBounds, path::Path, rc::Rc};
+
+use crate::{
+    error::{Error, Result},
+    sql::{
+        engine::{kv::KVEngine, BatchError, Engine, Session},
+        executor::ResultSet,
+        functions::UserFunction,
+        parser::{ast::Statement, Parser},
+        types::{DataType, Decimal, Json, Row, Rows, Value},
+    },
+    storage::{
+        disk::{DiskEngine, DiskEngineIterator},
+        engine::{Engine as StorageEngine, EngineIterator},
+        memory::{MemoryEngine, MemoryEngineIterator},
+    },
+};
+
+// Embedded database, the entry point for applications
+//
+//     let db = Database::in_memory();
+//     db.execute("create table t (id int primary key, name string);")?;
+//     for row in db.query("select * from t;")? {
+//         let name: Option<String> = row?.get("name")?;
+//     }
+//
+// handles are cheap to clone and share the same data
+#[derive(Clone)]
+pub struct Database {
+    engine: KVEngine<Storage>,
+}
+
+impl Database {
+    // opens the database file at path, it is created if missing
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let engine = DiskEngine::new_compact(path.as_ref().to_path_buf())?;
+        Ok(Self {
+            engine: KVEngine::new(Storage::Disk(engine))?,
+        })
+    }
+
+    // a database whose data is gone once the last handle is dropped
+    pub fn in_memory() -> Self {
+        // empty storage has no transaction to recover
+        let engine = KVEngine::new(Storage::Memory(MemoryEngine::new()))
+            .expect("[Database] Empty in-memory storage cannot fail to open");
+        Self { engine }
+    }
+
+    // runs a statement in a transaction of its own
+    pub fn execute(&self, sql: &str) -> Result<ResultSet> {
+        self.session().execute(sql)
+    }
+
+    // runs the statements of a script in order, see Session::execute_batch
+    pub fn execute_batch(&self, sql: &str) -> std::result::Result<Vec<ResultSet>, BatchError> {
+        self.session().execute_batch(sql)
+    }
+
+    // runs a query in a transaction of its own, the rows are read as they are
+    // consumed, other statements are refused without running
+    pub fn query(&self, sql: &str) -> Result<QueryRows> {
+        let statement = Parser::new(sql).parse()?;
+        if !matches!(
+            statement,
+            Statement::Select { .. } | Statement::SetOperation { .. }
+        ) {
+            return Err(Error::Value(
+                "[Database] Statement returns no rows".to_string(),
+            ));
+        }
+        match self.session().execute_statement(statement)? {
+            ResultSet::Select { columns, rows, .. } => Ok(QueryRows {
+                columns: Rc::new(columns),
+                rows,
+            }),
+            _ => Err(Error::Value(
+                "[Database] Statement returns no rows".to_string(),
+            )),
+        }
+    }
+
+    // makes a Rust function callable from SQL on every handle of the
+    // database, it is called with arity arguments and its result must
+    // convert to the return type, e.g.
+    //
+    //     db.register_function("tenant_id", 1, DataType::Integer, |args| match &args[0] {
+    //         Value::String(key) => Ok(Value::Integer(decode(key)?)),
+    //         _ => Ok(Value::Null),
+    //     })?;
+    pub fn register_function<F>(
+        &self,
+        name: &str,
+        arity: usize,
+        returns: DataType,
+        function: F,
+    ) -> Result<()>
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        self.engine
+            .functions
+            .register(UserFunction::new(name, arity, returns, function))
+    }
+
+    // a session keeps a transaction opened by BEGIN across statements
+    pub fn session(&self) -> Session<KVEngine<Storage>> {
+        self.engine.session()
+    }
+
+    // the SQL engine, e.g. for serving the database over the network
+    pub fn engine(&self) -> KVEngine<Storage> {
+        self.engine.clone()
+    }
+}
+
+pub struct QueryRows {
+    columns: Rc<Vec<String>>,
+    rows: Rows,
+}
+
+impl QueryRows {
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+}
+
+impl Iterator for QueryRows {
+    type Item = Result<QueryRow>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next().map(|row| {
+            row.map(|values| QueryRow {
+                columns: self.columns.clone(),
+                values,
+            })
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryRow {
+    columns: Rc<Vec<String>>,
+    values: Row,
+}
+
+impl QueryRow {
+    // value of the named column converted to T
+    pub fn get<T: FromValue>(&self, column: &str) -> Result<T> {
+        let index = self
+            .columns
+            .iter()
+            .position(|c| c == column)
+            .ok_or_else(|| {
+                Error::NotFound(format!("[Database] Column {} does not exist", column))
+            })?;
+        self.get_index(index)
+    }
+
+    pub fn get_index<T: FromValue>(&self, index: usize) -> Result<T> {
+        let value = self.values.get(index).ok_or_else(|| {
+            Error::NotFound(format!("[Database] Column index {} out of range", index))
+        })?;
+        T::from_value(value.clone())
+    }
+
+    pub fn values(&self) -> &Row {
+        &self.values
+    }
+
+    pub fn into_values(self) -> Row {
+        self.values
+    }
+}
+
+// conversion of a value to a Rust type, NULL only converts to Option and Value
+pub trait FromValue: Sized {
+    fn from_value(value: Value) -> Result<Self>;
+}
+
+fn conversion_error<T>(value: &Value) -> Error {
+    Error::Value(format!(
+        "[Database] Cannot convert {} to {}",
+        value,
+        std::any::type_name::<T>()
+    ))
+}
+
+impl FromValue for Value {
+    fn from_value(value: Value) -> Result<Self> {
+        Ok(value)
+    }
+}
+
+impl FromValue for bool {
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Boolean(b) => Ok(b),
+            v => Err(conversion_error::<Self>(&v)),
+        }
+    }
+}
+
+impl FromValue for i64 {
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Integer(i) => Ok(i),
+            v => Err(conversion_error::<Self>(&v)),
+        }
+    }
+}
+
+impl FromValue for f64 {
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Float(f) => Ok(f),
+            Value::Integer(i) => Ok(i as f64),
+            Value::Decimal(d) => Ok(d.to_f64()),
+            v => Err(conversion_error::<Self>(&v)),
+        }
+    }
+}
+
+impl FromValue for Decimal {
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Decimal(d) => Ok(d),
+            Value::Integer(i) => Ok(Decimal::from(i)),
+            v => Err(conversion_error::<Self>(&v)),
+        }
+    }
+}
+
+impl FromValue for String {
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::String(s) => Ok(s),
+            v => Err(conversion_error::<Self>(&v)),
+        }
+    }
+}
+
+impl FromValue for Vec<u8> {
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Blob(b) => Ok(b),
+            v => Err(conversion_error::<Self>(&v)),
+        }
+    }
+}
+
+impl FromValue for Json {
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Json(j) => Ok(j),
+            v => Err(conversion_error::<Self>(&v)),
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Option<T> {
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            v => Ok(Some(T::from_value(v)?)),
+        }
+    }
+}
+
+// storage engine chosen when the database is opened
+pub enum Storage {
+    Memory(MemoryEngine),
+    Disk(DiskEngine),
+}
+
+impl StorageEngine for Storage {
+    type EngineIterator<'a> = StorageIterator<'a>;
+
+    fn set(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        match self {
+            Storage::Memory(eng) => eng.set(key, value),
+            Storage::Disk(eng) => eng.set(key, value),
+        }
+    }
+
+    fn get(&mut self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        match self {
+            Storage::Memory(eng) => eng.get(key),
+            Storage::Disk(eng) => eng.get(key),
+        }
+    }
+
+    fn delete(&mut self, key: Vec<u8>) -> Result<()> {
+        match self {
+            Storage::Memory(eng) => eng.delete(key),
+            Storage::Disk(eng) => eng.delete(key),
+        }
+    }
+
+    fn scan(&mut self, range: impl RangeBounds<Vec<u8>>) -> Self::EngineIterator<'_> {
+        match self {
+            Storage::Memory(eng) => StorageIterator::Memory(eng.scan(range)),
+            Storage::Disk(eng) => StorageIterator::Disk(eng.scan(range)),
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        match self {
+            Storage::Memory(eng) => eng.flush(),
+            Storage::Disk(eng) => eng.flush(),
+        }
+    }
+}
+
+pub enum StorageIterator<'a> {
+    Memory(MemoryEngineIterator<'a>),
+    Disk(DiskEngineIterator<'a>),
+}
+
+impl EngineIterator for StorageIterator<'_> {}
+
+impl Iterator for StorageIterator<'_> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            StorageIterator::Memory(iter) => iter.next(),
+            StorageIterator::Disk(iter) => iter.next(),
+        }
+    }
+}
+
+impl DoubleEndedIterator for StorageIterator<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self {
+            StorageIterator::Memory(iter) => iter.next_back(),
+            StorageIterator::Disk(iter) => iter.next_back(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Database;
+    use crate::{
+        error::Result,
+        sql::executor::ResultSet,
+        sql::types::{DataType, Value},
+    };
+
+    #[test]
+    fn test_database() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("db");
+        {
+            let db = Database::open(&path)?;
+            db.execute("create table t (id int primary key, name string, score float);")?;
+            db.execute("insert into t values (1, 'a', 1.5), (2, null, 2.0);")?;
+
+            let mut session = db.session();
+            session.execute("begin;")?;
+            session.execute("insert into t values (3, 'c', 3.0);")?;
+            assert_eq!(db.query("select * from t;")?.count(), 2);
+            session.execute("commit;")?;
+        }
+
+        let db = Database::open(&path)?;
+        let rows = db.query("select id, name, score from t where id >= 2;")?;
+        assert_eq!(rows.columns(), ["id", "name", "score"]);
+        let rows = rows.collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get::<i64>("id")?, 2);
+        assert_eq!(rows[0].get::<Option<String>>("name")?, None);
+        assert_eq!(
+            rows[1].get::<Option<String>>("name")?,
+            Some("c".to_string())
+        );
+        assert_eq!(rows[1].get_index::<f64>(2)?, 3.0);
+        assert_eq!(rows[1].get::<Value>("id")?, Value::Integer(3));
+        assert!(rows[0].get::<String>("name").is_err());
+        assert!(rows[0].get::<bool>("id").is_err());
+        assert!(rows[0].get::<i64>("nope").is_err());
+
+        // statements other than queries are refused before they run
+        assert_eq!(
+            db.query("delete from t;").err().map(|e| e.to_string()),
+            Some("[Database] Statement returns no rows".to_string())
+        );
+        assert_eq!(db.query("select * from t;")?.count(), 3);
+        assert!(matches!(
+            Database::in_memory().execute("create table t (id int primary key);")?,
+            ResultSet::CreateTable { .. }
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_user_functions() -> Result<()> {
+        let db = Database::in_memory();
+        db.execute("create table t (id int primary key, name string);")?;
+        db.execute("insert into t values (1, 'ann'), (2, null), (3, 'cid');")?;
+
+        // user functions are resolved like built-ins, for all handles
+        db.register_function("initial", 1, DataType::String, |args| {
+            Ok(match &args[0] {
+                Value::String(s) => Value::String(s.chars().take(1).collect()),
+                _ => Value::Null,
+            })
+        })?;
+        let other = db.clone();
+        let rows = other
+            .query("select initial(name) || '.' from t where initial(name) = 'c';")?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].get_index::<String>(0)?, "c.");
+        other.execute("update t set name = initial(upper(name)) where id = 3;")?;
+        let rows = db
+            .query("select name from t where id = 3;")?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows[0].get::<String>("name")?, "C");
+
+        // the number of arguments is checked before the statement runs
+        let error = |sql: &str| db.query(sql).err().map(|e| e.to_string());
+        assert_eq!(
+            error("select initial() from t;"),
+            Some("[Function] initial expects 1 argument, got 0".to_string())
+        );
+        db.register_function("pair", 2, DataType::Integer, |_| Ok(Value::Null))?;
+        assert_eq!(
+            error("select pair(id) from t;"),
+            Some("[Function] pair expects 2 arguments, got 1".to_string())
+        );
+        assert_eq!(
+            error("select initials(name) from t;"),
+            Some("[Planner] Unknown function initials".to_string())
+        );
+
+        // the result converts to the return type, NULL is always allowed
+        db.register_function("answer", 0, DataType::Float, |_| Ok(Value::Integer(42)))?;
+        db.register_function("seven", 0, DataType::Integer, |_| {
+            Ok(Value::String("7".to_string()))
+        })?;
+        let rows = db
+            .query("select answer(), seven(), pair(1, 2) from t where id = 1;")?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows[0].get_index::<Value>(0)?, Value::Float(42.0));
+        assert_eq!(rows[0].get_index::<Value>(1)?, Value::Integer(7));
+        assert_eq!(rows[0].get_index::<Value>(2)?, Value::Null);
+        db.register_function("flag", 0, DataType::Boolean, |_| Ok(Value::Float(1.5)))?;
+        let err = db
+            .query("select flag() from t;")?
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[Function] flag returns BOOLEAN, got FLOAT"
+        );
+
+        // built-in names are taken, a user function of the same name is
+        // replaced, names are case insensitive
+        let err = db
+            .register_function("Upper", 1, DataType::String, |args| Ok(args[0].clone()))
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[Function] Cannot replace built-in function upper"
+        );
+        db.register_function("ANSWER", 1, DataType::Integer, |args| Ok(args[0].clone()))?;
+        assert!(db.query("select answer() from t;").is_err());
+        let rows = db
+            .query("select answer(id) from t where id = 2;")?
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(rows[0].get_index::<i64>(0)?, 2);
+        Ok(())
+    }
+}
@@ -1,18 +1,208 @@
+use std::{fmt::Display, sync::PoisonError};
+
+use serde::{Deserialize, Serialize};
+
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug, Clone, PartialEq)]
+// errors by kind, so that callers can branch on the variant or the
+// SQLSTATE code instead of the message
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Error {
-    Parse(String),
+    // message and the position in the source text, if known
+    Parse(String, Option<Span>),
+    // invalid values, type mismatches and invalid definitions
+    Value(String),
+    // a number outside the range of its type
+    OutOfRange(String),
+    DivisionByZero(String),
+    // text that is not a valid value of the type it is converted to
+    InvalidText(String),
+    // a bug or corrupt data
+    Internal(String),
+    Io(String),
+    // a row violates a constraint, named the way PostgreSQL names them,
+    // e.g. t_pkey or t_name_not_null
+    Constraint {
+        kind: ConstraintKind,
+        name: String,
+        message: String,
+    },
+    // serialization failure, another transaction wrote the same key
+    WriteConflict,
+    // a table, column, index or row that does not exist
+    NotFound(String),
+    // a table or index that exists already
+    AlreadyExists(String),
+    // a statement not allowed in the transaction state, e.g. COMMIT
+    // without BEGIN
+    Transaction(String),
+    // a statement in a transaction that failed and was not ended yet
+    Aborted(String),
+    // a write where only reads are allowed
+    ReadOnly(String),
+    // a string longer than its column allows
+    TooLong(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ConstraintKind {
+    NotNull,
+    // unique indexes and primary keys
+    Unique,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Parse(err, None) => write!(f, "{}", err),
+            Error::Parse(err, Some(span)) => {
+                write!(f, "{} at line {}, column {}", err, span.line, span.column)
+            }
+            Error::Value(err)
+            | Error::OutOfRange(err)
+            | Error::DivisionByZero(err)
+            | Error::InvalidText(err)
+            | Error::Internal(err)
+            | Error::Io(err)
+            | Error::NotFound(err)
+            | Error::AlreadyExists(err)
+            | Error::Transaction(err)
+            | Error::Aborted(err)
+            | Error::ReadOnly(err)
+            | Error::TooLong(err) => write!(f, "{}", err),
+            Error::Constraint { message, .. } => write!(f, "{}", message),
+            Error::WriteConflict => write!(f, "write conflict, try transaction"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    // the SQLSTATE code of the error, as listed in the PostgreSQL manual
+    pub fn sqlstate(&self) -> &'static str {
+        match self {
+            Error::Parse(..) => "42601",
+            Error::Value(_) => "22000",
+            Error::OutOfRange(_) => "22003",
+            Error::DivisionByZero(_) => "22012",
+            Error::InvalidText(_) => "22P02",
+            Error::Internal(_) => "XX000",
+            Error::Io(_) => "58030",
+            Error::Constraint {
+                kind: ConstraintKind::NotNull,
+                ..
+            } => "23502",
+            Error::Constraint {
+                kind: ConstraintKind::Unique,
+                ..
+            } => "23505",
+            Error::WriteConflict => "40001",
+            Error::NotFound(_) => "42704",
+            Error::AlreadyExists(_) => "42710",
+            Error::Transaction(_) => "25000",
+            Error::Aborted(_) => "25P02",
+            Error::ReadOnly(_) => "25006",
+            Error::TooLong(_) => "22001",
+        }
+    }
+
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Error::Parse(_, span) => *span,
+            _ => None,
+        }
+    }
+}
+
+// a piece of SQL text, line and column start at 1 and the column counts
+// characters rather than bytes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub offset: usize,
+    pub len: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    // the line of source holding the span with a caret underneath
+    //
+    // LINE 1: select a, from t;
+    //                   ^^^^
+    pub fn highlight(&self, source: &str) -> String {
+        let line = source.lines().nth(self.line - 1).unwrap_or_default();
+        let prefix = format!("LINE {}: ", self.line);
+        // tabs are kept so that the caret lines up
+        let indent = line
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        let width = source
+            .get(self.offset..self.offset + self.len)
+            .unwrap_or_default()
+            .lines()
+            .next()
+            .map_or(0, |text| text.chars().count());
+        format!(
+            "{}{}\n{}{}{}",
+            prefix,
+            line,
+            " ".repeat(prefix.len()),
+            indent,
+            "^".repeat(width.max(1))
+        )
+    }
 }
 
 impl From<std::num::ParseIntError> for Error {
     fn from(value: std::num::ParseIntError) -> Self {
-        Error::Parse(format!("Failed to parse integer: {}", value))
+        Error::Parse(format!("Failed to parse integer: {}", value), None)
     }
 }
 
 impl From<std::num::ParseFloatError> for Error {
     fn from(value: std::num::ParseFloatError) -> Self {
-        Error::Parse(format!("Failed to parse float: {}", value))
+        Error::Parse(format!("Failed to parse float: {}", value), None)
+    }
+}
+
+impl<T> From<PoisonError<T>> for Error {
+    fn from(value: PoisonError<T>) -> Self {
+        Error::Internal(value.to_string())
+    }
+}
+
+impl From<bincode::Error> for Error {
+    fn from(value: bincode::Error) -> Self {
+        match *value {
+            bincode::ErrorKind::Io(err) => err.into(),
+            err => Error::Internal(err.to_string()),
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(value: std::io::Error) -> Self {
+        Error::Io(value.to_string())
+    }
+}
+
+impl From<std::array::TryFromSliceError> for Error {
+    fn from(value: std::array::TryFromSliceError) -> Self {
+        Error::Internal(value.to_string())
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Internal(msg.to_string())
+    }
+}
+
+impl serde::de::Error for Error {
+    fn custom<T: Display>(msg: T) -> Self {
+        Error::Internal(msg.to_string())
     }
 }
@@ -1,2 +1,7 @@
 pub mod sql;
-pub mod error;
\ No newline at end of file
+pub mod error;
+pub mod storage;
+pub mod server;
+pub mod client;
+pub mod pgwire;
+pub mod database;
\ No newline at end of file
@@ -0,0 +1,746 @@
+use std::{
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use crate::{
+    error::{Error, Result},
+    sql::{
+        engine::{Engine, Session},
+        executor::ResultSet,
+        parser::{ends_statement, Parser},
+        types::{DataType, Row, Value},
+    },
+};
+
+// PostgreSQL frontend/backend protocol version 3.0, and the codes sent
+// in place of a version to ask for SSL or to cancel a query
+const PROTOCOL_VERSION: i32 = 196608;
+const SSL_REQUEST_CODE: i32 = 80877103;
+const CANCEL_REQUEST_CODE: i32 = 80877102;
+
+const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+// type OIDs from the pg_type catalog
+const BOOL_OID: i32 = 16;
+const BYTEA_OID: i32 = 17;
+const INT8_OID: i32 = 20;
+const TEXT_OID: i32 = 25;
+const FLOAT8_OID: i32 = 701;
+const DATE_OID: i32 = 1082;
+const TIME_OID: i32 = 1083;
+const TIMESTAMP_OID: i32 = 1114;
+const INTERVAL_OID: i32 = 1186;
+const NUMERIC_OID: i32 = 1700;
+const JSONB_OID: i32 = 3802;
+
+pub fn type_oid(datatype: &DataType) -> i32 {
+    match datatype {
+        DataType::Boolean => BOOL_OID,
+        DataType::Integer => INT8_OID,
+        DataType::Float => FLOAT8_OID,
+        DataType::String => TEXT_OID,
+        DataType::Date => DATE_OID,
+        DataType::Time => TIME_OID,
+        DataType::Timestamp => TIMESTAMP_OID,
+        DataType::Interval => INTERVAL_OID,
+        DataType::Decimal => NUMERIC_OID,
+        DataType::Blob => BYTEA_OID,
+        DataType::Json => JSONB_OID,
+    }
+}
+
+// size of the type as in pg_type.typlen, -1 for variable length
+fn type_len(oid: i32) -> i16 {
+    match oid {
+        BOOL_OID => 1,
+        DATE_OID => 4,
+        INT8_OID | FLOAT8_OID | TIME_OID | TIMESTAMP_OID => 8,
+        INTERVAL_OID => 16,
+        _ => -1,
+    }
+}
+
+// the tag sent in CommandComplete, psql prints it for statements without rows
+pub fn command_tag(result: &ResultSet) -> String {
+    match result {
+        ResultSet::CreateTable { .. } => "CREATE TABLE".to_string(),
+        ResultSet::CreateIndex { .. } => "CREATE INDEX".to_string(),
+        ResultSet::DropIndex { .. } => "DROP INDEX".to_string(),
+        // the 0 stands for the OID of the inserted row in older servers
+        ResultSet::Insert { count } => format!("INSERT 0 {}", count),
+        ResultSet::Select { .. } => "SELECT".to_string(),
+        ResultSet::Update { count } => format!("UPDATE {}", count),
+        ResultSet::Delete { count } => format!("DELETE {}", count),
+        ResultSet::Explain { .. } => "EXPLAIN".to_string(),
+        ResultSet::Begin { .. } => "BEGIN".to_string(),
+        ResultSet::Commit { .. } => "COMMIT".to_string(),
+        ResultSet::Rollback { .. } => "ROLLBACK".to_string(),
+    }
+}
+
+// values in the text format, NULL has no text
+fn text_value(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Boolean(b) => Some(if *b { "t" } else { "f" }.to_string()),
+        Value::Float(f) if f.is_nan() => Some("NaN".to_string()),
+        Value::Float(f) if f.is_infinite() => {
+            Some(if *f > 0.0 { "Infinity" } else { "-Infinity" }.to_string())
+        }
+        v => Some(v.to_string()),
+    }
+}
+
+// PostgreSQL compatible front end, so that psql and Postgres drivers can
+// connect, only the simple query protocol is supported
+pub struct PgServer<E: Engine> {
+    engine: E,
+}
+
+impl<E: Engine + Send + 'static> PgServer<E> {
+    pub fn new(engine: E) -> Self {
+        Self { engine }
+    }
+
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            // a failed accept only affects the connection being accepted
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("[PgServer] Accept failed: {}", err);
+                    continue;
+                }
+            };
+            let engine = self.engine.clone();
+            thread::spawn(move || {
+                let peer = stream.peer_addr().ok();
+                let result = Connection::new(engine.session(), stream).and_then(|c| c.run());
+                if let Err(err) = result {
+                    eprintln!("[PgServer] Connection {:?} failed: {}", peer, err);
+                }
+            });
+        }
+        Ok(())
+    }
+}
+
+struct Connection<E: Engine> {
+    session: Session<E>,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl<E: Engine> Connection<E> {
+    fn new(session: Session<E>, stream: TcpStream) -> Result<Self> {
+        Ok(Self {
+            session,
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+        })
+    }
+
+    fn run(mut self) -> Result<()> {
+        if !self.startup()? {
+            return Ok(());
+        }
+        // after an error in the extended query protocol, messages are
+        // ignored until the Sync that ends the failed batch
+        let mut skip_to_sync = false;
+        while let Some((tag, body)) = self.read_message()? {
+            match tag {
+                b'Q' => self.query(&body)?,
+                b'X' => break,
+                b'S' => {
+                    skip_to_sync = false;
+                    self.ready_for_query()?;
+                }
+                _ if skip_to_sync => {}
+                b'P' | b'B' | b'D' | b'E' | b'C' | b'H' | b'F' => {
+                    skip_to_sync = true;
+                    self.error_response(
+                        "0A000",
+                        "[PgServer] Only the simple query protocol is supported",
+                        None,
+                    )?;
+                }
+                tag => {
+                    return Err(Error::Internal(format!(
+                        "[PgServer] Unknown message type {:?}",
+                        tag as char
+                    )))
+                }
+            }
+            self.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    // false if the client went away or only wanted to cancel a query
+    fn startup(&mut self) -> Result<bool> {
+        loop {
+            let Some(body) = self.read_body()? else {
+                return Ok(false);
+            };
+            let mut buf = Reader::new(&body);
+            match buf.i32()? {
+                PROTOCOL_VERSION => break,
+                // no SSL, the client goes on in plain text
+                SSL_REQUEST_CODE => {
+                    self.writer.write_all(b"N")?;
+                    self.writer.flush()?;
+                }
+                CANCEL_REQUEST_CODE => return Ok(false),
+                version => {
+                    self.error_response(
+                        "08P01",
+                        &format!(
+                            "[PgServer] Unsupported protocol version {}.{}",
+                            version >> 16,
+                            version & 0xffff
+                        ),
+                        None,
+                    )?;
+                    self.writer.flush()?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        // authentication ok, no password is asked
+        self.write_message(b'R', Writer::new().i32(0))?;
+        for (name, value) in [
+            ("server_version", "14.0"),
+            ("server_encoding", "UTF8"),
+            ("client_encoding", "UTF8"),
+            ("DateStyle", "ISO, MDY"),
+            ("integer_datetimes", "on"),
+            ("standard_conforming_strings", "on"),
+        ] {
+            self.write_message(b'S', Writer::new().str(name).str(value))?;
+        }
+        self.ready_for_query()?;
+        self.writer.flush()?;
+        Ok(true)
+    }
+
+    fn query(&mut self, body: &[u8]) -> Result<()> {
+        let sql = Reader::new(body).str()?;
+        // psql leaves out the terminating semicolon, it goes on a line of
+        // its own in case the query ends with a comment
+        let sql = match ends_statement(&sql) {
+            true => sql,
+            false => format!("{}\n;", sql),
+        };
+        let statements = match Parser::new(&sql).parse_all() {
+            Ok(statements) => statements,
+            Err(err) => {
+                self.syntax_error(&sql, &err)?;
+                return self.ready_for_query();
+            }
+        };
+        if statements.is_empty() {
+            self.write_message(b'I', Writer::new())?;
+            return self.ready_for_query();
+        }
+
+        // a query may hold several statements, each one gets its own
+        // response and the first failure skips the rest
+        for statement in statements {
+            let ok = match self.session.execute_statement(statement) {
+                Ok(ResultSet::Select {
+                    columns,
+                    types,
+                    rows,
+                }) => self.rows(&columns, &types, rows)?,
+                Ok(ResultSet::Explain { plan }) => {
+                    self.row_description(&["QUERY PLAN".to_string()], &[TEXT_OID])?;
+                    for line in plan.lines() {
+                        self.data_row(&vec![Value::String(line.to_string())])?;
+                    }
+                    self.write_message(b'C', Writer::new().str("EXPLAIN"))?;
+                    true
+                }
+                Ok(result) => {
+                    self.write_message(b'C', Writer::new().str(&command_tag(&result)))?;
+                    true
+                }
+                Err(err) => {
+                    self.error(&err)?;
+                    false
+                }
+            };
+            if !ok {
+                break;
+            }
+        }
+        self.ready_for_query()
+    }
+
+    // returns false if reading the rows failed, which fails the transaction
+    fn rows(
+        &mut self,
+        columns: &[String],
+        types: &[Option<DataType>],
+        mut rows: crate::sql::types::Rows,
+    ) -> Result<bool> {
+        // the first row is read before the description, so that a
+        // statement failing right away only gets an error
+        let first = match rows.next().transpose() {
+            Ok(first) => first,
+            Err(err) => {
+                self.session.abort();
+                self.error(&err)?;
+                return Ok(false);
+            }
+        };
+        // the types come from the plan, columns of unknown type such as
+        // NULL are sent as text
+        let oids = (0..columns.len())
+            .map(|i| match types.get(i) {
+                Some(Some(datatype)) => type_oid(datatype),
+                _ => TEXT_OID,
+            })
+            .collect::<Vec<_>>();
+        self.row_description(columns, &oids)?;
+
+        let mut count = 0;
+        for row in first.into_iter().map(Ok).chain(rows) {
+            match row {
+                Ok(row) => self.data_row(&row)?,
+                Err(err) => {
+                    self.session.abort();
+                    self.error(&err)?;
+                    return Ok(false);
+                }
+            }
+            count += 1;
+        }
+        self.write_message(b'C', Writer::new().str(&format!("SELECT {}", count)))?;
+        Ok(true)
+    }
+
+    fn row_description(&mut self, columns: &[String], oids: &[i32]) -> Result<()> {
+        let mut body = Writer::new().i16(columns.len() as i16);
+        for (name, oid) in columns.iter().zip(oids) {
+            body = body
+                .str(name)
+                // not taken from a table
+                .i32(0)
+                .i16(0)
+                .i32(*oid)
+                .i16(type_len(*oid))
+                .i32(-1)
+                // text format
+                .i16(0);
+        }
+        self.write_message(b'T', body)
+    }
+
+    fn data_row(&mut self, row: &Row) -> Result<()> {
+        let mut body = Writer::new().i16(row.len() as i16);
+        for value in row {
+            body = match text_value(value) {
+                Some(text) => body.i32(text.len() as i32).bytes(text.as_bytes()),
+                None => body.i32(-1),
+            };
+        }
+        self.write_message(b'D', body)
+    }
+
+    fn error(&mut self, err: &Error) -> Result<()> {
+        self.error_response(err.sqlstate(), &err.to_string(), None)
+    }
+
+    // the position lets psql point at the error, it counts characters
+    // of the query from 1
+    fn syntax_error(&mut self, sql: &str, err: &Error) -> Result<()> {
+        match err {
+            Error::Parse(msg, Some(span)) => {
+                let position = sql[..span.offset].chars().count() + 1;
+                self.error_response(err.sqlstate(), msg, Some(position))
+            }
+            err => self.error(err),
+        }
+    }
+
+    fn error_response(&mut self, code: &str, message: &str, position: Option<usize>) -> Result<()> {
+        let mut body = Writer::new()
+            .u8(b'S')
+            .str("ERROR")
+            .u8(b'V')
+            .str("ERROR")
+            .u8(b'C')
+            .str(code)
+            .u8(b'M')
+            .str(message);
+        if let Some(position) = position {
+            body = body.u8(b'P').str(&position.to_string());
+        }
+        self.write_message(b'E', body.u8(0))
+    }
+
+    // idle, or in a transaction block
+    fn ready_for_query(&mut self) -> Result<()> {
+        let status = match self.session.version() {
+            _ if self.session.is_aborted() => b'E',
+            Some(_) => b'T',
+            None => b'I',
+        };
+        self.write_message(b'Z', Writer::new().u8(status))
+    }
+
+    fn write_message(&mut self, tag: u8, body: Writer) -> Result<()> {
+        self.writer.write_all(&[tag])?;
+        self.writer
+            .write_all(&(body.0.len() as i32 + 4).to_be_bytes())?;
+        self.writer.write_all(&body.0)?;
+        Ok(())
+    }
+
+    // a message type byte, then the body, none if the client went away
+    fn read_message(&mut self) -> Result<Option<(u8, Vec<u8>)>> {
+        let mut tag = [0u8; 1];
+        match self.reader.read_exact(&mut tag) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        match self.read_body()? {
+            Some(body) => Ok(Some((tag[0], body))),
+            None => Ok(None),
+        }
+    }
+
+    // a length including itself, then the body
+    fn read_body(&mut self) -> Result<Option<Vec<u8>>> {
+        let mut len = [0u8; 4];
+        match self.reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        }
+        let len = i32::from_be_bytes(len) as usize;
+        if !(4..=MAX_MESSAGE_SIZE).contains(&len) {
+            return Err(Error::Internal(format!(
+                "[PgServer] Invalid message length {}",
+                len
+            )));
+        }
+        let mut body = vec![0; len - 4];
+        self.reader.read_exact(&mut body)?;
+        Ok(Some(body))
+    }
+}
+
+// builds a message body, integers are big-endian
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn new() -> Self {
+        Self(Vec::new())
+    }
+
+    fn u8(mut self, v: u8) -> Self {
+        self.0.push(v);
+        self
+    }
+
+    fn i16(mut self, v: i16) -> Self {
+        self.0.extend(v.to_be_bytes());
+        self
+    }
+
+    fn i32(mut self, v: i32) -> Self {
+        self.0.extend(v.to_be_bytes());
+        self
+    }
+
+    fn bytes(mut self, v: &[u8]) -> Self {
+        self.0.extend(v);
+        self
+    }
+
+    // null terminated
+    fn str(self, v: &str) -> Self {
+        self.bytes(v.as_bytes()).u8(0)
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self(data)
+    }
+
+    fn i32(&mut self) -> Result<i32> {
+        if self.0.len() < 4 {
+            return Err(Error::Internal("[PgServer] Message too short".to_string()));
+        }
+        let (v, rest) = self.0.split_at(4);
+        self.0 = rest;
+        Ok(i32::from_be_bytes(v.try_into()?))
+    }
+
+    fn str(&mut self) -> Result<String> {
+        let end = self.0.iter().position(|b| *b == 0).ok_or_else(|| {
+            Error::Internal("[PgServer] Unterminated string in message".to_string())
+        })?;
+        let s = String::from_utf8(self.0[..end].to_vec())
+            .map_err(|err| Error::Internal(format!("[PgServer] {}", err)))?;
+        self.0 = &self.0[end + 1..];
+        Ok(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use super::PgServer;
+    use crate::{error::Result, sql::engine::kv::KVEngine, storage::memory::MemoryEngine};
+
+    // a minimal frontend speaking the simple query protocol
+    struct Frontend(TcpStream);
+
+    impl Frontend {
+        fn send(&mut self, tag: Option<u8>, body: &[u8]) -> Result<()> {
+            if let Some(tag) = tag {
+                self.0.write_all(&[tag])?;
+            }
+            self.0.write_all(&(body.len() as i32 + 4).to_be_bytes())?;
+            self.0.write_all(body)?;
+            Ok(())
+        }
+
+        fn receive(&mut self) -> Result<(u8, Vec<u8>)> {
+            let mut header = [0u8; 5];
+            self.0.read_exact(&mut header)?;
+            let len = i32::from_be_bytes(header[1..].try_into()?) as usize;
+            let mut body = vec![0; len - 4];
+            self.0.read_exact(&mut body)?;
+            Ok((header[0], body))
+        }
+
+        // messages up to and including ReadyForQuery, as readable strings
+        fn query(&mut self, sql: &str) -> Result<Vec<String>> {
+            let mut body = sql.as_bytes().to_vec();
+            body.push(0);
+            self.send(Some(b'Q'), &body)?;
+            let mut messages = Vec::new();
+            loop {
+                let (tag, body) = self.receive()?;
+                messages.push(describe(tag, &body));
+                if tag == b'Z' {
+                    return Ok(messages);
+                }
+            }
+        }
+    }
+
+    fn cstrings(body: &[u8]) -> Vec<String> {
+        body.split(|b| *b == 0)
+            .filter(|s| !s.is_empty())
+            .map(|s| String::from_utf8_lossy(s).to_string())
+            .collect()
+    }
+
+    fn describe(tag: u8, body: &[u8]) -> String {
+        let tag = tag as char;
+        match tag {
+            'T' => {
+                let mut fields = Vec::new();
+                let mut rest = &body[2..];
+                while !rest.is_empty() {
+                    let end = rest.iter().position(|b| *b == 0).unwrap();
+                    let name = String::from_utf8_lossy(&rest[..end]).to_string();
+                    let oid = i32::from_be_bytes(rest[end + 7..end + 11].try_into().unwrap());
+                    fields.push(format!("{}:{}", name, oid));
+                    rest = &rest[end + 19..];
+                }
+                format!("T {}", fields.join(","))
+            }
+            'D' => {
+                let mut values = Vec::new();
+                let mut rest = &body[2..];
+                while !rest.is_empty() {
+                    let len = i32::from_be_bytes(rest[..4].try_into().unwrap());
+                    rest = &rest[4..];
+                    if len < 0 {
+                        values.push("NULL".to_string());
+                    } else {
+                        values.push(String::from_utf8_lossy(&rest[..len as usize]).to_string());
+                        rest = &rest[len as usize..];
+                    }
+                }
+                format!("D {}", values.join(","))
+            }
+            'E' => {
+                let fields = cstrings(body);
+                let code = fields.iter().find(|f| f.starts_with('C')).unwrap();
+                match fields.iter().find(|f| f.starts_with('P')) {
+                    Some(position) => format!("E {} at {}", &code[1..], &position[1..]),
+                    None => format!("E {}", &code[1..]),
+                }
+            }
+            'Z' => format!("Z {}", body[0] as char),
+            tag => format!("{} {}", tag, cstrings(body).join(" ")),
+        }
+    }
+
+    #[test]
+    fn test_pgwire() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        thread::spawn(move || PgServer::new(KVEngine::new(MemoryEngine::new())?).serve(listener));
+
+        let mut fe = Frontend(TcpStream::connect(addr)?);
+        // SSL is declined before the startup message
+        fe.send(None, &80877103i32.to_be_bytes())?;
+        let mut answer = [0u8; 1];
+        fe.0.read_exact(&mut answer)?;
+        assert_eq!(&answer, b"N");
+
+        let mut startup = 196608i32.to_be_bytes().to_vec();
+        startup.extend(b"user\0test\0database\0test\0\0");
+        fe.send(None, &startup)?;
+        let (tag, body) = fe.receive()?;
+        assert_eq!((tag, body), (b'R', vec![0, 0, 0, 0]));
+        loop {
+            let (tag, body) = fe.receive()?;
+            if tag == b'Z' {
+                assert_eq!(body, b"I");
+                break;
+            }
+            assert_eq!(tag, b'S');
+        }
+
+        assert_eq!(
+            fe.query("create table t (id int primary key, name string, ok bool)")?,
+            vec!["C CREATE TABLE", "Z I"]
+        );
+        assert_eq!(
+            fe.query("insert into t values (1, 'a', true), (2, null, false);")?,
+            vec!["C INSERT 0 2", "Z I"]
+        );
+        assert_eq!(
+            fe.query("select * from t")?,
+            vec![
+                "T id:20,name:25,ok:16",
+                "D 1,a,t",
+                "D 2,NULL,f",
+                "C SELECT 2",
+                "Z I"
+            ]
+        );
+        assert_eq!(fe.query("begin")?, vec!["C BEGIN", "Z T"]);
+        assert_eq!(
+            fe.query("update t set name = 'b' where id = 2")?,
+            vec!["C UPDATE 1", "Z T"]
+        );
+        assert_eq!(fe.query("commit")?, vec!["C COMMIT", "Z I"]);
+        assert_eq!(
+            fe.query("explain select id from t where id = 2")?,
+            vec![
+                "T QUERY PLAN:25",
+                "D Projection (id)",
+                "D   -> Key Lookup on t (keys: 2)",
+                "D        Filter: id = 2",
+                "C EXPLAIN",
+                "Z I"
+            ]
+        );
+        assert_eq!(fe.query("selec 1")?, vec!["E 42601 at 1", "Z I"]);
+        assert_eq!(fe.query("select x from t")?, vec!["E 42704", "Z I"]);
+        assert_eq!(fe.query("select id / 0 from t")?, vec!["E 22012", "Z I"]);
+        assert_eq!(
+            fe.query("select 9223372036854775807 + id from t")?,
+            vec!["E 22003", "Z I"]
+        );
+        assert_eq!(fe.query("select 'abc'::int from t")?, vec!["E 22P02", "Z I"]);
+        assert_eq!(fe.query(" ")?, vec!["I ", "Z I"]);
+        assert_eq!(
+            fe.query("select id from t where id = 1 -- the first;")?,
+            vec!["T id:20", "D 1", "C SELECT 1", "Z I"]
+        );
+
+        // each statement of a query is answered, up to the first failure
+        assert_eq!(
+            fe.query("begin; insert into t values (3, 'c', true); select id from t where id = 3")?,
+            vec![
+                "C BEGIN",
+                "C INSERT 0 1",
+                "T id:20",
+                "D 3",
+                "C SELECT 1",
+                "Z T"
+            ]
+        );
+        assert_eq!(
+            fe.query("rollback; select x from t; select id from t")?,
+            vec!["C ROLLBACK", "E 42704", "Z I"]
+        );
+        assert_eq!(
+            fe.query("select id from t; selec 1")?,
+            vec!["E 42601 at 19", "Z I"]
+        );
+
+        // a failure aborts the transaction until it is rolled back
+        assert_eq!(fe.query("begin")?, vec!["C BEGIN", "Z T"]);
+        assert_eq!(
+            fe.query("update t set name = 'x' where id = 1")?,
+            vec!["C UPDATE 1", "Z T"]
+        );
+        assert_eq!(
+            fe.query("insert into t values (1, 'd', true)")?,
+            vec!["E 23505", "Z E"]
+        );
+        assert_eq!(
+            fe.query("update t set name = 'y' where id = 2")?,
+            vec!["E 25P02", "Z E"]
+        );
+        assert_eq!(fe.query("rollback")?, vec!["C ROLLBACK", "Z I"]);
+        assert_eq!(
+            fe.query("select name from t")?,
+            vec!["T name:25", "D a", "D b", "C SELECT 2", "Z I"]
+        );
+
+        // column types are those of the plan, whatever the rows hold
+        assert_eq!(
+            fe.query("select id, name, 1.5 from t where id > 5")?,
+            vec!["T id:20,name:25,1.5:1700", "C SELECT 0", "Z I"]
+        );
+        assert_eq!(
+            fe.query("select case when id = 1 then 1 else 2.5e0 end as n, null as x from t")?,
+            vec![
+                "T n:701,x:25",
+                "D 1,NULL",
+                "D 2.5,NULL",
+                "C SELECT 2",
+                "Z I"
+            ]
+        );
+        assert_eq!(
+            fe.query("select case when id = 1 then null else id > 1 end as big from t")?,
+            vec!["T big:16", "D NULL", "D t", "C SELECT 2", "Z I"]
+        );
+
+        // the extended query protocol is refused until the next Sync
+        fe.send(Some(b'P'), b"\0select 1\0\0\0")?;
+        fe.send(Some(b'B'), b"\0\0\0\0\0\0\0\0")?;
+        fe.send(Some(b'S'), b"")?;
+        let (tag, body) = fe.receive()?;
+        assert_eq!(describe(tag, &body), "E 0A000");
+        let (tag, body) = fe.receive()?;
+        assert_eq!(describe(tag, &body), "Z I");
+        assert_eq!(fe.query("select id from t where id = 1")?.len(), 4);
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    error::{Error, Result},
+    sql::{
+        engine::{Engine, Session},
+        executor::ResultSet,
+        types::Row,
+    },
+};
+
+// frames larger than this are rejected rather than allocated
+const MAX_FRAME_SIZE: u32 = 64 << 20;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Request {
+    Execute(String),
+}
+
+// the reply to Execute is a single Result, a select result is
+// followed by a Row for each row and End, or by an Error
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Response {
+    Result(ResultSet),
+    Row(Row),
+    End,
+    Error(Error),
+}
+
+// every frame is a u32 big-endian length followed by the bincode message
+pub fn write_frame<W: Write, T: Serialize>(writer: &mut W, message: &T) -> Result<()> {
+    let data = bincode::serialize(message)?;
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(&data)?;
+    Ok(())
+}
+
+// none if the peer closed the connection between frames
+pub fn read_frame<R: Read, T: DeserializeOwned>(reader: &mut R) -> Result<Option<T>> {
+    let mut len = [0u8; 4];
+    match reader.read_exact(&mut len) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    let len = u32::from_be_bytes(len);
+    if len > MAX_FRAME_SIZE {
+        return Err(Error::Internal(format!(
+            "[Server] Frame of {} bytes is too large",
+            len
+        )));
+    }
+    let mut data = vec![0; len as usize];
+    reader.read_exact(&mut data)?;
+    Ok(Some(bincode::deserialize(&data)?))
+}
+
+// SQL server, every connection has a session of its own
+pub struct Server<E: Engine> {
+    engine: E,
+}
+
+impl<E: Engine + Send + 'static> Server<E> {
+    pub fn new(engine: E) -> Self {
+        Self { engine }
+    }
+
+    pub fn serve(&self, listener: TcpListener) -> Result<()> {
+        for stream in listener.incoming() {
+            // a failed accept only affects the connection being accepted
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(err) => {
+                    eprintln!("[Server] Accept failed: {}", err);
+                    continue;
+                }
+            };
+            let engine = self.engine.clone();
+            thread::spawn(move || {
+                let peer = stream.peer_addr().ok();
+                if let Err(err) = Self::handle(engine.session(), stream) {
+                    eprintln!("[Server] Connection {:?} failed: {}", peer, err);
+                }
+            });
+        }
+        Ok(())
+    }
+
+    fn handle(mut session: Session<E>, stream: TcpStream) -> Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut writer = BufWriter::new(stream);
+        while let Some(request) = read_frame(&mut reader)? {
+            match request {
+                Request::Execute(sql) => match session.execute(&sql) {
+                    Ok(ResultSet::Select {
+                        columns,
+                        types,
+                        rows,
+                    }) => {
+                        write_frame(
+                            &mut writer,
+                            &Response::Result(ResultSet::Select {
+                                columns,
+                                types,
+                                rows: Box::new(std::iter::empty()),
+                            }),
+                        )?;
+                        // rows are sent as they are read
+                        let mut end = Response::End;
+                        for row in rows {
+                            match row {
+                                Ok(row) => write_frame(&mut writer, &Response::Row(row))?,
+                                Err(err) => {
+                                    session.abort();
+                                    end = Response::Error(err);
+                                    break;
+                                }
+                            }
+                        }
+                        write_frame(&mut writer, &end)?;
+                    }
+                    Ok(result) => write_frame(&mut writer, &Response::Result(result))?,
+                    Err(err) => write_frame(&mut writer, &Response::Error(err))?,
+                },
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, thread, time::Duration};
+
+    use super::Server;
+    use crate::{
+        client::Client,
+        error::{Error, Result},
+        sql::{engine::kv::KVEngine, executor::ResultSet, types::Value},
+        storage::memory::MemoryEngine,
+    };
+
+    fn select(client: &mut Client, sql: &str) -> Result<Vec<Vec<Value>>> {
+        match client.execute(sql)? {
+            ResultSet::Select { rows, .. } => rows.collect(),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+    }
+
+    #[test]
+    fn test_server() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        thread::spawn(move || Server::new(KVEngine::new(MemoryEngine::new())?).serve(listener));
+
+        let mut c1 = Client::connect(addr)?;
+        let mut c2 = Client::connect(addr)?;
+        c1.execute("create table t (id int primary key, v int);")?;
+        c1.execute("insert into t values (1, 10), (2, 20);")?;
+
+        // each connection has a transaction of its own
+        assert!(matches!(c1.execute("begin;")?, ResultSet::Begin { .. }));
+        c1.execute("update t set v = 11 where id = 1;")?;
+        assert_eq!(
+            select(&mut c2, "select v from t;")?,
+            vec![vec![Value::Integer(10)], vec![Value::Integer(20)]]
+        );
+        assert_eq!(
+            c2.execute("delete from t where id = 1;").unwrap_err(),
+            Error::WriteConflict
+        );
+        c1.execute("commit;")?;
+        assert_eq!(
+            select(&mut c2, "select v from t where id = 1;")?,
+            vec![vec![Value::Integer(11)]]
+        );
+
+        // errors are reported and the connection stays usable
+        assert!(c2.execute("select * from nope;").is_err());
+        assert!(select(&mut c2, "select x from t;").is_err());
+        assert!(matches!(
+            c2.execute("delete from t;")?,
+            ResultSet::Delete { count: 2 }
+        ));
+
+        // rows are read as they are consumed, unread rows are skipped
+        c2.execute("insert into t values (1, 10), (2, 20), (3, 30);")?;
+        let ResultSet::Select { mut rows, .. } = c2.execute("select id from t;")? else {
+            panic!("expected a select");
+        };
+        assert_eq!(rows.next().transpose()?, Some(vec![Value::Integer(1)]));
+        assert!(matches!(
+            c2.execute("delete from t where id = 3;")?,
+            ResultSet::Delete { count: 1 }
+        ));
+        assert!(rows.next().is_none());
+        let ResultSet::Select { mut rows, .. } = c2.execute("select v / 0 from t;")? else {
+            panic!("expected a select");
+        };
+        assert!(rows.next().unwrap().is_err());
+        assert!(rows.next().is_none());
+        assert_eq!(
+            select(&mut c2, "select v from t;")?,
+            vec![vec![Value::Integer(10)], vec![Value::Integer(20)]]
+        );
+        c2.execute("delete from t;")?;
+
+        // a dropped connection rolls back its transaction
+        c1.execute("begin;")?;
+        c1.execute("insert into t values (3, 30);")?;
+        drop(c1);
+        let mut c3 = Client::connect(addr)?;
+        // the server notices the closed connection asynchronously
+        let mut retries = 0;
+        while let Err(Error::WriteConflict) = c3.execute("insert into t values (3, 31);") {
+            retries += 1;
+            assert!(
+                retries < 100,
+                "transaction of the dropped connection is still active"
+            );
+            thread::sleep(Duration::from_millis(20));
+        }
+        assert_eq!(
+            select(&mut c3, "select v from t;")?,
+            vec![vec![Value::Integer(31)]]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,1829 @@
+use std::{ops::Bound, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+
+use super::{Engine, Transaction};
+use crate::{
+    error::{ConstraintKind, Error, Result},
+    sql::{
+        functions::{UserFunction, UserFunctions},
+        plan::Expression,
+        schema::{Index, Table},
+        types::{Row, Rows, Value},
+    },
+    storage::{
+        self,
+        engine::{prefix_end, Engine as StorageEngine},
+        keycode::serialize_key,
+    },
+};
+
+// SQL engine on top of the key/value storage engine
+pub struct KVEngine<E: StorageEngine> {
+    pub kv: storage::mvcc::Mvcc<E>,
+    pub functions: UserFunctions,
+}
+
+impl<E: StorageEngine> Clone for KVEngine<E> {
+    fn clone(&self) -> Self {
+        Self {
+            kv: self.kv.clone(),
+            functions: self.functions.clone(),
+        }
+    }
+}
+
+impl<E: StorageEngine> KVEngine<E> {
+    pub fn new(engine: E) -> Result<Self> {
+        Ok(Self {
+            kv: storage::mvcc::Mvcc::new(engine)?,
+            functions: UserFunctions::default(),
+        })
+    }
+}
+
+impl<E: StorageEngine + 'static> Engine for KVEngine<E> {
+    type Transaction = KVTransaction<E>;
+
+    fn begin(&self) -> Result<Self::Transaction> {
+        Ok(KVTransaction::new(self.kv.begin()?, self.functions.clone()))
+    }
+}
+
+pub struct KVTransaction<E: StorageEngine> {
+    txn: storage::mvcc::MvccTransaction<E>,
+    functions: UserFunctions,
+}
+
+// clones read and write through the same transaction
+impl<E: StorageEngine> Clone for KVTransaction<E> {
+    fn clone(&self) -> Self {
+        Self {
+            txn: self.txn.clone(),
+            functions: self.functions.clone(),
+        }
+    }
+}
+
+impl<E: StorageEngine + 'static> KVTransaction<E> {
+    pub fn new(txn: storage::mvcc::MvccTransaction<E>, functions: UserFunctions) -> Self {
+        Self { txn, functions }
+    }
+
+    fn scan_tables(&self) -> Result<Vec<Table>> {
+        let prefix = KeyPrefix::Table.encode()?;
+        self.txn
+            .scan_prefix(prefix)?
+            .map(|r| Ok(bincode::deserialize(&r?.value)?))
+            .collect()
+    }
+
+    fn save_table(&mut self, table: &Table) -> Result<()> {
+        let key = Key::Table(table.name.clone()).encode()?;
+        self.txn.set(key, bincode::serialize(table)?)
+    }
+
+    fn insert_index_entry(
+        &mut self,
+        table: &Table,
+        index: &Index,
+        row: &Row,
+        id: &Value,
+    ) -> Result<()> {
+        let value = &row[table.get_col_index(&index.column)?];
+        // NULLs never violate a unique index
+        if index.unique
+            && *value != Value::Null
+            && !self.read_index(&table.name, &index.name, value)?.is_empty()
+        {
+            return Err(Error::Constraint {
+                kind: ConstraintKind::Unique,
+                name: index.name.clone(),
+                message: format!(
+                    "[Engine] Duplicate value {} for unique index {}",
+                    value, index.name
+                ),
+            });
+        }
+        let key = Self::index_key(table, index, value, id).encode()?;
+        self.txn.set(key, bincode::serialize(id)?)
+    }
+
+    // entries of a unique index are keyed by their value alone, so that
+    // concurrent inserts of a duplicate conflict, others by value and primary
+    // key, so that rows sharing a value are written independently
+    fn index_key(table: &Table, index: &Index, value: &Value, id: &Value) -> Key {
+        let id = match index.unique && *value != Value::Null {
+            true => Value::Null,
+            false => id.clone(),
+        };
+        Key::Index(table.name.clone(), index.name.clone(), value.clone(), id)
+    }
+
+    // encoded key range of the keys starting with the prefixes built from
+    // values within range under prefix
+    fn key_range<F: Fn(Value) -> Result<Vec<u8>>>(
+        prefix: KeyPrefix,
+        range: (Bound<Value>, Bound<Value>),
+        key: F,
+    ) -> Result<KeyRange> {
+        let after = |key: Vec<u8>| match prefix_end(key) {
+            Some(end) => Bound::Excluded(end),
+            None => Bound::Unbounded,
+        };
+        let prefix = prefix.encode()?;
+        let start = match range.0 {
+            Bound::Included(v) => Bound::Included(key(v)?),
+            Bound::Excluded(v) => match after(key(v)?) {
+                Bound::Excluded(end) => Bound::Included(end),
+                bound => bound,
+            },
+            Bound::Unbounded => Bound::Included(prefix.clone()),
+        };
+        let end = match range.1 {
+            Bound::Included(v) => after(key(v)?),
+            Bound::Excluded(v) => Bound::Excluded(key(v)?),
+            Bound::Unbounded => after(prefix),
+        };
+        Ok((start, end))
+    }
+
+    fn remove_index_entry(
+        &mut self,
+        table: &Table,
+        index: &Index,
+        row: &Row,
+        id: &Value,
+    ) -> Result<()> {
+        let value = &row[table.get_col_index(&index.column)?];
+        let key = Self::index_key(table, index, value, id).encode()?;
+        self.txn.delete(key)
+    }
+}
+
+impl<E: StorageEngine + 'static> Transaction for KVTransaction<E> {
+    fn commit(&self) -> Result<()> {
+        self.txn.commit()
+    }
+
+    fn rollback(&self) -> Result<()> {
+        self.txn.rollback()
+    }
+
+    fn version(&self) -> u64 {
+        self.txn.version()
+    }
+
+    fn create_row(&mut self, table_name: &str, row: Row) -> Result<()> {
+        let table = self.must_get_table(table_name)?;
+        let row = table.validate_row(row)?;
+
+        let id = table.get_primary_key(&row)?;
+        let key = Key::Row(table.name.clone(), id.clone()).encode()?;
+        if self.txn.get(key.clone())?.is_some() {
+            return Err(Error::Constraint {
+                kind: ConstraintKind::Unique,
+                name: format!("{}_pkey", table.name),
+                message: format!(
+                    "[Engine] Duplicate primary key {} for table {}",
+                    id, table.name
+                ),
+            });
+        }
+
+        for index in &table.indexes {
+            self.insert_index_entry(&table, index, &row, id)?;
+        }
+        self.txn.set(key, bincode::serialize(&row)?)
+    }
+
+    fn update_row(&mut self, table: &Table, id: &Value, row: Row) -> Result<()> {
+        let row = table.validate_row(row)?;
+        // primary key changed, move the row to its new key
+        if table.get_primary_key(&row)? != id {
+            self.delete_row(table, id)?;
+            return self.create_row(&table.name, row);
+        }
+
+        let old = self.read_by_id(&table.name, id)?.ok_or_else(|| {
+            Error::NotFound(format!(
+                "[Engine] Row {} not found in table {}",
+                id, table.name
+            ))
+        })?;
+        for index in &table.indexes {
+            let pos = table.get_col_index(&index.column)?;
+            if old[pos] != row[pos] {
+                self.remove_index_entry(table, index, &old, id)?;
+                self.insert_index_entry(table, index, &row, id)?;
+            }
+        }
+
+        let key = Key::Row(table.name.clone(), id.clone()).encode()?;
+        self.txn.set(key, bincode::serialize(&row)?)
+    }
+
+    fn delete_row(&mut self, table: &Table, id: &Value) -> Result<()> {
+        if let Some(row) = self.read_by_id(&table.name, id)? {
+            for index in &table.indexes {
+                self.remove_index_entry(table, index, &row, id)?;
+            }
+        }
+        let key = Key::Row(table.name.clone(), id.clone()).encode()?;
+        self.txn.delete(key)
+    }
+
+    fn read_by_id(&self, table_name: &str, id: &Value) -> Result<Option<Row>> {
+        let key = Key::Row(table_name.to_string(), id.clone()).encode()?;
+        Ok(match self.txn.get(key)? {
+            Some(value) => Some(bincode::deserialize(&value)?),
+            None => None,
+        })
+    }
+
+    fn read_index(&self, table_name: &str, index_name: &str, value: &Value) -> Result<Vec<Value>> {
+        let prefix =
+            KeyPrefix::Index(table_name.to_string(), index_name.to_string()).encode_value(value)?;
+        self.txn
+            .scan_prefix(prefix)?
+            .map(|r| Ok(bincode::deserialize(&r?.value)?))
+            .collect()
+    }
+
+    fn scan_index_range(
+        &self,
+        table_name: &str,
+        index_name: &str,
+        range: (Bound<Value>, Bound<Value>),
+    ) -> Result<Rows> {
+        let prefix = KeyPrefix::Index(table_name.to_string(), index_name.to_string());
+        let range = Self::key_range(prefix, range, |v| {
+            KeyPrefix::Index(table_name.to_string(), index_name.to_string()).encode_value(&v)
+        })?;
+        // rows are read as the index entries are consumed
+        let reader = self.clone();
+        let table_name = table_name.to_string();
+        Ok(Box::new(
+            self.txn
+                .scan(range)?
+                .map(|r| Ok(bincode::deserialize::<Value>(&r?.value)?))
+                .filter_map(move |id: Result<Value>| {
+                    id.and_then(|id| reader.read_by_id(&table_name, &id))
+                        .transpose()
+                }),
+        ))
+    }
+
+    fn scan_range(&self, table_name: &str, range: (Bound<Value>, Bound<Value>)) -> Result<Rows> {
+        let prefix = KeyPrefix::Row(table_name.to_string());
+        let range = Self::key_range(prefix, range, |v| {
+            Key::Row(table_name.to_string(), v).encode()
+        })?;
+        Ok(Box::new(
+            self.txn
+                .scan(range)?
+                .map(|r| Ok(bincode::deserialize(&r?.value)?)),
+        ))
+    }
+
+    fn scan_table(&self, table_name: &str, filter: Option<Expression>) -> Result<Rows> {
+        self.must_get_table(table_name)?;
+        let prefix = KeyPrefix::Row(table_name.to_string()).encode()?;
+        Ok(Box::new(self.txn.scan_prefix(prefix)?.filter_map(
+            move |r| -> Option<Result<Row>> {
+                let row = match r.and_then(|r| Ok(bincode::deserialize::<Row>(&r.value)?)) {
+                    Ok(row) => row,
+                    Err(err) => return Some(Err(err)),
+                };
+                match &filter {
+                    Some(filter) => match filter.evaluate_predicate(&row) {
+                        Ok(true) => Some(Ok(row)),
+                        Ok(false) => None,
+                        Err(err) => Some(Err(err)),
+                    },
+                    None => Some(Ok(row)),
+                }
+            },
+        )))
+    }
+
+    fn create_table(&mut self, table: Table) -> Result<()> {
+        if self.get_table(&table.name)?.is_some() {
+            return Err(Error::AlreadyExists(format!(
+                "[Engine] Table {} already exists",
+                table.name
+            )));
+        }
+        table.validate()?;
+        self.save_table(&table)
+    }
+
+    fn get_table(&self, table_name: &str) -> Result<Option<Table>> {
+        let key = Key::Table(table_name.to_string()).encode()?;
+        Ok(match self.txn.get(key)? {
+            Some(value) => Some(bincode::deserialize(&value)?),
+            None => None,
+        })
+    }
+
+    fn create_index(&mut self, table_name: &str, index: Index) -> Result<()> {
+        if self
+            .scan_tables()?
+            .iter()
+            .any(|t| t.get_index(&index.name).is_some())
+        {
+            return Err(Error::AlreadyExists(format!(
+                "[Engine] Index {} already exists",
+                index.name
+            )));
+        }
+        let mut table = self.must_get_table(table_name)?;
+        table.get_col_index(&index.column)?;
+
+        // build entries for the existing rows
+        for row in self.scan_table(table_name, None)? {
+            let row = row?;
+            let id = table.get_primary_key(&row)?.clone();
+            self.insert_index_entry(&table, &index, &row, &id)?;
+        }
+        table.indexes.push(index);
+        self.save_table(&table)
+    }
+
+    fn drop_index(&mut self, index_name: &str) -> Result<String> {
+        let mut table = self
+            .scan_tables()?
+            .into_iter()
+            .find(|t| t.get_index(index_name).is_some())
+            .ok_or_else(|| {
+                Error::NotFound(format!("[Engine] Index {} does not exist", index_name))
+            })?;
+
+        let prefix = KeyPrefix::Index(table.name.clone(), index_name.to_string()).encode()?;
+        for result in self.txn.scan_prefix(prefix)? {
+            self.txn.delete(result?.key)?;
+        }
+        table.indexes.retain(|i| i.name != index_name);
+        self.save_table(&table)?;
+        Ok(table.name)
+    }
+
+    fn get_function(&self, name: &str) -> Result<Option<Arc<UserFunction>>> {
+        self.functions.get(name)
+    }
+}
+
+type KeyRange = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+#[derive(Debug, Serialize, Deserialize)]
+enum Key {
+    Table(String),
+    Row(String, Value),
+    // the entry value holds the primary key, see index_key for the last field
+    Index(String, String, Value, Value),
+}
+
+impl Key {
+    fn encode(&self) -> Result<Vec<u8>> {
+        match self {
+            Key::Row(table, id) => serialize_key(&Key::Row(table.clone(), key_value(id)?)),
+            Key::Index(table, index, value, id) => serialize_key(&Key::Index(
+                table.clone(),
+                index.clone(),
+                key_value(value)?,
+                key_value(id)?,
+            )),
+            key => serialize_key(key),
+        }
+    }
+}
+
+// equal decimals of different scales, e.g. 1.5 and 1.50, share a key, and so
+// do equal intervals, which are ordered by their span
+fn key_value(value: &Value) -> Result<Value> {
+    Ok(match value {
+        Value::Decimal(d) => Value::Decimal(d.normalize()),
+        Value::Interval(i) => Value::Interval(i.normalize()?),
+        value => value.clone(),
+    })
+}
+
+// prefix of Key, variants must keep the same order as Key
+#[derive(Debug, Serialize, Deserialize)]
+enum KeyPrefix {
+    Table,
+    Row(String),
+    Index(String, String),
+}
+
+impl KeyPrefix {
+    fn encode(&self) -> Result<Vec<u8>> {
+        serialize_key(self)
+    }
+
+    // prefix of the keys whose next field is value, as fields are concatenated
+    fn encode_value(&self, value: &Value) -> Result<Vec<u8>> {
+        let mut prefix = self.encode()?;
+        prefix.extend(serialize_key(&key_value(value)?)?);
+        Ok(prefix)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::KVEngine;
+    use crate::{
+        error::{ConstraintKind, Error, Result},
+        sql::{
+            engine::{Engine, Transaction},
+            executor::ResultSet,
+            parser::Parser,
+            plan::Plan,
+            types::{Row, Value},
+        },
+        storage::memory::MemoryEngine,
+    };
+
+    fn execute(kv: &KVEngine<MemoryEngine>, sql: &str) -> Result<ResultSet> {
+        let mut txn = kv.begin()?;
+        let result = Parser::new(sql)
+            .parse()
+            .and_then(|stmt| Plan::build(stmt, &mut txn))
+            .and_then(|plan| plan.execute(&mut txn));
+        match result {
+            Ok(rs) => {
+                txn.commit()?;
+                Ok(rs)
+            }
+            Err(err) => {
+                txn.rollback()?;
+                Err(err)
+            }
+        }
+    }
+
+    fn select(kv: &KVEngine<MemoryEngine>, sql: &str) -> Result<(Vec<String>, Vec<Row>)> {
+        match execute(kv, sql)? {
+            ResultSet::Select { columns, rows, .. } => Ok((columns, rows.collect::<Result<_>>()?)),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+    }
+
+    fn select_ids(kv: &KVEngine<MemoryEngine>, sql: &str) -> Result<Vec<Value>> {
+        let (_, rows) = select(kv, sql)?;
+        Ok(rows.into_iter().map(|r| r[0].clone()).collect())
+    }
+
+    #[test]
+    fn test_index_maintenance() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(&kv, "create table t (id int primary key, b int, c string);")?;
+        execute(
+            &kv,
+            "insert into t values (1, 10, 'a'), (2, 20, 'b'), (3, 10, null);",
+        )?;
+        // entries for existing rows are built on creation
+        execute(&kv, "create index idx_b on t (b);")?;
+        execute(&kv, "insert into t values (4, 20, 'c');")?;
+
+        let ids = |sql| select_ids(&kv, sql);
+        assert_eq!(
+            ids("select * from t where b = 10;")?,
+            vec![Value::Integer(1), Value::Integer(3)]
+        );
+        assert_eq!(
+            ids("select * from t where b in (20, 30) and c != 'b';")?,
+            vec![Value::Integer(4)]
+        );
+
+        execute(&kv, "update t set b = 30 where id = 1;")?;
+        execute(&kv, "update t set id = 5 where id = 2;")?;
+        execute(&kv, "delete from t where c = 'c';")?;
+        assert_eq!(
+            ids("select * from t where b = 10;")?,
+            vec![Value::Integer(3)]
+        );
+        assert_eq!(
+            ids("select * from t where b = 20;")?,
+            vec![Value::Integer(5)]
+        );
+        assert_eq!(
+            ids("select * from t where b = 30;")?,
+            vec![Value::Integer(1)]
+        );
+
+        execute(&kv, "drop index idx_b;")?;
+        assert_eq!(
+            ids("select * from t where b = 30;")?,
+            vec![Value::Integer(1)]
+        );
+        assert!(execute(&kv, "drop index idx_b;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_index_concurrent() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (id int primary key, status string, email string);",
+        )?;
+        execute(&kv, "create index idx_status on t (status);")?;
+        execute(&kv, "create unique index idx_email on t (email);")?;
+        let row = |id, status: &str, email: Value| {
+            vec![Value::Integer(id), Value::String(status.to_string()), email]
+        };
+
+        // rows sharing an indexed value are written independently
+        let mut a = kv.begin()?;
+        let mut b = kv.begin()?;
+        a.create_row("t", row(1, "open", Value::Null))?;
+        b.create_row("t", row(2, "open", Value::Null))?;
+        a.commit()?;
+        b.commit()?;
+        assert_eq!(
+            select_ids(&kv, "select * from t where status = 'open';")?,
+            vec![Value::Integer(1), Value::Integer(2)]
+        );
+
+        // but a duplicate of a unique value still conflicts
+        let mut a = kv.begin()?;
+        let mut b = kv.begin()?;
+        a.create_row("t", row(3, "open", Value::String("a".to_string())))?;
+        assert_eq!(
+            b.create_row("t", row(4, "done", Value::String("a".to_string()))),
+            Err(Error::WriteConflict)
+        );
+        b.rollback()?;
+        a.commit()?;
+
+        execute(&kv, "delete from t where id = 1;")?;
+        assert_eq!(
+            select_ids(&kv, "select * from t where status = 'open';")?,
+            vec![Value::Integer(2), Value::Integer(3)]
+        );
+        assert_eq!(
+            select_ids(&kv, "select * from t where email = 'a';")?,
+            vec![Value::Integer(3)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_unique_index() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(&kv, "create table t (id int primary key, email string);")?;
+        execute(&kv, "insert into t values (1, 'a'), (2, 'a');")?;
+        assert!(execute(&kv, "create unique index idx_email on t (email);").is_err());
+
+        execute(&kv, "delete from t where id = 2;")?;
+        execute(&kv, "create unique index idx_email on t (email);")?;
+        // the whole statement fails on a duplicate, NULLs are allowed
+        assert!(execute(&kv, "insert into t values (3, 'b'), (4, 'a');").is_err());
+        execute(&kv, "insert into t values (3, null), (4, null);")?;
+        assert!(execute(&kv, "update t set email = 'a' where id = 3;").is_err());
+        execute(&kv, "update t set email = 'a' where id = 1;")?;
+
+        assert_eq!(
+            select_ids(&kv, "select * from t where email = 'b';")?,
+            vec![]
+        );
+        assert_eq!(
+            select_ids(&kv, "select * from t where email = 'a';")?,
+            vec![Value::Integer(1)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_range_scan() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (id int primary key, ts float, name string);",
+        )?;
+        execute(&kv, "create index idx_ts on t (ts);")?;
+        execute(&kv, "create index idx_name on t (name);")?;
+        execute(
+            &kv,
+            "insert into t values (-20, 3.5, 'b'), (-3, -1.5, 'a'), (0, 0.0, null), (7, 2.0, 'a'), (42, -8.25, 'c');",
+        )?;
+
+        let ids = |sql| select_ids(&kv, sql);
+        let ints = |v: Vec<i64>| v.into_iter().map(Value::Integer).collect::<Vec<_>>();
+        assert_eq!(
+            ids("select * from t where id < 7;")?,
+            ints(vec![-20, -3, 0])
+        );
+        assert_eq!(
+            ids("select * from t where id between 0 and 100;")?,
+            ints(vec![0, 7, 42])
+        );
+        assert_eq!(ids("select * from t where id > 42;")?, ints(vec![]));
+        assert_eq!(
+            ids("select * from t where id > 10 and id < 5;")?,
+            ints(vec![])
+        );
+        // index order, not primary key order
+        assert_eq!(
+            ids("select * from t where ts <= 2.0;")?,
+            ints(vec![42, -3, 0, 7])
+        );
+        assert_eq!(
+            ids("select * from t where ts between 0.0 and 3.0;")?,
+            ints(vec![0, 7])
+        );
+        // NULL entries of the index are left out by the filter
+        assert_eq!(ids("select * from t where name < 'b';")?, ints(vec![-3, 7]));
+
+        execute(&kv, "delete from t where ts <= 0.0 and id <= 0;")?;
+        assert_eq!(ids("select * from t where ts < 3.0;")?, ints(vec![42, 7]));
+        Ok(())
+    }
+
+    #[test]
+    fn test_datetime() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (ts timestamp primary key, day date, at time, took interval);",
+        )?;
+        execute(&kv, "create index idx_day on t (day);")?;
+        execute(
+            &kv,
+            "insert into t values
+                (timestamp '2026-10-17 09:00:00', date '2026-10-17', time '09:00', interval '1 hour'),
+                (timestamp '2026-10-16 23:59:59.5', date '2026-10-16', time '23:59:59.5', interval '30 mins'),
+                (timestamp '1999-12-31 12:00', date '1999-12-31', time '12:00', interval '1 day -02:00'),
+                (timestamp '2026-11-01 00:00', null, null, null);",
+        )?;
+
+        let (_, rows) = select(&kv, "select * from t where ts >= timestamp '2026-10-16';")?;
+        assert_eq!(
+            rows.iter().map(|r| r[0].to_string()).collect::<Vec<_>>(),
+            [
+                "2026-10-16 23:59:59.5",
+                "2026-10-17 09:00:00",
+                "2026-11-01 00:00:00"
+            ]
+        );
+        // dates compare with timestamps as their midnight
+        let (_, rows) = select(
+            &kv,
+            "select day from t where day between date '2000-01-01' and timestamp '2026-10-17';",
+        )?;
+        assert_eq!(
+            rows,
+            vec![vec![Value::Date(20_742)], vec![Value::Date(20_743)]]
+        );
+        match execute(
+            &kv,
+            "explain select * from t where day > date '2026-10-16';",
+        )? {
+            ResultSet::Explain { plan } => assert!(plan.starts_with(
+                "Index Range Scan on t using idx_day (range: (DATE '2026-10-16', +inf))"
+            )),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        let (columns, rows) = select(
+            &kv,
+            "select ts + took as ends, day - date '2026-01-01' as days, ts - ts as zero,
+                at - interval '10 hours', took * 2, day + 1 from t where day = date '1999-12-31';",
+        )?;
+        assert_eq!(columns[0], "ends");
+        assert_eq!(
+            rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            [
+                "2000-01-01 10:00:00",
+                "-9498",
+                "00:00:00",
+                "02:00:00",
+                "2 days -04:00:00",
+                "2000-01-01"
+            ]
+        );
+        let (_, rows) = select(
+            &kv,
+            "select ts - timestamp '2026-10-16 12:00', day + at, ts + interval '1 month'
+                from t where ts = timestamp '2026-10-16 23:59:59.5';",
+        )?;
+        assert_eq!(
+            rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            [
+                "11:59:59.5",
+                "2026-10-16 23:59:59.5",
+                "2026-11-16 23:59:59.5"
+            ]
+        );
+
+        let (_, rows) = select(
+            &kv,
+            "select current_date, now() - current_timestamp < interval '1 minute',
+                current_time from t where ts = timestamp '2026-11-01';",
+        )?;
+        assert!(matches!(
+            rows[0][..],
+            [Value::Date(_), Value::Boolean(true), Value::Time(_)]
+        ));
+
+        // types are checked on insert, and arithmetic stays in range
+        assert!(execute(
+            &kv,
+            "insert into t values (time '12:00', null, null, null);"
+        )
+        .is_err());
+        assert!(select(&kv, "select day + interval '8000 years' from t;").is_err());
+        assert!(select(&kv, "select day + day from t;").is_err());
+        assert!(execute(&kv, "select * from t where day = date '2026-02-30';").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interval_key() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (i interval primary key, took interval);",
+        )?;
+        execute(&kv, "create index idx_took on t (took);")?;
+        execute(
+            &kv,
+            "insert into t values (interval '1 day', interval '1 mon'),
+                (interval '100 hours', interval '29 days'), (interval '-1 hour', interval '31 days');",
+        )?;
+        let (_, rows) = select(
+            &kv,
+            "select interval '1 day' > interval '100 hours', interval '1 day' = interval '24 hours'
+                from t where i = interval '24 hours';",
+        )?;
+        assert_eq!(
+            rows,
+            vec![vec![Value::Boolean(false), Value::Boolean(true)]]
+        );
+
+        // keys order by span, and an equal span is the same key
+        let (_, rows) = select(&kv, "select i from t;")?;
+        assert_eq!(
+            rows.iter().map(|r| r[0].to_string()).collect::<Vec<_>>(),
+            ["-01:00:00", "1 day", "100:00:00"]
+        );
+        let (_, rows) = select(&kv, "select i from t where i > interval '2 days';")?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0].to_string(), "100:00:00");
+        match execute(&kv, "explain select * from t where i > interval '2 days';")? {
+            ResultSet::Explain { plan } => assert!(plan.starts_with("Range Scan"), "{}", plan),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+        let (_, rows) = select(&kv, "select took from t where took >= interval '720:00';")?;
+        assert_eq!(
+            rows.iter().map(|r| r[0].to_string()).collect::<Vec<_>>(),
+            ["1 mon", "31 days"]
+        );
+        let (_, rows) = select(&kv, "select i from t where took = interval '30 days';")?;
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0][0].to_string(), "1 day");
+        assert!(matches!(
+            execute(&kv, "insert into t values (interval '24 hours', null);"),
+            Err(Error::Constraint { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table p (id decimal(6, 2) primary key, price numeric(5, 2) not null, rate decimal);",
+        )?;
+        execute(&kv, "create index idx_price on p (price);")?;
+        // values are rounded to the scale of their column
+        execute(
+            &kv,
+            "insert into p values (1.5, 9.999, 0.1), (decimal '2.25', 3, 1.5e-3), (-7, decimal '0.125', null);",
+        )?;
+        let (_, rows) = select(&kv, "select * from p;")?;
+        assert_eq!(
+            rows.iter()
+                .map(|r| r.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            [
+                ["-7.00", "0.13", "NULL"],
+                ["1.50", "10.00", "0.1"],
+                ["2.25", "3.00", "0.0015"]
+            ]
+        );
+
+        // keys do not depend on the scale
+        let (_, rows) = select(&kv, "select price from p where id = decimal '1.5';")?;
+        assert_eq!(rows, vec![vec![Value::Decimal("10".parse()?)]]);
+        let (_, rows) = select(&kv, "select id from p where price = decimal '3';")?;
+        assert_eq!(rows[0][0].to_string(), "2.25");
+        assert!(matches!(
+            execute(&kv, "insert into p values (decimal '1.500', 1, null);"),
+            Err(Error::Constraint {
+                kind: ConstraintKind::Unique,
+                ..
+            })
+        ));
+
+        let (_, rows) = select(
+            &kv,
+            "select price * 2, price / 3, price + 0.5, rate * 1000, price > 2 from p
+                where id = decimal '2.25';",
+        )?;
+        assert_eq!(
+            rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            ["6.00", "1.0000000000000000", "3.50", "1.5000", "TRUE"]
+        );
+        assert!(select(&kv, "select price / decimal '0.0' from p;").is_err());
+
+        // literals with a fractional part are exact too
+        let (_, rows) = select(
+            &kv,
+            "select rate + 0.1, 0.1 + 0.2 = 0.3, 1.5e-3 from p where id = 1.5;",
+        )?;
+        assert_eq!(
+            rows[0],
+            [
+                Value::Decimal("0.2".parse()?),
+                Value::Boolean(true),
+                Value::Float(0.0015)
+            ]
+        );
+
+        // precision is enforced on insert and update
+        assert!(execute(&kv, "insert into p values (3, 1000, null);").is_err());
+        assert!(execute(&kv, "insert into p values (12345.678, 1, null);").is_err());
+        execute(&kv, "update p set price = price * 99.994 where id = 2.25;")?;
+        let (_, rows) = select(&kv, "select price from p where id = decimal '2.25';")?;
+        assert_eq!(rows[0][0].to_string(), "299.98");
+        assert!(execute(&kv, "update p set price = price * 1000 where id = 1.5;").is_err());
+        assert!(execute(&kv, "create table q (id decimal(39) primary key);").is_err());
+        assert!(execute(&kv, "create table q (id decimal(2, 3) primary key);").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_blob() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table f (hash bytea primary key, data blob, name string);",
+        )?;
+        execute(&kv, "create index idx_data on f (data);")?;
+        execute(
+            &kv,
+            "insert into f values (X'ff', X'', 'empty'), (X'00ff', X'DEADBEEF', 'héllo'),
+                (X'00', null, null), (X'01', X'DEADBEEF', 'b');",
+        )?;
+
+        // blobs compare byte by byte, so they work as keys
+        let (_, rows) = select(&kv, "select hash from f where hash > X'00';")?;
+        assert_eq!(
+            rows,
+            vec![
+                vec![Value::Blob(vec![0, 0xff])],
+                vec![Value::Blob(vec![1])],
+                vec![Value::Blob(vec![0xff])]
+            ]
+        );
+        let (_, rows) = select(&kv, "select name from f where data = X'deadbeef';")?;
+        assert_eq!(rows.len(), 2);
+        match execute(&kv, "explain select * from f where data = X'deadbeef';")? {
+            ResultSet::Explain { plan } => {
+                assert!(plan.starts_with("Index Lookup on f using idx_data (values: X'deadbeef')"))
+            }
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        let (_, rows) = select(
+            &kv,
+            "select length(data), substr(data, 2, 2), substr(data, 0, 2), length(name),
+                substr(name, 2), data from f where hash = X'00ff';",
+        )?;
+        assert_eq!(
+            rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            ["4", "\\xadbe", "\\xde", "5", "éllo", "\\xdeadbeef"]
+        );
+        let (_, rows) = select(
+            &kv,
+            "select length(data), substr(name, 1, 2), substr(data, 9) from f where hash = X'00';",
+        )?;
+        assert_eq!(rows[0], vec![Value::Null, Value::Null, Value::Null]);
+        let (_, rows) = select(&kv, "select substr(data, 9) from f where hash = X'ff';")?;
+        assert_eq!(rows[0], vec![Value::Blob(vec![])]);
+
+        assert!(execute(&kv, "insert into f values (12, null, null);").is_err());
+        assert!(select(&kv, "select length(1) from f;").is_err());
+        assert!(select(&kv, "select substr(data, 1, -1) from f;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_json() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table events (id int primary key, payload json not null);",
+        )?;
+        execute(
+            &kv,
+            r#"insert into events values
+                (1, '{"user": {"name": "ann", "age": 31}, "tags": ["a", "b"]}'),
+                (2, json '{"user": {"name": "bob", "age": 25.5}, "tags": []}'),
+                (3, '{"user": null, "tags": ["c"]}');"#,
+        )?;
+        // invalid documents are rejected on insert
+        assert!(execute(&kv, r#"insert into events values (4, '{"a": }');"#).is_err());
+        assert!(execute(&kv, "insert into events values (4, 1);").is_err());
+
+        let (_, rows) = select(&kv, "select payload from events where id = 1;")?;
+        assert_eq!(
+            rows[0][0].to_string(),
+            r#"{"tags": ["a", "b"], "user": {"age": 31, "name": "ann"}}"#
+        );
+        let (_, rows) = select(
+            &kv,
+            "select payload -> 'user' ->> 'name', payload -> 'tags' -> -1,
+                json_array_length(payload -> 'tags'), json_extract(payload, '$.user.age'),
+                json_extract(payload, '$.tags') from events;",
+        )?;
+        assert_eq!(
+            rows.iter()
+                .map(|r| r.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            [
+                ["ann", "\"b\"", "2", "31", r#"["a", "b"]"#],
+                ["bob", "NULL", "0", "25.5", "[]"],
+                ["NULL", "\"c\"", "1", "NULL", r#"["c"]"#],
+            ]
+        );
+
+        // extracted scalars compare with SQL values
+        let ids = |sql: &str| -> Result<Vec<Value>> {
+            let (_, rows) = select(&kv, sql)?;
+            Ok(rows.into_iter().map(|r| r[0].clone()).collect())
+        };
+        assert_eq!(
+            ids("select id from events where payload -> 'user' -> 'age' > 30;")?,
+            [Value::Integer(1)]
+        );
+        assert_eq!(
+            ids("select id from events where payload -> 'user' ->> 'name' = 'bob';")?,
+            [Value::Integer(2)]
+        );
+        assert_eq!(
+            ids("select id from events where json_extract(payload, '$.user.age') < 30;")?,
+            [Value::Integer(2)]
+        );
+        assert_eq!(
+            ids(r#"select id from events where payload -> 'tags' = json '["c"]';"#)?,
+            [Value::Integer(3)]
+        );
+
+        assert!(select(&kv, "select json_array_length(payload) from events;").is_err());
+        assert!(select(&kv, "select json_extract(payload, 'tags') from events;").is_err());
+        assert!(select(&kv, "select id -> 'a' from events;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_char_length() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (code char(4) primary key, name varchar(5), flag char);",
+        )?;
+        execute(
+            &kv,
+            "insert into t values ('ab', 'ann', 'y'), ('abcd', 'bob  ', null);",
+        )?;
+
+        // too long values are rejected, unless the excess is only spaces
+        let err = execute(&kv, "insert into t values ('c', 'carolyn', 'n');").unwrap_err();
+        assert_eq!(err.sqlstate(), "22001");
+        assert!(execute(&kv, "insert into t values ('abcde', 'c', 'n');").is_err());
+        assert!(execute(&kv, "insert into t values ('c', 'c', 'no');").is_err());
+        assert!(execute(&kv, "update t set name = 'robert' where code = 'abcd';").is_err());
+
+        let (_, rows) = select(&kv, "select code, name, flag from t;")?;
+        assert_eq!(
+            rows,
+            [
+                vec![
+                    Value::String("ab  ".to_string()),
+                    Value::String("ann".to_string()),
+                    Value::String("y".to_string()),
+                ],
+                vec![
+                    Value::String("abcd".to_string()),
+                    Value::String("bob  ".to_string()),
+                    Value::Null,
+                ],
+            ]
+        );
+
+        // constants compared with CHAR(n) ignore the padding the same way
+        for sql in [
+            "select name from t where code = 'ab';",
+            "select name from t where code = 'ab     ';",
+            "select name from t where code in ('ab', 'xy');",
+            "select name from t where 'ab' = code and code < 'abc';",
+        ] {
+            let (_, rows) = select(&kv, sql)?;
+            assert_eq!(rows, [vec![Value::String("ann".to_string())]], "{}", sql);
+        }
+        let (_, rows) = select(&kv, "select name from t where name = 'ann  ';")?;
+        assert!(rows.is_empty());
+        // the key is looked up as stored
+        match execute(&kv, "explain select * from t where code = 'ab';")? {
+            ResultSet::Explain { plan } => {
+                assert!(plan.starts_with("Key Lookup on t (keys: 'ab  ')"))
+            }
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        // within expressions CHAR(n) values have no padding
+        let (_, rows) = select(
+            &kv,
+            "select code = 'ab', length(code), code like 'ab', code || '|', 'ab'::char(3) = code from t;",
+        )?;
+        assert_eq!(
+            rows,
+            [
+                vec![
+                    Value::Boolean(true),
+                    Value::Integer(2),
+                    Value::Boolean(true),
+                    Value::String("ab|".to_string()),
+                    Value::Boolean(true),
+                ],
+                vec![
+                    Value::Boolean(false),
+                    Value::Integer(4),
+                    Value::Boolean(false),
+                    Value::String("abcd|".to_string()),
+                    Value::Boolean(false),
+                ],
+            ]
+        );
+
+        // and compare with VARCHAR values without the padding of either
+        execute(&kv, "create table v (id int primary key, s varchar(6));")?;
+        execute(
+            &kv,
+            "insert into v values (1, 'ab'), (2, 'abcd  '), (3, 'a');",
+        )?;
+        for (sql, ids) in [
+            (
+                "select id from v where s in (select code from t);",
+                vec![1, 2],
+            ),
+            (
+                "select id from v where s = (select code from t where flag = 'y');",
+                vec![1],
+            ),
+            (
+                "select id from v where exists (select * from t where code = s);",
+                vec![1, 2],
+            ),
+        ] {
+            let ids = ids.into_iter().map(Value::Integer).collect::<Vec<_>>();
+            assert_eq!(select_ids(&kv, sql)?, ids, "{}", sql);
+        }
+        let (_, rows) = select(&kv, "select name from t where code in (select s from v);")?;
+        assert_eq!(rows.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cast() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (id int primary key, f float, d decimal(5, 2), day date, ok bool);",
+        )?;
+        // values convert implicitly to the column type when nothing is lost
+        execute(
+            &kv,
+            "insert into t values (1, 2, 3, '2026-10-17', 'yes'), ('2', '2.5', 1.25, null, 0 = 1);",
+        )?;
+        for sql in [
+            "insert into t values (3.5, 1, 1, null, true);",
+            "insert into t values ('three', 1, 1, null, true);",
+            "insert into t values (3, 9007199254740993, 1, null, true);",
+            "insert into t values (3, 1, 1, timestamp '2026-10-17 12:00', true);",
+            "insert into t values (3, 1, 1, null, 1);",
+            "update t set id = 1.5 where id = 1;",
+        ] {
+            assert!(execute(&kv, sql).is_err(), "{}", sql);
+        }
+        execute(&kv, "update t set f = 3.0 * 2, id = 10.0 where id = 1;")?;
+        assert_eq!(
+            select(&kv, "select * from t;")?.1,
+            [
+                vec![
+                    Value::Integer(2),
+                    Value::Float(2.5),
+                    Value::Decimal("1.25".parse()?),
+                    Value::Null,
+                    Value::Boolean(false),
+                ],
+                vec![
+                    Value::Integer(10),
+                    Value::Float(6.0),
+                    Value::Decimal("3.00".parse()?),
+                    Value::Date(20_743),
+                    Value::Boolean(true),
+                ],
+            ]
+        );
+
+        // strings compared with other types convert to them
+        assert_eq!(
+            select_ids(&kv, "select id from t where id = '2';")?,
+            [Value::Integer(2)]
+        );
+        assert_eq!(
+            select_ids(
+                &kv,
+                "select id from t where day < '2027-01-01' and ok = 't';"
+            )?,
+            [Value::Integer(10)]
+        );
+        assert!(select(&kv, "select id from t where id = 'two';").is_err());
+
+        // explicit casts round and convert between any types that make sense
+        let (columns, rows) = select(
+            &kv,
+            "select cast(f as int), d::float, d::int::string, cast(day as timestamp),
+                cast(id as varchar(1)), '42'::int + 1, cast('1.255' as decimal(4, 2))
+                from t where id = 10;",
+        )?;
+        assert_eq!(columns[0], "CAST(f AS INTEGER)");
+        assert_eq!(
+            rows[0].iter().map(|v| v.to_string()).collect::<Vec<_>>(),
+            ["6", "3", "3", "2026-10-17 00:00:00", "1", "43", "1.26"]
+        );
+        assert!(select(&kv, "select day::int from t;").is_err());
+        assert!(select(&kv, "select cast(d as decimal(2, 2)) from t;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_functions() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (id int primary key, s string, f float, d decimal(6, 3));",
+        )?;
+        execute(
+            &kv,
+            "insert into t values (1, '  Héllo ', -2.5, 12.345), (2, null, 9, -0.5);",
+        )?;
+        let (_, rows) = select(
+            &kv,
+            "select upper(s), lower(trim(s)), replace(s, 'l', 'L'), 'id ' || id,
+                concat(s, id, null), abs(f), round(d, 1), floor(d), ceil(f), round(f),
+                sqrt(id * 4), pow(id, 3), mod(id + 7, 3), coalesce(s, 'none'),
+                ifnull(s, 'x') = s, nullif(id, 2), length(s), substr(s, 3, 2)
+                from t;",
+        )?;
+        let text = |row: &Vec<Value>| row.iter().map(|v| v.to_string()).collect::<Vec<_>>();
+        assert_eq!(
+            text(&rows[0]),
+            [
+                "  HÉLLO ",
+                "héllo",
+                "  HéLLo ",
+                "id 1",
+                "  Héllo 1",
+                "2.5",
+                "12.3",
+                "12",
+                "-2",
+                "-3",
+                "2",
+                "1",
+                "2",
+                "  Héllo ",
+                "TRUE",
+                "1",
+                "8",
+                "Hé"
+            ]
+        );
+        assert_eq!(
+            text(&rows[1]),
+            [
+                "NULL",
+                "NULL",
+                "NULL",
+                "id 2",
+                "2",
+                "9",
+                "-0.5",
+                "-1",
+                "9",
+                "9",
+                "2.8284271247461903",
+                "8",
+                "0",
+                "none",
+                "NULL",
+                "NULL",
+                "NULL",
+                "NULL"
+            ]
+        );
+
+        // the NULL functions return the type all their arguments convert to
+        let (_, rows) = select(
+            &kv,
+            "select coalesce(id, f), ifnull(d, id), nullif(f, '9') from t;",
+        )?;
+        assert_eq!(
+            rows[1],
+            [
+                Value::Float(2.0),
+                Value::Decimal("-0.5".parse()?),
+                Value::Null,
+            ]
+        );
+        assert_eq!(rows[0][0], Value::Float(1.0));
+
+        // functions are checked before the statement runs
+        for sql in [
+            "select coalesce(id, 'a') from t;",
+            "select ifnull(s, d) from t;",
+            "select coalesce(s, 1) from t;",
+            "select lower(id) from t;",
+            "select round(s) from t;",
+            "select * from t where upper() = 'A';",
+            "update t set s = left(s, 1);",
+        ] {
+            assert!(select(&kv, sql).is_err(), "{}", sql);
+        }
+        assert!(select(&kv, "select mod(id, 0) from t;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_case_like() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(&kv, "create table t (id int primary key, n int, s string);")?;
+        execute(
+            &kv,
+            "insert into t values (1, 5, 'Apple'), (2, null, '50%_off'), (3, -1, null), (4, 0, 'a_b');",
+        )?;
+        let ids = |sql: &str| select_ids(&kv, &format!("select id from t where {};", sql));
+        let int = |ids: &[i64]| ids.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // NULL on either side is unknown, so NOT of it matches nothing either
+        assert_eq!(ids("n in (5, null)")?, int(&[1]));
+        assert_eq!(ids("n not in (5, 0)")?, int(&[3]));
+        assert_eq!(ids("n not in (5, null)")?, int(&[]));
+        assert_eq!(ids("n not between 0 and 4")?, int(&[1, 3]));
+        assert_eq!(ids("s like 'a%'")?, int(&[4]));
+        assert_eq!(ids("s ilike 'A%'")?, int(&[1, 4]));
+        assert_eq!(ids("s not like '%p%'")?, int(&[2, 4]));
+        assert_eq!(ids("s like '%\\%\\_%'")?, int(&[2]));
+        assert_eq!(ids("s like '_!_%' escape '!'")?, int(&[4]));
+        assert_eq!(ids("s like 'a_b' escape ''")?, int(&[4]));
+        assert_eq!(ids("s like '%' || 'e'")?, int(&[1]));
+        assert_eq!(ids("s like null")?, int(&[]));
+        assert!(ids("s like 'a!' escape '!'").is_err());
+        assert!(ids("s like 'a' escape 'ab'").is_err());
+        assert!(ids("n like '1'").is_err());
+
+        let (columns, rows) = select(
+            &kv,
+            "select case when n > 0 then 'pos' when n < 0 then 'neg' else 'zero or null' end,
+                case n when 5 then 'five' when null then 'null' end,
+                case when n = 0 then 0 else 10 / n end as x
+                from t;",
+        )?;
+        assert_eq!(columns[2], "x");
+        assert_eq!(
+            rows.iter()
+                .map(|row| row.iter().map(|v| v.to_string()).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            [
+                ["pos", "five", "2"],
+                ["zero or null", "NULL", "NULL"],
+                ["neg", "NULL", "-10"],
+                // only the branch taken is evaluated
+                ["zero or null", "NULL", "0"],
+            ]
+        );
+        assert!(select(&kv, "select case when n then 1 end from t;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_subquery() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(&kv, "create table t (id int primary key, n int, s string);")?;
+        execute(&kv, "create table u (uid int primary key, tid int, v int);")?;
+        execute(
+            &kv,
+            "insert into t values (1, 5, 'a'), (2, null, 'b'), (3, -1, 'c'), (4, 0, 'd');",
+        )?;
+        execute(
+            &kv,
+            "insert into u values (10, 1, 5), (11, 1, 7), (12, 3, 0), (13, null, null);",
+        )?;
+        let ids = |sql: &str| select_ids(&kv, &format!("select id from t where {};", sql));
+        let int = |ids: &[i64]| ids.iter().map(|i| Value::Integer(*i)).collect::<Vec<_>>();
+
+        // a scalar subquery without rows is NULL
+        assert_eq!(ids("n = (select v from u where uid = 10)")?, int(&[1]));
+        assert_eq!(ids("n = (select v from u where uid = 99)")?, int(&[]));
+        assert_eq!(ids("n in (select v from u)")?, int(&[1, 4]));
+        assert_eq!(ids("n not in (select v from u where uid < 13)")?, int(&[3]));
+        assert_eq!(ids("n not in (select v from u)")?, int(&[]));
+
+        // correlated subqueries see the row of the outer query
+        assert_eq!(
+            ids("exists (select * from u where tid = id)")?,
+            int(&[1, 3])
+        );
+        assert_eq!(
+            ids("not exists (select * from u where tid = id)")?,
+            int(&[2, 4])
+        );
+        assert_eq!(
+            ids("exists (select * from u where tid = id and exists (select * from u as w where w.v > n + 5))")?,
+            int(&[3])
+        );
+        assert_eq!(
+            ids(
+                "exists (select * from u where tid = id and v in (select t2.n from t t2 where t2.s = 'a'))"
+            )?,
+            int(&[1])
+        );
+        let (_, rows) = select(
+            &kv,
+            "select id, (select v from u where tid = id and uid != 11) from t;",
+        )?;
+        assert_eq!(
+            rows.iter().map(|r| r[1].clone()).collect::<Vec<_>>(),
+            [
+                Value::Integer(5),
+                Value::Null,
+                Value::Integer(0),
+                Value::Null
+            ]
+        );
+
+        // a column name of both the subquery and the outer query is
+        // qualified by a table name or alias, the innermost one first
+        execute(&kv, "create table c (id int primary key, n int);")?;
+        execute(&kv, "insert into c values (1, 5), (3, 7);")?;
+        assert_eq!(
+            ids("exists (select * from c where c.id = t.id and c.n = t.n)")?,
+            int(&[1])
+        );
+        assert_eq!(ids("(select c.n from c where c.id = t.id) > n")?, int(&[3]));
+        assert_eq!(
+            ids("exists (select * from t as o where o.n > t.n)")?,
+            int(&[3, 4])
+        );
+        assert_eq!(
+            ids("t.n = (select m.v from (select v from u where uid = 10) m)")?,
+            int(&[1])
+        );
+
+        // derived tables
+        let (columns, rows) = select(
+            &kv,
+            "select d.x from (select n + 1 as x from t where n >= 0) as d where d.x > 1;",
+        )?;
+        assert_eq!(columns, ["x"]);
+        assert_eq!(rows, [[Value::Integer(6)]]);
+        assert_eq!(
+            ids("n in (select x from (select v as x from u) d)")?,
+            int(&[1, 4])
+        );
+        assert_eq!(
+            ids("exists (select * from (select tid from u where tid = id) d)")?,
+            int(&[1, 3])
+        );
+
+        for (sql, err) in [
+            (
+                "select id from t where n = (select v from u where tid = id);",
+                "[Expression] Subquery returns more than one row",
+            ),
+            (
+                "select id from t where n in (select * from u);",
+                "[Planner] Subquery must return one column, got 3",
+            ),
+            (
+                "select (select v, tid from u) from t;",
+                "[Planner] Subquery must return one column, got 2",
+            ),
+            // columns are resolved before anything is executed
+            (
+                "select id from t where exists (select * from u where w = id);",
+                "[Planner] Column w does not exist",
+            ),
+            (
+                "select id from t where exists (select * from c where id = 1);",
+                "[Planner] Column reference id is ambiguous",
+            ),
+            (
+                "select x from (select n as x, s as x from t) d;",
+                "[Planner] Column reference x is ambiguous",
+            ),
+            ("select u.v from t;", "[Planner] Column u.v does not exist"),
+        ] {
+            assert_eq!(select(&kv, sql).unwrap_err().to_string(), err, "{}", sql);
+        }
+
+        execute(
+            &kv,
+            "update t set n = (select v from u where uid = 11) where id = 2;",
+        )?;
+        execute(&kv, "delete from t where t.id in (select u.tid from u);")?;
+        let (_, rows) = select(&kv, "select id, n from t;")?;
+        assert_eq!(
+            rows,
+            [
+                [Value::Integer(2), Value::Integer(7)],
+                [Value::Integer(4), Value::Integer(0)]
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_operation() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(&kv, "create table a (id int primary key, n int, s string);")?;
+        execute(
+            &kv,
+            "create table b (id int primary key, x float, s string);",
+        )?;
+        execute(
+            &kv,
+            "insert into a values (1, 1, 'x'), (2, 2, 'y'), (3, 2, null), (4, null, null);",
+        )?;
+        execute(
+            &kv,
+            "insert into b values (1, 2.0, 'y'), (2, 2.0, null), (3, 5.5, 'z'), (4, null, null);",
+        )?;
+        let rows = |sql: &str| -> Result<Vec<Row>> { Ok(select(&kv, sql)?.1) };
+        let float = |f: f64| Value::Float(f);
+
+        // integers are converted to floats, NULLs are equal to each other
+        let (columns, union) = select(&kv, "select n as v from a union select x from b;")?;
+        assert_eq!(columns, ["v"]);
+        assert_eq!(
+            union,
+            [[float(1.0)], [float(2.0)], [Value::Null], [float(5.5)]]
+        );
+        assert_eq!(rows("select n from a union all select x from b;")?.len(), 8);
+        assert_eq!(
+            rows("select n, s from a intersect select x, s from b;")?,
+            [
+                [float(2.0), Value::String("y".to_string())],
+                [float(2.0), Value::Null],
+                [Value::Null, Value::Null]
+            ]
+        );
+        assert_eq!(
+            rows("select n from a intersect all select x from b;")?,
+            [[float(2.0)], [float(2.0)], [Value::Null]]
+        );
+        assert_eq!(
+            rows("select n from a except select x from b;")?,
+            [[float(1.0)]]
+        );
+        assert_eq!(
+            rows("select n from a except all select x from b where id = 1;")?,
+            [[float(1.0)], [float(2.0)], [Value::Null]]
+        );
+        // only string literals convert to the type of the other query
+        assert_eq!(
+            rows("select n from a where id = 1 union all select '7' from b where id = 1;")?,
+            [[Value::Integer(1)], [Value::Integer(7)]]
+        );
+        assert!(select(&kv, "select n from a union select 'x' from b;").is_err());
+        assert_eq!(
+            rows("select s from a where id = 1 union select 'w' from b where id = 1;")?,
+            [
+                [Value::String("x".to_string())],
+                [Value::String("w".to_string())]
+            ]
+        );
+        assert_eq!(
+            rows("select s from a union select s from b limit 2 offset 1;")?,
+            [[Value::String("y".to_string())], [Value::Null]]
+        );
+        assert_eq!(
+            select_ids(
+                &kv,
+                "select id from a where n in (select x from b intersect select a2.n from a a2);"
+            )?,
+            [Value::Integer(2), Value::Integer(3)]
+        );
+
+        // CHAR(n) values are matched without their padding
+        execute(&kv, "create table c (id int primary key, c char(3));")?;
+        execute(&kv, "insert into c values (1, 'a'), (2, 'bc'), (3, null);")?;
+        assert_eq!(
+            rows("select c from c union select 'a' from c;")?,
+            [
+                [Value::String("a".to_string())],
+                [Value::String("bc".to_string())],
+                [Value::Null]
+            ]
+        );
+        assert_eq!(
+            rows("select c from c where id < 3 except select s from b;")?,
+            [
+                [Value::String("a".to_string())],
+                [Value::String("bc".to_string())]
+            ]
+        );
+        assert_eq!(
+            rows("select c from c intersect select 'bc  ' from c;")?,
+            [[Value::String("bc".to_string())]]
+        );
+
+        for (sql, err) in [
+            (
+                "select n, s from a union select x from b;",
+                "[Planner] Each UNION query must have the same number of columns, got 2 and 1",
+            ),
+            (
+                "select n from a except select x > 1 from b;",
+                "[Planner] EXCEPT types INTEGER and BOOLEAN cannot be matched",
+            ),
+            (
+                "select n from a union select s from b;",
+                "[Planner] UNION types INTEGER and STRING cannot be matched",
+            ),
+            (
+                "select s from a intersect select id from b;",
+                "[Planner] INTERSECT types STRING and INTEGER cannot be matched",
+            ),
+        ] {
+            assert_eq!(select(&kv, sql).unwrap_err().to_string(), err, "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_distinct() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(&kv, "create table t (id int primary key, n int, s string);")?;
+        execute(
+            &kv,
+            "insert into t values (1, 1, 'x'), (2, 1, 'y'), (3, null, 'x'), (4, null, null), (5, 2, 'y');",
+        )?;
+        let rows = |sql: &str| -> Result<Vec<Row>> { Ok(select(&kv, sql)?.1) };
+        let string = |s: &str| Value::String(s.to_string());
+
+        // NULLs are equal, the first row of each key is kept
+        assert_eq!(
+            rows("select distinct n from t;")?,
+            [[Value::Integer(1)], [Value::Null], [Value::Integer(2)]]
+        );
+        assert_eq!(
+            rows("select distinct s from t limit 2 offset 1;")?,
+            [[string("y")], [Value::Null]]
+        );
+        assert_eq!(
+            rows("select distinct on (n) id, s from t;")?,
+            [
+                [Value::Integer(1), string("x")],
+                [Value::Integer(3), string("x")],
+                [Value::Integer(5), string("y")]
+            ]
+        );
+        assert_eq!(
+            rows("select distinct on (n > 1, s) id from t where id > 1;")?,
+            [
+                [Value::Integer(2)],
+                [Value::Integer(3)],
+                [Value::Integer(4)],
+                [Value::Integer(5)]
+            ]
+        );
+        assert_eq!(
+            rows("select distinct * from (select s from t where n < 2) as d;")?,
+            [[string("x")], [string("y")]]
+        );
+        assert_eq!(
+            select(&kv, "select distinct on (m) id from t;")
+                .unwrap_err()
+                .to_string(),
+            "[Planner] Column m does not exist"
+        );
+
+        // NaNs are duplicates of each other and -0.0 of 0.0, SQL cannot
+        // produce them so the rows are written directly
+        execute(&kv, "create table f (id int primary key, f float);")?;
+        let mut txn = kv.begin()?;
+        for (id, f) in [(1, f64::NAN), (2, 0.0), (3, -f64::NAN), (4, -0.0), (5, 1.5)] {
+            txn.create_row("f", vec![Value::Integer(id), Value::Float(f)])?;
+        }
+        txn.commit()?;
+        let ids = rows("select distinct on (f) id from f;")?;
+        assert_eq!(
+            ids,
+            [
+                [Value::Integer(1)],
+                [Value::Integer(2)],
+                [Value::Integer(5)]
+            ]
+        );
+        assert_eq!(rows("select distinct f from f;")?.len(), 3);
+        assert_eq!(
+            rows("select f from f where id = 1 union select f from f where id = 3;")?.len(),
+            1
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_projection() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(&kv, "create table t (id int primary key, b int, c string);")?;
+        execute(&kv, "insert into t values (1, 10, 'a'), (2, 20, null);")?;
+        assert_eq!(
+            select(
+                &kv,
+                "select c, b > 15 as big, id from t where b in (10, 20);"
+            )?,
+            (
+                vec!["c".to_string(), "big".to_string(), "id".to_string()],
+                vec![
+                    vec![
+                        Value::String("a".to_string()),
+                        Value::Boolean(false),
+                        Value::Integer(1)
+                    ],
+                    vec![Value::Null, Value::Boolean(true), Value::Integer(2)],
+                ],
+            )
+        );
+        assert!(select(&kv, "select d from t;").is_err());
+
+        // unary minus applies to any number expression
+        let (columns, rows) = select(&kv, "select -b, -(b + id) * 2 from t where -b < -15;")?;
+        assert_eq!(columns, ["-b", "-(b + id) * 2"]);
+        assert_eq!(rows, vec![vec![Value::Integer(-20), Value::Integer(-44)]]);
+        let (_, rows) = select(
+            &kv,
+            "select -(b * 1.5), -interval '1 day' from t where id = 1;",
+        )?;
+        assert_eq!(rows[0][0], Value::Decimal("-15.0".parse()?));
+        assert_eq!(rows[0][1].to_string(), "-1 days");
+        assert!(select(&kv, "select -c from t;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_quoted_identifiers() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (
+                id int primary key, -- the key
+                \"Name\" string,
+                /* a keyword as column name */ \"select\" int
+            );",
+        )?;
+        execute(&kv, "insert into t values (1, 'it''s', 2);")?;
+        assert_eq!(
+            select(
+                &kv,
+                "select \"Name\", \"select\" > 1, \"select\" from t where \"Name\" = 'it''s';"
+            )?,
+            (
+                vec![
+                    "Name".to_string(),
+                    "\"select\" > 1".to_string(),
+                    "select".to_string()
+                ],
+                vec![vec![
+                    Value::String("it's".to_string()),
+                    Value::Boolean(true),
+                    Value::Integer(2)
+                ]],
+            )
+        );
+        // unquoted identifiers are folded to lower case
+        assert!(select(&kv, "select Name from t;").is_err());
+        assert!(select(&kv, "select ID from t;").is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_limit_offset() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(&kv, "create table t (id int primary key, b int);")?;
+        execute(&kv, "create index idx_b on t (b);")?;
+        let mut txn = kv.begin()?;
+        for i in 0..1000 {
+            txn.create_row("t", vec![Value::Integer(i), Value::Integer(i % 10)])?;
+        }
+        txn.commit()?;
+
+        let ids = |sql| select_ids(&kv, sql);
+        let ints = |v: Vec<i64>| v.into_iter().map(Value::Integer).collect::<Vec<_>>();
+        assert_eq!(
+            ids("select * from t limit 3 offset 500;")?,
+            ints(vec![500, 501, 502])
+        );
+        assert_eq!(
+            ids("select * from t where b = 7 limit 2 offset 1;")?,
+            ints(vec![17, 27])
+        );
+        assert_eq!(
+            ids("select id from t where id >= 998;")?,
+            ints(vec![998, 999])
+        );
+        assert_eq!(ids("select * from t limit 0;")?, ints(vec![]));
+        assert!(execute(&kv, "select * from t limit -1;").is_err());
+
+        // rows are only read as they are consumed, so an open scan sees
+        // its own snapshot while the table changes underneath it
+        let mut txn = kv.begin()?;
+        let rows = match Plan::build(Parser::new("select * from t;").parse()?, &mut txn)?
+            .execute(&mut txn)?
+        {
+            ResultSet::Select { mut rows, .. } => {
+                assert_eq!(
+                    rows.next().transpose()?.map(|r| r[0].clone()),
+                    Some(Value::Integer(0))
+                );
+                rows
+            }
+            rs => panic!("unexpected result set {:?}", rs),
+        };
+        execute(&kv, "delete from t where id >= 500;")?;
+        assert_eq!(rows.count(), 999);
+        txn.commit()?;
+        assert_eq!(ids("select * from t;")?.len(), 500);
+        Ok(())
+    }
+
+    #[test]
+    fn test_error_kinds() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        execute(
+            &kv,
+            "create table t (id int primary key, name string not null, email string);",
+        )?;
+        execute(&kv, "create unique index idx_email on t (email);")?;
+        execute(&kv, "insert into t values (1, 'a', 'a');")?;
+
+        let constraint = |sql| match execute(&kv, sql) {
+            Err(Error::Constraint { kind, name, .. }) => (kind, name),
+            r => panic!("unexpected result {:?}", r.map(|_| ())),
+        };
+        assert_eq!(
+            constraint("insert into t values (1, 'b', 'b');"),
+            (ConstraintKind::Unique, "t_pkey".to_string())
+        );
+        assert_eq!(
+            constraint("insert into t values (2, 'b', 'a');"),
+            (ConstraintKind::Unique, "idx_email".to_string())
+        );
+        assert_eq!(
+            constraint("insert into t values (2, null, 'b');"),
+            (ConstraintKind::NotNull, "t_name_not_null".to_string())
+        );
+        assert_eq!(
+            constraint("insert into t (id) values (2);"),
+            (ConstraintKind::NotNull, "t_name_not_null".to_string())
+        );
+
+        let sqlstate = |sql| execute(&kv, sql).unwrap_err().sqlstate();
+        assert_eq!(sqlstate("insert into t values (1, 'b', 'b');"), "23505");
+        assert_eq!(sqlstate("update t set name = null;"), "23502");
+        assert_eq!(sqlstate("insert into t values (2, 3, 'b');"), "22000");
+        assert_eq!(sqlstate("update t set id = id / 0;"), "22012");
+        assert_eq!(
+            sqlstate("update t set id = 9223372036854775807 + id;"),
+            "22003"
+        );
+        assert_eq!(sqlstate("update t set id = 1.5e300 * 1e300;"), "22003");
+        assert_eq!(sqlstate("update t set id = 'abc'::int;"), "22P02");
+        assert_eq!(sqlstate("update t set id = 1e300::int;"), "22003");
+        assert_eq!(sqlstate("select * from nope;"), "42704");
+        assert_eq!(
+            select(&kv, "select nope from t;").unwrap_err().sqlstate(),
+            "42704"
+        );
+        assert_eq!(sqlstate("create table t (id int primary key);"), "42710");
+        assert_eq!(sqlstate("select * from t where"), "42601");
+        Ok(())
+    }
+}
@@ -0,0 +1,442 @@
+use std::{ops::Bound, sync::Arc};
+
+use crate::error::{Error, Result};
+
+use super::{
+    executor::ResultSet,
+    functions::UserFunction,
+    parser::{ast::Statement, Parser},
+    plan::{Expression, Plan},
+    schema::{Index, Table},
+    types::{Row, Rows, Value},
+};
+
+pub mod kv;
+
+// SQL engine, hands out transactions
+pub trait Engine: Clone {
+    type Transaction: Transaction + Clone + 'static;
+
+    fn begin(&self) -> Result<Self::Transaction>;
+
+    fn session(&self) -> Session<Self> {
+        Session {
+            engine: self.clone(),
+            txn: None,
+            aborted: None,
+        }
+    }
+}
+
+// a client of the SQL engine, statements outside of BEGIN ... COMMIT
+// run in a transaction of their own
+pub struct Session<E: Engine> {
+    engine: E,
+    // explicit transaction opened by BEGIN
+    txn: Option<E::Transaction>,
+    // version of the explicit transaction a failed statement rolled back,
+    // statements are refused until COMMIT or ROLLBACK ends it
+    aborted: Option<u64>,
+}
+
+impl<E: Engine> Session<E> {
+    pub fn execute(&mut self, sql: &str) -> Result<ResultSet> {
+        let statement = Parser::new(sql).parse()?;
+        self.execute_statement(statement)
+    }
+
+    // runs the statements of a script in order and stops at the first
+    // failure, nothing runs if the script does not parse. A transaction
+    // begun by the script is ended by the failure, one begun before it is
+    // left aborted
+    pub fn execute_batch(&mut self, sql: &str) -> std::result::Result<Vec<ResultSet>, BatchError> {
+        let version = self.version();
+        let mut parser = Parser::new(sql);
+        let mut statements = Vec::new();
+        loop {
+            match parser.parse_next() {
+                Ok(Some(statement)) => statements.push(statement),
+                Ok(None) => break,
+                Err(error) => {
+                    return Err(BatchError {
+                        statement: statements.len() + 1,
+                        results: Vec::new(),
+                        error,
+                    })
+                }
+            }
+        }
+
+        let mut results = Vec::with_capacity(statements.len());
+        for (i, statement) in statements.into_iter().enumerate() {
+            // rows are read before the next statement runs, so that a
+            // failure is reported for the statement that caused it
+            let result = self
+                .execute_statement(statement)
+                .and_then(|result| match result {
+                    ResultSet::Select {
+                        columns,
+                        types,
+                        rows,
+                    } => {
+                        let rows = rows.collect::<Result<Vec<_>>>()?;
+                        Ok(ResultSet::Select {
+                            columns,
+                            types,
+                            rows: Box::new(rows.into_iter().map(Ok)),
+                        })
+                    }
+                    result => Ok(result),
+                });
+            match result {
+                Ok(result) => results.push(result),
+                Err(error) => {
+                    self.abort();
+                    if self.aborted != version {
+                        self.aborted = None;
+                    }
+                    return Err(BatchError {
+                        statement: i + 1,
+                        results,
+                        error,
+                    });
+                }
+            }
+        }
+        Ok(results)
+    }
+
+    pub fn execute_statement(&mut self, statement: Statement) -> Result<ResultSet> {
+        if let Some(version) = self.aborted {
+            return match statement {
+                // there is nothing left to commit
+                Statement::Commit | Statement::Rollback => {
+                    self.aborted = None;
+                    Ok(ResultSet::Rollback { version })
+                }
+                _ => Err(Error::Aborted(
+                    "[Session] Transaction is aborted, statements are ignored until ROLLBACK"
+                        .to_string(),
+                )),
+            };
+        }
+        match statement {
+            Statement::Begin if self.txn.is_some() => Err(Error::Transaction(
+                "[Session] Already in a transaction".to_string(),
+            )),
+            Statement::Begin => {
+                let txn = self.engine.begin()?;
+                let version = txn.version();
+                self.txn = Some(txn);
+                Ok(ResultSet::Begin { version })
+            }
+            Statement::Commit | Statement::Rollback if self.txn.is_none() => Err(
+                Error::Transaction("[Session] Not in a transaction".to_string()),
+            ),
+            Statement::Commit => {
+                let txn = self.txn.take().unwrap();
+                let version = txn.version();
+                txn.commit()?;
+                Ok(ResultSet::Commit { version })
+            }
+            Statement::Rollback => {
+                let txn = self.txn.take().unwrap();
+                let version = txn.version();
+                txn.rollback()?;
+                Ok(ResultSet::Rollback { version })
+            }
+            stmt => {
+                let explicit = self.txn.is_some();
+                let mut txn = match self.txn.take() {
+                    Some(txn) => txn,
+                    None => self.engine.begin()?,
+                };
+                match Plan::build(stmt, &mut txn).and_then(|plan| plan.execute(&mut txn)) {
+                    Ok(result) if explicit => {
+                        self.txn = Some(txn);
+                        Ok(result)
+                    }
+                    Ok(result) => {
+                        txn.commit()?;
+                        Ok(result)
+                    }
+                    // a failed statement may be half applied, the whole
+                    // transaction is rolled back
+                    Err(err) => {
+                        if explicit {
+                            self.aborted = Some(txn.version());
+                        }
+                        txn.rollback()?;
+                        Err(err)
+                    }
+                }
+            }
+        }
+    }
+
+    // version of the open explicit transaction, also when it is aborted
+    pub fn version(&self) -> Option<u64> {
+        self.txn.as_ref().map(|txn| txn.version()).or(self.aborted)
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.is_some()
+    }
+
+    // rolls back the explicit transaction and refuses statements until it
+    // is ended, e.g. when reading the rows of a select failed
+    pub fn abort(&mut self) {
+        if let Some(txn) = self.txn.take() {
+            self.aborted = Some(txn.version());
+            let _ = txn.rollback();
+        }
+    }
+}
+
+// failure of a batch, with the results of the statements that ran before it
+#[derive(Debug)]
+pub struct BatchError {
+    // position of the failed statement in the script, starting at 1
+    pub statement: usize,
+    pub results: Vec<ResultSet>,
+    pub error: Error,
+}
+
+impl std::fmt::Display for BatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "[Session] Statement {} failed: {}",
+            self.statement, self.error
+        )
+    }
+}
+
+impl std::error::Error for BatchError {}
+
+// a transaction left open, e.g. by a dropped connection, is not committed
+impl<E: Engine> Drop for Session<E> {
+    fn drop(&mut self) {
+        if let Some(txn) = self.txn.take() {
+            let _ = txn.rollback();
+        }
+    }
+}
+
+// SQL level operations of a transaction
+pub trait Transaction {
+    fn commit(&self) -> Result<()>;
+
+    fn rollback(&self) -> Result<()>;
+
+    fn version(&self) -> u64;
+
+    fn create_row(&mut self, table_name: &str, row: Row) -> Result<()>;
+
+    fn update_row(&mut self, table: &Table, id: &Value, row: Row) -> Result<()>;
+
+    fn delete_row(&mut self, table: &Table, id: &Value) -> Result<()>;
+
+    fn read_by_id(&self, table_name: &str, id: &Value) -> Result<Option<Row>>;
+
+    // primary keys of rows whose indexed column equals value
+    fn read_index(&self, table_name: &str, index_name: &str, value: &Value) -> Result<Vec<Value>>;
+
+    // rows whose indexed column is within range, in index order
+    fn scan_index_range(
+        &self,
+        table_name: &str,
+        index_name: &str,
+        range: (Bound<Value>, Bound<Value>),
+    ) -> Result<Rows>;
+
+    // rows whose primary key is within range, in primary key order
+    fn scan_range(&self, table_name: &str, range: (Bound<Value>, Bound<Value>)) -> Result<Rows>;
+
+    fn scan_table(&self, table_name: &str, filter: Option<Expression>) -> Result<Rows>;
+
+    fn create_table(&mut self, table: Table) -> Result<()>;
+
+    fn get_table(&self, table_name: &str) -> Result<Option<Table>>;
+
+    fn must_get_table(&self, table_name: &str) -> Result<Table> {
+        self.get_table(table_name)?
+            .ok_or_else(|| Error::NotFound(format!("[Engine] Table {} does not exist", table_name)))
+    }
+
+    fn create_index(&mut self, table_name: &str, index: Index) -> Result<()>;
+
+    // the user function of the name registered with the engine
+    fn get_function(&self, name: &str) -> Result<Option<Arc<UserFunction>>>;
+
+    // returns the name of the table the index belonged to
+    fn drop_index(&mut self, index_name: &str) -> Result<String>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{kv::KVEngine, Engine, Session};
+    use crate::{
+        error::Result,
+        sql::{executor::ResultSet, types::Value},
+        storage::{disk::DiskEngine, memory::MemoryEngine},
+    };
+
+    #[test]
+    fn test_session_reopen() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("sqldb");
+        {
+            let mut session = KVEngine::new(DiskEngine::new(path.clone())?)?.session();
+            session.execute("create table t (id int primary key, name string);")?;
+            session.execute("insert into t values (1, 'a'), (2, 'b');")?;
+            session.execute("update t set name = 'c' where id = 2;")?;
+            // a failed statement leaves nothing behind
+            assert!(session
+                .execute("insert into t values (3, 'x'), (1, 'y');")
+                .is_err());
+            // the file as a crash leaves it, with a transaction still open
+            session.execute("begin;")?;
+            session.execute("update t set name = 'd' where id = 1;")?;
+            std::fs::copy(&path, dir.path().join("crashed"))?;
+        }
+
+        for path in [path, dir.path().join("crashed")] {
+            let mut session = KVEngine::new(DiskEngine::new_compact(path)?)?.session();
+            let names = |session: &mut Session<_>| match session.execute("select name from t;")? {
+                ResultSet::Select { rows, .. } => rows.collect::<Result<Vec<_>>>(),
+                rs => panic!("unexpected result set {:?}", rs),
+            };
+            assert_eq!(
+                names(&mut session)?,
+                vec![
+                    vec![Value::String("a".to_string())],
+                    vec![Value::String("c".to_string())],
+                ]
+            );
+            // writes of the interrupted transaction no longer conflict
+            session.execute("update t set name = 'e' where id = 1;")?;
+            assert_eq!(
+                names(&mut session)?[0],
+                vec![Value::String("e".to_string())]
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_transaction() -> Result<()> {
+        let engine = KVEngine::new(MemoryEngine::new())?;
+        let mut session = engine.session();
+        let count = |session: &mut Session<_>| -> Result<usize> {
+            match session.execute("select * from t;")? {
+                ResultSet::Select { rows, .. } => Ok(rows.count()),
+                rs => panic!("unexpected result set {:?}", rs),
+            }
+        };
+        session.execute("create table t (id int primary key);")?;
+        assert!(session.execute("commit;").is_err());
+
+        session.execute("begin;")?;
+        assert!(session.version().is_some());
+        assert!(session.execute("begin;").is_err());
+        session.execute("insert into t values (1), (2);")?;
+        assert_eq!(count(&mut engine.session())?, 0);
+        session.execute("rollback;")?;
+        assert_eq!(count(&mut session)?, 0);
+
+        // a failed statement aborts the transaction it ran in, statements
+        // are refused until it is ended
+        session.execute("begin;")?;
+        session.execute("insert into t values (1);")?;
+        assert!(session.execute("insert into t values (2), (1);").is_err());
+        assert!(session.is_aborted());
+        let err = session.execute("insert into t values (3);").unwrap_err();
+        assert_eq!(err.sqlstate(), "25P02");
+        assert!(session.execute("begin;").is_err());
+        assert!(matches!(
+            session.execute("rollback;")?,
+            ResultSet::Rollback { .. }
+        ));
+        assert_eq!(session.version(), None);
+        assert_eq!(count(&mut session)?, 0);
+
+        // COMMIT of an aborted transaction rolls it back
+        session.execute("begin;")?;
+        session.execute("insert into t values (1);")?;
+        assert!(session.execute("insert into t values (1);").is_err());
+        assert!(matches!(
+            session.execute("commit;")?,
+            ResultSet::Rollback { .. }
+        ));
+        assert_eq!(count(&mut session)?, 0);
+
+        session.execute("begin;")?;
+        session.execute("insert into t values (1);")?;
+        session.execute("commit;")?;
+        assert_eq!(count(&mut engine.session())?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_session_batch() -> Result<()> {
+        let engine = KVEngine::new(MemoryEngine::new())?;
+        let mut session = engine.session();
+        let results = session
+            .execute_batch(
+                "create table t (id int primary key, name string);
+                insert into t values (1, 'a');
+                select * from t;",
+            )
+            .map_err(|err| err.error)?;
+        assert_eq!(results.len(), 3);
+        match &results[2] {
+            ResultSet::Select { columns, .. } => assert_eq!(columns.len(), 2),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        // a syntax error anywhere keeps the whole script from running
+        let err = session
+            .execute_batch("insert into t values (2, 'b'); selec * from t;")
+            .unwrap_err();
+        assert_eq!((err.statement, err.results.len()), (2, 0));
+        assert_eq!(
+            session
+                .execute_batch("select * from t;")
+                .map_err(|err| err.error)?
+                .len(),
+            1
+        );
+
+        // the transaction of the script is rolled back on failure
+        let err = session
+            .execute_batch(
+                "begin;
+                insert into t values (2, 'b');
+                insert into t values (1, 'c');
+                commit;",
+            )
+            .unwrap_err();
+        assert_eq!((err.statement, err.results.len()), (3, 2));
+        assert_eq!(session.version(), None);
+        match session.execute("select * from t;")? {
+            ResultSet::Select { rows, .. } => assert_eq!(rows.count(), 1),
+            rs => panic!("unexpected result set {:?}", rs),
+        }
+
+        // rows that fail to read fail their statement
+        session.execute("begin;")?;
+        let err = session
+            .execute_batch("insert into t values (3, 'c'); select missing from t;")
+            .unwrap_err();
+        assert_eq!(err.statement, 2);
+        assert!(session.is_aborted());
+        session.execute("rollback;")?;
+        assert_eq!(session.version(), None);
+        assert_eq!(
+            err.to_string(),
+            format!("[Session] Statement 2 failed: {}", err.error)
+        );
+        Ok(())
+    }
+}
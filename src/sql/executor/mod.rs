@@ -1,36 +1,134 @@
+use serde::{Deserialize, Serialize};
+
 use crate::error::Result;
-use super::{plan::Node, types::Row};
-use schema::CreateTable;
-use mutation::Insert;
-use query::Scan;
+use super::{engine::Transaction, plan::Node, types::{DataType, Rows}};
+use schema::{CreateIndex, CreateTable, DropIndex};
+use mutation::{Delete, Insert, Update};
+use query::{
+    Distinct, Explain, Filter, IndexLookup, IndexRangeScan, KeyLookup, Limit, Offset, Projection, RangeScan,
+    Scan, SetOperation, SubqueryScan,
+};
 
 mod schema;
 mod mutation;
 mod query;
 
-pub trait Executor {
-    fn execute(&self) -> Result<ResultSet>;
+pub trait Executor<T: Transaction> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet>;
 }
 
-impl dyn Executor {
-    pub fn build(node: Node) -> Box<dyn Executor> {
+impl<T: Transaction + 'static> dyn Executor<T> {
+    pub fn build(node: Node) -> Box<dyn Executor<T>> {
         match node {
             Node::CreateTable { schema } => CreateTable::new(schema),
+            Node::CreateIndex { table_name, index } => CreateIndex::new(table_name, index),
+            Node::DropIndex { name } => DropIndex::new(name),
             Node::Insert { table_name, columns, values } => Insert::new(table_name, columns, values),
-            Node::Scan { table_name } => Scan::new(table_name),
+            Node::Scan { table_name, filter } => Scan::new(table_name, filter),
+            Node::KeyLookup { table_name, keys, filter } => KeyLookup::new(table_name, keys, filter),
+            Node::IndexLookup { table_name, index_name, values, filter } => {
+                IndexLookup::new(table_name, index_name, values, filter)
+            }
+            Node::RangeScan { table_name, range, filter } => RangeScan::new(table_name, range, filter),
+            Node::IndexRangeScan { table_name, index_name, range, filter } => {
+                IndexRangeScan::new(table_name, index_name, range, filter)
+            }
+            Node::Update { table_name, source, columns } => {
+                Update::new(table_name, Self::build(*source), columns)
+            }
+            Node::Delete { table_name, source } => Delete::new(table_name, Self::build(*source)),
+            Node::Projection { source, exprs, types } => {
+                Projection::new(Self::build(*source), exprs, types)
+            }
+            Node::Limit { source, limit } => Limit::new(Self::build(*source), limit),
+            Node::Offset { source, offset } => Offset::new(Self::build(*source), offset),
+            Node::Distinct { source, on } => Distinct::new(Self::build(*source), on),
+            Node::Filter { source, predicate } => Filter::new(Self::build(*source), predicate),
+            Node::SubqueryScan { source, .. } => SubqueryScan::new(Self::build(*source)),
+            Node::SetOperation { operator, all, left, right, types } => {
+                SetOperation::new(operator, all, Self::build(*left), Self::build(*right), types)
+            }
+            Node::Explain { source } => Explain::new(*source),
         }
     }
 }
 
+// rows are not serialized, they are sent one at a time after the result set
+#[derive(Serialize, Deserialize)]
 pub enum ResultSet {
     CreateTable {
         create_table: String,
     },
+    CreateIndex {
+        index_name: String,
+    },
+    DropIndex {
+        index_name: String,
+    },
     Insert {
         count: usize,
     },
+    // rows are produced as they are consumed, types are those of the
+    // columns where the plan knows them
     Select {
         columns: Vec<String>,
-        rows: Vec<Row>,
+        types: Vec<Option<DataType>>,
+        #[serde(skip, default = "no_rows")]
+        rows: Rows,
+    },
+    Update {
+        count: usize,
+    },
+    Delete {
+        count: usize,
+    },
+    // the plan tree as indented text
+    Explain {
+        plan: String,
+    },
+    Begin {
+        version: u64,
     },
-}
\ No newline at end of file
+    Commit {
+        version: u64,
+    },
+    Rollback {
+        version: u64,
+    },
+}
+
+fn no_rows() -> Rows {
+    Box::new(std::iter::empty())
+}
+
+impl std::fmt::Debug for ResultSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResultSet::CreateTable { create_table } => {
+                f.debug_struct("CreateTable").field("create_table", create_table).finish()
+            }
+            ResultSet::CreateIndex { index_name } => {
+                f.debug_struct("CreateIndex").field("index_name", index_name).finish()
+            }
+            ResultSet::DropIndex { index_name } => {
+                f.debug_struct("DropIndex").field("index_name", index_name).finish()
+            }
+            ResultSet::Insert { count } => f.debug_struct("Insert").field("count", count).finish(),
+            ResultSet::Select { columns, types, .. } => f
+                .debug_struct("Select")
+                .field("columns", columns)
+                .field("types", types)
+                .finish_non_exhaustive(),
+            ResultSet::Update { count } => f.debug_struct("Update").field("count", count).finish(),
+            ResultSet::Delete { count } => f.debug_struct("Delete").field("count", count).finish(),
+            ResultSet::Explain { plan } => f.debug_struct("Explain").field("plan", plan).finish(),
+            ResultSet::Begin { version } => f.debug_struct("Begin").field("version", version).finish(),
+            ResultSet::Commit { version } => {
+                f.debug_struct("Commit").field("version", version).finish()
+            }
+            ResultSet::Rollback { version } => {
+                f.debug_struct("Rollback").field("version", version).finish()
+            }
+        }
+    }
+}
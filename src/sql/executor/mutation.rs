@@ -1,5 +1,11 @@
-use crate::sql::parser::ast::Expression;
-use super::Executor;
+use std::collections::BTreeMap;
+
+use crate::error::{Error, Result};
+use crate::sql::engine::Transaction;
+use crate::sql::plan::Expression;
+use crate::sql::schema::Table;
+use crate::sql::types::{Row, Value};
+use super::{Executor, ResultSet};
 pub struct Insert {
     table_name: String,
     columns: Vec<String>,
@@ -12,8 +18,141 @@ impl Insert {
     }
 }
 
-impl Executor for Insert {
-    fn execute(&self) -> crate::error::Result<super::ResultSet> {
-        todo!()
+// fill the missing trailing values with column defaults
+fn pad_row(table: &Table, row: &Row) -> Result<Row> {
+    let mut results = row.clone();
+    for column in table.columns.iter().skip(row.len()) {
+        match &column.default {
+            Some(default) => results.push(default.clone()),
+            None => {
+                return Err(column.null_violation(
+                    &table.name,
+                    format!("[Insert] No default value for column {}", column.name),
+                ))
+            }
+        }
+    }
+    Ok(results)
+}
+
+// map values to the given columns, others take their defaults
+fn make_row(table: &Table, columns: &[String], values: &Row) -> Result<Row> {
+    if columns.len() != values.len() {
+        return Err(Error::Value(format!(
+            "[Insert] Expect {} values, got {}",
+            columns.len(),
+            values.len()
+        )));
+    }
+
+    let mut inputs = BTreeMap::new();
+    for (col, value) in columns.iter().zip(values) {
+        table.get_col_index(col)?;
+        inputs.insert(col, value);
+    }
+
+    let mut results = Vec::with_capacity(table.columns.len());
+    for column in &table.columns {
+        if let Some(value) = inputs.get(&column.name) {
+            results.push((*value).clone());
+        } else if let Some(default) = &column.default {
+            results.push(default.clone());
+        } else {
+            return Err(column.null_violation(
+                &table.name,
+                format!("[Insert] No value given for column {}", column.name),
+            ));
+        }
+    }
+    Ok(results)
+}
+
+impl<T: Transaction> Executor<T> for Insert {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(&self.table_name)?;
+        let mut count = 0;
+        for exprs in self.values {
+            let values = exprs
+                .iter()
+                .map(|e| e.evaluate(&Vec::new()))
+                .collect::<Result<Vec<_>>>()?;
+            let row = if self.columns.is_empty() {
+                pad_row(&table, &values)?
+            } else {
+                make_row(&table, &self.columns, &values)?
+            };
+            txn.create_row(&table.name, row)?;
+            count += 1;
+        }
+        Ok(ResultSet::Insert { count })
+    }
+}
+
+pub struct Update<T: Transaction> {
+    table_name: String,
+    source: Box<dyn Executor<T>>,
+    columns: BTreeMap<String, Expression>,
+}
+
+impl<T: Transaction> Update<T> {
+    pub fn new(
+        table_name: String,
+        source: Box<dyn Executor<T>>,
+        columns: BTreeMap<String, Expression>,
+    ) -> Box<Self> {
+        Box::new(Self { table_name, source, columns })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Update<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let rows = match self.source.execute(txn)? {
+            ResultSet::Select { rows, .. } => rows,
+            _ => return Err(Error::Internal("[Update] Unexpected result set".into())),
+        };
+        // read all matched rows before writing, an updated row must not be
+        // found again by the scan, e.g. after its primary key is moved ahead
+        let rows = rows.collect::<Result<Vec<_>>>()?;
+        let table = txn.must_get_table(&self.table_name)?;
+        let mut count = 0;
+        for row in rows {
+            let id = table.get_primary_key(&row)?.clone();
+            let mut new_row = row.clone();
+            for (col, expr) in &self.columns {
+                new_row[table.get_col_index(col)?] = expr.evaluate(&row)?;
+            }
+            txn.update_row(&table, &id, new_row)?;
+            count += 1;
+        }
+        Ok(ResultSet::Update { count })
     }
-}
\ No newline at end of file
+}
+
+pub struct Delete<T: Transaction> {
+    table_name: String,
+    source: Box<dyn Executor<T>>,
+}
+
+impl<T: Transaction> Delete<T> {
+    pub fn new(table_name: String, source: Box<dyn Executor<T>>) -> Box<Self> {
+        Box::new(Self { table_name, source })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Delete<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let rows = match self.source.execute(txn)? {
+            ResultSet::Select { rows, .. } => rows,
+            _ => return Err(Error::Internal("[Delete] Unexpected result set".into())),
+        };
+        let rows = rows.collect::<Result<Vec<_>>>()?;
+        let table = txn.must_get_table(&self.table_name)?;
+        let mut count = 0;
+        for row in rows {
+            let id: &Value = table.get_primary_key(&row)?;
+            txn.delete_row(&table, id)?;
+            count += 1;
+        }
+        Ok(ResultSet::Delete { count })
+    }
+}
@@ -1,17 +1,441 @@
-use super::Executor;
+use std::collections::{HashMap, HashSet};
+use std::ops::Bound;
+
+use super::{Executor, ResultSet};
+use crate::error::{Error, Result};
+use crate::sql::engine::Transaction;
+use crate::sql::parser::ast::SetOperator;
+use crate::sql::plan::{self, Expression, Node};
+use crate::sql::types::{DataType, Row, Rows, Value};
 
 pub struct Scan {
     table_name: String,
+    filter: Option<Expression>,
 }
 
 impl Scan {
-    pub fn new(table_name: String) -> Box<Self> {
-        Box::new(Self { table_name })
+    pub fn new(table_name: String, filter: Option<Expression>) -> Box<Self> {
+        Box::new(Self { table_name, filter })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Scan {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(&self.table_name)?;
+        let rows = txn.scan_table(&self.table_name, self.filter)?;
+        Ok(ResultSet::Select { columns: table.column_names(), types: table.column_types(), rows })
+    }
+}
+
+pub struct KeyLookup {
+    table_name: String,
+    keys: Vec<Value>,
+    filter: Option<Expression>,
+}
+
+impl KeyLookup {
+    pub fn new(table_name: String, keys: Vec<Value>, filter: Option<Expression>) -> Box<Self> {
+        Box::new(Self { table_name, keys, filter })
+    }
+}
+
+impl<T: Transaction> Executor<T> for KeyLookup {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(&self.table_name)?;
+        // every key is a range of its own, read when reached
+        let mut rows: Rows = Box::new(std::iter::empty());
+        for key in self.keys {
+            let range = (Bound::Included(key.clone()), Bound::Included(key));
+            rows = Box::new(rows.chain(txn.scan_range(&self.table_name, range)?));
+        }
+        let rows = filter_rows(rows, self.filter);
+        Ok(ResultSet::Select { columns: table.column_names(), types: table.column_types(), rows })
+    }
+}
+
+pub struct IndexLookup {
+    table_name: String,
+    index_name: String,
+    values: Vec<Value>,
+    filter: Option<Expression>,
+}
+
+impl IndexLookup {
+    pub fn new(
+        table_name: String,
+        index_name: String,
+        values: Vec<Value>,
+        filter: Option<Expression>,
+    ) -> Box<Self> {
+        Box::new(Self { table_name, index_name, values, filter })
+    }
+}
+
+impl<T: Transaction> Executor<T> for IndexLookup {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(&self.table_name)?;
+        let mut rows: Rows = Box::new(std::iter::empty());
+        for value in self.values {
+            let range = (Bound::Included(value.clone()), Bound::Included(value));
+            rows = Box::new(rows.chain(txn.scan_index_range(
+                &self.table_name,
+                &self.index_name,
+                range,
+            )?));
+        }
+        let rows = filter_rows(rows, self.filter);
+        Ok(ResultSet::Select { columns: table.column_names(), types: table.column_types(), rows })
+    }
+}
+
+pub struct RangeScan {
+    table_name: String,
+    range: (Bound<Value>, Bound<Value>),
+    filter: Option<Expression>,
+}
+
+impl RangeScan {
+    pub fn new(
+        table_name: String,
+        range: (Bound<Value>, Bound<Value>),
+        filter: Option<Expression>,
+    ) -> Box<Self> {
+        Box::new(Self { table_name, range, filter })
+    }
+}
+
+impl<T: Transaction> Executor<T> for RangeScan {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(&self.table_name)?;
+        let rows = txn.scan_range(&self.table_name, self.range)?;
+        let rows = filter_rows(rows, self.filter);
+        Ok(ResultSet::Select { columns: table.column_names(), types: table.column_types(), rows })
+    }
+}
+
+pub struct IndexRangeScan {
+    table_name: String,
+    index_name: String,
+    range: (Bound<Value>, Bound<Value>),
+    filter: Option<Expression>,
+}
+
+impl IndexRangeScan {
+    pub fn new(
+        table_name: String,
+        index_name: String,
+        range: (Bound<Value>, Bound<Value>),
+        filter: Option<Expression>,
+    ) -> Box<Self> {
+        Box::new(Self { table_name, index_name, range, filter })
+    }
+}
+
+impl<T: Transaction> Executor<T> for IndexRangeScan {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table = txn.must_get_table(&self.table_name)?;
+        let rows = txn.scan_index_range(&self.table_name, &self.index_name, self.range)?;
+        let rows = filter_rows(rows, self.filter);
+        Ok(ResultSet::Select { columns: table.column_names(), types: table.column_types(), rows })
+    }
+}
+
+// the filter is checked again since lookups only match a part of it
+fn filter_rows(rows: Rows, filter: Option<Expression>) -> Rows {
+    let Some(filter) = filter else {
+        return rows;
+    };
+    Box::new(rows.filter_map(move |row| {
+        let row = match row {
+            Ok(row) => row,
+            Err(err) => return Some(Err(err)),
+        };
+        match filter.evaluate_predicate(&row) {
+            Ok(true) => Some(Ok(row)),
+            Ok(false) => None,
+            Err(err) => Some(Err(err)),
+        }
+    }))
+}
+
+pub struct Projection<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    exprs: Vec<(Expression, Option<String>)>,
+    types: Vec<Option<DataType>>,
+}
+
+impl<T: Transaction> Projection<T> {
+    pub fn new(
+        source: Box<dyn Executor<T>>,
+        exprs: Vec<(Expression, Option<String>)>,
+        types: Vec<Option<DataType>>,
+    ) -> Box<Self> {
+        Box::new(Self { source, exprs, types })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Projection<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        match self.source.execute(txn)? {
+            ResultSet::Select { rows, .. } => {
+                let columns =
+                    self.exprs.iter().map(|(expr, alias)| plan::column_name(expr, alias)).collect();
+                let exprs = self.exprs;
+                let rows = rows.map(move |row| {
+                    let row = row?;
+                    exprs.iter().map(|(expr, _)| expr.evaluate(&row)).collect()
+                });
+                Ok(ResultSet::Select {
+                    columns,
+                    types: self.types,
+                    rows: Box::new(rows),
+                })
+            }
+            _ => Err(Error::Internal("[Projection] Unexpected result set".into())),
+        }
+    }
+}
+
+pub struct Limit<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    limit: usize,
+}
+
+impl<T: Transaction> Limit<T> {
+    pub fn new(source: Box<dyn Executor<T>>, limit: usize) -> Box<Self> {
+        Box::new(Self { source, limit })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Limit<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        match self.source.execute(txn)? {
+            // rows past the limit are never read
+            ResultSet::Select { columns, types, rows } => {
+                Ok(ResultSet::Select { columns, types, rows: Box::new(rows.take(self.limit)) })
+            }
+            _ => Err(Error::Internal("[Limit] Unexpected result set".into())),
+        }
+    }
+}
+
+pub struct Offset<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    offset: usize,
+}
+
+impl<T: Transaction> Offset<T> {
+    pub fn new(source: Box<dyn Executor<T>>, offset: usize) -> Box<Self> {
+        Box::new(Self { source, offset })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Offset<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        match self.source.execute(txn)? {
+            // errors are passed on rather than skipped
+            ResultSet::Select { columns, types, rows } => {
+                let offset = self.offset;
+                let rows = rows
+                    .enumerate()
+                    .filter_map(move |(i, row)| (i >= offset || row.is_err()).then_some(row));
+                Ok(ResultSet::Select { columns, types, rows: Box::new(rows) })
+            }
+            _ => Err(Error::Internal("[Offset] Unexpected result set".into())),
+        }
+    }
+}
+
+pub struct Distinct<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    on: Vec<Expression>,
+}
+
+impl<T: Transaction> Distinct<T> {
+    pub fn new(source: Box<dyn Executor<T>>, on: Vec<Expression>) -> Box<Self> {
+        Box::new(Self { source, on })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Distinct<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        match self.source.execute(txn)? {
+            // keys are hashed as the rows are read, NULLs are equal here
+            ResultSet::Select { columns, types, rows } => {
+                let on = self.on;
+                let mut seen = HashSet::new();
+                let rows = rows.filter_map(move |row| {
+                    let row = match row {
+                        Ok(row) => row,
+                        Err(err) => return Some(Err(err)),
+                    };
+                    let key = match on.is_empty() {
+                        true => Ok(row.clone()),
+                        false => on.iter().map(|e| e.evaluate(&row)).collect(),
+                    };
+                    match key {
+                        Ok(key) => seen.insert(key).then_some(Ok(row)),
+                        Err(err) => Some(Err(err)),
+                    }
+                });
+                Ok(ResultSet::Select { columns, types, rows: Box::new(rows) })
+            }
+            _ => Err(Error::Internal("[Distinct] Unexpected result set".into())),
+        }
+    }
+}
+
+pub struct Filter<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+    predicate: Expression,
+}
+
+impl<T: Transaction> Filter<T> {
+    pub fn new(source: Box<dyn Executor<T>>, predicate: Expression) -> Box<Self> {
+        Box::new(Self { source, predicate })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Filter<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        match self.source.execute(txn)? {
+            ResultSet::Select { columns, types, rows } => Ok(ResultSet::Select {
+                rows: filter_rows(rows, Some(self.predicate)),
+                columns,
+                types,
+            }),
+            _ => Err(Error::Internal("[Filter] Unexpected result set".into())),
+        }
+    }
+}
+
+// the rows of a derived table are those of its query
+pub struct SubqueryScan<T: Transaction> {
+    source: Box<dyn Executor<T>>,
+}
+
+impl<T: Transaction> SubqueryScan<T> {
+    pub fn new(source: Box<dyn Executor<T>>) -> Box<Self> {
+        Box::new(Self { source })
+    }
+}
+
+impl<T: Transaction> Executor<T> for SubqueryScan<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        match self.source.execute(txn)? {
+            result @ ResultSet::Select { .. } => Ok(result),
+            _ => Err(Error::Internal("[Subquery Scan] Unexpected result set".into())),
+        }
     }
 }
 
-impl Executor for Scan {
-    fn execute(&self) -> crate::error::Result<super::ResultSet> {
-        todo!()
+pub struct SetOperation<T: Transaction> {
+    operator: SetOperator,
+    all: bool,
+    left: Box<dyn Executor<T>>,
+    right: Box<dyn Executor<T>>,
+    types: Vec<Option<DataType>>,
+}
+
+impl<T: Transaction> SetOperation<T> {
+    pub fn new(
+        operator: SetOperator,
+        all: bool,
+        left: Box<dyn Executor<T>>,
+        right: Box<dyn Executor<T>>,
+        types: Vec<Option<DataType>>,
+    ) -> Box<Self> {
+        Box::new(Self { operator, all, left, right, types })
     }
-}
\ No newline at end of file
+}
+
+impl<T: Transaction> Executor<T> for SetOperation<T> {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let (columns, left_types, left) = match self.left.execute(txn)? {
+            ResultSet::Select { columns, types, rows } => (columns, types, rows),
+            _ => return Err(Error::Internal("[Set Operation] Unexpected result set".into())),
+        };
+        let right = match self.right.execute(txn)? {
+            ResultSet::Select { rows, .. } => rows,
+            _ => return Err(Error::Internal("[Set Operation] Unexpected result set".into())),
+        };
+        let (operator, all) = (self.operator, self.all);
+        // the types of the left query unless they had to be matched
+        let columns_types = match self.types.is_empty() {
+            true => left_types,
+            false => self.types.clone(),
+        };
+        let types = self.types.clone();
+        let left = left.map(move |row| coerce_row(row?, &types));
+        let types = self.types;
+        let right = right.map(move |row| coerce_row(row?, &types));
+
+        // duplicates are found by hashing the rows, NULLs are equal here
+        let mut seen = HashSet::new();
+        let rows: Rows = match operator {
+            SetOperator::Union if all => Box::new(left.chain(right)),
+            SetOperator::Union => Box::new(left.chain(right).filter(move |row| match row {
+                Ok(row) => seen.insert(row.clone()),
+                Err(_) => true,
+            })),
+            // the right rows are counted first, with ALL a left row is
+            // matched by as many right rows as there are
+            operator => {
+                let mut counts = HashMap::new();
+                for row in right {
+                    *counts.entry(row?).or_insert(0usize) += 1;
+                }
+                Box::new(left.filter(move |row| {
+                    let Ok(row) = row else {
+                        return true;
+                    };
+                    let found = match counts.get_mut(row) {
+                        Some(count) if *count > 0 => {
+                            if all {
+                                *count -= 1;
+                            }
+                            true
+                        }
+                        _ => false,
+                    };
+                    let keep = match operator {
+                        SetOperator::Intersect => found,
+                        _ => !found,
+                    };
+                    keep && (all || seen.insert(row.clone()))
+                }))
+            }
+        };
+        Ok(ResultSet::Select { columns, types: columns_types, rows })
+    }
+}
+
+// values converted to the column types of a set operation
+fn coerce_row(row: Row, types: &[Option<DataType>]) -> Result<Row> {
+    if types.is_empty() {
+        return Ok(row);
+    }
+    row.into_iter()
+        .zip(types)
+        .map(|(value, datatype)| match datatype {
+            Some(datatype) => value.coerce(datatype),
+            None => Ok(value),
+        })
+        .collect()
+}
+
+pub struct Explain {
+    source: Node,
+}
+
+impl Explain {
+    pub fn new(source: Node) -> Box<Self> {
+        Box::new(Self { source })
+    }
+}
+
+impl<T: Transaction> Executor<T> for Explain {
+    fn execute(self: Box<Self>, _txn: &mut T) -> Result<ResultSet> {
+        Ok(ResultSet::Explain { plan: self.source.to_string() })
+    }
+}
@@ -1,6 +1,7 @@
-use super::Executor;
+use super::{Executor, ResultSet};
 use crate::error::Result;
-use crate::sql::schema::Table;
+use crate::sql::engine::Transaction;
+use crate::sql::schema::{Index, Table};
 
 
 pub struct CreateTable {
@@ -13,8 +14,46 @@ impl CreateTable {
     }
 }
 
-impl Executor for CreateTable {
-    fn execute(&self) -> Result<super::ResultSet> {
-        todo!()
+impl<T: Transaction> Executor<T> for CreateTable {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let table_name = self.schema.name.clone();
+        txn.create_table(self.schema)?;
+        Ok(ResultSet::CreateTable { create_table: table_name })
     }
-}
\ No newline at end of file
+}
+
+pub struct CreateIndex {
+    table_name: String,
+    index: Index,
+}
+
+impl CreateIndex {
+    pub fn new(table_name: String, index: Index) -> Box<Self> {
+        Box::new(Self { table_name, index })
+    }
+}
+
+impl<T: Transaction> Executor<T> for CreateIndex {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        let index_name = self.index.name.clone();
+        txn.create_index(&self.table_name, self.index)?;
+        Ok(ResultSet::CreateIndex { index_name })
+    }
+}
+
+pub struct DropIndex {
+    name: String,
+}
+
+impl DropIndex {
+    pub fn new(name: String) -> Box<Self> {
+        Box::new(Self { name })
+    }
+}
+
+impl<T: Transaction> Executor<T> for DropIndex {
+    fn execute(self: Box<Self>, txn: &mut T) -> Result<ResultSet> {
+        txn.drop_index(&self.name)?;
+        Ok(ResultSet::DropIndex { index_name: self.name })
+    }
+}
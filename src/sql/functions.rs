@@ -0,0 +1,877 @@
+use std::{
+    collections::HashMap,
+    fmt::Display,
+    ops::Range,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    error::{Error, Result},
+    sql::{
+        parser::ast::format_literal,
+        plan::expression::compare_values,
+        types::{DataType, Decimal, Json, Value},
+    },
+};
+
+// the kinds of argument a function takes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Param {
+    Any,
+    String,
+    // strings and blobs
+    Text,
+    // integers, floats and decimals
+    Number,
+    Integer,
+    // JSON values and strings of JSON text
+    Json,
+}
+
+impl Param {
+    pub fn accepts(&self, datatype: &DataType) -> bool {
+        match self {
+            Param::Any => true,
+            Param::String => *datatype == DataType::String,
+            Param::Text => matches!(datatype, DataType::String | DataType::Blob),
+            Param::Number => is_number(datatype),
+            Param::Integer => *datatype == DataType::Integer,
+            Param::Json => matches!(datatype, DataType::Json | DataType::String),
+        }
+    }
+}
+
+impl Display for Param {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Param::Any => "any value",
+            Param::String => "STRING",
+            Param::Text => "STRING or BLOB",
+            Param::Number => "a number",
+            Param::Integer => "INTEGER",
+            Param::Json => "JSON",
+        })
+    }
+}
+
+// the type of the result of a function
+#[derive(Debug, Clone, PartialEq)]
+pub enum Returns {
+    Type(DataType),
+    // the type of the first argument
+    First,
+    // the type all the arguments convert to implicitly, the result is
+    // converted to it
+    Common,
+    // FLOAT if any argument is a float, else DECIMAL if any is a decimal,
+    // else INTEGER
+    Number,
+    // known only once evaluated
+    Unknown,
+}
+
+// a built-in scalar function
+pub struct Function {
+    pub name: &'static str,
+    // the kinds of the arguments, the last one repeats for variadic functions
+    pub params: &'static [Param],
+    pub min_args: usize,
+    // none for variadic functions
+    pub max_args: Option<usize>,
+    pub returns: Returns,
+    // NULL in any argument gives NULL without calling the function
+    pub strict: bool,
+    eval: fn(&[Value]) -> Result<Value>,
+}
+
+const STRING: Returns = Returns::Type(DataType::String);
+const INTEGER: Returns = Returns::Type(DataType::Integer);
+const FLOAT: Returns = Returns::Type(DataType::Float);
+
+static FUNCTIONS: [Function; 19] = [
+    // strings
+    Function {
+        name: "upper",
+        params: &[Param::String],
+        min_args: 1,
+        max_args: Some(1),
+        returns: STRING,
+        strict: true,
+        eval: |args| Ok(Value::String(string(&args[0])?.to_uppercase())),
+    },
+    Function {
+        name: "lower",
+        params: &[Param::String],
+        min_args: 1,
+        max_args: Some(1),
+        returns: STRING,
+        strict: true,
+        eval: |args| Ok(Value::String(string(&args[0])?.to_lowercase())),
+    },
+    // characters of strings and bytes of blobs
+    Function {
+        name: "length",
+        params: &[Param::Text],
+        min_args: 1,
+        max_args: Some(1),
+        returns: INTEGER,
+        strict: true,
+        eval: length,
+    },
+    Function {
+        name: "substr",
+        params: &[Param::Text, Param::Integer, Param::Integer],
+        min_args: 2,
+        max_args: Some(3),
+        returns: Returns::First,
+        strict: true,
+        eval: substr,
+    },
+    // trim(s) removes spaces from both ends, trim(s, chars) any of chars
+    Function {
+        name: "trim",
+        params: &[Param::String, Param::String],
+        min_args: 1,
+        max_args: Some(2),
+        returns: STRING,
+        strict: true,
+        eval: |args| {
+            let chars = match args.get(1) {
+                Some(chars) => string(chars)?,
+                None => " ",
+            };
+            Ok(Value::String(
+                string(&args[0])?
+                    .trim_matches(|c| chars.contains(c))
+                    .to_string(),
+            ))
+        },
+    },
+    Function {
+        name: "replace",
+        params: &[Param::String, Param::String, Param::String],
+        min_args: 3,
+        max_args: Some(3),
+        returns: STRING,
+        strict: true,
+        eval: |args| {
+            let (s, from, to) = (string(&args[0])?, string(&args[1])?, string(&args[2])?);
+            Ok(Value::String(match from.is_empty() {
+                true => s.to_string(),
+                false => s.replace(from, to),
+            }))
+        },
+    },
+    // the text of all arguments, NULLs are skipped
+    Function {
+        name: "concat",
+        params: &[Param::Any],
+        min_args: 1,
+        max_args: None,
+        returns: STRING,
+        strict: false,
+        eval: |args| {
+            let mut text = String::new();
+            for arg in args {
+                if let Value::String(s) = arg.clone().cast(&DataType::String)? {
+                    text.push_str(&s);
+                }
+            }
+            Ok(Value::String(text))
+        },
+    },
+    // numbers
+    Function {
+        name: "abs",
+        params: &[Param::Number],
+        min_args: 1,
+        max_args: Some(1),
+        returns: Returns::First,
+        strict: true,
+        eval: |args| {
+            Ok(match &args[0] {
+                Value::Integer(i) => Value::Integer(i.checked_abs().ok_or_else(|| {
+                    Error::OutOfRange("[Value] Integer out of range".to_string())
+                })?),
+                Value::Float(f) => Value::Float(f.abs()),
+                Value::Decimal(d) => Value::Decimal(d.abs()),
+                v => return Err(unexpected(v)),
+            })
+        },
+    },
+    // half away from zero, to places digits after the point, a negative
+    // number of places rounds to tens, hundreds and so on
+    Function {
+        name: "round",
+        params: &[Param::Number, Param::Integer],
+        min_args: 1,
+        max_args: Some(2),
+        returns: Returns::First,
+        strict: true,
+        eval: round,
+    },
+    Function {
+        name: "floor",
+        params: &[Param::Number],
+        min_args: 1,
+        max_args: Some(1),
+        returns: Returns::First,
+        strict: true,
+        eval: |args| {
+            Ok(match &args[0] {
+                Value::Float(f) => Value::Float(f.floor()),
+                Value::Decimal(d) => Value::Decimal(d.floor()),
+                v => v.clone(),
+            })
+        },
+    },
+    Function {
+        name: "ceil",
+        params: &[Param::Number],
+        min_args: 1,
+        max_args: Some(1),
+        returns: Returns::First,
+        strict: true,
+        eval: |args| {
+            Ok(match &args[0] {
+                Value::Float(f) => Value::Float(f.ceil()),
+                Value::Decimal(d) => Value::Decimal(d.ceil()),
+                v => v.clone(),
+            })
+        },
+    },
+    Function {
+        name: "sqrt",
+        params: &[Param::Number],
+        min_args: 1,
+        max_args: Some(1),
+        returns: FLOAT,
+        strict: true,
+        eval: |args| match number(&args[0])? {
+            n if n < 0.0 => Err(Error::Value(format!(
+                "[Function] Cannot take the square root of {}",
+                args[0]
+            ))),
+            n => Ok(Value::Float(n.sqrt())),
+        },
+    },
+    Function {
+        name: "pow",
+        params: &[Param::Number, Param::Number],
+        min_args: 2,
+        max_args: Some(2),
+        returns: FLOAT,
+        strict: true,
+        eval: |args| match number(&args[0])?.powf(number(&args[1])?) {
+            n if n.is_finite() => Ok(Value::Float(n)),
+            _ => Err(Error::OutOfRange(format!(
+                "[Function] pow({}, {}) is out of range",
+                args[0], args[1]
+            ))),
+        },
+    },
+    // the remainder has the sign of the dividend
+    Function {
+        name: "mod",
+        params: &[Param::Number, Param::Number],
+        min_args: 2,
+        max_args: Some(2),
+        returns: Returns::Number,
+        strict: true,
+        eval: |args| args[0].checked_rem(&args[1]),
+    },
+    // NULL handling
+    Function {
+        name: "coalesce",
+        params: &[Param::Any],
+        min_args: 1,
+        max_args: None,
+        returns: Returns::Common,
+        strict: false,
+        eval: coalesce,
+    },
+    Function {
+        name: "ifnull",
+        params: &[Param::Any, Param::Any],
+        min_args: 2,
+        max_args: Some(2),
+        returns: Returns::Common,
+        strict: false,
+        eval: coalesce,
+    },
+    // NULL if both arguments are equal, the first otherwise
+    Function {
+        name: "nullif",
+        params: &[Param::Any, Param::Any],
+        min_args: 2,
+        max_args: Some(2),
+        returns: Returns::Common,
+        strict: false,
+        eval: |args| {
+            Ok(match (&args[0], &args[1]) {
+                (Value::Null, _) | (_, Value::Null) => args[0].clone(),
+                (a, b) if compare_values(a, b)?.is_eq() => Value::Null,
+                (a, _) => a.clone(),
+            })
+        },
+    },
+    // JSON, scalars are extracted as SQL values and arrays and objects as
+    // JSON, like SQLite
+    Function {
+        name: "json_extract",
+        params: &[Param::Json, Param::String],
+        min_args: 2,
+        max_args: Some(2),
+        returns: Returns::Unknown,
+        strict: true,
+        eval: |args| {
+            Ok(match as_json(&args[0])?.path(string(&args[1])?)? {
+                Some(json) => json
+                    .as_scalar()
+                    .unwrap_or_else(|| Value::Json(json.clone())),
+                None => Value::Null,
+            })
+        },
+    },
+    Function {
+        name: "json_array_length",
+        params: &[Param::Json],
+        min_args: 1,
+        max_args: Some(1),
+        returns: INTEGER,
+        strict: true,
+        eval: |args| match as_json(&args[0])? {
+            Json::Array(elements) => Ok(Value::Integer(elements.len() as i64)),
+            json => Err(Error::Value(format!(
+                "[Function] Cannot get array length of {}",
+                json
+            ))),
+        },
+    },
+];
+
+// the built-in function of the name, names are lowercase
+pub fn get(name: &str) -> Option<&'static Function> {
+    FUNCTIONS.iter().find(|f| f.name == name)
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Function")
+            .field("name", &self.name)
+            .finish()
+    }
+}
+
+// built-in functions are told apart by their name
+impl PartialEq for Function {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl Function {
+    // check a call before it is evaluated, args are the types of the
+    // arguments where known, returns the type of the result if known
+    pub fn check(&self, args: &[Option<DataType>]) -> Result<Option<DataType>> {
+        if args.len() < self.min_args || self.max_args.is_some_and(|max| args.len() > max) {
+            let (expect, count) = match self.max_args {
+                Some(max) if max == self.min_args => (max.to_string(), max),
+                Some(max) => (format!("{} to {}", self.min_args, max), max),
+                None => (format!("at least {}", self.min_args), self.min_args),
+            };
+            return Err(Error::Value(format!(
+                "[Function] {} expects {} {}, got {}",
+                self.name,
+                expect,
+                arguments(count),
+                args.len()
+            )));
+        }
+        for (i, arg) in args.iter().enumerate() {
+            if let Some(datatype) = arg {
+                self.check_arg(i, datatype)?;
+            }
+        }
+        Ok(match &self.returns {
+            Returns::Type(datatype) => Some(datatype.clone()),
+            Returns::First => args.iter().flatten().next().cloned(),
+            Returns::Common => self.common_type(args)?,
+            Returns::Number => number_type(args.iter().map(|a| a.as_ref())),
+            Returns::Unknown => None,
+        })
+    }
+
+    pub fn evaluate(&self, args: &[Value]) -> Result<Value> {
+        if self.strict && args.contains(&Value::Null) {
+            return Ok(Value::Null);
+        }
+        for (i, arg) in args.iter().enumerate() {
+            if let Some(datatype) = arg.datatype() {
+                self.check_arg(i, &datatype)?;
+            }
+        }
+        (self.eval)(args)
+    }
+
+    // the type of the arguments where known, numbers convert to the type of
+    // their arithmetic, other types as they do implicitly, except that a
+    // STRING value does not convert to another type
+    fn common_type(&self, args: &[Option<DataType>]) -> Result<Option<DataType>> {
+        let mut common: Option<DataType> = None;
+        for datatype in args.iter().flatten() {
+            let Some(c) = common else {
+                common = Some(datatype.clone());
+                continue;
+            };
+            let string = |d: &DataType| *d == DataType::String;
+            common = Some(match number_type([Some(&c), Some(datatype)].into_iter()) {
+                Some(number) => number,
+                None if c == *datatype => c,
+                None if !string(datatype) && datatype.coerces_to(&c) => c,
+                None if !string(&c) && c.coerces_to(datatype) => datatype.clone(),
+                None => {
+                    return Err(Error::Value(format!(
+                        "[Function] {} arguments of types {} and {} cannot be matched",
+                        self.name, c, datatype
+                    )))
+                }
+            });
+        }
+        Ok(common)
+    }
+
+    fn check_arg(&self, i: usize, datatype: &DataType) -> Result<()> {
+        let param = self.params[i.min(self.params.len() - 1)];
+        match param.accepts(datatype) {
+            true => Ok(()),
+            false => Err(Error::Value(format!(
+                "[Function] {} expects {} for argument {}, got {}",
+                self.name,
+                param,
+                i + 1,
+                datatype
+            ))),
+        }
+    }
+}
+
+// a scalar function registered by the application, called like the built-in
+// functions, e.g. Database::register_function
+pub struct UserFunction {
+    pub name: String,
+    pub arity: usize,
+    pub returns: DataType,
+    eval: Box<UserEval>,
+}
+
+type UserEval = dyn Fn(&[Value]) -> Result<Value> + Send + Sync;
+
+impl UserFunction {
+    pub fn new<F>(name: &str, arity: usize, returns: DataType, eval: F) -> Self
+    where
+        F: Fn(&[Value]) -> Result<Value> + Send + Sync + 'static,
+    {
+        Self {
+            name: name.to_lowercase(),
+            arity,
+            returns,
+            eval: Box::new(eval),
+        }
+    }
+
+    // arguments of any type are passed on, the function checks them itself
+    pub fn check(&self, args: &[Option<DataType>]) -> Result<Option<DataType>> {
+        if args.len() != self.arity {
+            return Err(Error::Value(format!(
+                "[Function] {} expects {} {}, got {}",
+                self.name,
+                self.arity,
+                arguments(self.arity),
+                args.len()
+            )));
+        }
+        Ok(Some(self.returns.clone()))
+    }
+
+    // the result is converted to the return type, NULL is always allowed
+    pub fn evaluate(&self, args: &[Value]) -> Result<Value> {
+        let value = (self.eval)(args)?;
+        match value.datatype() {
+            Some(datatype) if !datatype.coerces_to(&self.returns) => Err(Error::Value(format!(
+                "[Function] {} returns {}, got {}",
+                self.name, self.returns, datatype
+            ))),
+            _ => value.coerce(&self.returns),
+        }
+    }
+}
+
+impl std::fmt::Debug for UserFunction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("UserFunction")
+            .field("name", &self.name)
+            .field("arity", &self.arity)
+            .field("returns", &self.returns)
+            .finish()
+    }
+}
+
+// functions are told apart by their signature, closures cannot be compared
+impl PartialEq for UserFunction {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name && self.arity == other.arity && self.returns == other.returns
+    }
+}
+
+// the user functions of a database, shared by its handles and transactions
+#[derive(Debug, Clone, Default)]
+pub struct UserFunctions(Arc<RwLock<HashMap<String, Arc<UserFunction>>>>);
+
+impl UserFunctions {
+    // names of built-in functions are taken, a user function of the same
+    // name is replaced
+    pub fn register(&self, function: UserFunction) -> Result<()> {
+        if get(&function.name).is_some() {
+            return Err(Error::Value(format!(
+                "[Function] Cannot replace built-in function {}",
+                function.name
+            )));
+        }
+        self.0
+            .write()?
+            .insert(function.name.clone(), Arc::new(function));
+        Ok(())
+    }
+
+    pub fn get(&self, name: &str) -> Result<Option<Arc<UserFunction>>> {
+        Ok(self.0.read()?.get(name).cloned())
+    }
+}
+
+// the noun for a number of arguments
+fn arguments(count: usize) -> &'static str {
+    match count {
+        1 => "argument",
+        _ => "arguments",
+    }
+}
+
+pub fn is_number(datatype: &DataType) -> bool {
+    matches!(
+        datatype,
+        DataType::Integer | DataType::Float | DataType::Decimal
+    )
+}
+
+// the type of arithmetic on numbers of the types, none if any is unknown
+// or not a number
+pub fn number_type<'a>(types: impl Iterator<Item = Option<&'a DataType>>) -> Option<DataType> {
+    let mut result = DataType::Integer;
+    for datatype in types {
+        match datatype? {
+            DataType::Float => result = DataType::Float,
+            DataType::Decimal if result == DataType::Integer => result = DataType::Decimal,
+            datatype if is_number(datatype) => {}
+            _ => return None,
+        }
+    }
+    Some(result)
+}
+
+// JSON values, and strings holding JSON text
+pub fn as_json(value: &Value) -> Result<Json> {
+    match value {
+        Value::Json(json) => Ok(json.clone()),
+        Value::String(s) => s.parse(),
+        v => Err(Error::Value(format!(
+            "[Expression] Expect JSON, got {}",
+            format_literal(v)
+        ))),
+    }
+}
+
+// arguments are checked against the parameters before evaluation, so other
+// values are a bug
+fn unexpected(value: &Value) -> Error {
+    Error::Internal(format!(
+        "[Function] Unexpected argument {}",
+        format_literal(value)
+    ))
+}
+
+fn string(value: &Value) -> Result<&str> {
+    match value {
+        Value::String(s) => Ok(s),
+        v => Err(unexpected(v)),
+    }
+}
+
+fn number(value: &Value) -> Result<f64> {
+    match value {
+        Value::Integer(i) => Ok(*i as f64),
+        Value::Float(f) => Ok(*f),
+        Value::Decimal(d) => Ok(d.to_f64()),
+        v => Err(unexpected(v)),
+    }
+}
+
+fn coalesce(args: &[Value]) -> Result<Value> {
+    Ok(args
+        .iter()
+        .find(|v| **v != Value::Null)
+        .cloned()
+        .unwrap_or(Value::Null))
+}
+
+fn length(args: &[Value]) -> Result<Value> {
+    Ok(match &args[0] {
+        Value::String(s) => Value::Integer(s.chars().count() as i64),
+        Value::Blob(b) => Value::Integer(b.len() as i64),
+        v => return Err(unexpected(v)),
+    })
+}
+
+fn substr(args: &[Value]) -> Result<Value> {
+    let (start, count) = match &args[1..] {
+        [Value::Integer(start)] => (*start, None),
+        [Value::Integer(start), Value::Integer(count)] => (*start, Some(*count)),
+        args => return Err(unexpected(&args[0])),
+    };
+    Ok(match &args[0] {
+        Value::String(s) => {
+            let range = substr_range(start, count, s.chars().count())?;
+            Value::String(s.chars().skip(range.start).take(range.len()).collect())
+        }
+        Value::Blob(b) => Value::Blob(b[substr_range(start, count, b.len())?].to_vec()),
+        v => return Err(unexpected(v)),
+    })
+}
+
+// the part of len characters or bytes taken by substr(x, start, count),
+// positions start at 1 and those before the first are cut off
+fn substr_range(start: i64, count: Option<i64>, len: usize) -> Result<Range<usize>> {
+    let end = match count {
+        Some(n) if n < 0 => {
+            return Err(Error::Value(
+                "[Function] Negative substr length".to_string(),
+            ))
+        }
+        Some(n) => start.saturating_add(n),
+        None => i64::MAX,
+    };
+    let clamp = |pos: i64| (pos.max(1) - 1).min(len as i64) as usize;
+    Ok(clamp(start)..clamp(end).max(clamp(start)))
+}
+
+fn round(args: &[Value]) -> Result<Value> {
+    let places = match args.get(1) {
+        Some(Value::Integer(places)) => *places,
+        Some(v) => return Err(unexpected(v)),
+        None => 0,
+    };
+    Ok(match &args[0] {
+        Value::Integer(i) if places >= 0 => Value::Integer(*i),
+        Value::Integer(i) => {
+            let rounded = Decimal::from(*i).round_places(places)?;
+            Value::Integer(
+                rounded
+                    .to_i64()
+                    .ok_or_else(|| Error::OutOfRange("[Value] Integer out of range".to_string()))?,
+            )
+        }
+        Value::Float(f) => {
+            // 10^places is exact for the places a float can hold
+            let scale = 10f64.powi(places.clamp(-308, 308) as i32);
+            match (f * scale).round() / scale {
+                n if n.is_finite() => Value::Float(n),
+                _ => Value::Float(*f),
+            }
+        }
+        Value::Decimal(d) => Value::Decimal(d.round_places(places)?),
+        v => return Err(unexpected(v)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(name: &str, args: &[Value]) -> Result<Value> {
+        get(name).unwrap().evaluate(args)
+    }
+
+    fn s(s: &str) -> Value {
+        Value::String(s.to_string())
+    }
+
+    #[test]
+    fn test_functions() -> Result<()> {
+        let decimal = |s: &str| -> Result<Value> { Ok(Value::Decimal(s.parse()?)) };
+        let ok = [
+            ("upper", vec![s("straße")], s("STRASSE")),
+            ("lower", vec![s("ÀB")], s("àb")),
+            ("trim", vec![s("  a b  ")], s("a b")),
+            ("trim", vec![s("xxaxyx"), s("xy")], s("a")),
+            ("replace", vec![s("a-b-c"), s("-"), s("--")], s("a--b--c")),
+            ("replace", vec![s("abc"), s(""), s("x")], s("abc")),
+            (
+                "concat",
+                vec![s("a"), Value::Null, Value::Integer(1), Value::Float(1.5)],
+                s("a11.5"),
+            ),
+            ("abs", vec![Value::Integer(-3)], Value::Integer(3)),
+            ("abs", vec![decimal("-1.50")?], decimal("1.50")?),
+            ("round", vec![Value::Float(2.5)], Value::Float(3.0)),
+            (
+                "round",
+                vec![Value::Float(-1.2345), Value::Integer(2)],
+                Value::Float(-1.23),
+            ),
+            (
+                "round",
+                vec![Value::Integer(1250), Value::Integer(-2)],
+                Value::Integer(1300),
+            ),
+            (
+                "round",
+                vec![decimal("-1.255")?, Value::Integer(2)],
+                decimal("-1.26")?,
+            ),
+            (
+                "round",
+                vec![decimal("123.4")?, Value::Integer(-1)],
+                decimal("120")?,
+            ),
+            ("floor", vec![decimal("-1.5")?], decimal("-2")?),
+            ("ceil", vec![decimal("-1.5")?], decimal("-1")?),
+            ("ceil", vec![Value::Float(1.2)], Value::Float(2.0)),
+            ("floor", vec![Value::Integer(7)], Value::Integer(7)),
+            ("sqrt", vec![Value::Integer(16)], Value::Float(4.0)),
+            (
+                "pow",
+                vec![Value::Integer(2), Value::Integer(-1)],
+                Value::Float(0.5),
+            ),
+            (
+                "mod",
+                vec![Value::Integer(-7), Value::Integer(3)],
+                Value::Integer(-1),
+            ),
+            (
+                "mod",
+                vec![Value::Integer(i64::MIN), Value::Integer(-1)],
+                Value::Integer(0),
+            ),
+            (
+                "mod",
+                vec![decimal("7.5")?, Value::Integer(2)],
+                decimal("1.5")?,
+            ),
+            (
+                "coalesce",
+                vec![
+                    Value::Null,
+                    Value::Null,
+                    Value::Integer(2),
+                    Value::Integer(3),
+                ],
+                Value::Integer(2),
+            ),
+            ("ifnull", vec![Value::Null, s("x")], s("x")),
+            (
+                "nullif",
+                vec![Value::Integer(1), Value::Float(1.0)],
+                Value::Null,
+            ),
+            ("nullif", vec![s("a"), s("b")], s("a")),
+            ("length", vec![s("héllo")], Value::Integer(5)),
+            ("upper", vec![Value::Null], Value::Null),
+        ];
+        for (name, args, expect) in ok {
+            assert_eq!(call(name, &args)?, expect, "{}({:?})", name, args);
+        }
+
+        let err = [
+            ("upper", vec![Value::Integer(1)]),
+            ("abs", vec![Value::Integer(i64::MIN)]),
+            ("sqrt", vec![Value::Float(-1.0)]),
+            ("pow", vec![Value::Integer(10), Value::Integer(400)]),
+            ("pow", vec![Value::Integer(-8), Value::Float(0.5)]),
+            ("mod", vec![Value::Integer(1), Value::Integer(0)]),
+            ("round", vec![Value::Integer(1), Value::Float(1.0)]),
+            (
+                "substr",
+                vec![s("abc"), Value::Integer(1), Value::Integer(-1)],
+            ),
+        ];
+        for (name, args) in err {
+            assert!(call(name, &args).is_err(), "{}({:?})", name, args);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_check() -> Result<()> {
+        let check = |name: &str, args: &[Option<DataType>]| get(name).unwrap().check(args);
+        assert_eq!(
+            check("mod", &[Some(DataType::Integer), Some(DataType::Decimal)])?,
+            Some(DataType::Decimal)
+        );
+        assert_eq!(
+            check("coalesce", &[None, Some(DataType::Date)])?,
+            Some(DataType::Date)
+        );
+        assert_eq!(
+            check("substr", &[Some(DataType::Blob), None])?,
+            Some(DataType::Blob)
+        );
+        assert_eq!(
+            check("concat", &[None, None, None])?,
+            Some(DataType::String)
+        );
+
+        let err = check("substr", &[Some(DataType::String)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[Function] substr expects 2 to 3 arguments, got 1"
+        );
+        let err = check("round", &[Some(DataType::String)]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[Function] round expects a number for argument 1, got STRING"
+        );
+        assert_eq!(
+            check(
+                "coalesce",
+                &[Some(DataType::Integer), None, Some(DataType::Float)]
+            )?,
+            Some(DataType::Float)
+        );
+        assert_eq!(
+            check("ifnull", &[Some(DataType::Date), Some(DataType::Timestamp)])?,
+            Some(DataType::Timestamp)
+        );
+        let err = check(
+            "coalesce",
+            &[Some(DataType::Integer), Some(DataType::String)],
+        );
+        assert_eq!(
+            err.unwrap_err().to_string(),
+            "[Function] coalesce arguments of types INTEGER and STRING cannot be matched"
+        );
+        assert!(check("nullif", &[Some(DataType::Boolean), Some(DataType::Date)]).is_err());
+        let err = check("coalesce", &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[Function] coalesce expects at least 1 argument, got 0"
+        );
+        let err = check("upper", &[]).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[Function] upper expects 1 argument, got 0"
+        );
+        assert!(check("lower", &[None, None]).is_err());
+        Ok(())
+    }
+}
@@ -2,4 +2,6 @@ pub mod parser;
 pub mod types;
 pub mod executor;
 pub mod plan;
-pub mod schema;
\ No newline at end of file
+pub mod schema;
+pub mod engine;
+pub mod functions;
@@ -1,32 +1,261 @@
-use crate::sql::types::DataType;
+use std::{collections::BTreeMap, fmt::Display};
+
+use super::lexer::Keyword;
+use crate::{
+    error::Result,
+    sql::types::{format_hex, DataType, Decimal, Interval, Json, Value},
+};
 // Abstract Syntax Tree
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Statement {
     CreateTable {
         name: String,
         columns: Vec<Column>,
     },
+    CreateIndex {
+        name: String,
+        table_name: String,
+        column: String,
+        unique: bool,
+    },
+    DropIndex {
+        name: String,
+    },
     Insert {
         table_name: String,
         columns: Option<Vec<String>>,
         values: Vec<Vec<Expression>>,
     },
     Select {
+        // no expressions for SELECT DISTINCT, the key of DISTINCT ON (...)
+        distinct: Option<Vec<Expression>>,
+        // empty for SELECT *
+        select: Vec<(Expression, Option<String>)>,
+        from: FromItem,
+        where_clause: Option<Expression>,
+        limit: Option<Expression>,
+        offset: Option<Expression>,
+    },
+    // left UNION [ALL] right, INTERSECT or EXCEPT, the operands are SELECTs
+    // without LIMIT or OFFSET, or set operations themselves
+    SetOperation {
+        operator: SetOperator,
+        all: bool,
+        left: Box<Statement>,
+        right: Box<Statement>,
+        limit: Option<Expression>,
+        offset: Option<Expression>,
+    },
+    Update {
         table_name: String,
+        columns: BTreeMap<String, Expression>,
+        where_clause: Option<Expression>,
     },
+    Delete {
+        table_name: String,
+        where_clause: Option<Expression>,
+    },
+    Explain {
+        statement: Box<Statement>,
+    },
+    Begin,
+    Commit,
+    Rollback,
+}
+
+// the rows a SELECT reads
+#[derive(Debug, PartialEq, Clone)]
+pub enum FromItem {
+    // a table and its alias, FROM t [AS] alias
+    Table(String, Option<String>),
+    // FROM (SELECT ...) AS alias, a derived table
+    Subquery(Box<Statement>, String),
+}
+
+// queries as written in SQL, the other statements as their syntax tree
+impl Display for Statement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (distinct, select, from, where_clause, limit, offset) = match self {
+            Statement::Select {
+                distinct,
+                select,
+                from,
+                where_clause,
+                limit,
+                offset,
+            } => (distinct, select, from, where_clause, limit, offset),
+            Statement::SetOperation {
+                operator,
+                all,
+                left,
+                right,
+                limit,
+                offset,
+            } => {
+                write!(f, "{} {}", left, operator)?;
+                if *all {
+                    write!(f, " ALL")?;
+                }
+                write!(f, " {}", right)?;
+                return fmt_limit(f, limit, offset);
+            }
+            _ => return write!(f, "{:?}", self),
+        };
+        let items = select
+            .iter()
+            .map(|(expr, alias)| match alias {
+                Some(alias) => format!("{} AS {}", expr, format_ident(alias)),
+                None => expr.to_string(),
+            })
+            .collect::<Vec<_>>();
+        write!(f, "SELECT")?;
+        match distinct.as_deref() {
+            Some([]) => write!(f, " DISTINCT")?,
+            Some(on) => {
+                let on = on.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                write!(f, " DISTINCT ON ({})", on.join(", "))?
+            }
+            None => {}
+        }
+        match items.is_empty() {
+            true => write!(f, " *")?,
+            false => write!(f, " {}", items.join(", "))?,
+        }
+        match from {
+            FromItem::Table(name, alias) => {
+                write!(f, " FROM {}", format_ident(name))?;
+                if let Some(alias) = alias {
+                    write!(f, " AS {}", format_ident(alias))?;
+                }
+            }
+            FromItem::Subquery(query, alias) => {
+                write!(f, " FROM ({}) AS {}", query, format_ident(alias))?
+            }
+        }
+        if let Some(expr) = where_clause {
+            write!(f, " WHERE {}", expr)?;
+        }
+        fmt_limit(f, limit, offset)
+    }
+}
+
+fn fmt_limit(
+    f: &mut std::fmt::Formatter<'_>,
+    limit: &Option<Expression>,
+    offset: &Option<Expression>,
+) -> std::fmt::Result {
+    if let Some(expr) = limit {
+        write!(f, " LIMIT {}", expr)?;
+    }
+    if let Some(expr) = offset {
+        write!(f, " OFFSET {}", expr)?;
+    }
+    Ok(())
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum SetOperator {
+    Union,
+    Intersect,
+    Except,
 }
 
-#[derive(Debug, PartialEq)]
+impl Display for SetOperator {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SetOperator::Union => "UNION",
+            SetOperator::Intersect => "INTERSECT",
+            SetOperator::Except => "EXCEPT",
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub struct Column {
     pub name: String,
     pub datatype: DataType,
     pub nullable: Option<bool>,
     pub default: Option<Expression>,
+    pub primary_key: bool,
+    // precision and scale of DECIMAL(p, s)
+    pub precision: Option<(u32, u32)>,
+    // length of VARCHAR(n) and CHAR(n)
+    pub length: Option<u32>,
+    // CHAR(n)
+    pub padded: bool,
+}
+
+// a data type with its modifiers, e.g. DECIMAL(10, 2) or VARCHAR(20)
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeName {
+    pub datatype: DataType,
+    pub precision: Option<(u32, u32)>,
+    pub length: Option<u32>,
+    pub padded: bool,
+}
+
+impl TypeName {
+    // CAST(value AS type), unlike a column a string longer than the length
+    // is cut off rather than an error
+    pub fn cast(&self, value: Value) -> Result<Value> {
+        Ok(
+            match (value.cast(&self.datatype)?, self.precision, self.length) {
+                (Value::Decimal(d), Some((precision, scale)), _) => {
+                    Value::Decimal(d.with_precision(precision, scale)?)
+                }
+                (Value::String(s), _, Some(length)) => {
+                    let s = match self.padded {
+                        true => s.trim_end_matches(' '),
+                        false => &s,
+                    };
+                    let count = s.chars().count();
+                    let length = length as usize;
+                    Value::String(match count >= length {
+                        true => s.chars().take(length).collect(),
+                        false if self.padded => format!("{}{}", s, " ".repeat(length - count)),
+                        false => s.to_string(),
+                    })
+                }
+                (value, ..) => value,
+            },
+        )
+    }
+}
+
+impl Display for TypeName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match (self.precision, self.length) {
+            (Some((precision, scale)), _) => write!(f, "DECIMAL({}, {})", precision, scale),
+            (_, Some(length)) if self.padded => write!(f, "CHAR({})", length),
+            (_, Some(length)) => write!(f, "VARCHAR({})", length),
+            _ => write!(f, "{}", self.datatype),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone)]
 pub enum Expression {
     Consts(Consts),
+    // a column, optionally qualified by a table name or alias, e.g. t.a
+    Field(Option<String>, String),
+    Current(Current),
+    Operation(Operation),
+    // function call, e.g. length(name)
+    Function(String, Vec<Expression>),
+    // CAST(expr AS type) or expr::type
+    Cast(Box<Expression>, TypeName),
+    // CASE [operand] WHEN a THEN b [...] [ELSE c] END, without an operand
+    // the WHEN expressions are conditions, with one they are compared to it
+    Case(
+        Option<Box<Expression>>,
+        Vec<(Expression, Expression)>,
+        Option<Box<Expression>>,
+    ),
+    // (SELECT ...), the value of the single row, NULL without rows, the
+    // query may refer to the columns of the query it is part of
+    Subquery(Box<Statement>),
+    // EXISTS (SELECT ...)
+    Exists(Box<Statement>),
 }
 
 impl From<Consts> for Expression {
@@ -35,11 +264,336 @@ impl From<Consts> for Expression {
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl From<Operation> for Expression {
+    fn from(value: Operation) -> Self {
+        Self::Operation(value)
+    }
+}
+
+impl From<Value> for Consts {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => Consts::Null,
+            Value::Boolean(b) => Consts::Boolean(b),
+            Value::Integer(i) => Consts::Integer(i),
+            Value::Float(f) => Consts::Float(f),
+            Value::String(s) => Consts::String(s),
+            Value::Date(d) => Consts::Date(d),
+            Value::Time(t) => Consts::Time(t),
+            Value::Timestamp(t) => Consts::Timestamp(t),
+            Value::Interval(i) => Consts::Interval(i),
+            Value::Decimal(d) => Consts::Decimal(d),
+            Value::Blob(b) => Consts::Blob(b),
+            Value::Json(j) => Consts::Json(j),
+        }
+    }
+}
+
+impl From<Consts> for Value {
+    fn from(value: Consts) -> Self {
+        match value {
+            Consts::Null => Value::Null,
+            Consts::Boolean(b) => Value::Boolean(b),
+            Consts::Integer(i) => Value::Integer(i),
+            Consts::Float(f) => Value::Float(f),
+            Consts::String(s) => Value::String(s),
+            Consts::Date(d) => Value::Date(d),
+            Consts::Time(t) => Value::Time(t),
+            Consts::Timestamp(t) => Value::Timestamp(t),
+            Consts::Interval(i) => Value::Interval(i),
+            Consts::Decimal(d) => Value::Decimal(d),
+            Consts::Blob(b) => Value::Blob(b),
+            Consts::Json(j) => Value::Json(j),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Consts {
     Null,
     Boolean(bool),
     Integer(i64),
     Float(f64),
     String(String),
+    Date(i32),
+    Time(i64),
+    Timestamp(i64),
+    Interval(Interval),
+    Decimal(Decimal),
+    Blob(Vec<u8>),
+    Json(Json),
+}
+
+// CURRENT_DATE, CURRENT_TIME and CURRENT_TIMESTAMP, now() is the latter,
+// the clock is read when the expression is evaluated
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Current {
+    Date,
+    Time,
+    Timestamp,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operation {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Equal(Box<Expression>, Box<Expression>),
+    NotEqual(Box<Expression>, Box<Expression>),
+    GreaterThan(Box<Expression>, Box<Expression>),
+    GreaterThanOrEqual(Box<Expression>, Box<Expression>),
+    LessThan(Box<Expression>, Box<Expression>),
+    LessThanOrEqual(Box<Expression>, Box<Expression>),
+    In(Box<Expression>, Vec<Expression>),
+    Between(Box<Expression>, Box<Expression>, Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    // -expr, a sign right before a number is part of the literal instead
+    Negate(Box<Expression>),
+    // json -> key, the member as JSON
+    JsonGet(Box<Expression>, Box<Expression>),
+    // json ->> key, the member as text
+    JsonGetText(Box<Expression>, Box<Expression>),
+    // a || b, strings concatenated, or blobs
+    Concat(Box<Expression>, Box<Expression>),
+    // expr LIKE pattern [ESCAPE char], ILIKE ignores case
+    Like(Box<Expression>, Box<Expression>, Option<Box<Expression>>),
+    ILike(Box<Expression>, Box<Expression>, Option<Box<Expression>>),
+    // expr IN (SELECT ...)
+    InSubquery(Box<Expression>, Box<Statement>),
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Consts(Consts::Null) => write!(f, "NULL"),
+            Expression::Consts(Consts::Boolean(b)) if *b => write!(f, "TRUE"),
+            Expression::Consts(Consts::Boolean(_)) => write!(f, "FALSE"),
+            Expression::Consts(Consts::Integer(i)) => write!(f, "{}", i),
+            Expression::Consts(Consts::Float(v)) => write!(f, "{:?}", v),
+            Expression::Consts(Consts::String(s)) => write!(f, "{}", format_string(s)),
+            Expression::Consts(Consts::Date(d)) => {
+                write!(f, "{}", format_literal(&Value::Date(*d)))
+            }
+            Expression::Consts(Consts::Time(t)) => {
+                write!(f, "{}", format_literal(&Value::Time(*t)))
+            }
+            Expression::Consts(Consts::Timestamp(t)) => {
+                write!(f, "{}", format_literal(&Value::Timestamp(*t)))
+            }
+            Expression::Consts(Consts::Interval(i)) => {
+                write!(f, "{}", format_literal(&Value::Interval(*i)))
+            }
+            Expression::Consts(Consts::Decimal(d)) => {
+                write!(f, "{}", format_literal(&Value::Decimal(*d)))
+            }
+            Expression::Consts(Consts::Blob(b)) => {
+                write!(f, "{}", format_literal(&Value::Blob(b.clone())))
+            }
+            Expression::Consts(Consts::Json(j)) => {
+                write!(f, "{}", format_literal(&Value::Json(j.clone())))
+            }
+            Expression::Field(Some(qualifier), name) => {
+                write!(f, "{}.{}", format_ident(qualifier), format_ident(name))
+            }
+            Expression::Field(None, name) => write!(f, "{}", format_ident(name)),
+            Expression::Current(Current::Date) => write!(f, "CURRENT_DATE"),
+            Expression::Current(Current::Time) => write!(f, "CURRENT_TIME"),
+            Expression::Current(Current::Timestamp) => write!(f, "CURRENT_TIMESTAMP"),
+            Expression::Operation(op) => write!(f, "{}", op),
+            Expression::Function(name, args) => write!(
+                f,
+                "{}({})",
+                name,
+                args.iter()
+                    .map(|arg| arg.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            Expression::Cast(expr, typename) => write!(f, "CAST({} AS {})", expr, typename),
+            Expression::Case(operand, branches, default) => {
+                write!(f, "CASE")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", operand)?;
+                }
+                for (when, then) in branches {
+                    write!(f, " WHEN {} THEN {}", when, then)?;
+                }
+                if let Some(default) = default {
+                    write!(f, " ELSE {}", default)?;
+                }
+                write!(f, " END")
+            }
+            Expression::Subquery(query) => write!(f, "({})", query),
+            Expression::Exists(query) => write!(f, "EXISTS ({})", query),
+        }
+    }
+}
+
+// a string literal as written in SQL
+pub fn format_string(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+// a value as a literal in SQL, e.g. 'it''s' or DATE '2026-10-17'
+pub fn format_literal(value: &Value) -> String {
+    match (value, value.datatype()) {
+        (Value::String(s), _) => format_string(s),
+        (Value::Blob(b), _) => format!("X'{}'", format_hex(b)),
+        // a number with a fractional part reads back as a DECIMAL
+        (Value::Decimal(d), _) if d.scale() > 0 => d.to_string(),
+        (
+            Value::Date(_)
+            | Value::Time(_)
+            | Value::Timestamp(_)
+            | Value::Interval(_)
+            | Value::Decimal(_)
+            | Value::Json(_),
+            Some(datatype),
+        ) => format!("{} {}", datatype, format_string(&value.to_string())),
+        (v, _) => v.to_string(),
+    }
+}
+
+// an identifier as written in SQL, quoted unless it reads back the same
+pub fn format_ident(name: &str) -> String {
+    let plain = name.starts_with(|c: char| c.is_alphabetic())
+        && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+        && name.to_lowercase() == name
+        && Keyword::from_str(name).is_none();
+    match plain {
+        true => name.to_string(),
+        false => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+impl Operation {
+    // binding strength, used to decide where parentheses are needed
+    fn precedence(&self) -> u8 {
+        match self {
+            Operation::Or(..) => 1,
+            Operation::And(..) => 2,
+            Operation::Not(..) => 3,
+            Operation::Concat(..) => 5,
+            Operation::Add(..) | Operation::Subtract(..) => 6,
+            Operation::Multiply(..) | Operation::Divide(..) => 7,
+            Operation::JsonGet(..) | Operation::JsonGetText(..) => 8,
+            Operation::Negate(..) => 9,
+            _ => 4,
+        }
+    }
+}
+
+impl Display for Operation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // wrap operands binding looser than this operation, comparisons
+        // do not chain, and arithmetic groups to the left
+        let operand = |e: &Expression| match e {
+            Expression::Operation(op)
+                if op.precedence() < self.precedence()
+                    || (op.precedence() == 4 && self.precedence() == 4) =>
+            {
+                format!("({})", e)
+            }
+            e => e.to_string(),
+        };
+        let right = |e: &Expression| match e {
+            Expression::Operation(op)
+                if op.precedence() == self.precedence() && self.precedence() > 4 =>
+            {
+                format!("({})", e)
+            }
+            e => operand(e),
+        };
+        match self {
+            Operation::And(l, r) => write!(f, "{} AND {}", operand(l), operand(r)),
+            Operation::Or(l, r) => write!(f, "{} OR {}", operand(l), operand(r)),
+            Operation::Not(e) => match e.as_ref() {
+                // x NOT IN (...) rather than NOT x IN (...)
+                Expression::Operation(
+                    op @ (Operation::In(..)
+                    | Operation::InSubquery(..)
+                    | Operation::Between(..)
+                    | Operation::Like(..)
+                    | Operation::ILike(..)),
+                ) => op.fmt_predicate(f, "NOT "),
+                e => write!(f, "NOT {}", operand(e)),
+            },
+            Operation::Equal(l, r) => write!(f, "{} = {}", operand(l), operand(r)),
+            Operation::NotEqual(l, r) => write!(f, "{} != {}", operand(l), operand(r)),
+            Operation::GreaterThan(l, r) => write!(f, "{} > {}", operand(l), operand(r)),
+            Operation::GreaterThanOrEqual(l, r) => write!(f, "{} >= {}", operand(l), operand(r)),
+            Operation::LessThan(l, r) => write!(f, "{} < {}", operand(l), operand(r)),
+            Operation::LessThanOrEqual(l, r) => write!(f, "{} <= {}", operand(l), operand(r)),
+            Operation::In(..)
+            | Operation::InSubquery(..)
+            | Operation::Between(..)
+            | Operation::Like(..)
+            | Operation::ILike(..) => self.fmt_predicate(f, ""),
+            Operation::Add(l, r) => write!(f, "{} + {}", operand(l), right(r)),
+            Operation::Subtract(l, r) => write!(f, "{} - {}", operand(l), right(r)),
+            Operation::Multiply(l, r) => write!(f, "{} * {}", operand(l), right(r)),
+            Operation::Divide(l, r) => write!(f, "{} / {}", operand(l), right(r)),
+            // -(5) and -(-5), not the literal -5 or the comment --5
+            Operation::Negate(e) => match e.as_ref() {
+                Expression::Consts(_) => write!(f, "-({})", e),
+                e => write!(f, "-{}", operand(e)),
+            },
+            Operation::JsonGet(l, r) => write!(f, "{} -> {}", operand(l), right(r)),
+            Operation::JsonGetText(l, r) => write!(f, "{} ->> {}", operand(l), right(r)),
+            Operation::Concat(l, r) => write!(f, "{} || {}", operand(l), right(r)),
+        }
+    }
+}
+
+impl Operation {
+    // IN, BETWEEN, LIKE and ILIKE, with not put before the keyword
+    fn fmt_predicate(&self, f: &mut std::fmt::Formatter<'_>, not: &str) -> std::fmt::Result {
+        let operand = |e: &Expression| match e {
+            Expression::Operation(op) if op.precedence() <= self.precedence() => {
+                format!("({})", e)
+            }
+            e => e.to_string(),
+        };
+        let escape = |escape: &Option<Box<Expression>>| match escape {
+            Some(escape) => format!(" ESCAPE {}", operand(escape)),
+            None => String::new(),
+        };
+        match self {
+            Operation::In(e, list) => {
+                let list = list.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                write!(f, "{} {}IN ({})", operand(e), not, list.join(", "))
+            }
+            Operation::InSubquery(e, query) => {
+                write!(f, "{} {}IN ({})", operand(e), not, query)
+            }
+            Operation::Between(e, low, high) => write!(
+                f,
+                "{} {}BETWEEN {} AND {}",
+                operand(e),
+                not,
+                operand(low),
+                operand(high)
+            ),
+            Operation::Like(e, pattern, esc) => write!(
+                f,
+                "{} {}LIKE {}{}",
+                operand(e),
+                not,
+                operand(pattern),
+                escape(esc)
+            ),
+            Operation::ILike(e, pattern, esc) => write!(
+                f,
+                "{} {}ILIKE {}{}",
+                operand(e),
+                not,
+                operand(pattern),
+                escape(esc)
+            ),
+            op => write!(f, "{}", op),
+        }
+    }
 }
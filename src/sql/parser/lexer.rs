@@ -1,6 +1,6 @@
 use std::{fmt::Display, iter::Peekable, str::Chars};
 
-use crate::{error::Error, error::Result};
+use crate::error::{Error, Result, Span};
 
 // CREATE TABLE table_name (
 // [ column_name data_type [ column_constraint [...]]]
@@ -11,7 +11,13 @@ use crate::{error::Error, error::Result};
 // - BOOLEAN (BOOL): true | false
 // - FLOAT (DOUBLE)
 // - INTEGER (INT)
-// - STRING (TEXT, VARCHAR)
+// - STRING (TEXT, VARCHAR [ ( length ) ])
+// - CHAR [ ( length ) ]: padded with spaces to its length, 1 if not given
+// - DATE, TIME, TIMESTAMP: without time zone
+// - INTERVAL: months, days and time
+// - DECIMAL (NUMERIC) [ ( precision [, scale ] ) ]: exact, up to 38 digits
+// - BLOB (BYTEA): binary data
+// - JSON: validated on insert, stored parsed
 //
 // where column_constraint is:
 // [ NOT NULL | NULL | DEFAULT expr ]
@@ -22,7 +28,57 @@ use crate::{error::Error, error::Result};
 // values ( expr [, ...]);
 
 // 查询数据
-// SELECT * FROM table_name;
+// SELECT [ DISTINCT [ ON ( expr [, ...] ) ] ] { * | expr [ AS alias ] [, ...] }
+// FROM { table_name | ( select ) [ AS ] alias } [ WHERE expr ]
+// [ { UNION | INTERSECT | EXCEPT } [ ALL ] SELECT ... ]
+// [ LIMIT count ] [ OFFSET start ];
+//
+// INTERSECT binds tighter than UNION and EXCEPT, LIMIT and OFFSET apply to
+// the combined rows, which are distinct unless ALL is given, DISTINCT ON
+// keeps the first row of each key, NULLs are equal in both
+//
+// where expr supports comparisons, AND, OR, NOT, + - * /,
+// expr [ NOT ] IN ( expr [, ...] ), expr [ NOT ] BETWEEN expr AND expr,
+// expr [ NOT ] { LIKE | ILIKE } pattern [ ESCAPE char ] where % matches any
+// characters and _ a single one, \ escapes them unless ESCAPE says otherwise,
+// CASE [ expr ] WHEN expr THEN expr [ ... ] [ ELSE expr ] END,
+// subqueries ( select ) of one column and at most one row,
+// expr [ NOT ] IN ( select ) and EXISTS ( select ), which may use the
+// columns of the outer query,
+// typed literals DATE '2026-10-17', TIME '12:30:00',
+// TIMESTAMP '2026-10-17 12:30:00', INTERVAL '1 day 02:00:00',
+// DECIMAL '12.50' and JSON '{"a": [1, 2]}',
+// CURRENT_DATE, CURRENT_TIME, CURRENT_TIMESTAMP and now(),
+// json -> key or index, json ->> key or index as text,
+// json_extract(json, '$.path[0]') and json_array_length(json),
+// CAST(expr AS data_type) and expr::data_type, see types/cast.rs for the
+// conversions allowed, string || string,
+// and the built-in functions of sql/functions.rs, e.g. upper(name)
+
+// 更新数据
+// UPDATE table_name SET column_name = expr [, ...] [ WHERE expr ];
+
+// 删除数据
+// DELETE FROM table_name [ WHERE expr ];
+
+// 创建和删除索引
+// CREATE [ UNIQUE ] INDEX index_name ON table_name ( column_name );
+// DROP INDEX index_name;
+
+// 查看执行计划
+// EXPLAIN statement;
+
+// 事务
+// BEGIN; COMMIT; ROLLBACK;
+
+// 词法
+// -- comment to the end of the line, /* block comment */
+// "Quoted Identifier" keeps its case and may be a keyword, "" is a quote in it
+// 'string literal', '' is a quote in it
+// X'DEADBEEF' binary literal of hex digits
+// numbers are integers such as 42 or 0xFF, or floats such as 1.5, .5, 1e10
+// and 2.5E-3, a - or + right before a number is folded into the literal by
+// the parser, so -9223372036854775808 is a valid integer
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Keyword {
@@ -35,8 +91,21 @@ pub enum Keyword {
     String,
     Text,
     Varchar,
+    Char,
     Float,
     Double,
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Decimal,
+    Numeric,
+    Blob,
+    Bytea,
+    Json,
+    CurrentDate,
+    CurrentTime,
+    CurrentTimestamp,
     Select,
     From,
     Insert,
@@ -49,6 +118,40 @@ pub enum Keyword {
     Null,
     Primary,
     Key,
+    Where,
+    And,
+    Or,
+    In,
+    Update,
+    Set,
+    Delete,
+    Drop,
+    Index,
+    Unique,
+    On,
+    Between,
+    As,
+    Cast,
+    Case,
+    When,
+    Then,
+    Else,
+    End,
+    Like,
+    ILike,
+    Escape,
+    Exists,
+    Distinct,
+    Union,
+    Intersect,
+    Except,
+    All,
+    Explain,
+    Limit,
+    Offset,
+    Begin,
+    Commit,
+    Rollback,
 }
 
 impl Keyword {
@@ -63,8 +166,21 @@ impl Keyword {
             "STRING" => Keyword::String,
             "TEXT" => Keyword::Text,
             "VARCHAR" => Keyword::Varchar,
+            "CHAR" => Keyword::Char,
             "FLOAT" => Keyword::Float,
             "DOUBLE" => Keyword::Double,
+            "DATE" => Keyword::Date,
+            "TIME" => Keyword::Time,
+            "TIMESTAMP" => Keyword::Timestamp,
+            "INTERVAL" => Keyword::Interval,
+            "DECIMAL" => Keyword::Decimal,
+            "NUMERIC" => Keyword::Numeric,
+            "BLOB" => Keyword::Blob,
+            "BYTEA" => Keyword::Bytea,
+            "JSON" => Keyword::Json,
+            "CURRENT_DATE" => Keyword::CurrentDate,
+            "CURRENT_TIME" => Keyword::CurrentTime,
+            "CURRENT_TIMESTAMP" => Keyword::CurrentTimestamp,
             "SELECT" => Keyword::Select,
             "FROM" => Keyword::From,
             "INSERT" => Keyword::Insert,
@@ -77,6 +193,40 @@ impl Keyword {
             "NULL" => Keyword::Null,
             "PRIMARY" => Keyword::Primary,
             "KEY" => Keyword::Key,
+            "WHERE" => Keyword::Where,
+            "AND" => Keyword::And,
+            "OR" => Keyword::Or,
+            "IN" => Keyword::In,
+            "UPDATE" => Keyword::Update,
+            "SET" => Keyword::Set,
+            "DELETE" => Keyword::Delete,
+            "DROP" => Keyword::Drop,
+            "INDEX" => Keyword::Index,
+            "UNIQUE" => Keyword::Unique,
+            "ON" => Keyword::On,
+            "BETWEEN" => Keyword::Between,
+            "AS" => Keyword::As,
+            "CAST" => Keyword::Cast,
+            "CASE" => Keyword::Case,
+            "WHEN" => Keyword::When,
+            "THEN" => Keyword::Then,
+            "ELSE" => Keyword::Else,
+            "END" => Keyword::End,
+            "LIKE" => Keyword::Like,
+            "ILIKE" => Keyword::ILike,
+            "ESCAPE" => Keyword::Escape,
+            "EXISTS" => Keyword::Exists,
+            "DISTINCT" => Keyword::Distinct,
+            "UNION" => Keyword::Union,
+            "INTERSECT" => Keyword::Intersect,
+            "EXCEPT" => Keyword::Except,
+            "ALL" => Keyword::All,
+            "EXPLAIN" => Keyword::Explain,
+            "LIMIT" => Keyword::Limit,
+            "OFFSET" => Keyword::Offset,
+            "BEGIN" => Keyword::Begin,
+            "COMMIT" => Keyword::Commit,
+            "ROLLBACK" => Keyword::Rollback,
             _ => return None,
         })
     }
@@ -92,8 +242,21 @@ impl Keyword {
             Keyword::String => "STRING",
             Keyword::Text => "TEXT",
             Keyword::Varchar => "VARCHAR",
+            Keyword::Char => "CHAR",
             Keyword::Float => "FLOAT",
             Keyword::Double => "DOUBLE",
+            Keyword::Date => "DATE",
+            Keyword::Time => "TIME",
+            Keyword::Timestamp => "TIMESTAMP",
+            Keyword::Interval => "INTERVAL",
+            Keyword::Decimal => "DECIMAL",
+            Keyword::Numeric => "NUMERIC",
+            Keyword::Blob => "BLOB",
+            Keyword::Bytea => "BYTEA",
+            Keyword::Json => "JSON",
+            Keyword::CurrentDate => "CURRENT_DATE",
+            Keyword::CurrentTime => "CURRENT_TIME",
+            Keyword::CurrentTimestamp => "CURRENT_TIMESTAMP",
             Keyword::Select => "SELECT",
             Keyword::From => "FROM",
             Keyword::Insert => "INSERT",
@@ -106,6 +269,40 @@ impl Keyword {
             Keyword::Null => "NULL",
             Keyword::Primary => "PRIMARY",
             Keyword::Key => "KEY",
+            Keyword::Where => "WHERE",
+            Keyword::And => "AND",
+            Keyword::Or => "OR",
+            Keyword::In => "IN",
+            Keyword::Update => "UPDATE",
+            Keyword::Set => "SET",
+            Keyword::Delete => "DELETE",
+            Keyword::Drop => "DROP",
+            Keyword::Index => "INDEX",
+            Keyword::Unique => "UNIQUE",
+            Keyword::On => "ON",
+            Keyword::Between => "BETWEEN",
+            Keyword::As => "AS",
+            Keyword::Cast => "CAST",
+            Keyword::Case => "CASE",
+            Keyword::When => "WHEN",
+            Keyword::Then => "THEN",
+            Keyword::Else => "ELSE",
+            Keyword::End => "END",
+            Keyword::Like => "LIKE",
+            Keyword::ILike => "ILIKE",
+            Keyword::Escape => "ESCAPE",
+            Keyword::Exists => "EXISTS",
+            Keyword::Distinct => "DISTINCT",
+            Keyword::Union => "UNION",
+            Keyword::Intersect => "INTERSECT",
+            Keyword::Except => "EXCEPT",
+            Keyword::All => "ALL",
+            Keyword::Explain => "EXPLAIN",
+            Keyword::Limit => "LIMIT",
+            Keyword::Offset => "OFFSET",
+            Keyword::Begin => "BEGIN",
+            Keyword::Commit => "COMMIT",
+            Keyword::Rollback => "ROLLBACK",
         }
     }
 }
@@ -124,8 +321,12 @@ pub enum Token {
     Ident(String),
     // 字符串类型的数据
     String(String),
-    // 数值类型,比如整数和浮点数
-    Number(String),
+    // 整数,十进制或者 0x 开头的十六进制
+    Integer(String),
+    // 浮点数,带小数点或者指数
+    Float(String),
+    // 二进制数据 X'DEADBEEF',只保存十六进制数字
+    Blob(String),
     // 左括号 (
     OpenParen,
     // 右括号 )
@@ -134,6 +335,8 @@ pub enum Token {
     Comma,
     // 分号 ;
     Semicolon,
+    // 句点 .,限定列名 t.a
+    Period,
     // 星号 *
     Asterisk,
     // 加号 +
@@ -142,6 +345,26 @@ pub enum Token {
     Minus,
     // 斜杠 /
     Slash,
+    // 等号 =
+    Equal,
+    // 不等号 != 或 <>
+    NotEqual,
+    // 大于号 >
+    GreaterThan,
+    // 大于等于号 >=
+    GreaterThanOrEqual,
+    // 小于号 <
+    LessThan,
+    // 小于等于号 <=
+    LessThanOrEqual,
+    // 箭头 ->,取出 JSON 成员
+    Arrow,
+    // 双箭头 ->>,取出 JSON 成员的文本
+    LongArrow,
+    // 双冒号 ::,类型转换
+    DoubleColon,
+    // 双竖线 ||,字符串拼接
+    Concat,
 }
 
 impl Display for Token {
@@ -150,49 +373,143 @@ impl Display for Token {
             Token::Keyword(keyword) => keyword.to_str(),
             Token::Ident(ident) => ident,
             Token::String(v) => v,
-            Token::Number(n) => n,
+            Token::Integer(n) => n,
+            Token::Float(n) => n,
+            Token::Blob(hex) => return write!(f, "X'{}'", hex),
             Token::OpenParen => "(",
             Token::CloseParen => ")",
             Token::Comma => ",",
             Token::Semicolon => ";",
+            Token::Period => ".",
             Token::Asterisk => "*",
             Token::Plus => "+",
             Token::Minus => "-",
             Token::Slash => "/",
+            Token::Equal => "=",
+            Token::NotEqual => "!=",
+            Token::GreaterThan => ">",
+            Token::GreaterThanOrEqual => ">=",
+            Token::LessThan => "<",
+            Token::LessThanOrEqual => "<=",
+            Token::Arrow => "->",
+            Token::LongArrow => "->>",
+            Token::DoubleColon => "::",
+            Token::Concat => "||",
         })
     }
 }
 
 pub struct Lexer<'a> {
     iter: Peekable<Chars<'a>>,
+    // position of the next character
+    offset: usize,
+    line: usize,
+    column: usize,
 }
 
 impl<'a> Iterator for Lexer<'a> {
-    type Item = Result<Token>;
+    type Item = Result<(Token, Span)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        match self.scan() {
-            Ok(Some(token)) => Some(Ok(token)),
-            Ok(None) => self
-                .iter
-                .peek()
-                .map(|c| Err(Error::Parse(format!("[Lexer] Unexpected character: {}", c)))),
+        if let Err(err) = self.erase_whitespace() {
+            return Some(Err(err));
+        }
+        let (offset, line, column) = (self.offset, self.line, self.column);
+        let result = self.scan();
+        let span = Span {
+            offset,
+            len: self.offset - offset,
+            line,
+            column,
+        };
+        match result {
+            Ok(Some(token)) => Some(Ok((token, span))),
+            Ok(None) => self.iter.peek().map(|c| {
+                let span = Span {
+                    len: c.len_utf8(),
+                    ..span
+                };
+                Err(Error::Parse(
+                    format!("[Lexer] Unexpected character: {}", c),
+                    Some(span),
+                ))
+            }),
+            Err(Error::Parse(msg, None)) => Some(Err(Error::Parse(msg, Some(span)))),
             Err(e) => Some(Err(e)),
         }
     }
 }
 
+// position just past the end of input
+pub fn end_span(input: &str) -> Span {
+    Span {
+        offset: input.len(),
+        len: 0,
+        line: input.split('\n').count(),
+        column: input
+            .rsplit('\n')
+            .next()
+            .unwrap_or_default()
+            .chars()
+            .count()
+            + 1,
+    }
+}
+
+// whether the input ends with a semicolon outside of strings, quoted
+// identifiers and comments, i.e. its last statement is complete
+pub fn ends_statement(input: &str) -> bool {
+    let mut lexer = Lexer::new(input);
+    let mut complete = false;
+    loop {
+        // an unterminated block comment
+        if lexer.erase_whitespace().is_err() {
+            return false;
+        }
+        if lexer.iter.peek().is_none() {
+            return complete;
+        }
+        complete = match Lexer::scan(&mut lexer) {
+            Ok(Some(token)) => token == Token::Semicolon,
+            // an unknown character, reported once the statement runs
+            Ok(None) => {
+                lexer.next_char();
+                false
+            }
+            // an unterminated string or quoted identifier has read the rest
+            // of the input, other errors are reported once the statement runs
+            Err(_) => false,
+        };
+    }
+}
+
 #[allow(dead_code)]
 impl<'a> Lexer<'a> {
     pub fn new(sql_text: &'a str) -> Self {
         Self {
             iter: sql_text.chars().peekable(),
+            offset: 0,
+            line: 1,
+            column: 1,
         }
     }
 
+    // consume a character, keeping track of the position
+    fn next_char(&mut self) -> Option<char> {
+        let c = self.iter.next()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
     fn next_if<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<char> {
         self.iter.peek().filter(|&c| predicate(*c))?;
-        self.iter.next()
+        self.next_char()
     }
 
     fn next_while<F: Fn(char) -> bool>(&mut self, predicate: F) -> Option<String> {
@@ -205,20 +522,23 @@ impl<'a> Lexer<'a> {
 
     fn next_if_token<F: Fn(char) -> Option<Token>>(&mut self, predicate: F) -> Option<Token> {
         let token = self.iter.peek().and_then(|c| predicate(*c))?;
-        self.iter.next();
+        self.next_char();
         Some(token)
     }
 
-    // get next token
+    // get next token, whitespace before it has been skipped
     fn scan(&mut self) -> Result<Option<Token>> {
-        // skip whitespace
-        self.erase_whitespace();
         // check first char
-        match self.iter.peek() {
+        match self.iter.peek().copied() {
             Some('\'') => self.scan_string(),
-            Some(c) if c.is_ascii_digit() => Ok(self.scan_number()),
+            Some('x' | 'X') if self.peek_second() == Some('\'') => self.scan_blob(),
+            Some('"') => self.scan_quoted_ident(),
+            Some(c) if c.is_ascii_digit() => self.scan_number(),
+            Some('.') if self.peek_second().is_some_and(|c| c.is_ascii_digit()) => {
+                self.scan_number()
+            }
             Some(c) if c.is_alphabetic() => Ok(self.scan_ident()),
-            Some(_) => Ok(self.scan_symbol()),
+            Some(_) => self.scan_symbol(),
             None => Ok(None),
         }
     }
@@ -230,29 +550,173 @@ impl<'a> Lexer<'a> {
 
         let mut value = String::new();
         loop {
-            match self.iter.next() {
+            match self.next_char() {
+                // a quote inside a string is written twice
+                Some('\'') if self.next_if(|c| c == '\'').is_some() => value.push('\''),
                 Some('\'') => break,
                 Some(c) => value.push(c),
-                None => return Err(Error::Parse("[Lexer] Unexpected end of string".to_string())),
+                None => {
+                    return Err(Error::Parse(
+                        "[Lexer] Unexpected end of string".to_string(),
+                        None,
+                    ))
+                }
             }
         }
         Ok(Some(Token::String(value)))
     }
 
-    fn erase_whitespace(&mut self) {
-        self.next_while(|c| c.is_whitespace());
+    // X'DEADBEEF', an even number of hex digits
+    fn scan_blob(&mut self) -> Result<Option<Token>> {
+        self.next_char();
+        let hex = match self.scan_string()? {
+            Some(Token::String(hex)) => hex,
+            _ => return Ok(None),
+        };
+        if hex.len() % 2 == 1 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(Error::Parse(
+                format!("[Lexer] Invalid hex string: X'{}'", hex),
+                None,
+            ));
+        }
+        Ok(Some(Token::Blob(hex)))
+    }
+
+    // quoted identifiers keep their case and may be keywords
+    fn scan_quoted_ident(&mut self) -> Result<Option<Token>> {
+        if self.next_if(|c| c == '"').is_none() {
+            return Ok(None);
+        }
+
+        let mut value = String::new();
+        loop {
+            match self.next_char() {
+                Some('"') if self.next_if(|c| c == '"').is_some() => value.push('"'),
+                Some('"') => break,
+                Some(c) => value.push(c),
+                None => {
+                    return Err(Error::Parse(
+                        "[Lexer] Unexpected end of quoted identifier".to_string(),
+                        None,
+                    ))
+                }
+            }
+        }
+        if value.is_empty() {
+            return Err(Error::Parse(
+                "[Lexer] Empty quoted identifier".to_string(),
+                None,
+            ));
+        }
+        Ok(Some(Token::Ident(value)))
+    }
+
+    // skip whitespace and comments, which run from -- to the end of the
+    // line or between /* and */
+    fn erase_whitespace(&mut self) -> Result<()> {
+        loop {
+            self.next_while(|c| c.is_whitespace());
+            match (self.iter.peek().copied(), self.peek_second()) {
+                (Some('-'), Some('-')) => {
+                    self.next_while(|c| c != '\n');
+                }
+                (Some('/'), Some('*')) => self.erase_block_comment()?,
+                _ => return Ok(()),
+            }
+        }
+    }
+
+    // block comments may be nested
+    fn erase_block_comment(&mut self) -> Result<()> {
+        let (offset, line, column) = (self.offset, self.line, self.column);
+        self.next_char();
+        self.next_char();
+        let mut depth = 1;
+        while depth > 0 {
+            let c = self.next_char();
+            match (c, self.iter.peek().copied()) {
+                (Some('/'), Some('*')) => {
+                    self.next_char();
+                    depth += 1;
+                }
+                (Some('*'), Some('/')) => {
+                    self.next_char();
+                    depth -= 1;
+                }
+                (Some(_), _) => {}
+                (None, _) => {
+                    return Err(Error::Parse(
+                        "[Lexer] Unterminated block comment".to_string(),
+                        Some(Span {
+                            offset,
+                            len: self.offset - offset,
+                            line,
+                            column,
+                        }),
+                    ))
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        let mut iter = self.iter.clone();
+        iter.next();
+        iter.next()
     }
 
-    fn scan_number(&mut self) -> Option<Token> {
-        let mut num = self.next_while(|c| c.is_ascii_digit())?;
+    // the sign is not part of the token, the parser folds it into the literal
+    fn scan_number(&mut self) -> Result<Option<Token>> {
+        if self.iter.peek() == Some(&'0') && matches!(self.peek_second(), Some('x' | 'X')) {
+            self.next_char();
+            self.next_char();
+            let digits = self
+                .next_while(|c| c.is_ascii_hexdigit())
+                .unwrap_or_default();
+            return self.end_number(format!("0x{}", digits), digits.is_empty(), false);
+        }
+
+        let mut num = self.next_while(|c| c.is_ascii_digit()).unwrap_or_default();
+        let mut float = false;
         if let Some(sep) = self.next_if(|c| c == '.') {
             num.push(sep);
-            while let Some(c) = self.next_if(|c| c.is_ascii_digit()) {
-                num.push(c);
+            num.push_str(&self.next_while(|c| c.is_ascii_digit()).unwrap_or_default());
+            float = true;
+        }
+        let mut invalid = false;
+        if let Some(e) = self.next_if(|c| c == 'e' || c == 'E') {
+            num.push(e);
+            if let Some(sign) = self.next_if(|c| c == '+' || c == '-') {
+                num.push(sign);
             }
+            let exponent = self.next_while(|c| c.is_ascii_digit()).unwrap_or_default();
+            invalid = exponent.is_empty();
+            num.push_str(&exponent);
+            float = true;
         }
+        self.end_number(num, invalid, float)
+    }
 
-        Some(Token::Number(num))
+    // a number running into letters, e.g. 12ab or 0xfg, is an error
+    fn end_number(&mut self, num: String, invalid: bool, float: bool) -> Result<Option<Token>> {
+        let trailing = self
+            .iter
+            .peek()
+            .is_some_and(|c| c.is_alphanumeric() || *c == '_');
+        if invalid || trailing {
+            let rest = self
+                .next_while(|c| c.is_alphanumeric() || c == '_')
+                .unwrap_or_default();
+            return Err(Error::Parse(
+                format!("[Lexer] Invalid number: {}{}", num, rest),
+                None,
+            ));
+        }
+        Ok(Some(match float {
+            true => Token::Float(num),
+            false => Token::Integer(num),
+        }))
     }
 
     fn scan_ident(&mut self) -> Option<Token> {
@@ -263,18 +727,61 @@ impl<'a> Lexer<'a> {
         Some(Keyword::from_str(&value).map_or(Token::Ident(value.to_lowercase()), Token::Keyword))
     }
 
-    fn scan_symbol(&mut self) -> Option<Token> {
-        self.next_if_token(|c| match c {
+    fn scan_symbol(&mut self) -> Result<Option<Token>> {
+        let token = match self.next_if_token(|c| match c {
             '(' => Some(Token::OpenParen),
             ')' => Some(Token::CloseParen),
             ',' => Some(Token::Comma),
             ';' => Some(Token::Semicolon),
+            '.' => Some(Token::Period),
             '*' => Some(Token::Asterisk),
             '+' => Some(Token::Plus),
             '-' => Some(Token::Minus),
             '/' => Some(Token::Slash),
+            '=' => Some(Token::Equal),
+            '>' => Some(Token::GreaterThan),
+            '<' => Some(Token::LessThan),
+            '!' => Some(Token::NotEqual),
+            ':' => Some(Token::DoubleColon),
+            '|' => Some(Token::Concat),
             _ => None,
-        })
+        }) {
+            Some(token) => token,
+            None => return Ok(None),
+        };
+        // two characters operators
+        Ok(Some(match token {
+            Token::GreaterThan if self.next_if(|c| c == '=').is_some() => Token::GreaterThanOrEqual,
+            Token::LessThan if self.next_if(|c| c == '=').is_some() => Token::LessThanOrEqual,
+            Token::LessThan if self.next_if(|c| c == '>').is_some() => Token::NotEqual,
+            Token::Minus if self.next_if(|c| c == '>').is_some() => {
+                match self.next_if(|c| c == '>') {
+                    Some(_) => Token::LongArrow,
+                    None => Token::Arrow,
+                }
+            }
+            // a single ! is not a valid operator
+            Token::NotEqual if self.next_if(|c| c == '=').is_none() => {
+                return Err(Error::Parse(
+                    "[Lexer] Unexpected character: !".to_string(),
+                    None,
+                ))
+            }
+            // so are a single : and |
+            Token::DoubleColon if self.next_if(|c| c == ':').is_none() => {
+                return Err(Error::Parse(
+                    "[Lexer] Unexpected character: :".to_string(),
+                    None,
+                ))
+            }
+            Token::Concat if self.next_if(|c| c == '|').is_none() => {
+                return Err(Error::Parse(
+                    "[Lexer] Unexpected character: |".to_string(),
+                    None,
+                ))
+            }
+            token => token,
+        }))
     }
 }
 
@@ -282,7 +789,12 @@ impl<'a> Lexer<'a> {
 mod tests {
     use crate::error::Result;
 
-    use super::{Keyword, Lexer, Token};
+    use super::{ends_statement, Keyword, Lexer, Token};
+    use crate::error::{Error, Span};
+
+    fn tokens(sql: &str) -> Result<Vec<Token>> {
+        Lexer::new(sql).map(|t| t.map(|(token, _)| token)).collect()
+    }
 
     // "create table tbl ((id1 int primary key, id2 integer, c1 bool null,
     //         c2 boolean not null, c3 float null, c4 double, c5 string, c6 text, c7 varchar default 'foo',
@@ -291,13 +803,11 @@ mod tests {
     //     ",
     #[test]
     fn test_lexer_create_table() -> Result<()> {
-        let tokens = Lexer::new(
+        let tokens = tokens(
             "create table tbl (id1 int primary key, id2 integer
         );
         ",
-        )
-        .peekable()
-        .collect::<Result<Vec<_>>>()?;
+        )?;
 
         assert_eq!(
             tokens,
@@ -323,9 +833,7 @@ mod tests {
 
     #[test]
     fn test_lexer_insert_into() -> Result<()> {
-        let tokens = Lexer::new("insert into tbl values (1, 2, '3', true, false, 4.55);")
-            .peekable()
-            .collect::<Result<Vec<_>>>()?;
+        let tokens = tokens("insert into tbl values (1, 2, '3', true, false, 4.55);")?;
 
         assert_eq!(
             tokens,
@@ -335,9 +843,9 @@ mod tests {
                 Token::Ident("tbl".to_string()),
                 Token::Keyword(Keyword::Values),
                 Token::OpenParen,
-                Token::Number("1".to_string()),
+                Token::Integer("1".to_string()),
                 Token::Comma,
-                Token::Number("2".to_string()),
+                Token::Integer("2".to_string()),
                 Token::Comma,
                 Token::String("3".to_string()),
                 Token::Comma,
@@ -345,7 +853,7 @@ mod tests {
                 Token::Comma,
                 Token::Keyword(Keyword::False),
                 Token::Comma,
-                Token::Number("4.55".to_string()),
+                Token::Float("4.55".to_string()),
                 Token::CloseParen,
                 Token::Semicolon,
             ]
@@ -356,7 +864,7 @@ mod tests {
 
     #[test]
     fn test_lexer_select_from() -> Result<()> {
-        let tokens = Lexer::new("select * from tbl;").collect::<Result<Vec<_>>>()?;
+        let tokens = tokens("select * from tbl;")?;
 
         assert_eq!(
             tokens,
@@ -371,4 +879,224 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_lexer_operators() -> Result<()> {
+        let ops = tokens("a = 1 and b != 2 or c <> 3 and d >= 4 and e <= 5 and f > 6 and g < 7")?
+            .into_iter()
+            .filter(|t| !matches!(t, Token::Ident(_) | Token::Integer(_) | Token::Keyword(_)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            ops,
+            vec![
+                Token::Equal,
+                Token::NotEqual,
+                Token::NotEqual,
+                Token::GreaterThanOrEqual,
+                Token::LessThanOrEqual,
+                Token::GreaterThan,
+                Token::LessThan,
+            ]
+        );
+        assert!(tokens("a ! b").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_spans() -> Result<()> {
+        let spans = Lexer::new("select a,\n  'ü', bc;")
+            .map(|t| t.map(|(_, span)| (span.offset, span.len, span.line, span.column)))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(
+            spans,
+            vec![
+                (0, 6, 1, 1),
+                (7, 1, 1, 8),
+                (8, 1, 1, 9),
+                (12, 4, 2, 3),
+                (16, 1, 2, 6),
+                (18, 2, 2, 8),
+                (20, 1, 2, 10),
+            ]
+        );
+
+        let err = tokens("select a\n  from t where b = 'x;").unwrap_err();
+        assert_eq!(
+            err,
+            Error::Parse(
+                "[Lexer] Unexpected end of string".to_string(),
+                Some(Span {
+                    offset: 28,
+                    len: 3,
+                    line: 2,
+                    column: 20
+                })
+            )
+        );
+        assert_eq!(
+            tokens("a ? b")
+                .unwrap_err()
+                .span()
+                .map(|s| (s.column, s.len)),
+            Some((3, 1))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_comments_and_quotes() -> Result<()> {
+        let scanned = tokens(
+            "-- leading comment
+            select \"Mixed Case\", \"select\", \"a\"\"b\" /* block /* nested */ comment */
+            from tbl -- trailing comment
+            where name = 'it''s' or name = '';--",
+        )?;
+        assert_eq!(
+            scanned,
+            vec![
+                Token::Keyword(Keyword::Select),
+                Token::Ident("Mixed Case".to_string()),
+                Token::Comma,
+                Token::Ident("select".to_string()),
+                Token::Comma,
+                Token::Ident("a\"b".to_string()),
+                Token::Keyword(Keyword::From),
+                Token::Ident("tbl".to_string()),
+                Token::Keyword(Keyword::Where),
+                Token::Ident("name".to_string()),
+                Token::Equal,
+                Token::String("it's".to_string()),
+                Token::Keyword(Keyword::Or),
+                Token::Ident("name".to_string()),
+                Token::Equal,
+                Token::String("".to_string()),
+                Token::Semicolon,
+            ]
+        );
+        // minus and slash are still operators
+        assert_eq!(
+            tokens("a - b / c")?,
+            vec![
+                Token::Ident("a".to_string()),
+                Token::Minus,
+                Token::Ident("b".to_string()),
+                Token::Slash,
+                Token::Ident("c".to_string()),
+            ]
+        );
+
+        let err = tokens("select 1 /* a /* b */").unwrap_err();
+        assert_eq!(err.span().map(|s| (s.column, s.len)), Some((10, 12)));
+        assert!(tokens("select \"\" from t").is_err());
+        assert!(tokens("select \"abc from t").is_err());
+        assert!(tokens("select 'it''s from t").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_numbers() -> Result<()> {
+        assert_eq!(
+            tokens("1 1.5 .5 2. 1e10 2.5E-3 1e+2 0xFF 0X1a -3")?,
+            vec![
+                Token::Integer("1".to_string()),
+                Token::Float("1.5".to_string()),
+                Token::Float(".5".to_string()),
+                Token::Float("2.".to_string()),
+                Token::Float("1e10".to_string()),
+                Token::Float("2.5E-3".to_string()),
+                Token::Float("1e+2".to_string()),
+                Token::Integer("0xFF".to_string()),
+                Token::Integer("0x1a".to_string()),
+                Token::Minus,
+                Token::Integer("3".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokens("a-1")?,
+            vec![
+                Token::Ident("a".to_string()),
+                Token::Minus,
+                Token::Integer("1".to_string())
+            ]
+        );
+        // a period before a letter qualifies a column name
+        assert_eq!(
+            tokens("t.a")?,
+            vec![
+                Token::Ident("t".to_string()),
+                Token::Period,
+                Token::Ident("a".to_string())
+            ]
+        );
+        for sql in ["0x", "0xfg", "1e", "1e+", "12ab", "1.5x"] {
+            let err = tokens(sql).unwrap_err();
+            assert_eq!(err.span().map(|s| s.len), Some(sql.len()), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_lexer_blobs() -> Result<()> {
+        assert_eq!(
+            tokens("X'DEADbeef' x'' x 'a'")?,
+            vec![
+                Token::Blob("DEADbeef".to_string()),
+                Token::Blob("".to_string()),
+                Token::Ident("x".to_string()),
+                Token::String("a".to_string()),
+            ]
+        );
+        assert_eq!(
+            tokens("a->'b'->>0 - >")?,
+            vec![
+                Token::Ident("a".to_string()),
+                Token::Arrow,
+                Token::String("b".to_string()),
+                Token::LongArrow,
+                Token::Integer("0".to_string()),
+                Token::Minus,
+                Token::GreaterThan,
+            ]
+        );
+        assert_eq!(
+            tokens("'1'::int")?,
+            vec![
+                Token::String("1".to_string()),
+                Token::DoubleColon,
+                Token::Keyword(Keyword::Int),
+            ]
+        );
+        assert!(tokens("a : int").is_err());
+        for sql in ["X'abc'", "x'0g'", "X'00"] {
+            let err = tokens(sql).unwrap_err();
+            assert_eq!(err.span().map(|s| s.len), Some(sql.len()), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_ends_statement() {
+        for sql in [
+            "select 1;",
+            "select 'a;' ;  ",
+            "select 1; -- done",
+            "select /* ; */ 1\n;",
+            "select \"a;\" from t; select ?;",
+        ] {
+            assert!(ends_statement(sql), "{}", sql);
+        }
+        for sql in [
+            "",
+            "select 1",
+            "select 'a;",
+            "select 'it''s;",
+            "select \"a;",
+            "select 1 /* ;",
+            "select 1 /* /* */ ;",
+            "select 1 -- ;",
+            "select 1; select 2",
+        ] {
+            assert!(!ends_statement(sql), "{}", sql);
+        }
+    }
 }
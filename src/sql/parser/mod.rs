@@ -1,40 +1,106 @@
-use crate::{error::Result};
+use crate::error::{Result, Span};
 use lexer::{Keyword, Lexer, Token};
-use std::iter::Peekable;
+use std::{collections::BTreeMap, iter::Peekable};
 
 pub mod ast;
 mod lexer;
 
+pub use lexer::ends_statement;
+
 use crate::error::Error;
-use ast::{Column, Statement};
-use crate::sql::types::DataType;
+use ast::{Column, Expression, Operation, Statement};
+use crate::sql::types::{self, datetime, decimal, DataType};
 
 pub struct Parser<'a> {
+    input: &'a str,
     lexer: Peekable<Lexer<'a>>,
+    // span of the last token read
+    span: Span,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
         Parser {
+            input,
             lexer: Lexer::new(input).peekable(),
+            span: Span {
+                offset: 0,
+                len: 0,
+                line: 1,
+                column: 1,
+            },
         }
     }
     pub fn parse(&mut self) -> Result<Statement> {
-        let statement = self.parse_statement()?;
-        self.next_expect(Token::Semicolon)?;
-        if let Some(token) = self.peek()? {
-            return Err(Error::Parse(format!("[Parse] Unexpected token {}", token)));
+        let result = || -> Result<Statement> {
+            let statement = self.parse_statement()?;
+            self.next_expect(Token::Semicolon)?;
+            if self.peek()?.is_some() {
+                let token = self.next()?;
+                return Err(Error::Parse(format!("[Parse] Unexpected token {}", token), None));
+            }
+            Ok(statement)
+        }();
+        result.map_err(|err| self.locate(err))
+    }
+
+    // parses the next statement of a script, none at its end, empty
+    // statements are skipped
+    pub fn parse_next(&mut self) -> Result<Option<Statement>> {
+        let result = || -> Result<Option<Statement>> {
+            while self.next_if_token(Token::Semicolon).is_some() {}
+            if self.peek()?.is_none() {
+                return Ok(None);
+            }
+            let statement = self.parse_statement()?;
+            self.next_expect(Token::Semicolon)?;
+            Ok(Some(statement))
+        }();
+        result.map_err(|err| self.locate(err))
+    }
+
+    // parses a script of semicolon terminated statements
+    pub fn parse_all(&mut self) -> Result<Vec<Statement>> {
+        let mut statements = Vec::new();
+        while let Some(statement) = self.parse_next()? {
+            statements.push(statement);
         }
-        Ok(statement)
+        Ok(statements)
     }
 
     fn parse_statement(&mut self) -> Result<Statement> {
         match self.peek()? {
-            Some(Token::Keyword(Keyword::Create)) => self.parse_ddl(),
+            Some(Token::Keyword(Keyword::Create)) | Some(Token::Keyword(Keyword::Drop)) => {
+                self.parse_ddl()
+            }
             Some(Token::Keyword(Keyword::Insert)) => self.parse_insert(),
             Some(Token::Keyword(Keyword::Select)) => self.parse_select(),
-            Some(token) => Err(Error::Parse(format!("[Parse] Unexpected token {}", token))),
-            None => Err(Error::Parse(format!("[Parse] Unexpected end of input"))),
+            Some(Token::Keyword(Keyword::Update)) => self.parse_update(),
+            Some(Token::Keyword(Keyword::Delete)) => self.parse_delete(),
+            Some(Token::Keyword(Keyword::Explain)) => self.parse_explain(),
+            Some(Token::Keyword(Keyword::Begin)) => {
+                self.next()?;
+                Ok(Statement::Begin)
+            }
+            Some(Token::Keyword(Keyword::Commit)) => {
+                self.next()?;
+                Ok(Statement::Commit)
+            }
+            Some(Token::Keyword(Keyword::Rollback)) => {
+                self.next()?;
+                Ok(Statement::Rollback)
+            }
+            Some(_) => {
+                let token = self.next()?;
+                Err(Error::Parse(format!("[Parse] Unexpected token {}", token), None))
+            }
+            None => {
+                self.span = lexer::end_span(self.input);
+                Err(Error::Parse(
+                    "[Parse] Unexpected end of input".to_string(),
+                    None,
+                ))
+            }
         }
     }
 
@@ -42,24 +108,205 @@ impl<'a> Parser<'a> {
         match self.next()? {
             Token::Keyword(Keyword::Create) => match self.next()? {
                 Token::Keyword(Keyword::Table) => self.parse_ddl_create_table(),
-                token => Err(Error::Parse(format!(
-                    "[Parse] Unexpected end of token: {}",
-                    token
-                ))),
+                Token::Keyword(Keyword::Index) => self.parse_ddl_create_index(false),
+                Token::Keyword(Keyword::Unique) => {
+                    self.next_expect(Token::Keyword(Keyword::Index))?;
+                    self.parse_ddl_create_index(true)
+                }
+                token => Err(Error::Parse(
+                    format!("[Parse] Unexpected end of token: {}", token),
+                    None,
+                )),
+            },
+            Token::Keyword(Keyword::Drop) => match self.next()? {
+                Token::Keyword(Keyword::Index) => Ok(Statement::DropIndex {
+                    name: self.next_ident()?,
+                }),
+                token => Err(Error::Parse(
+                    format!("[Parse] Unexpected end of token: {}", token),
+                    None,
+                )),
             },
-            token => Err(Error::Parse(format!(
-                "[Parse] Unexpected end of token: {}",
-                token
-            ))),
+            token => Err(Error::Parse(
+                format!("[Parse] Unexpected end of token: {}", token),
+                None,
+            )),
+        }
+    }
+
+    fn parse_explain(&mut self) -> Result<Statement> {
+        self.next_expect(Token::Keyword(Keyword::Explain))?;
+        if let Some(Token::Keyword(Keyword::Explain)) = self.peek()? {
+            self.next()?;
+            return Err(Error::Parse(
+                "[Parse] Cannot explain the explain statement".to_string(),
+                None,
+            ));
         }
+        Ok(Statement::Explain {
+            statement: Box::new(self.parse_statement()?),
+        })
     }
 
+    // a SELECT, or SELECTs combined by set operations, LIMIT and OFFSET
+    // apply to the combined rows
     fn parse_select(&mut self) -> Result<Statement> {
+        let mut query = self.parse_set_operation(false)?;
+        let limit = match self.next_if_token(Token::Keyword(Keyword::Limit)) {
+            Some(_) => Some(self.parse_expression()?),
+            None => None,
+        };
+        let offset = match self.next_if_token(Token::Keyword(Keyword::Offset)) {
+            Some(_) => Some(self.parse_expression()?),
+            None => None,
+        };
+        if let Statement::Select {
+            limit: l,
+            offset: o,
+            ..
+        }
+        | Statement::SetOperation {
+            limit: l,
+            offset: o,
+            ..
+        } = &mut query
+        {
+            (*l, *o) = (limit, offset);
+        }
+        Ok(query)
+    }
+
+    // left { UNION | EXCEPT } [ ALL ] right, INTERSECT binds tighter and
+    // is parsed first
+    fn parse_set_operation(&mut self, intersect: bool) -> Result<Statement> {
+        let operand = |parser: &mut Self| match intersect {
+            true => parser.parse_select_core(),
+            false => parser.parse_set_operation(true),
+        };
+        let mut query = operand(self)?;
+        loop {
+            let operator = match self.peek()? {
+                Some(Token::Keyword(Keyword::Intersect)) if intersect => {
+                    ast::SetOperator::Intersect
+                }
+                Some(Token::Keyword(Keyword::Union)) if !intersect => ast::SetOperator::Union,
+                Some(Token::Keyword(Keyword::Except)) if !intersect => ast::SetOperator::Except,
+                _ => return Ok(query),
+            };
+            self.next()?;
+            let all = self.next_if_token(Token::Keyword(Keyword::All)).is_some();
+            query = Statement::SetOperation {
+                operator,
+                all,
+                left: Box::new(query),
+                right: Box::new(operand(self)?),
+                limit: None,
+                offset: None,
+            };
+        }
+    }
+
+    // SELECT [ DISTINCT [ ON (...) ] ] ... FROM ... [ WHERE ... ]
+    fn parse_select_core(&mut self) -> Result<Statement> {
         self.next_expect(Token::Keyword(Keyword::Select))?;
-        self.next_expect(Token::Asterisk)?;
+        let distinct = match self.next_if_token(Token::Keyword(Keyword::Distinct)) {
+            Some(_) => Some(self.parse_distinct_on()?),
+            None => None,
+        };
+        let mut select = Vec::new();
+        if self.next_if_token(Token::Asterisk).is_none() {
+            loop {
+                let expr = self.parse_expression()?;
+                let alias = match self.next_if_token(Token::Keyword(Keyword::As)) {
+                    Some(_) => Some(self.next_ident()?),
+                    None => None,
+                };
+                select.push((expr, alias));
+                if self.next_if_token(Token::Comma).is_none() {
+                    break;
+                }
+            }
+        }
         self.next_expect(Token::Keyword(Keyword::From))?;
+        let from = match self.next_if_token(Token::OpenParen) {
+            Some(_) => {
+                let query = self.parse_select()?;
+                self.next_expect(Token::CloseParen)?;
+                self.next_if_token(Token::Keyword(Keyword::As));
+                match self.next()? {
+                    Token::Ident(alias) => ast::FromItem::Subquery(Box::new(query), alias),
+                    token => {
+                        return Err(Error::Parse(
+                            format!("[Parse] Expect an alias for the subquery, got token: {}", token),
+                            None,
+                        ))
+                    }
+                }
+            }
+            None => {
+                let name = self.next_ident()?;
+                let alias = match self.next_if_token(Token::Keyword(Keyword::As)) {
+                    Some(_) => Some(self.next_ident()?),
+                    None => self.next_if_ident(),
+                };
+                ast::FromItem::Table(name, alias)
+            }
+        };
+        let where_clause = self.parse_where_clause()?;
+        Ok(Statement::Select {
+            distinct,
+            select,
+            from,
+            where_clause,
+            limit: None,
+            offset: None,
+        })
+    }
+
+    fn parse_update(&mut self) -> Result<Statement> {
+        self.next_expect(Token::Keyword(Keyword::Update))?;
         let table_name = self.next_ident()?;
-        Ok(Statement::Select { table_name })
+        self.next_expect(Token::Keyword(Keyword::Set))?;
+
+        let mut columns = BTreeMap::new();
+        loop {
+            let col = self.next_ident()?;
+            let span = self.span;
+            self.next_expect(Token::Equal)?;
+            let value = self.parse_expression()?;
+            if columns.contains_key(&col) {
+                return Err(Error::Parse(
+                    format!("[Parse] Duplicate column {} for update", col),
+                    Some(span),
+                ));
+            }
+            columns.insert(col, value);
+            if self.next_if_token(Token::Comma).is_none() {
+                break;
+            }
+        }
+        Ok(Statement::Update {
+            table_name,
+            columns,
+            where_clause: self.parse_where_clause()?,
+        })
+    }
+
+    fn parse_delete(&mut self) -> Result<Statement> {
+        self.next_expect(Token::Keyword(Keyword::Delete))?;
+        self.next_expect(Token::Keyword(Keyword::From))?;
+        let table_name = self.next_ident()?;
+        Ok(Statement::Delete {
+            table_name,
+            where_clause: self.parse_where_clause()?,
+        })
+    }
+
+    fn parse_where_clause(&mut self) -> Result<Option<Expression>> {
+        if self.next_if_token(Token::Keyword(Keyword::Where)).is_none() {
+            return Ok(None);
+        }
+        Ok(Some(self.parse_expression()?))
     }
 
     fn parse_insert(&mut self) -> Result<Statement> {
@@ -76,7 +323,7 @@ impl<'a> Parser<'a> {
                     Token::CloseParen => break,
                     Token::Comma => continue,
                     token => {
-                        return Err(Error::Parse(format!("[Parse] Unexpected token: {}", token)))
+                        return Err(Error::Parse(format!("[Parse] Unexpected token: {}", token), None))
                     }
                 }
             }
@@ -96,7 +343,7 @@ impl<'a> Parser<'a> {
                     Token::CloseParen => break,
                     Token::Comma => continue,
                     token => {
-                        return Err(Error::Parse(format!("[Parse] Unexpected token: {}", token)))
+                        return Err(Error::Parse(format!("[Parse] Unexpected token: {}", token), None))
                     }
                 }
             }
@@ -133,29 +380,33 @@ impl<'a> Parser<'a> {
         })
     }
 
+    fn parse_ddl_create_index(&mut self, unique: bool) -> Result<Statement> {
+        let name = self.next_ident()?;
+        self.next_expect(Token::Keyword(Keyword::On))?;
+        let table_name = self.next_ident()?;
+        self.next_expect(Token::OpenParen)?;
+        let column = self.next_ident()?;
+        self.next_expect(Token::CloseParen)?;
+        Ok(Statement::CreateIndex {
+            name,
+            table_name,
+            column,
+            unique,
+        })
+    }
+
     fn parse_ddl_column(&mut self) -> Result<Column> {
+        let name = self.next_ident()?;
+        let typename = self.parse_type()?;
         let mut column = Column {
-            name: self.next_ident()?,
-            datatype: match self.next()? {
-                Token::Keyword(Keyword::Int) | Token::Keyword(Keyword::Integer) => {
-                    DataType::Integer
-                }
-                Token::Keyword(Keyword::Float) | Token::Keyword(Keyword::Double) => DataType::Float,
-                Token::Keyword(Keyword::Bool) | Token::Keyword(Keyword::Boolean) => {
-                    DataType::Boolean
-                }
-                Token::Keyword(Keyword::String)
-                | Token::Keyword(Keyword::Text)
-                | Token::Keyword(Keyword::Varchar) => DataType::String,
-                token => {
-                    return Err(Error::Parse(format!(
-                        "[Parse] Unexpected datatype: {}",
-                        token
-                    )));
-                }
-            },
+            name,
+            datatype: typename.datatype,
             nullable: None,
             default: None,
+            primary_key: false,
+            precision: typename.precision,
+            length: typename.length,
+            padded: typename.padded,
         };
         // parse column default, and if can be null
         while let Some(Token::Keyword(keyword)) = self.next_if_keyword() {
@@ -166,61 +417,553 @@ impl<'a> Parser<'a> {
                     column.nullable = Some(false);
                 }
                 Keyword::Default => column.default = Some(self.parse_expression()?),
-                k => return Err(Error::Parse(format!("[Parse] Unexpected keyword: {}", k))),
+                Keyword::Primary => {
+                    self.next_expect(Token::Keyword(Keyword::Key))?;
+                    column.primary_key = true;
+                }
+                k => return Err(Error::Parse(format!("[Parse] Unexpected keyword: {}", k), None)),
             }
         }
         Ok(column)
     }
 
+    // a data type with its optional modifiers, of a column or a CAST
+    fn parse_type(&mut self) -> Result<ast::TypeName> {
+        let token = self.next()?;
+        let mut typename = ast::TypeName {
+            datatype: match &token {
+                Token::Keyword(Keyword::Int) | Token::Keyword(Keyword::Integer) => {
+                    DataType::Integer
+                }
+                Token::Keyword(Keyword::Float) | Token::Keyword(Keyword::Double) => DataType::Float,
+                Token::Keyword(Keyword::Bool) | Token::Keyword(Keyword::Boolean) => {
+                    DataType::Boolean
+                }
+                Token::Keyword(Keyword::String)
+                | Token::Keyword(Keyword::Text)
+                | Token::Keyword(Keyword::Varchar)
+                | Token::Keyword(Keyword::Char) => DataType::String,
+                Token::Keyword(Keyword::Date) => DataType::Date,
+                Token::Keyword(Keyword::Time) => DataType::Time,
+                Token::Keyword(Keyword::Timestamp) => DataType::Timestamp,
+                Token::Keyword(Keyword::Interval) => DataType::Interval,
+                Token::Keyword(Keyword::Decimal) | Token::Keyword(Keyword::Numeric) => {
+                    DataType::Decimal
+                }
+                Token::Keyword(Keyword::Blob) | Token::Keyword(Keyword::Bytea) => DataType::Blob,
+                Token::Keyword(Keyword::Json) => DataType::Json,
+                token => {
+                    return Err(Error::Parse(
+                        format!("[Parse] Unexpected datatype: {}", token),
+                        None,
+                    ));
+                }
+            },
+            precision: None,
+            length: None,
+            padded: false,
+        };
+        match token {
+            Token::Keyword(Keyword::Decimal | Keyword::Numeric) => {
+                typename.precision = self.parse_ddl_precision()?
+            }
+            Token::Keyword(Keyword::Varchar) => typename.length = self.parse_ddl_length()?,
+            // CHAR alone is CHAR(1)
+            Token::Keyword(Keyword::Char) => {
+                typename.length = Some(self.parse_ddl_length()?.unwrap_or(1));
+                typename.padded = true;
+            }
+            _ => {}
+        }
+        Ok(typename)
+    }
+
+    // optional (precision[, scale]) of DECIMAL, the scale defaults to 0
+    fn parse_ddl_precision(&mut self) -> Result<Option<(u32, u32)>> {
+        if self.next_if_token(Token::OpenParen).is_none() {
+            return Ok(None);
+        }
+        let span = self.span;
+        let precision = self.next_u32()?;
+        let scale = match self.next_if_token(Token::Comma) {
+            Some(_) => self.next_u32()?,
+            None => 0,
+        };
+        self.next_expect(Token::CloseParen)?;
+        if precision == 0 || precision > decimal::MAX_PRECISION || scale > precision {
+            return Err(Error::Parse(
+                format!(
+                    "[Parse] Invalid DECIMAL({}, {}), expect precision 1 to {} and scale up to it",
+                    precision,
+                    scale,
+                    decimal::MAX_PRECISION
+                ),
+                Some(span),
+            ));
+        }
+        Ok(Some((precision, scale)))
+    }
+
+    // optional (length) of VARCHAR and CHAR, in characters
+    fn parse_ddl_length(&mut self) -> Result<Option<u32>> {
+        if self.next_if_token(Token::OpenParen).is_none() {
+            return Ok(None);
+        }
+        let span = self.span;
+        let length = self.next_u32()?;
+        self.next_expect(Token::CloseParen)?;
+        if length == 0 {
+            return Err(Error::Parse(
+                "[Parse] Length must be at least 1".to_string(),
+                Some(span),
+            ));
+        }
+        Ok(Some(length))
+    }
+
+    // expression precedence from low to high: OR, AND, NOT, comparison, ||,
+    // + and -, * and /, -> and ->>, ::
     fn parse_expression(&mut self) -> Result<ast::Expression> {
+        let mut expr = self.parse_expression_and()?;
+        while self.next_if_token(Token::Keyword(Keyword::Or)).is_some() {
+            let right = self.parse_expression_and()?;
+            expr = Operation::Or(Box::new(expr), Box::new(right)).into();
+        }
+        Ok(expr)
+    }
+
+    fn parse_expression_and(&mut self) -> Result<ast::Expression> {
+        let mut expr = self.parse_expression_not()?;
+        while self.next_if_token(Token::Keyword(Keyword::And)).is_some() {
+            let right = self.parse_expression_not()?;
+            expr = Operation::And(Box::new(expr), Box::new(right)).into();
+        }
+        Ok(expr)
+    }
+
+    fn parse_expression_not(&mut self) -> Result<ast::Expression> {
+        if self.next_if_token(Token::Keyword(Keyword::Not)).is_some() {
+            return Ok(Operation::Not(Box::new(self.parse_expression_not()?)).into());
+        }
+        self.parse_expression_compare()
+    }
+
+    fn parse_expression_compare(&mut self) -> Result<ast::Expression> {
+        let left = Box::new(self.parse_expression_concat()?);
+        // x NOT IN, NOT BETWEEN and NOT LIKE are NOT applied to the predicate
+        if self.next_if_token(Token::Keyword(Keyword::Not)).is_some() {
+            let expr = match self.peek()? {
+                Some(Token::Keyword(
+                    Keyword::In | Keyword::Between | Keyword::Like | Keyword::ILike,
+                )) => self.parse_predicate(left)?,
+                _ => {
+                    let token = self.next()?;
+                    return Err(Error::Parse(
+                        format!(
+                            "[Parse] Expect IN, BETWEEN, LIKE or ILIKE after NOT, got token: {}",
+                            token
+                        ),
+                        None,
+                    ));
+                }
+            };
+            return Ok(Operation::Not(Box::new(expr)).into());
+        }
+        if let Some(Token::Keyword(
+            Keyword::In | Keyword::Between | Keyword::Like | Keyword::ILike,
+        )) = self.peek()?
+        {
+            return self.parse_predicate(left);
+        }
+
+        let operation: fn(Box<Expression>, Box<Expression>) -> Operation = match self.peek()? {
+            Some(Token::Equal) => Operation::Equal,
+            Some(Token::NotEqual) => Operation::NotEqual,
+            Some(Token::GreaterThan) => Operation::GreaterThan,
+            Some(Token::GreaterThanOrEqual) => Operation::GreaterThanOrEqual,
+            Some(Token::LessThan) => Operation::LessThan,
+            Some(Token::LessThanOrEqual) => Operation::LessThanOrEqual,
+            _ => return Ok(*left),
+        };
+        self.next()?;
+        let right = Box::new(self.parse_expression_concat()?);
+        Ok(operation(left, right).into())
+    }
+
+    // left IN (...), left BETWEEN a AND b, left LIKE pattern [ESCAPE c]
+    fn parse_predicate(&mut self, left: Box<Expression>) -> Result<ast::Expression> {
+        if self.next_if_token(Token::Keyword(Keyword::In)).is_some() {
+            self.next_expect(Token::OpenParen)?;
+            if let Some(Token::Keyword(Keyword::Select)) = self.peek()? {
+                let subquery = self.parse_subquery()?;
+                return Ok(Operation::InSubquery(left, Box::new(subquery)).into());
+            }
+            let mut list = Vec::new();
+            loop {
+                list.push(self.parse_expression()?);
+                match self.next()? {
+                    Token::CloseParen => break,
+                    Token::Comma => continue,
+                    token => {
+                        return Err(Error::Parse(format!("[Parse] Unexpected token: {}", token), None))
+                    }
+                }
+            }
+            return Ok(Operation::In(left, list).into());
+        }
+        if self.next_if_token(Token::Keyword(Keyword::Between)).is_some() {
+            let low = Box::new(self.parse_expression_concat()?);
+            self.next_expect(Token::Keyword(Keyword::And))?;
+            let high = Box::new(self.parse_expression_concat()?);
+            return Ok(Operation::Between(left, low, high).into());
+        }
+        let operation = match self.next()? {
+            Token::Keyword(Keyword::Like) => Operation::Like,
+            Token::Keyword(Keyword::ILike) => Operation::ILike,
+            token => {
+                return Err(Error::Parse(
+                    format!("[Parse] Unexpected token: {}", token),
+                    None,
+                ))
+            }
+        };
+        let pattern = Box::new(self.parse_expression_concat()?);
+        let escape = match self.next_if_token(Token::Keyword(Keyword::Escape)) {
+            Some(_) => Some(Box::new(self.parse_expression_concat()?)),
+            None => None,
+        };
+        Ok(operation(left, pattern, escape).into())
+    }
+
+    fn parse_expression_concat(&mut self) -> Result<ast::Expression> {
+        let mut expr = self.parse_expression_sum()?;
+        while self.next_if_token(Token::Concat).is_some() {
+            let right = self.parse_expression_sum()?;
+            expr = Operation::Concat(Box::new(expr), Box::new(right)).into();
+        }
+        Ok(expr)
+    }
+
+    fn parse_expression_sum(&mut self) -> Result<ast::Expression> {
+        let mut expr = self.parse_expression_product()?;
+        loop {
+            let operation: fn(Box<Expression>, Box<Expression>) -> Operation = match self.peek()? {
+                Some(Token::Plus) => Operation::Add,
+                Some(Token::Minus) => Operation::Subtract,
+                _ => return Ok(expr),
+            };
+            self.next()?;
+            let right = self.parse_expression_product()?;
+            expr = operation(Box::new(expr), Box::new(right)).into();
+        }
+    }
+
+    fn parse_expression_product(&mut self) -> Result<ast::Expression> {
+        let mut expr = self.parse_expression_json()?;
+        loop {
+            let operation: fn(Box<Expression>, Box<Expression>) -> Operation = match self.peek()? {
+                Some(Token::Asterisk) => Operation::Multiply,
+                Some(Token::Slash) => Operation::Divide,
+                _ => return Ok(expr),
+            };
+            self.next()?;
+            let right = self.parse_expression_json()?;
+            expr = operation(Box::new(expr), Box::new(right)).into();
+        }
+    }
+
+    fn parse_expression_json(&mut self) -> Result<ast::Expression> {
+        let mut expr = self.parse_expression_cast()?;
+        loop {
+            let operation: fn(Box<Expression>, Box<Expression>) -> Operation = match self.peek()? {
+                Some(Token::Arrow) => Operation::JsonGet,
+                Some(Token::LongArrow) => Operation::JsonGetText,
+                _ => return Ok(expr),
+            };
+            self.next()?;
+            let right = self.parse_expression_cast()?;
+            expr = operation(Box::new(expr), Box::new(right)).into();
+        }
+    }
+
+    fn parse_expression_cast(&mut self) -> Result<ast::Expression> {
+        let mut expr = self.parse_expression_atom()?;
+        while self.next_if_token(Token::DoubleColon).is_some() {
+            expr = ast::Expression::Cast(Box::new(expr), self.parse_type()?);
+        }
+        Ok(expr)
+    }
+
+    fn parse_expression_atom(&mut self) -> Result<ast::Expression> {
         Ok(match self.next()? {
-            Token::Number(n) => {
-                if n.chars().all(|c| c.is_ascii_digit()) {
-                    ast::Consts::Integer(n.parse()?).into()
-                } else {
-                    ast::Consts::Float(n.parse()?).into()
+            Token::Integer(n) => ast::Consts::Integer(Self::parse_integer(&n)?).into(),
+            Token::Float(n) => Self::parse_fraction(&n)?,
+            // a sign right before a number is part of the literal, so that
+            // -9223372036854775808 fits, otherwise it applies to the operand
+            sign @ (Token::Minus | Token::Plus) => {
+                let sign = if sign == Token::Minus { "-" } else { "" };
+                match self.next_if(|t| matches!(t, Token::Integer(_) | Token::Float(_))) {
+                    Some(Token::Integer(n)) => {
+                        ast::Consts::Integer(Self::parse_integer(&format!("{}{}", sign, n))?).into()
+                    }
+                    Some(Token::Float(n)) => Self::parse_fraction(&format!("{}{}", sign, n))?,
+                    _ if sign == "-" => {
+                        Operation::Negate(Box::new(self.parse_expression_cast()?)).into()
+                    }
+                    _ => return self.parse_expression_cast(),
                 }
             }
             Token::String(s) => ast::Consts::String(s).into(),
+            Token::Blob(hex) => ast::Consts::Blob(types::parse_hex(&hex)?).into(),
             Token::Keyword(Keyword::True) => ast::Consts::Boolean(true).into(),
             Token::Keyword(Keyword::False) => ast::Consts::Boolean(false).into(),
             Token::Keyword(Keyword::Null) => ast::Consts::Null.into(),
+            // typed literals, e.g. DATE '2026-10-17'
+            Token::Keyword(
+                keyword @ (Keyword::Date
+                | Keyword::Time
+                | Keyword::Timestamp
+                | Keyword::Interval
+                | Keyword::Decimal
+                | Keyword::Numeric
+                | Keyword::Json),
+            ) => {
+                let text = match self.next()? {
+                    Token::String(text) => text,
+                    token => {
+                        return Err(Error::Parse(
+                            format!(
+                                "[Parse] Expect string after {}, got token: {}",
+                                keyword, token
+                            ),
+                            None,
+                        ))
+                    }
+                };
+                match keyword {
+                    Keyword::Date => ast::Consts::Date(datetime::parse_date(&text)?),
+                    Keyword::Time => ast::Consts::Time(datetime::parse_time(&text)?),
+                    Keyword::Timestamp => ast::Consts::Timestamp(datetime::parse_timestamp(&text)?),
+                    Keyword::Decimal | Keyword::Numeric => ast::Consts::Decimal(text.parse()?),
+                    Keyword::Json => ast::Consts::Json(text.parse()?),
+                    _ => ast::Consts::Interval(datetime::parse_interval(&text)?),
+                }
+                .into()
+            }
+            Token::Keyword(Keyword::Cast) => {
+                self.next_expect(Token::OpenParen)?;
+                let expr = self.parse_expression()?;
+                self.next_expect(Token::Keyword(Keyword::As))?;
+                let typename = self.parse_type()?;
+                self.next_expect(Token::CloseParen)?;
+                ast::Expression::Cast(Box::new(expr), typename)
+            }
+            Token::Keyword(Keyword::Case) => {
+                let operand = match self.peek()? {
+                    Some(Token::Keyword(Keyword::When)) => None,
+                    _ => Some(Box::new(self.parse_expression()?)),
+                };
+                let mut branches = Vec::new();
+                while self.next_if_token(Token::Keyword(Keyword::When)).is_some() {
+                    let when = self.parse_expression()?;
+                    self.next_expect(Token::Keyword(Keyword::Then))?;
+                    branches.push((when, self.parse_expression()?));
+                }
+                if branches.is_empty() {
+                    return Err(Error::Parse(
+                        "[Parse] Expect WHEN in CASE".to_string(),
+                        None,
+                    ));
+                }
+                let default = match self.next_if_token(Token::Keyword(Keyword::Else)) {
+                    Some(_) => Some(Box::new(self.parse_expression()?)),
+                    None => None,
+                };
+                self.next_expect(Token::Keyword(Keyword::End))?;
+                ast::Expression::Case(operand, branches, default)
+            }
+            Token::Keyword(Keyword::CurrentDate) => ast::Expression::Current(ast::Current::Date),
+            Token::Keyword(Keyword::CurrentTime) => ast::Expression::Current(ast::Current::Time),
+            Token::Keyword(Keyword::CurrentTimestamp) => {
+                ast::Expression::Current(ast::Current::Timestamp)
+            }
+            Token::Ident(ident) => {
+                if self.next_if_token(Token::Period).is_some() {
+                    return Ok(ast::Expression::Field(Some(ident), self.next_ident()?));
+                }
+                if self.next_if_token(Token::OpenParen).is_none() {
+                    return Ok(ast::Expression::Field(None, ident));
+                }
+                let name = ident.to_lowercase();
+                if name == "now" {
+                    self.next_expect(Token::CloseParen)?;
+                    return Ok(ast::Expression::Current(ast::Current::Timestamp));
+                }
+                // the function and its arguments are checked by the planner
+                let mut args = Vec::new();
+                if self.next_if_token(Token::CloseParen).is_none() {
+                    loop {
+                        args.push(self.parse_expression()?);
+                        if self.next_if_token(Token::Comma).is_none() {
+                            break;
+                        }
+                    }
+                    self.next_expect(Token::CloseParen)?;
+                }
+                ast::Expression::Function(name, args)
+            }
+            Token::OpenParen => match self.peek()? {
+                Some(Token::Keyword(Keyword::Select)) => {
+                    ast::Expression::Subquery(Box::new(self.parse_subquery()?))
+                }
+                _ => {
+                    let expr = self.parse_expression()?;
+                    self.next_expect(Token::CloseParen)?;
+                    expr
+                }
+            },
+            Token::Keyword(Keyword::Exists) => {
+                self.next_expect(Token::OpenParen)?;
+                ast::Expression::Exists(Box::new(self.parse_subquery()?))
+            }
             token => {
-                return Err(Error::Parse(format!(
-                    "[Parse] Unexpected expression: {}",
-                    token
-                )))
+                return Err(Error::Parse(
+                    format!("[Parse] Unexpected expression: {}", token),
+                    None,
+                ))
             }
         })
     }
 
+    // ON (expr [, ...]) after DISTINCT, no expressions without it
+    fn parse_distinct_on(&mut self) -> Result<Vec<Expression>> {
+        let mut on = Vec::new();
+        if self.next_if_token(Token::Keyword(Keyword::On)).is_none() {
+            return Ok(on);
+        }
+        self.next_expect(Token::OpenParen)?;
+        loop {
+            on.push(self.parse_expression()?);
+            match self.next()? {
+                Token::CloseParen => return Ok(on),
+                Token::Comma => continue,
+                token => {
+                    return Err(Error::Parse(format!("[Parse] Unexpected token: {}", token), None))
+                }
+            }
+        }
+    }
+
+    // SELECT ...) of a subquery, the opening parenthesis is already read
+    fn parse_subquery(&mut self) -> Result<ast::Statement> {
+        let query = self.parse_select()?;
+        self.next_expect(Token::CloseParen)?;
+        Ok(query)
+    }
+
+    // decimal or 0x prefixed hexadecimal, with an optional minus sign
+    fn parse_integer(text: &str) -> Result<i64> {
+        let (sign, digits) = match text.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", text),
+        };
+        let (radix, digits) = match digits.strip_prefix("0x") {
+            Some(digits) => (16, digits),
+            None => (10, digits),
+        };
+        i64::from_str_radix(&format!("{}{}", sign, digits), radix).map_err(|_| {
+            Error::Parse(
+                format!("[Parse] Integer {} is out of range", text),
+                None,
+            )
+        })
+    }
+
+    // exact like in PostgreSQL, so 0.1 is a DECIMAL, only numbers with an
+    // exponent are FLOAT
+    fn parse_fraction(text: &str) -> Result<ast::Expression> {
+        if text.contains(['e', 'E']) {
+            return Ok(ast::Consts::Float(Self::parse_float(text)?).into());
+        }
+        let value = text.parse().map_err(|_| {
+            Error::Parse(format!("[Parse] Decimal {} is out of range", text), None)
+        })?;
+        Ok(ast::Consts::Decimal(value).into())
+    }
+
+    fn parse_float(text: &str) -> Result<f64> {
+        let value: f64 = text.parse()?;
+        if value.is_infinite() {
+            return Err(Error::Parse(
+                format!("[Parse] Float {} is out of range", text),
+                None,
+            ));
+        }
+        Ok(value)
+    }
+
+    // errors raised while parsing point at the last token read
+    fn locate(&self, err: Error) -> Error {
+        match err {
+            Error::Parse(msg, None) => Error::Parse(msg, Some(self.span)),
+            err => err,
+        }
+    }
+
     fn peek(&mut self) -> Result<Option<Token>> {
-        self.lexer.peek().cloned().transpose()
+        self.lexer
+            .peek()
+            .cloned()
+            .map(|t| t.map(|(token, _)| token))
+            .transpose()
     }
 
     fn next(&mut self) -> Result<Token> {
-        self.lexer
-            .next()
-            .unwrap_or_else(|| Err(Error::Parse(format!("[Parse] Unexpected end of input"))))
+        match self.lexer.next() {
+            Some(Ok((token, span))) => {
+                self.span = span;
+                Ok(token)
+            }
+            Some(Err(err)) => Err(err),
+            None => {
+                self.span = lexer::end_span(self.input);
+                Err(Error::Parse(
+                    "[Parse] Unexpected end of input".to_string(),
+                    None,
+                ))
+            }
+        }
     }
 
     fn next_ident(&mut self) -> Result<String> {
         match self.next()? {
             Token::Ident(ident) => Ok(ident),
-            token => Err(Error::Parse(format!(
-                "[Parse] Expect Ident, got token: {}",
-                token
-            ))),
+            token => Err(Error::Parse(
+                format!("[Parse] Expect Ident, got token: {}", token),
+                None,
+            )),
+        }
+    }
+
+    fn next_u32(&mut self) -> Result<u32> {
+        match self.next()? {
+            Token::Integer(n) => n.parse().map_err(|_| {
+                Error::Parse(format!("[Parse] Integer {} is out of range", n), None)
+            }),
+            token => Err(Error::Parse(
+                format!("[Parse] Expect Integer, got token: {}", token),
+                None,
+            )),
         }
     }
 
     fn next_expect(&mut self, expect: Token) -> Result<()> {
         let token = self.next()?;
         if token != expect {
-            return Err(Error::Parse(format!(
-                "[Parse] Expect token: {}, got token: {}",
-                expect, token
-            )));
+            return Err(Error::Parse(
+                format!("[Parse] Expect token: {}, got token: {}", expect, token),
+                None,
+            ));
         }
         Ok(())
     }
@@ -233,6 +976,13 @@ impl<'a> Parser<'a> {
         self.next_if(|t| matches!(t, Token::Keyword(_)))
     }
 
+    fn next_if_ident(&mut self) -> Option<String> {
+        match self.next_if(|t| matches!(t, Token::Ident(_))) {
+            Some(Token::Ident(ident)) => Some(ident),
+            _ => None,
+        }
+    }
+
     fn next_if_token(&mut self, token: Token) -> Option<Token> {
         self.next_if(|t| t == &token)
     }
@@ -240,8 +990,10 @@ impl<'a> Parser<'a> {
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
     use super::{
-        ast::{Consts, Expression},
+        ast::{Consts, Expression, FromItem, Operation, SetOperator},
         Parser, Statement,
     };
     use crate::error::Result;
@@ -292,4 +1044,652 @@ mod tests {
         println!("{:?}", stmt2);
         Ok(())
     }
+
+    #[test]
+    fn test_parser_where_clause() -> Result<()> {
+        let stmt = Parser::new("select * from tbl1 where a >= 1 and not b = 'x' or c in (1, 2);").parse()?;
+        let a = Box::new(Expression::Field(None, "a".to_string()));
+        let b = Box::new(Expression::Field(None, "b".to_string()));
+        let c = Box::new(Expression::Field(None, "c".to_string()));
+        assert_eq!(
+            stmt,
+            Statement::Select {
+                distinct: None,
+                select: vec![],
+                from: FromItem::Table("tbl1".to_string(), None),
+                where_clause: Some(
+                    Operation::Or(
+                        Box::new(
+                            Operation::And(
+                                Box::new(
+                                    Operation::GreaterThanOrEqual(a, Box::new(Consts::Integer(1).into()))
+                                        .into()
+                                ),
+                                Box::new(
+                                    Operation::Not(Box::new(
+                                        Operation::Equal(b, Box::new(Consts::String("x".to_string()).into()))
+                                            .into()
+                                    ))
+                                    .into()
+                                ),
+                            )
+                            .into()
+                        ),
+                        Box::new(
+                            Operation::In(c, vec![Consts::Integer(1).into(), Consts::Integer(2).into()])
+                                .into()
+                        ),
+                    )
+                    .into()
+                ),
+                limit: None,
+                offset: None,
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_update_delete_index() -> Result<()> {
+        let stmt = Parser::new("update tbl1 set a = 1, b = 'x' where c != 2;").parse()?;
+        assert_eq!(
+            stmt,
+            Statement::Update {
+                table_name: "tbl1".to_string(),
+                columns: BTreeMap::from([
+                    ("a".to_string(), Consts::Integer(1).into()),
+                    ("b".to_string(), Consts::String("x".to_string()).into()),
+                ]),
+                where_clause: Some(
+                    Operation::NotEqual(
+                        Box::new(Expression::Field(None, "c".to_string())),
+                        Box::new(Consts::Integer(2).into())
+                    )
+                    .into()
+                ),
+            }
+        );
+
+        let stmt = Parser::new("delete from tbl1 where a between 1 and 2 and b = 3;").parse()?;
+        assert_eq!(
+            stmt,
+            Statement::Delete {
+                table_name: "tbl1".to_string(),
+                where_clause: Some(
+                    Operation::And(
+                        Box::new(
+                            Operation::Between(
+                                Box::new(Expression::Field(None, "a".to_string())),
+                                Box::new(Consts::Integer(1).into()),
+                                Box::new(Consts::Integer(2).into()),
+                            )
+                            .into()
+                        ),
+                        Box::new(
+                            Operation::Equal(
+                                Box::new(Expression::Field(None, "b".to_string())),
+                                Box::new(Consts::Integer(3).into())
+                            )
+                            .into()
+                        ),
+                    )
+                    .into()
+                ),
+            }
+        );
+
+        let stmt = Parser::new("delete from tbl1;").parse()?;
+        assert_eq!(
+            stmt,
+            Statement::Delete {
+                table_name: "tbl1".to_string(),
+                where_clause: None,
+            }
+        );
+
+        let stmt = Parser::new("create unique index idx1 on tbl1 (a);").parse()?;
+        assert_eq!(
+            stmt,
+            Statement::CreateIndex {
+                name: "idx1".to_string(),
+                table_name: "tbl1".to_string(),
+                column: "a".to_string(),
+                unique: true,
+            }
+        );
+
+        let stmt = Parser::new("drop index idx1;").parse()?;
+        assert_eq!(stmt, Statement::DropIndex { name: "idx1".to_string() });
+        assert!(Parser::new("update tbl1 set a = 1, a = 2;").parse().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_select_explain() -> Result<()> {
+        let stmt = Parser::new("explain select a, b > 1 as c from tbl1;").parse()?;
+        assert_eq!(
+            stmt,
+            Statement::Explain {
+                statement: Box::new(Statement::Select {
+                    distinct: None,
+                    select: vec![
+                        (Expression::Field(None, "a".to_string()), None),
+                        (
+                            Operation::GreaterThan(
+                                Box::new(Expression::Field(None, "b".to_string())),
+                                Box::new(Consts::Integer(1).into())
+                            )
+                            .into(),
+                            Some("c".to_string())
+                        ),
+                    ],
+                    from: FromItem::Table("tbl1".to_string(), None),
+                    where_clause: None,
+                    limit: None,
+                    offset: None,
+                })
+            }
+        );
+        assert!(Parser::new("explain explain select * from tbl1;").parse().is_err());
+        assert_eq!(Parser::new("begin;").parse()?, Statement::Begin);
+        assert_eq!(Parser::new("ROLLBACK;").parse()?, Statement::Rollback);
+        assert!(Parser::new("commit work;").parse().is_err());
+        assert!(Parser::new("select a, from tbl1;").parse().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_limit_offset() -> Result<()> {
+        let stmt = Parser::new("select * from tbl1 where a = 1 limit 10 offset 5;").parse()?;
+        assert!(matches!(
+            stmt,
+            Statement::Select {
+                where_clause: Some(_),
+                limit: Some(Expression::Consts(Consts::Integer(10))),
+                offset: Some(Expression::Consts(Consts::Integer(5))),
+                ..
+            }
+        ));
+        let stmt = Parser::new("select * from tbl1 offset 5;").parse()?;
+        assert!(matches!(
+            stmt,
+            Statement::Select {
+                limit: None,
+                offset: Some(_),
+                ..
+            }
+        ));
+        assert!(Parser::new("select * from tbl1 offset 5 limit 10;").parse().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_parse_all() -> Result<()> {
+        let sql = "
+            create table t (id int primary key);;
+            begin;
+            insert into t values (1);
+            commit;
+        ";
+        let stmts = Parser::new(sql).parse_all()?;
+        assert_eq!(stmts.len(), 4);
+        assert_eq!(stmts[1], Statement::Begin);
+        assert_eq!(stmts[3], Statement::Commit);
+        assert_eq!(Parser::new(" ; ").parse_all()?, vec![]);
+
+        let mut parser = Parser::new("begin; commit rollback;");
+        assert_eq!(parser.parse_next()?, Some(Statement::Begin));
+        assert!(parser.parse_next().is_err());
+        // every statement needs its semicolon
+        assert!(Parser::new("begin; commit").parse_all().is_err());
+        assert!(Parser::new("begin; commit;").parse().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_error_span() -> Result<()> {
+        let sql = "create table t (id int primary key);\ninsert into t values (1,, 2);";
+        let err = Parser::new(sql).parse_all().unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!((span.offset, span.line, span.column), (61, 2, 25));
+        assert_eq!(
+            err.to_string(),
+            "[Parse] Unexpected expression: , at line 2, column 25"
+        );
+        assert_eq!(
+            span.highlight(sql),
+            [
+                "LINE 2: insert into t values (1,, 2);",
+                "                                ^"
+            ]
+            .join("\n")
+        );
+
+        // end of input is past the last character
+        let err = Parser::new("select *\n  from").parse().unwrap_err();
+        let span = err.span().unwrap();
+        assert_eq!((span.offset, span.len, span.line, span.column), (15, 0, 2, 7));
+
+        let err = Parser::new("update t set a = 1, b = 2, a = 3;").parse().unwrap_err();
+        assert_eq!(err.span().map(|s| s.column), Some(28));
+        let err = Parser::new("select * from t where a = 99999999999999999999;")
+            .parse()
+            .unwrap_err();
+        assert_eq!(err.span().map(|s| (s.column, s.len)), Some((27, 20)));
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_numbers() -> Result<()> {
+        let value = |sql: &str| -> Result<Expression> {
+            match Parser::new(&format!("select * from t where a = {};", sql)).parse()? {
+                Statement::Select {
+                    where_clause: Some(Expression::Operation(Operation::Equal(_, value))),
+                    ..
+                } => Ok(*value),
+                stmt => panic!("unexpected statement {:?}", stmt),
+            }
+        };
+        assert_eq!(value("-5")?, Consts::Integer(-5).into());
+        assert_eq!(value("+ 5")?, Consts::Integer(5).into());
+        assert_eq!(value("0xff")?, Consts::Integer(255).into());
+        assert_eq!(value("-0x10")?, Consts::Integer(-16).into());
+        assert_eq!(value("1e3")?, Consts::Float(1000.0).into());
+        assert_eq!(value("-.5")?, Consts::Decimal("-0.5".parse()?).into());
+        assert_eq!(value("2.")?, Consts::Decimal("2".parse()?).into());
+        assert_eq!(value("2.5E-1")?, Consts::Float(0.25).into());
+        assert_eq!(
+            value("9223372036854775807")?,
+            Consts::Integer(i64::MAX).into()
+        );
+        assert_eq!(
+            value("-9223372036854775808")?,
+            Consts::Integer(i64::MIN).into()
+        );
+        assert_eq!(
+            value("0x7fffffffffffffff")?,
+            Consts::Integer(i64::MAX).into()
+        );
+
+        let err = value("9223372036854775808").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "[Parse] Integer 9223372036854775808 is out of range at line 1, column 27"
+        );
+        assert!(value("0x8000000000000000").is_err());
+        assert!(value("1e400").is_err());
+        // the sign is part of a number right after it, otherwise an operator
+        let b = || Box::new(Expression::Field(None, "b".to_string()));
+        assert_eq!(value("-b")?, Operation::Negate(b()).into());
+        assert_eq!(value("+b")?, *b());
+        assert_eq!(
+            value("- -5")?,
+            Operation::Negate(Box::new(Consts::Integer(-5).into())).into()
+        );
+        let sum = Operation::Add(b(), Box::new(Expression::Field(None, "c".to_string())));
+        assert_eq!(
+            value("-(b + c)")?,
+            Operation::Negate(Box::new(sum.into())).into()
+        );
+        assert_eq!(
+            value("2 * -b")?,
+            Operation::Multiply(
+                Box::new(Consts::Integer(2).into()),
+                Box::new(Operation::Negate(b()).into())
+            )
+            .into()
+        );
+        for sql in [
+            "-b",
+            "-(b + c)",
+            "2 * -b",
+            "-(-5)",
+            "-(5)",
+            "-(b -> 'x')",
+            "-CAST(b AS STRING)",
+        ] {
+            assert_eq!(value(sql)?.to_string(), sql);
+        }
+
+        // a negative limit is rejected by the planner, not the parser
+        assert!(Parser::new("select * from t limit -1;").parse().is_ok());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_arithmetic_and_datetime() -> Result<()> {
+        let filter = |sql: &str| -> Result<Expression> {
+            match Parser::new(&format!("select * from t where {};", sql)).parse()? {
+                Statement::Select {
+                    where_clause: Some(expr),
+                    ..
+                } => Ok(expr),
+                stmt => panic!("unexpected statement {:?}", stmt),
+            }
+        };
+        let field = |name: &str| Box::new(Expression::Field(None, name.to_string()));
+        let int = |i| Box::new(Expression::Consts(Consts::Integer(i)));
+        assert_eq!(
+            filter("a + b * 2 - 1 > 3")?,
+            Operation::GreaterThan(
+                Box::new(
+                    Operation::Subtract(
+                        Box::new(
+                            Operation::Add(
+                                field("a"),
+                                Box::new(Operation::Multiply(field("b"), int(2)).into())
+                            )
+                            .into()
+                        ),
+                        int(1)
+                    )
+                    .into()
+                ),
+                int(3)
+            )
+            .into()
+        );
+        // a sign right after an operator still belongs to the number
+        assert_eq!(
+            filter("a -1 = a - -1")?,
+            Operation::Equal(
+                Box::new(Operation::Subtract(field("a"), int(1)).into()),
+                Box::new(Operation::Subtract(field("a"), int(-1)).into())
+            )
+            .into()
+        );
+
+        // printed expressions parse back to the same tree
+        for sql in [
+            "a - (b - c) = (a + b) * c / 2",
+            "a BETWEEN b - 1 AND b + 1",
+            "(a > 1) = (b < 2)",
+            "ts + INTERVAL '1 day 02:00:00' < CURRENT_TIMESTAMP",
+            "d = DATE '2026-10-17' OR t >= TIME '12:30:00.5'",
+            "ts = TIMESTAMP '2026-10-17 00:00:00' AND i != INTERVAL '-1 years -2 mons'",
+            "b = X'00ff' OR length(substr(b, 2, 3)) > 1",
+            "j -> 'a' ->> 0 = 'x' AND j -> 'n' * 2 > 1",
+            "j -> (k -> 'b') = JSON '{\"a\": [1, null]}'",
+            "CAST(a AS DECIMAL(10, 2)) > CAST(b * 2 AS FLOAT)",
+            "CAST(CAST(c AS CHAR(2)) AS VARCHAR(5)) = CAST(d AS STRING)",
+            "a || b + 1 || 'x' = concat(upper(c), coalesce(d, '')) AND e != f || g",
+            "a || b || c = a || (b || c)",
+        ] {
+            let expr = filter(sql)?;
+            assert_eq!(expr.to_string(), sql);
+        }
+        assert_eq!(
+            filter("d < current_date and ts <= NOW ( )")?.to_string(),
+            "d < CURRENT_DATE AND ts <= CURRENT_TIMESTAMP"
+        );
+        // :: binds tighter than any operator
+        assert_eq!(
+            filter("a::int::text = '1' and j -> 'k'::bytea > 1.5::numeric(3, 1)")?.to_string(),
+            "CAST(CAST(a AS INTEGER) AS STRING) = '1' AND j -> CAST('k' AS BLOB) > CAST(1.5 AS DECIMAL(3, 1))"
+        );
+        assert!(filter("cast(a as) = 1").is_err());
+        assert!(filter("a::varchar(0) = 1").is_err());
+        assert_eq!(
+            filter("d = date '2026-10-17'")?,
+            Operation::Equal(field("d"), Box::new(Consts::Date(20_743).into())).into()
+        );
+
+        assert!(filter("d = date '2026-10-32'").is_err());
+        assert!(filter("d = date 1").is_err());
+        assert!(filter("i = interval '1 parsec'").is_err());
+        let err = filter("a = now(1)").unwrap_err();
+        assert_eq!(err.span().map(|s| s.column), Some(31));
+        assert_eq!(
+            filter("LENGTH(x'0A') = 1")?,
+            Operation::Equal(
+                Box::new(Expression::Function(
+                    "length".to_string(),
+                    vec![Consts::Blob(vec![10]).into()]
+                )),
+                int(1)
+            )
+            .into()
+        );
+        // functions are looked up by the planner
+        assert_eq!(
+            filter("a = Today(1, b)")?,
+            Operation::Equal(
+                field("a"),
+                Box::new(Expression::Function(
+                    "today".to_string(),
+                    vec![Consts::Integer(1).into(), Expression::Field(None, "b".to_string())]
+                ))
+            )
+            .into()
+        );
+        assert!(filter("a = f(1,)").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_case_like() -> Result<()> {
+        let filter = |sql: &str| -> Result<Expression> {
+            match Parser::new(&format!("select * from t where {};", sql)).parse()? {
+                Statement::Select {
+                    where_clause: Some(expr),
+                    ..
+                } => Ok(expr),
+                stmt => panic!("unexpected statement {:?}", stmt),
+            }
+        };
+        let field = |name: &str| Box::new(Expression::Field(None, name.to_string()));
+        let string = |s: &str| Box::new(Expression::from(Consts::String(s.to_string())));
+
+        // NOT before IN, BETWEEN or LIKE negates the predicate
+        assert_eq!(
+            filter("a not like 'x%' escape '!' and not b ilike 'y'")?,
+            Operation::And(
+                Box::new(
+                    Operation::Not(Box::new(
+                        Operation::Like(field("a"), string("x%"), Some(string("!"))).into()
+                    ))
+                    .into()
+                ),
+                Box::new(
+                    Operation::Not(Box::new(
+                        Operation::ILike(field("b"), string("y"), None).into()
+                    ))
+                    .into()
+                ),
+            )
+            .into()
+        );
+        assert_eq!(
+            filter("case a when 1 then b end")?,
+            Expression::Case(
+                Some(field("a")),
+                vec![(Consts::Integer(1).into(), *field("b"))],
+                None
+            )
+        );
+
+        // printed expressions parse back to the same tree
+        for sql in [
+            "a NOT IN (1, 2) AND b NOT BETWEEN 1 AND c + 1",
+            "a LIKE 'x\\_%' OR b NOT ILIKE c || '%' ESCAPE '#'",
+            "(a LIKE b) = (c NOT LIKE d)",
+            "CASE WHEN a > 1 THEN 'big' WHEN c THEN NULL ELSE 'small' END = b",
+            "CASE a + 1 WHEN 2 THEN CASE WHEN b THEN 1 END ELSE 0 END > 1",
+        ] {
+            let expr = filter(sql)?;
+            assert_eq!(expr.to_string(), sql);
+        }
+        assert_eq!(
+            filter("not a in (1) and not (b between 1 and 2)")?.to_string(),
+            "a NOT IN (1) AND b NOT BETWEEN 1 AND 2"
+        );
+
+        for sql in [
+            "a not = 1",
+            "a like",
+            "case end",
+            "case a then 1 end",
+            "case when a then 1",
+            "a like 'x' escape",
+        ] {
+            assert!(filter(sql).is_err(), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_subquery() -> Result<()> {
+        let filter = |sql: &str| -> Result<Expression> {
+            match Parser::new(&format!("select * from t where {};", sql)).parse()? {
+                Statement::Select {
+                    where_clause: Some(expr),
+                    ..
+                } => Ok(expr),
+                stmt => panic!("unexpected statement {:?}", stmt),
+            }
+        };
+        let query = |sql: &str| Parser::new(sql).parse();
+
+        assert_eq!(
+            filter("a in (select b from u)")?,
+            Operation::InSubquery(
+                Box::new(Expression::Field(None, "a".to_string())),
+                Box::new(query("select b from u;")?),
+            )
+            .into()
+        );
+        assert_eq!(
+            query("select * from (select a from t) as d;")?,
+            Statement::Select {
+                distinct: None,
+                select: vec![],
+                from: FromItem::Subquery(Box::new(query("select a from t;")?), "d".to_string()),
+                where_clause: None,
+                limit: None,
+                offset: None,
+            }
+        );
+
+        // columns qualified by a table name or alias, AS is optional
+        assert_eq!(
+            query("select u.b from t u where t.a = 1;")?,
+            query("select u.b from t as u where t.a = 1;")?
+        );
+        assert_eq!(
+            filter("t.a = d.b")?,
+            Operation::Equal(
+                Box::new(Expression::Field(Some("t".to_string()), "a".to_string())),
+                Box::new(Expression::Field(Some("d".to_string()), "b".to_string())),
+            )
+            .into()
+        );
+        assert!(filter("t. = 1").is_err());
+
+        // printed expressions parse back to the same tree
+        for sql in [
+            "a NOT IN (SELECT b FROM u WHERE c = a) AND EXISTS (SELECT * FROM u LIMIT 1)",
+            "EXISTS (SELECT * FROM u AS w WHERE w.b = t.a)",
+            "(SELECT max(b) AS m FROM (SELECT b FROM u) AS d WHERE b > 1) + 1 = a",
+            "NOT EXISTS (SELECT 1 FROM u WHERE b IN (SELECT c FROM v))",
+        ] {
+            let expr = filter(sql)?;
+            assert_eq!(expr.to_string(), sql);
+        }
+        // (SELECT ...) is a subquery, other parentheses group expressions
+        assert!(matches!(filter("((select a from u))")?, Expression::Subquery(_)));
+
+        for sql in [
+            "exists select a from u",
+            "exists (a)",
+            "a in (select a from u",
+            "(select a from u) from",
+        ] {
+            assert!(filter(sql).is_err(), "{}", sql);
+        }
+        assert!(query("select * from (select a from t);").is_err());
+        assert!(query("select * from (t) as d;").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_set_operation() -> Result<()> {
+        let query = |sql: &str| Parser::new(sql).parse();
+        let select = |sql: &str| -> Result<Box<Statement>> { Ok(Box::new(query(sql)?)) };
+
+        // INTERSECT binds tighter, LIMIT applies to the combined rows
+        assert_eq!(
+            query("select a from t union all select b from u intersect select c from v limit 2;")?,
+            Statement::SetOperation {
+                operator: SetOperator::Union,
+                all: true,
+                left: select("select a from t;")?,
+                right: Box::new(Statement::SetOperation {
+                    operator: SetOperator::Intersect,
+                    all: false,
+                    left: select("select b from u;")?,
+                    right: select("select c from v;")?,
+                    limit: None,
+                    offset: None,
+                }),
+                limit: Some(Expression::Consts(Consts::Integer(2))),
+                offset: None,
+            }
+        );
+
+        // printed queries parse back to the same tree
+        for sql in [
+            "SELECT a FROM t UNION SELECT b FROM u EXCEPT ALL SELECT c FROM v",
+            "SELECT * FROM t INTERSECT SELECT * FROM u LIMIT 1 OFFSET 2",
+            "SELECT x FROM (SELECT a AS x FROM t UNION SELECT b FROM u) AS d WHERE x IN (SELECT c FROM v EXCEPT SELECT d FROM w)",
+        ] {
+            let stmt = query(&format!("{};", sql))?;
+            assert_eq!(stmt.to_string(), sql);
+        }
+
+        for sql in [
+            "select a from t limit 1 union select b from u;",
+            "select a from t union;",
+            "select a from t union all;",
+            "select a from t intersect all all select b from u;",
+        ] {
+            assert!(query(sql).is_err(), "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_parser_distinct() -> Result<()> {
+        let query = |sql: &str| Parser::new(sql).parse();
+
+        match query("select distinct on (a, b + 1) a, c from t;")? {
+            Statement::Select { distinct, .. } => assert_eq!(
+                distinct,
+                Some(vec![
+                    Expression::Field(None, "a".to_string()),
+                    Expression::Operation(Operation::Add(
+                        Box::new(Expression::Field(None, "b".to_string())),
+                        Box::new(Expression::Consts(Consts::Integer(1)))
+                    )),
+                ])
+            ),
+            stmt => panic!("unexpected statement {:?}", stmt),
+        }
+
+        for sql in [
+            "SELECT DISTINCT * FROM t",
+            "SELECT DISTINCT a, b FROM t UNION SELECT DISTINCT ON (c) c, d FROM u LIMIT 3",
+        ] {
+            let stmt = query(&format!("{};", sql))?;
+            assert_eq!(stmt.to_string(), sql);
+        }
+
+        for sql in [
+            "select distinct from t;",
+            "select distinct on a from t;",
+            "select distinct on () a from t;",
+        ] {
+            assert!(query(sql).is_err(), "{}", sql);
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,622 @@
+use std::{fmt::Display, sync::Arc};
+
+use crate::{
+    error::{Error, Result},
+    sql::{
+        functions::{as_json, Function, UserFunction},
+        parser::ast::{self, format_literal, Current, TypeName},
+        types::{datetime, DataType, Json, Row, Value},
+    },
+};
+
+use super::SubqueryPlan;
+
+// an expression as planned: columns are positions in the row it is
+// evaluated against, functions and subqueries are resolved
+#[derive(Debug, PartialEq, Clone)]
+pub enum Expression {
+    Constant(Value),
+    // the column at the position in the row, and its name
+    Field(usize, String),
+    // a column of a query the subquery is part of, the position among the
+    // parameters of the subquery, replaced by its value before it runs
+    Param(usize, String),
+    Current(Current),
+    Operation(Operation),
+    Function(&'static Function, Vec<Expression>),
+    UserFunction(Arc<UserFunction>, Vec<Expression>),
+    Cast(Box<Expression>, TypeName),
+    // a CHAR(n) value without the spaces it is padded with, the way it is
+    // compared and passed to functions
+    Unpad(Box<Expression>),
+    Case(
+        Option<Box<Expression>>,
+        Vec<(Expression, Expression)>,
+        Option<Box<Expression>>,
+    ),
+    Subquery(Box<SubqueryPlan>),
+    Exists(Box<SubqueryPlan>),
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum Operation {
+    And(Box<Expression>, Box<Expression>),
+    Or(Box<Expression>, Box<Expression>),
+    Not(Box<Expression>),
+    Equal(Box<Expression>, Box<Expression>),
+    NotEqual(Box<Expression>, Box<Expression>),
+    GreaterThan(Box<Expression>, Box<Expression>),
+    GreaterThanOrEqual(Box<Expression>, Box<Expression>),
+    LessThan(Box<Expression>, Box<Expression>),
+    LessThanOrEqual(Box<Expression>, Box<Expression>),
+    In(Box<Expression>, Vec<Expression>),
+    Between(Box<Expression>, Box<Expression>, Box<Expression>),
+    Add(Box<Expression>, Box<Expression>),
+    Subtract(Box<Expression>, Box<Expression>),
+    Multiply(Box<Expression>, Box<Expression>),
+    Divide(Box<Expression>, Box<Expression>),
+    Negate(Box<Expression>),
+    JsonGet(Box<Expression>, Box<Expression>),
+    JsonGetText(Box<Expression>, Box<Expression>),
+    Concat(Box<Expression>, Box<Expression>),
+    Like(Box<Expression>, Box<Expression>, Option<Box<Expression>>),
+    ILike(Box<Expression>, Box<Expression>, Option<Box<Expression>>),
+    InSubquery(Box<Expression>, Box<SubqueryPlan>),
+}
+
+impl From<Operation> for Expression {
+    fn from(value: Operation) -> Self {
+        Self::Operation(value)
+    }
+}
+
+impl Expression {
+    // evaluate the expression against a row
+    pub fn evaluate(&self, row: &Row) -> Result<Value> {
+        Ok(match self {
+            Expression::Constant(value) => value.clone(),
+            Expression::Current(current) => {
+                let now = datetime::now();
+                match current {
+                    Current::Date => Value::Date(datetime::check_date(
+                        now.div_euclid(datetime::MICROS_PER_DAY),
+                    )?),
+                    Current::Time => Value::Time(now.rem_euclid(datetime::MICROS_PER_DAY)),
+                    Current::Timestamp => Value::Timestamp(datetime::check_timestamp(now)?),
+                }
+            }
+            Expression::Field(pos, name) => match row.get(*pos) {
+                Some(value) => value.clone(),
+                None => {
+                    return Err(Error::Internal(format!(
+                        "[Expression] Column {} is not in the row",
+                        name
+                    )))
+                }
+            },
+            Expression::Param(_, name) => {
+                return Err(Error::Internal(format!(
+                    "[Expression] Outer column {} has no value",
+                    name
+                )))
+            }
+            Expression::Operation(op) => op.evaluate(row)?,
+            Expression::Function(function, args) => function.evaluate(&evaluate_all(args, row)?)?,
+            Expression::UserFunction(function, args) => {
+                function.evaluate(&evaluate_all(args, row)?)?
+            }
+            Expression::Cast(expr, typename) => typename.cast(expr.evaluate(row)?)?,
+            Expression::Unpad(expr) => match expr.evaluate(row)? {
+                Value::String(s) => Value::String(s.trim_end_matches(' ').to_string()),
+                value => value,
+            },
+            Expression::Subquery(subquery) => {
+                let mut rows = subquery.rows(row)?;
+                match (rows.next().transpose()?, rows.next().transpose()?) {
+                    (None, _) => Value::Null,
+                    (Some(row), None) => row.into_iter().next().unwrap_or(Value::Null),
+                    (Some(_), Some(_)) => {
+                        return Err(Error::Value(
+                            "[Expression] Subquery returns more than one row".to_string(),
+                        ))
+                    }
+                }
+            }
+            Expression::Exists(subquery) => {
+                Value::Boolean(subquery.rows(row)?.next().transpose()?.is_some())
+            }
+            // only the branch taken is evaluated
+            Expression::Case(operand, branches, default) => {
+                let operand = match operand {
+                    Some(operand) => Some(operand.evaluate(row)?),
+                    None => None,
+                };
+                for (when, then) in branches {
+                    let when = when.evaluate(row)?;
+                    let matched = match (&operand, when) {
+                        // CASE x WHEN a is CASE WHEN x = a, NULL matches nothing
+                        (Some(Value::Null), _) | (Some(_), Value::Null) => false,
+                        (Some(value), when) => compare_values(value, &when)?.is_eq(),
+                        (None, Value::Boolean(b)) => b,
+                        (None, Value::Null) => false,
+                        (None, v) => {
+                            return Err(Error::Value(format!(
+                                "[Expression] CASE WHEN expects a boolean, got {}",
+                                v
+                            )))
+                        }
+                    };
+                    if matched {
+                        return then.evaluate(row);
+                    }
+                }
+                match default {
+                    Some(default) => default.evaluate(row)?,
+                    None => Value::Null,
+                }
+            }
+        })
+    }
+
+    // whether a row satisfies the predicate, NULL counts as not satisfied
+    pub fn evaluate_predicate(&self, row: &Row) -> Result<bool> {
+        match self.evaluate(row)? {
+            Value::Boolean(b) => Ok(b),
+            Value::Null => Ok(false),
+            v => Err(Error::Value(format!(
+                "[Expression] Predicate must be a boolean, got {}",
+                v
+            ))),
+        }
+    }
+
+    // f on the expression and then on the expressions within it, the
+    // queries of subqueries are not entered
+    pub fn walk_mut(&mut self, f: &mut dyn FnMut(&mut Expression)) {
+        f(self);
+        for child in self.children_mut() {
+            child.walk_mut(f);
+        }
+    }
+
+    fn children_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Expression::Constant(_)
+            | Expression::Field(..)
+            | Expression::Param(..)
+            | Expression::Current(_)
+            | Expression::Subquery(_)
+            | Expression::Exists(_) => Vec::new(),
+            Expression::Operation(op) => op.operands_mut(),
+            Expression::Function(_, args) | Expression::UserFunction(_, args) => {
+                args.iter_mut().collect()
+            }
+            Expression::Cast(expr, _) | Expression::Unpad(expr) => vec![expr],
+            Expression::Case(operand, branches, default) => operand
+                .iter_mut()
+                .map(|e| e.as_mut())
+                .chain(branches.iter_mut().flat_map(|(when, then)| [when, then]))
+                .chain(default.iter_mut().map(|e| e.as_mut()))
+                .collect(),
+        }
+    }
+
+    // the plan of (SELECT ...), EXISTS and IN (SELECT ...)
+    pub fn subquery_mut(&mut self) -> Option<&mut SubqueryPlan> {
+        match self {
+            Expression::Subquery(subquery)
+            | Expression::Exists(subquery)
+            | Expression::Operation(Operation::InSubquery(_, subquery)) => Some(subquery),
+            _ => None,
+        }
+    }
+
+    // the expression as written in SQL, outer columns by their name
+    pub fn to_ast(&self) -> ast::Expression {
+        match self {
+            Expression::Constant(value) => ast::Expression::Consts(value.clone().into()),
+            Expression::Field(_, name) | Expression::Param(_, name) => {
+                ast::Expression::Field(None, name.clone())
+            }
+            Expression::Current(current) => ast::Expression::Current(*current),
+            Expression::Operation(op) => ast::Expression::Operation(op.to_ast()),
+            Expression::Function(function, args) => {
+                ast::Expression::Function(function.name.to_string(), to_ast_all(args))
+            }
+            Expression::UserFunction(function, args) => {
+                ast::Expression::Function(function.name.clone(), to_ast_all(args))
+            }
+            Expression::Cast(expr, typename) => {
+                ast::Expression::Cast(Box::new(expr.to_ast()), typename.clone())
+            }
+            // the padding is implied by the type
+            Expression::Unpad(expr) => expr.to_ast(),
+            Expression::Case(operand, branches, default) => ast::Expression::Case(
+                operand.as_ref().map(|e| Box::new(e.to_ast())),
+                branches
+                    .iter()
+                    .map(|(when, then)| (when.to_ast(), then.to_ast()))
+                    .collect(),
+                default.as_ref().map(|e| Box::new(e.to_ast())),
+            ),
+            Expression::Subquery(subquery) => {
+                ast::Expression::Subquery(Box::new(subquery.query.clone()))
+            }
+            Expression::Exists(subquery) => {
+                ast::Expression::Exists(Box::new(subquery.query.clone()))
+            }
+        }
+    }
+}
+
+impl Display for Expression {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_ast())
+    }
+}
+
+fn evaluate_all(exprs: &[Expression], row: &Row) -> Result<Vec<Value>> {
+    exprs.iter().map(|e| e.evaluate(row)).collect()
+}
+
+fn to_ast_all(exprs: &[Expression]) -> Vec<ast::Expression> {
+    exprs.iter().map(|e| e.to_ast()).collect()
+}
+
+impl Operation {
+    fn operands_mut(&mut self) -> Vec<&mut Expression> {
+        match self {
+            Operation::Not(e) | Operation::Negate(e) | Operation::InSubquery(e, _) => vec![e],
+            Operation::And(l, r)
+            | Operation::Or(l, r)
+            | Operation::Equal(l, r)
+            | Operation::NotEqual(l, r)
+            | Operation::GreaterThan(l, r)
+            | Operation::GreaterThanOrEqual(l, r)
+            | Operation::LessThan(l, r)
+            | Operation::LessThanOrEqual(l, r)
+            | Operation::Add(l, r)
+            | Operation::Subtract(l, r)
+            | Operation::Multiply(l, r)
+            | Operation::Divide(l, r)
+            | Operation::JsonGet(l, r)
+            | Operation::JsonGetText(l, r)
+            | Operation::Concat(l, r) => vec![l, r],
+            Operation::In(e, list) => std::iter::once(e.as_mut()).chain(list).collect(),
+            Operation::Between(e, low, high) => vec![e, low, high],
+            Operation::Like(e, pattern, escape) | Operation::ILike(e, pattern, escape) => {
+                let mut operands = vec![e.as_mut(), pattern.as_mut()];
+                operands.extend(escape.as_deref_mut());
+                operands
+            }
+        }
+    }
+
+    fn to_ast(&self) -> ast::Operation {
+        let ast = |e: &Expression| Box::new(e.to_ast());
+        let escape = |e: &Option<Box<Expression>>| e.as_deref().map(ast);
+        match self {
+            Operation::And(l, r) => ast::Operation::And(ast(l), ast(r)),
+            Operation::Or(l, r) => ast::Operation::Or(ast(l), ast(r)),
+            Operation::Not(e) => ast::Operation::Not(ast(e)),
+            Operation::Equal(l, r) => ast::Operation::Equal(ast(l), ast(r)),
+            Operation::NotEqual(l, r) => ast::Operation::NotEqual(ast(l), ast(r)),
+            Operation::GreaterThan(l, r) => ast::Operation::GreaterThan(ast(l), ast(r)),
+            Operation::GreaterThanOrEqual(l, r) => {
+                ast::Operation::GreaterThanOrEqual(ast(l), ast(r))
+            }
+            Operation::LessThan(l, r) => ast::Operation::LessThan(ast(l), ast(r)),
+            Operation::LessThanOrEqual(l, r) => ast::Operation::LessThanOrEqual(ast(l), ast(r)),
+            Operation::In(e, list) => ast::Operation::In(ast(e), to_ast_all(list)),
+            Operation::Between(e, low, high) => {
+                ast::Operation::Between(ast(e), ast(low), ast(high))
+            }
+            Operation::Add(l, r) => ast::Operation::Add(ast(l), ast(r)),
+            Operation::Subtract(l, r) => ast::Operation::Subtract(ast(l), ast(r)),
+            Operation::Multiply(l, r) => ast::Operation::Multiply(ast(l), ast(r)),
+            Operation::Divide(l, r) => ast::Operation::Divide(ast(l), ast(r)),
+            Operation::Negate(e) => ast::Operation::Negate(ast(e)),
+            Operation::JsonGet(l, r) => ast::Operation::JsonGet(ast(l), ast(r)),
+            Operation::JsonGetText(l, r) => ast::Operation::JsonGetText(ast(l), ast(r)),
+            Operation::Concat(l, r) => ast::Operation::Concat(ast(l), ast(r)),
+            Operation::Like(e, pattern, esc) => {
+                ast::Operation::Like(ast(e), ast(pattern), escape(esc))
+            }
+            Operation::ILike(e, pattern, esc) => {
+                ast::Operation::ILike(ast(e), ast(pattern), escape(esc))
+            }
+            Operation::InSubquery(e, subquery) => {
+                ast::Operation::InSubquery(ast(e), Box::new(subquery.query.clone()))
+            }
+        }
+    }
+
+    fn evaluate(&self, row: &Row) -> Result<Value> {
+        Ok(match self {
+            // three-valued logic, NULL means unknown
+            Operation::And(l, r) => match (l.evaluate(row)?, r.evaluate(row)?) {
+                (Value::Boolean(false), _) | (_, Value::Boolean(false)) => Value::Boolean(false),
+                (Value::Boolean(true), Value::Boolean(true)) => Value::Boolean(true),
+                (Value::Boolean(_) | Value::Null, Value::Boolean(_) | Value::Null) => Value::Null,
+                (l, r) => {
+                    return Err(Error::Value(format!(
+                        "[Expression] Cannot evaluate {} AND {}",
+                        l, r
+                    )))
+                }
+            },
+            Operation::Or(l, r) => match (l.evaluate(row)?, r.evaluate(row)?) {
+                (Value::Boolean(true), _) | (_, Value::Boolean(true)) => Value::Boolean(true),
+                (Value::Boolean(false), Value::Boolean(false)) => Value::Boolean(false),
+                (Value::Boolean(_) | Value::Null, Value::Boolean(_) | Value::Null) => Value::Null,
+                (l, r) => {
+                    return Err(Error::Value(format!(
+                        "[Expression] Cannot evaluate {} OR {}",
+                        l, r
+                    )))
+                }
+            },
+            Operation::Not(e) => match e.evaluate(row)? {
+                Value::Boolean(b) => Value::Boolean(!b),
+                Value::Null => Value::Null,
+                v => {
+                    return Err(Error::Value(format!(
+                        "[Expression] Cannot evaluate NOT {}",
+                        v
+                    )))
+                }
+            },
+            Operation::Equal(l, r) => compare(l, r, row, |o| o.is_eq())?,
+            Operation::NotEqual(l, r) => compare(l, r, row, |o| o.is_ne())?,
+            Operation::GreaterThan(l, r) => compare(l, r, row, |o| o.is_gt())?,
+            Operation::GreaterThanOrEqual(l, r) => compare(l, r, row, |o| o.is_ge())?,
+            Operation::LessThan(l, r) => compare(l, r, row, |o| o.is_lt())?,
+            Operation::LessThanOrEqual(l, r) => compare(l, r, row, |o| o.is_le())?,
+            // x IN (a, b) is x = a OR x = b
+            Operation::In(expr, list) => contains(
+                expr.evaluate(row)?,
+                list.iter().map(|item| item.evaluate(row)),
+            )?,
+            // the values of the single column of the subquery rows
+            Operation::InSubquery(expr, subquery) => {
+                let value = expr.evaluate(row)?;
+                if value == Value::Null {
+                    return Ok(Value::Null);
+                }
+                contains(
+                    value,
+                    subquery
+                        .rows(row)?
+                        .map(|row| row.map(|row| row.into_iter().next().unwrap_or(Value::Null))),
+                )?
+            }
+            // x BETWEEN a AND b is x >= a AND x <= b
+            Operation::Between(expr, low, high) => {
+                let ge = compare(expr, low, row, |o| o.is_ge())?;
+                let le = compare(expr, high, row, |o| o.is_le())?;
+                match (ge, le) {
+                    (Value::Boolean(false), _) | (_, Value::Boolean(false)) => {
+                        Value::Boolean(false)
+                    }
+                    (Value::Boolean(true), Value::Boolean(true)) => Value::Boolean(true),
+                    _ => Value::Null,
+                }
+            }
+            Operation::Add(l, r) => l.evaluate(row)?.checked_add(&r.evaluate(row)?)?,
+            Operation::Subtract(l, r) => l.evaluate(row)?.checked_sub(&r.evaluate(row)?)?,
+            Operation::Multiply(l, r) => l.evaluate(row)?.checked_mul(&r.evaluate(row)?)?,
+            Operation::Negate(e) => e.evaluate(row)?.checked_neg()?,
+            Operation::JsonGet(l, r) => json_member(l, r, row)?.map_or(Value::Null, Value::Json),
+            Operation::JsonGetText(l, r) => {
+                json_member(l, r, row)?.map_or(Value::Null, |json| json.to_text())
+            }
+            Operation::Divide(l, r) => l.evaluate(row)?.checked_div(&r.evaluate(row)?)?,
+            // NULL if either side is NULL, other types are converted to text
+            Operation::Like(e, pattern, escape) => like(e, pattern, escape, false, row)?,
+            Operation::ILike(e, pattern, escape) => like(e, pattern, escape, true, row)?,
+            Operation::Concat(l, r) => match (l.evaluate(row)?, r.evaluate(row)?) {
+                (Value::Null, _) | (_, Value::Null) => Value::Null,
+                (Value::Blob(mut l), Value::Blob(r)) => {
+                    l.extend(r);
+                    Value::Blob(l)
+                }
+                (l, r) => match (l.cast(&DataType::String)?, r.cast(&DataType::String)?) {
+                    (Value::String(l), Value::String(r)) => Value::String(l + &r),
+                    (l, r) => {
+                        return Err(Error::Internal(format!(
+                            "[Expression] Cannot evaluate {} || {}",
+                            l, r
+                        )))
+                    }
+                },
+            },
+        })
+    }
+}
+
+// member of a JSON object by key, or element of an array by index, NULL
+// if either side is NULL or there is no such member
+fn json_member(l: &Expression, r: &Expression, row: &Row) -> Result<Option<Json>> {
+    let (json, key) = (l.evaluate(row)?, r.evaluate(row)?);
+    if json == Value::Null || key == Value::Null {
+        return Ok(None);
+    }
+    let json = as_json(&json)?;
+    Ok(match key {
+        Value::String(key) => json.get(&key).cloned(),
+        Value::Integer(index) => json.index(index).cloned(),
+        key => {
+            return Err(Error::Value(format!(
+                "[Expression] JSON key must be a string or an integer, got {}",
+                format_literal(&key)
+            )))
+        }
+    })
+}
+
+// x IN (a, b) is x = a OR x = b, items are evaluated until one is equal
+fn contains(value: Value, items: impl Iterator<Item = Result<Value>>) -> Result<Value> {
+    if value == Value::Null {
+        return Ok(Value::Null);
+    }
+    let mut res = Value::Boolean(false);
+    for item in items {
+        match item? {
+            Value::Null => res = Value::Null,
+            item => {
+                if compare_values(&value, &item)?.is_eq() {
+                    return Ok(Value::Boolean(true));
+                }
+            }
+        }
+    }
+    Ok(res)
+}
+
+// expr LIKE pattern, NULL if any operand is NULL, the escape character is
+// a backslash unless given, ESCAPE '' turns escaping off
+fn like(
+    expr: &Expression,
+    pattern: &Expression,
+    escape: &Option<Box<Expression>>,
+    ignore_case: bool,
+    row: &Row,
+) -> Result<Value> {
+    let escape = match escape {
+        Some(escape) => escape.evaluate(row)?,
+        None => Value::String("\\".to_string()),
+    };
+    let (value, pattern) = (expr.evaluate(row)?, pattern.evaluate(row)?);
+    let (value, pattern, escape) = match (value, pattern, escape) {
+        (Value::Null, _, _) | (_, Value::Null, _) | (_, _, Value::Null) => return Ok(Value::Null),
+        (Value::String(value), Value::String(pattern), Value::String(escape)) => {
+            (value, pattern, escape)
+        }
+        (value, pattern, escape) => {
+            return Err(Error::Value(format!(
+                "[Expression] Cannot evaluate {} LIKE {} ESCAPE {}",
+                format_literal(&value),
+                format_literal(&pattern),
+                format_literal(&escape)
+            )))
+        }
+    };
+    let mut chars = escape.chars();
+    let escape = match (chars.next(), chars.next()) {
+        (None, _) => None,
+        (Some(c), None) => Some(c),
+        _ => {
+            return Err(Error::Value(format!(
+                "[Expression] ESCAPE expects a single character, got '{}'",
+                escape
+            )))
+        }
+    };
+    Ok(Value::Boolean(like_match(
+        &value,
+        &pattern,
+        escape,
+        ignore_case,
+    )?))
+}
+
+#[derive(PartialEq)]
+enum LikeToken {
+    Char(char),
+    // _
+    One,
+    // %
+    Any,
+}
+
+fn like_match(value: &str, pattern: &str, escape: Option<char>, ignore_case: bool) -> Result<bool> {
+    let mut tokens = Vec::new();
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        tokens.push(match c {
+            c if Some(c) == escape => match chars.next() {
+                Some(c) => LikeToken::Char(c),
+                None => {
+                    return Err(Error::Value(format!(
+                        "[Expression] LIKE pattern '{}' ends with the escape character",
+                        pattern
+                    )))
+                }
+            },
+            '_' => LikeToken::One,
+            '%' => LikeToken::Any,
+            c => LikeToken::Char(c),
+        });
+    }
+    let eq = |a: char, b: char| match ignore_case {
+        true => a == b || a.to_lowercase().eq(b.to_lowercase()),
+        false => a == b,
+    };
+
+    // match greedily, after a mismatch the last % takes one more character
+    let value = value.chars().collect::<Vec<_>>();
+    let (mut v, mut t) = (0, 0);
+    let mut backtrack = None;
+    while v < value.len() {
+        match tokens.get(t) {
+            Some(LikeToken::Any) => {
+                backtrack = Some((t, v));
+                t += 1;
+                continue;
+            }
+            Some(LikeToken::One) => {
+                (v, t) = (v + 1, t + 1);
+                continue;
+            }
+            Some(LikeToken::Char(c)) if eq(*c, value[v]) => {
+                (v, t) = (v + 1, t + 1);
+                continue;
+            }
+            _ => {}
+        }
+        match backtrack {
+            Some((any, start)) => {
+                backtrack = Some((any, start + 1));
+                (v, t) = (start + 1, any + 1);
+            }
+            None => return Ok(false),
+        }
+    }
+    Ok(tokens[t..].iter().all(|t| *t == LikeToken::Any))
+}
+
+fn compare<F: Fn(std::cmp::Ordering) -> bool>(
+    l: &Expression,
+    r: &Expression,
+    row: &Row,
+    f: F,
+) -> Result<Value> {
+    let (l, r) = (l.evaluate(row)?, r.evaluate(row)?);
+    if l == Value::Null || r == Value::Null {
+        return Ok(Value::Null);
+    }
+    Ok(Value::Boolean(f(compare_values(&l, &r)?)))
+}
+
+// a string compared with another type is converted to that type first, e.g.
+// date_col < '2026-10-17'
+pub fn compare_values(l: &Value, r: &Value) -> Result<std::cmp::Ordering> {
+    let ordering = match (l, r) {
+        (Value::String(s), other) | (other, Value::String(s)) if l.partial_cmp(r).is_none() => {
+            let Some(datatype) = other.datatype() else {
+                return Err(cannot_compare(l, r));
+            };
+            let s = Value::String(s.clone()).coerce(&datatype)?;
+            match l {
+                Value::String(_) => s.partial_cmp(other),
+                _ => other.partial_cmp(&s),
+            }
+        }
+        (l, r) => l.partial_cmp(r),
+    };
+    ordering.ok_or_else(|| cannot_compare(l, r))
+}
+
+fn cannot_compare(l: &Value, r: &Value) -> Error {
+    Error::Value(format!("[Expression] Cannot compare {} and {}", l, r))
+}
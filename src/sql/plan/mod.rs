@@ -1,20 +1,37 @@
+use std::{cell::RefCell, collections::BTreeMap, fmt::Display, ops::Bound, rc::Rc};
+
 use planner::Planner;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 use super::{
-    parser::ast::{self, Expression},
-    schema::Table,
+    engine::Transaction,
+    executor::{Executor, ResultSet},
+    parser::ast,
+    schema::{Index, Table},
+    types::{DataType, Row, Rows, Value},
 };
 
+pub mod expression;
 mod planner;
 
-#[derive(Debug, PartialEq)]
+pub use expression::Expression;
+
+#[derive(Debug, PartialEq, Clone)]
 pub enum Node {
     CreateTable {
         schema: Table,
     },
 
+    CreateIndex {
+        table_name: String,
+        index: Index,
+    },
+
+    DropIndex {
+        name: String,
+    },
+
     Insert {
         table_name: String,
         columns: Vec<String>,
@@ -23,37 +40,523 @@ pub enum Node {
 
     Scan {
         table_name: String,
+        filter: Option<Expression>,
+    },
+
+    // rows fetched by primary key
+    KeyLookup {
+        table_name: String,
+        keys: Vec<Value>,
+        filter: Option<Expression>,
+    },
+
+    // rows fetched through a secondary index
+    IndexLookup {
+        table_name: String,
+        index_name: String,
+        values: Vec<Value>,
+        filter: Option<Expression>,
+    },
+
+    // rows with primary key within range
+    RangeScan {
+        table_name: String,
+        range: (Bound<Value>, Bound<Value>),
+        filter: Option<Expression>,
+    },
+
+    // rows with indexed column within range
+    IndexRangeScan {
+        table_name: String,
+        index_name: String,
+        range: (Bound<Value>, Bound<Value>),
+        filter: Option<Expression>,
+    },
+
+    Update {
+        table_name: String,
+        source: Box<Node>,
+        columns: BTreeMap<String, Expression>,
+    },
+
+    Delete {
+        table_name: String,
+        source: Box<Node>,
+    },
+
+    // types of the expressions where known
+    Projection {
+        source: Box<Node>,
+        exprs: Vec<(Expression, Option<String>)>,
+        types: Vec<Option<DataType>>,
+    },
+
+    Limit {
+        source: Box<Node>,
+        limit: usize,
+    },
+
+    Offset {
+        source: Box<Node>,
+        offset: usize,
+    },
+
+    // the first row of each key, whole rows if there are no expressions
+    Distinct {
+        source: Box<Node>,
+        on: Vec<Expression>,
     },
+
+    // rows of the source satisfying the predicate
+    Filter {
+        source: Box<Node>,
+        predicate: Expression,
+    },
+
+    // the rows of a derived table, FROM (SELECT ...) AS alias
+    SubqueryScan {
+        alias: String,
+        source: Box<Node>,
+    },
+
+    // UNION, INTERSECT or EXCEPT of the rows of two queries, the values
+    // are converted to the column types where known
+    SetOperation {
+        operator: ast::SetOperator,
+        all: bool,
+        left: Box<Node>,
+        right: Box<Node>,
+        types: Vec<Option<DataType>>,
+    },
+
+    Explain {
+        source: Box<Node>,
+    },
+}
+
+impl Node {
+    // write the node and its children, children are indented below their parent
+    fn format(&self, f: &mut std::fmt::Formatter<'_>, indent: usize) -> std::fmt::Result {
+        let prefix = if indent == 0 {
+            String::new()
+        } else {
+            format!("{}-> ", " ".repeat(indent - 3))
+        };
+        let detail_indent = " ".repeat(indent + 2);
+        let filter = |f: &mut std::fmt::Formatter<'_>, filter: &Option<Expression>| match filter {
+            Some(expr) => writeln!(f, "{}Filter: {}", detail_indent, expr),
+            None => Ok(()),
+        };
+
+        match self {
+            Node::CreateTable { schema } => writeln!(f, "{}Create Table: {}", prefix, schema.name),
+            Node::CreateIndex { table_name, index } => writeln!(
+                f,
+                "{}Create {}Index: {} on {} ({})",
+                prefix,
+                if index.unique { "Unique " } else { "" },
+                index.name,
+                table_name,
+                index.column
+            ),
+            Node::DropIndex { name } => writeln!(f, "{}Drop Index: {}", prefix, name),
+            Node::Insert {
+                table_name, values, ..
+            } => {
+                writeln!(
+                    f,
+                    "{}Insert on {} ({} rows)",
+                    prefix,
+                    table_name,
+                    values.len()
+                )
+            }
+            Node::Scan {
+                table_name,
+                filter: expr,
+            } => {
+                writeln!(f, "{}Scan on {}", prefix, table_name)?;
+                filter(f, expr)
+            }
+            Node::KeyLookup {
+                table_name,
+                keys,
+                filter: expr,
+            } => {
+                writeln!(
+                    f,
+                    "{}Key Lookup on {} (keys: {})",
+                    prefix,
+                    table_name,
+                    format_values(keys)
+                )?;
+                filter(f, expr)
+            }
+            Node::IndexLookup {
+                table_name,
+                index_name,
+                values,
+                filter: expr,
+            } => {
+                writeln!(
+                    f,
+                    "{}Index Lookup on {} using {} (values: {})",
+                    prefix,
+                    table_name,
+                    index_name,
+                    format_values(values)
+                )?;
+                filter(f, expr)
+            }
+            Node::RangeScan {
+                table_name,
+                range,
+                filter: expr,
+            } => {
+                writeln!(
+                    f,
+                    "{}Range Scan on {} (range: {})",
+                    prefix,
+                    table_name,
+                    format_range(range)
+                )?;
+                filter(f, expr)
+            }
+            Node::IndexRangeScan {
+                table_name,
+                index_name,
+                range,
+                filter: expr,
+            } => {
+                writeln!(
+                    f,
+                    "{}Index Range Scan on {} using {} (range: {})",
+                    prefix,
+                    table_name,
+                    index_name,
+                    format_range(range)
+                )?;
+                filter(f, expr)
+            }
+            Node::Update {
+                table_name,
+                source,
+                columns,
+            } => {
+                let set = columns
+                    .iter()
+                    .map(|(col, expr)| format!("{} = {}", col, expr))
+                    .collect::<Vec<_>>();
+                writeln!(
+                    f,
+                    "{}Update on {} (set: {})",
+                    prefix,
+                    table_name,
+                    set.join(", ")
+                )?;
+                source.format(f, indent + 5)
+            }
+            Node::Delete { table_name, source } => {
+                writeln!(f, "{}Delete on {}", prefix, table_name)?;
+                source.format(f, indent + 5)
+            }
+            Node::Projection { source, exprs, .. } => {
+                let exprs = exprs
+                    .iter()
+                    .map(|(expr, alias)| match alias {
+                        Some(alias) => format!("{} AS {}", expr, alias),
+                        None => expr.to_string(),
+                    })
+                    .collect::<Vec<_>>();
+                writeln!(f, "{}Projection ({})", prefix, exprs.join(", "))?;
+                source.format(f, indent + 5)
+            }
+            Node::Limit { source, limit } => {
+                writeln!(f, "{}Limit: {}", prefix, limit)?;
+                source.format(f, indent + 5)
+            }
+            Node::Offset { source, offset } => {
+                writeln!(f, "{}Offset: {}", prefix, offset)?;
+                source.format(f, indent + 5)
+            }
+            Node::Distinct { source, on } => {
+                match on.is_empty() {
+                    true => writeln!(f, "{}Distinct", prefix)?,
+                    false => {
+                        let on = on.iter().map(|e| e.to_string()).collect::<Vec<_>>();
+                        writeln!(f, "{}Distinct On ({})", prefix, on.join(", "))?
+                    }
+                }
+                source.format(f, indent + 5)
+            }
+            Node::Filter { source, predicate } => {
+                writeln!(f, "{}Filter: {}", prefix, predicate)?;
+                source.format(f, indent + 5)
+            }
+            Node::SubqueryScan { alias, source } => {
+                writeln!(f, "{}Subquery Scan on {}", prefix, alias)?;
+                source.format(f, indent + 5)
+            }
+            Node::SetOperation {
+                operator,
+                all,
+                left,
+                right,
+                ..
+            } => {
+                let operator = match operator {
+                    ast::SetOperator::Union => "Union",
+                    ast::SetOperator::Intersect => "Intersect",
+                    ast::SetOperator::Except => "Except",
+                };
+                writeln!(f, "{}{}{}", prefix, operator, if *all { " All" } else { "" })?;
+                left.format(f, indent + 5)?;
+                right.format(f, indent + 5)
+            }
+            Node::Explain { source } => {
+                writeln!(f, "{}Explain", prefix)?;
+                source.format(f, indent + 5)
+            }
+        }
+    }
+
+    // f on every expression of the node and its sources, including those
+    // of derived tables
+    fn walk_expressions(&mut self, f: &mut dyn FnMut(&mut Expression)) {
+        match self {
+            Node::CreateTable { .. } | Node::CreateIndex { .. } | Node::DropIndex { .. } => {}
+            Node::Insert { values, .. } => {
+                for expr in values.iter_mut().flatten() {
+                    expr.walk_mut(f);
+                }
+            }
+            Node::Scan { filter, .. }
+            | Node::KeyLookup { filter, .. }
+            | Node::IndexLookup { filter, .. }
+            | Node::RangeScan { filter, .. }
+            | Node::IndexRangeScan { filter, .. } => {
+                if let Some(filter) = filter {
+                    filter.walk_mut(f);
+                }
+            }
+            Node::Update {
+                source, columns, ..
+            } => {
+                for expr in columns.values_mut() {
+                    expr.walk_mut(f);
+                }
+                source.walk_expressions(f);
+            }
+            Node::Projection { source, exprs, .. } => {
+                for (expr, _) in exprs {
+                    expr.walk_mut(f);
+                }
+                source.walk_expressions(f);
+            }
+            Node::Distinct { source, on } => {
+                for expr in on {
+                    expr.walk_mut(f);
+                }
+                source.walk_expressions(f);
+            }
+            Node::Filter { source, predicate } => {
+                predicate.walk_mut(f);
+                source.walk_expressions(f);
+            }
+            Node::SubqueryScan { source, .. } => source.walk_expressions(f),
+            Node::SetOperation { left, right, .. } => {
+                left.walk_expressions(f);
+                right.walk_expressions(f);
+            }
+            Node::Delete { source, .. }
+            | Node::Limit { source, .. }
+            | Node::Offset { source, .. }
+            | Node::Explain { source } => source.walk_expressions(f),
+        }
+    }
+}
+
+// name of a projected column: the alias, the column name, or the
+// expression text itself
+pub fn column_name(expr: &Expression, alias: &Option<String>) -> String {
+    match (expr, alias) {
+        (_, Some(alias)) => alias.clone(),
+        (Expression::Field(_, name), None) => name.clone(),
+        (expr, None) => expr.to_string(),
+    }
+}
+
+impl Display for Node {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.format(f, 0)
+    }
+}
+
+fn format_values(values: &[Value]) -> String {
+    values
+        .iter()
+        .map(ast::format_literal)
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// interval notation, e.g. [1, 10) or (5, +inf)
+fn format_range(range: &(Bound<Value>, Bound<Value>)) -> String {
+    let start = match &range.0 {
+        Bound::Included(v) => format!("[{}", ast::format_literal(v)),
+        Bound::Excluded(v) => format!("({}", ast::format_literal(v)),
+        Bound::Unbounded => "(-inf".to_string(),
+    };
+    let end = match &range.1 {
+        Bound::Included(v) => format!("{}]", ast::format_literal(v)),
+        Bound::Excluded(v) => format!("{})", ast::format_literal(v)),
+        Bound::Unbounded => "+inf)".to_string(),
+    };
+    format!("{}, {}", start, end)
 }
 
 #[derive(Debug, PartialEq)]
 pub struct Plan(pub Node);
 
 impl Plan {
-    pub fn build(stmt: ast::Statement) -> Self {
-        Planner::new().build(stmt)
+    pub fn build<T: Transaction>(stmt: ast::Statement, txn: &mut T) -> Result<Self> {
+        Planner::new(txn).build(stmt)
+    }
+
+    pub fn execute<T: Transaction + Clone + 'static>(mut self, txn: &mut T) -> Result<ResultSet> {
+        // subqueries read through a transaction of their own, the rows of
+        // the statement may still be read when they run
+        let reader = txn.clone();
+        let runner = Runner(Rc::new(move |node| {
+            <dyn Executor<T>>::build(node).execute(&mut reader.clone())
+        }));
+        bind(&mut self.0, &runner);
+        <dyn Executor<T>>::build(self.0).execute(txn)
+    }
+}
+
+// the plan of a subquery, run for every row of the outer query that
+// evaluates it
+#[derive(Debug, Clone)]
+pub struct SubqueryPlan {
+    // the query as written, shown in plans
+    query: ast::Statement,
+    node: Node,
+    // values of the parameters, the columns of the outer queries it refers
+    // to, evaluated against the row of the query it is part of
+    outer: Vec<Expression>,
+    // set when the statement is executed
+    runner: Option<Runner>,
+    // rows of a subquery without parameters, which are read only once
+    cache: Rc<RefCell<Option<Vec<Row>>>>,
+}
+
+impl PartialEq for SubqueryPlan {
+    fn eq(&self, other: &Self) -> bool {
+        self.node == other.node && self.outer == other.outer
+    }
+}
+
+impl SubqueryPlan {
+    fn new(query: ast::Statement, node: Node, outer: Vec<Expression>) -> Self {
+        Self {
+            query,
+            node,
+            outer,
+            runner: None,
+            cache: Rc::default(),
+        }
     }
 
-    // pub fn execute<T: Transaction>(self, txn: &mut T) -> Result<ResultSet> {
-    //     <dyn Executor<T>>::build(self.0).execute(txn)
-    // }
+    // rows of the subquery for a row of the outer query
+    pub fn rows(&self, row: &Row) -> Result<Rows> {
+        let Some(runner) = &self.runner else {
+            return Err(Error::Value(
+                "[Expression] Subquery cannot be used here".to_string(),
+            ));
+        };
+        if !self.outer.is_empty() {
+            let values = self
+                .outer
+                .iter()
+                .map(|e| e.evaluate(row))
+                .collect::<Result<Vec<_>>>()?;
+            let mut node = self.node.clone();
+            substitute(&mut node, &values);
+            return select_rows((runner.0)(node)?);
+        }
+
+        if let Some(rows) = self.cache.borrow().as_ref() {
+            return Ok(Box::new(rows.clone().into_iter().map(Ok)));
+        }
+        let rows = select_rows((runner.0)(self.node.clone())?)?.collect::<Result<Vec<_>>>()?;
+        *self.cache.borrow_mut() = Some(rows.clone());
+        Ok(Box::new(rows.into_iter().map(Ok)))
+    }
+}
+
+// runs a subquery node in the transaction of the statement
+#[derive(Clone)]
+struct Runner(Rc<dyn Fn(Node) -> Result<ResultSet>>);
+
+impl std::fmt::Debug for Runner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Runner")
+    }
+}
+
+fn select_rows(result: ResultSet) -> Result<Rows> {
+    match result {
+        ResultSet::Select { rows, .. } => Ok(rows),
+        _ => Err(Error::Internal(
+            "[Subquery] Unexpected result set".to_string(),
+        )),
+    }
+}
+
+// give the subqueries within node, and those within them, the runner
+fn bind(node: &mut Node, runner: &Runner) {
+    node.walk_expressions(&mut |expr| {
+        if let Some(plan) = expr.subquery_mut() {
+            plan.runner = Some(runner.clone());
+            bind(&mut plan.node, runner);
+        }
+    });
+}
+
+// replace the parameters in node by their values, the subqueries within it
+// may pass them on as parameters of their own
+fn substitute(node: &mut Node, values: &[Value]) {
+    let param = |expr: &mut Expression| {
+        if let Expression::Param(i, _) = expr {
+            *expr = Expression::Constant(values[*i].clone());
+        }
+    };
+    node.walk_expressions(&mut |expr| match expr.subquery_mut() {
+        Some(plan) => plan.outer.iter_mut().for_each(param),
+        None => param(expr),
+    });
 }
 
 #[cfg(test)]
 mod tests {
+    use std::ops::Bound;
+
     use crate::{
         error::Result,
         sql::{
-            parser::{
-                ast::{self, Expression},
-                Parser,
-            },
-            plan::{Node, Plan},
+            engine::{kv::KVEngine, Engine, Transaction},
+            executor::ResultSet,
+            parser::Parser,
+            plan::{Expression, Node, Plan},
+            types::Value,
         },
+        storage::memory::MemoryEngine,
     };
 
     #[test]
     fn test_plan_create_table() -> Result<()> {
+        let mut txn = KVEngine::new(MemoryEngine::new())?.begin()?;
         let sql1 = "
         create table tbl1 (
             a int default 100,
@@ -63,7 +566,7 @@ mod tests {
         );
         ";
         let stmt1 = Parser::new(sql1).parse()?;
-        let p1 = Plan::build(stmt1);
+        let p1 = Plan::build(stmt1, &mut txn)?;
 
         let sql2 = "
         create            table tbl1 (
@@ -74,7 +577,7 @@ mod tests {
         );
         ";
         let stmt2 = Parser::new(sql2).parse()?;
-        let p2 = Plan::build(stmt2);
+        let p2 = Plan::build(stmt2, &mut txn)?;
         assert_eq!(p1, p2);
 
         Ok(())
@@ -82,27 +585,28 @@ mod tests {
 
     #[test]
     fn test_plan_insert() -> Result<()> {
+        let mut txn = KVEngine::new(MemoryEngine::new())?.begin()?;
         let sql1 = "insert into tbl1 values (1, 2, 3, 'a', true);";
         let stmt1 = Parser::new(sql1).parse()?;
-        let p1 = Plan::build(stmt1);
+        let p1 = Plan::build(stmt1, &mut txn)?;
         assert_eq!(
             p1,
             Plan(Node::Insert {
                 table_name: "tbl1".to_string(),
                 columns: vec![],
                 values: vec![vec![
-                    Expression::Consts(ast::Consts::Integer(1)),
-                    Expression::Consts(ast::Consts::Integer(2)),
-                    Expression::Consts(ast::Consts::Integer(3)),
-                    Expression::Consts(ast::Consts::String("a".to_string())),
-                    Expression::Consts(ast::Consts::Boolean(true)),
+                    Expression::Constant(Value::Integer(1)),
+                    Expression::Constant(Value::Integer(2)),
+                    Expression::Constant(Value::Integer(3)),
+                    Expression::Constant(Value::String("a".to_string())),
+                    Expression::Constant(Value::Boolean(true)),
                 ]],
             })
         );
 
         let sql2 = "insert into tbl2 (c1, c2, c3) values (3, 'a', true),(4, 'b', false);";
         let stmt2 = Parser::new(sql2).parse()?;
-        let p2 = Plan::build(stmt2);
+        let p2 = Plan::build(stmt2, &mut txn)?;
         assert_eq!(
             p2,
             Plan(Node::Insert {
@@ -110,14 +614,14 @@ mod tests {
                 columns: vec!["c1".to_string(), "c2".to_string(), "c3".to_string()],
                 values: vec![
                     vec![
-                        Expression::Consts(ast::Consts::Integer(3)),
-                        Expression::Consts(ast::Consts::String("a".to_string())),
-                        Expression::Consts(ast::Consts::Boolean(true)),
+                        Expression::Constant(Value::Integer(3)),
+                        Expression::Constant(Value::String("a".to_string())),
+                        Expression::Constant(Value::Boolean(true)),
                     ],
                     vec![
-                        Expression::Consts(ast::Consts::Integer(4)),
-                        Expression::Consts(ast::Consts::String("b".to_string())),
-                        Expression::Consts(ast::Consts::Boolean(false)),
+                        Expression::Constant(Value::Integer(4)),
+                        Expression::Constant(Value::String("b".to_string())),
+                        Expression::Constant(Value::Boolean(false)),
                     ],
                 ],
             })
@@ -128,16 +632,227 @@ mod tests {
 
     #[test]
     fn test_plan_select() -> Result<()> {
+        let mut txn = KVEngine::new(MemoryEngine::new())?.begin()?;
+        let sql = "create table tbl1 (a int primary key);";
+        Plan::build(Parser::new(sql).parse()?, &mut txn)?.execute(&mut txn)?;
         let sql = "select * from tbl1;";
         let stmt = Parser::new(sql).parse()?;
-        let p = Plan::build(stmt);
+        let p = Plan::build(stmt, &mut txn)?;
         assert_eq!(
             p,
             Plan(Node::Scan {
                 table_name: "tbl1".to_string(),
+                filter: None,
             })
         );
 
         Ok(())
     }
+
+    #[test]
+    fn test_plan_index_lookup() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        let mut txn = kv.begin()?;
+        for sql in [
+            "create table tbl1 (a int primary key, b int, c string);",
+            "create index idx_b on tbl1 (b);",
+        ] {
+            Plan::build(Parser::new(sql).parse()?, &mut txn)?.execute(&mut txn)?;
+        }
+
+        let sql = "select * from tbl1 where c = 'x' and b in (1, 2, null, 1);";
+        let p = Plan::build(Parser::new(sql).parse()?, &mut txn)?;
+        assert!(matches!(
+            p,
+            Plan(Node::IndexLookup { ref index_name, ref values, filter: Some(_), .. })
+                if index_name == "idx_b" && *values == vec![Value::Integer(1), Value::Integer(2)]
+        ));
+
+        let sql = "select * from tbl1 where 3 = a;";
+        let p = Plan::build(Parser::new(sql).parse()?, &mut txn)?;
+        assert!(matches!(
+            p,
+            Plan(Node::KeyLookup { ref keys, .. }) if *keys == vec![Value::Integer(3)]
+        ));
+
+        // lookup values are converted to the column type
+        let sql = "select * from tbl1 where b = 1.0 and a in ('4', 4.0);";
+        let p = Plan::build(Parser::new(sql).parse()?, &mut txn)?;
+        assert!(matches!(
+            p,
+            Plan(Node::IndexLookup { ref values, .. }) if *values == vec![Value::Integer(1)]
+        ));
+        let sql = "select * from tbl1 where a in ('4', 4.0);";
+        let p = Plan::build(Parser::new(sql).parse()?, &mut txn)?;
+        assert!(matches!(
+            p,
+            Plan(Node::KeyLookup { ref keys, .. }) if *keys == vec![Value::Integer(4)]
+        ));
+
+        // no usable index, or a lookup value that does not convert exactly
+        for sql in [
+            "select * from tbl1 where c = 'x';",
+            "select * from tbl1 where b = 1 or c = 'x';",
+            "select * from tbl1 where b = 1.5;",
+            "select * from tbl1 where a = 'x';",
+        ] {
+            let p = Plan::build(Parser::new(sql).parse()?, &mut txn)?;
+            assert!(matches!(
+                p,
+                Plan(Node::Scan {
+                    filter: Some(_),
+                    ..
+                })
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_range_scan() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        let mut txn = kv.begin()?;
+        for sql in [
+            "create table tbl1 (a int primary key, b float, c string);",
+            "create index idx_b on tbl1 (b);",
+        ] {
+            Plan::build(Parser::new(sql).parse()?, &mut txn)?.execute(&mut txn)?;
+        }
+
+        let sql = "select * from tbl1 where a > 10 and 100 >= a and a >= 20 and c = 'x';";
+        let p = Plan::build(Parser::new(sql).parse()?, &mut txn)?;
+        assert!(matches!(
+            p,
+            Plan(Node::RangeScan { ref range, .. })
+                if *range == (Bound::Included(Value::Integer(20)), Bound::Included(Value::Integer(100)))
+        ));
+
+        let sql = "select * from tbl1 where b between 1.5 and 2.5 and b < 2.5;";
+        let p = Plan::build(Parser::new(sql).parse()?, &mut txn)?;
+        assert!(matches!(
+            p,
+            Plan(Node::IndexRangeScan { ref index_name, ref range, .. })
+                if index_name == "idx_b"
+                    && *range == (Bound::Included(Value::Float(1.5)), Bound::Excluded(Value::Float(2.5)))
+        ));
+
+        // bounds of another type, or on columns without index
+        for sql in [
+            "select * from tbl1 where a > 1.5;",
+            "select * from tbl1 where c > 'x';",
+            "select * from tbl1 where a > 1 or b < 2.0;",
+        ] {
+            let p = Plan::build(Parser::new(sql).parse()?, &mut txn)?;
+            assert!(matches!(
+                p,
+                Plan(Node::Scan {
+                    filter: Some(_),
+                    ..
+                })
+            ));
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_functions() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        let mut txn = kv.begin()?;
+        let sql = "create table tbl1 (a int primary key, b float, c string, d blob);";
+        Plan::build(Parser::new(sql).parse()?, &mut txn)?.execute(&mut txn)?;
+
+        let build = |txn: &mut _, sql: &str| Plan::build(Parser::new(sql).parse()?, txn);
+        build(
+            &mut txn,
+            "select upper(c) || 'x', round(b, 1), length(substr(d, 2)) from tbl1
+                where coalesce(a, 0) = mod(a, 2);",
+        )?;
+        build(&mut txn, "update tbl1 set c = trim(c || d) where abs(b) > 1;")?;
+
+        // unknown functions, wrong number or types of arguments are
+        // reported before anything is executed
+        for (sql, err) in [
+            (
+                "select * from tbl1 where a = today();",
+                "[Planner] Unknown function today",
+            ),
+            (
+                "select substr(c) from tbl1;",
+                "[Function] substr expects 2 to 3 arguments, got 1",
+            ),
+            (
+                "select upper(length(c)) from tbl1;",
+                "[Function] upper expects STRING for argument 1, got INTEGER",
+            ),
+            (
+                "update tbl1 set b = sqrt(c);",
+                "[Function] sqrt expects a number for argument 1, got STRING",
+            ),
+            (
+                "insert into tbl1 values (1, pow(2), 'a', null);",
+                "[Function] pow expects 2 arguments, got 1",
+            ),
+            (
+                "delete from tbl1 where round(a + b, c) > 1;",
+                "[Function] round expects INTEGER for argument 2, got STRING",
+            ),
+        ] {
+            assert_eq!(build(&mut txn, sql).unwrap_err().to_string(), err, "{}", sql);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_plan_explain() -> Result<()> {
+        let kv = KVEngine::new(MemoryEngine::new())?;
+        let mut txn = kv.begin()?;
+        for sql in [
+            "create table tbl1 (a int primary key, b int, c string);",
+            "create index idx_b on tbl1 (b);",
+        ] {
+            Plan::build(Parser::new(sql).parse()?, &mut txn)?.execute(&mut txn)?;
+        }
+
+        let explain = |txn: &mut _, sql: &str| -> Result<String> {
+            match Plan::build(Parser::new(sql).parse()?, txn)?.execute(txn)? {
+                ResultSet::Explain { plan } => Ok(plan),
+                rs => panic!("unexpected result set {:?}", rs),
+            }
+        };
+
+        assert_eq!(
+            explain(&mut txn, "explain select a, b > 1 as x from tbl1 where b >= 1 and b < 5 or c = 'y';")?,
+            "Projection (a, b > 1 AS x)\n  -> Scan on tbl1\n       Filter: b >= 1 AND b < 5 OR c = 'y'\n"
+        );
+        assert_eq!(
+            explain(&mut txn, "explain select * from tbl1 where c = 'x' and b between 1 and 5;")?,
+            "Index Range Scan on tbl1 using idx_b (range: [1, 5])\n  Filter: c = 'x' AND b BETWEEN 1 AND 5\n"
+        );
+        assert_eq!(
+            explain(&mut txn, "explain update tbl1 set c = 'z' where a in (1, 2);")?,
+            "Update on tbl1 (set: c = 'z')\n  -> Key Lookup on tbl1 (keys: 1, 2)\n       Filter: a IN (1, 2)\n"
+        );
+        assert_eq!(
+            explain(&mut txn, "explain delete from tbl1 where a > 3;")?,
+            "Delete on tbl1\n  -> Range Scan on tbl1 (range: (3, +inf))\n       Filter: a > 3\n"
+        );
+
+        assert_eq!(
+            explain(&mut txn, "explain select x from (select b as x from tbl1) as d where x > 1 limit 2;")?,
+            "Limit: 2\n  -> Projection (x)\n       -> Filter: x > 1\n            -> Subquery Scan on d\n                 -> Projection (b AS x)\n                      -> Scan on tbl1\n"
+        );
+
+        assert_eq!(
+            explain(&mut txn, "explain select a from tbl1 union all select b from tbl1 where b = 1 limit 1;")?,
+            "Limit: 1\n  -> Union All\n       -> Projection (a)\n            -> Scan on tbl1\n       -> Projection (b)\n            -> Index Lookup on tbl1 using idx_b (values: 1)\n                 Filter: b = 1\n"
+        );
+
+        // explaining a statement does not execute it
+        explain(&mut txn, "explain create index idx_c on tbl1 (c);")?;
+        assert!(txn.must_get_table("tbl1")?.get_index("idx_c").is_none());
+
+        Ok(())
+    }
 }
@@ -1,47 +1,196 @@
-use crate::sql::{
-    parser::ast,
-    schema::{self, Table},
-    types::Value,
+use std::{cell::RefCell, ops::Bound};
+
+use crate::{
+    error::{Error, Result},
+    sql::{
+        engine::Transaction,
+        functions,
+        parser::ast,
+        schema::{self, Table},
+        types::{DataType, Value},
+    },
+};
+
+use super::{
+    expression::{Expression, Operation},
+    Node, Plan, SubqueryPlan,
 };
 
-use super::{Node, Plan};
+pub struct Planner<'a, T: Transaction> {
+    txn: &'a mut T,
+}
+
+// a column of the rows of a query, its type where known
+#[derive(Clone)]
+struct Column {
+    name: String,
+    datatype: Option<DataType>,
+    // CHAR(n), the values are padded with spaces
+    padded: bool,
+}
+
+// the columns an expression can refer to: those of the rows it is
+// evaluated against and, within a subquery, those of the queries it is
+// part of
+#[derive(Default)]
+struct Scope<'a> {
+    // the table name or alias that qualifies the columns, e.g. t in t.a
+    qualifier: Option<String>,
+    columns: Vec<Column>,
+    outer: Option<&'a Outer<'a>>,
+}
+
+// the query a subquery is part of, the columns of it that the subquery
+// refers to are collected as the parameters of the subquery
+struct Outer<'a> {
+    scope: &'a Scope<'a>,
+    params: RefCell<Vec<Expression>>,
+}
+
+impl Scope<'_> {
+    // the column of the name, a qualified name is looked up in the innermost
+    // query with that qualifier, an unqualified one must name a single column
+    // of all the queries, a column of an outer query is a parameter of the
+    // subquery
+    fn resolve(
+        &self,
+        qualifier: Option<&str>,
+        name: &str,
+    ) -> Result<(Expression, Option<DataType>)> {
+        let full_name = match qualifier {
+            Some(qualifier) => format!("{}.{}", qualifier, name),
+            None => name.to_string(),
+        };
+        let mut found = None;
+        let (mut scope, mut depth) = (Some(self), 0);
+        while let Some(current) = scope {
+            let mut positions = current.lookup(qualifier, name);
+            if let Some(pos) = positions.next() {
+                if positions.next().is_some() || found.is_some() {
+                    return Err(Error::Value(format!(
+                        "[Planner] Column reference {} is ambiguous",
+                        full_name
+                    )));
+                }
+                found = Some((depth, pos, current.columns[pos].padded));
+                if qualifier.is_some() {
+                    break;
+                }
+            }
+            scope = current.outer.map(|outer| outer.scope);
+            depth += 1;
+        }
+        match found {
+            // CHAR(n) values are used without their padding
+            Some((depth, pos, true)) => {
+                let (expr, datatype) = self.bind(depth, pos, name);
+                Ok((Expression::Unpad(Box::new(expr)), datatype))
+            }
+            Some((depth, pos, false)) => Ok(self.bind(depth, pos, name)),
+            None => Err(Error::NotFound(format!(
+                "[Planner] Column {} does not exist",
+                full_name
+            ))),
+        }
+    }
+
+    // the positions of the columns the name can refer to
+    fn lookup<'b>(
+        &'b self,
+        qualifier: Option<&'b str>,
+        name: &'b str,
+    ) -> impl Iterator<Item = usize> + 'b {
+        let qualified = qualifier.is_none() || qualifier == self.qualifier.as_deref();
+        self.columns
+            .iter()
+            .enumerate()
+            .filter(move |(_, c)| qualified && c.name == name)
+            .map(|(pos, _)| pos)
+    }
+
+    // the column at pos of the query depth levels out, each subquery in
+    // between passes it on as a parameter
+    fn bind(&self, depth: usize, pos: usize, name: &str) -> (Expression, Option<DataType>) {
+        let outer = match self.outer {
+            Some(outer) if depth > 0 => outer,
+            _ => {
+                let datatype = self.columns[pos].datatype.clone();
+                return (Expression::Field(pos, name.to_string()), datatype);
+            }
+        };
+        let (expr, datatype) = outer.scope.bind(depth - 1, pos, name);
+        let mut params = outer.params.borrow_mut();
+        let param = match params.iter().position(|p| *p == expr) {
+            Some(param) => param,
+            None => {
+                params.push(expr);
+                params.len() - 1
+            }
+        };
+        (Expression::Param(param, name.to_string()), datatype)
+    }
+}
 
-pub struct Planner;
+// the rows a SELECT reads
+enum Source {
+    Table(Table),
+    // a derived table and its alias
+    Derived(Node, String),
+}
 
-impl Planner {
-    pub fn new() -> Self {
-        Self {}
+impl<'a, T: Transaction> Planner<'a, T> {
+    pub fn new(txn: &'a mut T) -> Self {
+        Self { txn }
     }
 
-    pub fn build(&mut self, stmt: ast::Statement) -> Plan {
-        Plan(self.build_statment(stmt))
+    pub fn build(&mut self, stmt: ast::Statement) -> Result<Plan> {
+        Ok(Plan(self.build_statment(stmt)?))
     }
 
-    fn build_statment(&self, stmt: ast::Statement) -> Node {
-        match stmt {
+    fn build_statment(&mut self, stmt: ast::Statement) -> Result<Node> {
+        Ok(match stmt {
             ast::Statement::CreateTable { name, columns } => Node::CreateTable {
                 schema: Table {
                     name,
                     columns: columns
                         .into_iter()
                         .map(|c| {
-                            let nullable = c.nullable.unwrap_or(true);
+                            let nullable = c.nullable.unwrap_or(!c.primary_key);
                             let default = match c.default {
-                                Some(expr) => Some(Value::from_expression(expr)),
+                                Some(expr) => Some(self.evaluate_constant(expr)?),
                                 None if nullable => Some(Value::Null),
                                 None => None,
                             };
 
-                            schema::Column {
+                            Ok(schema::Column {
                                 name: c.name,
                                 datatype: c.datatype,
                                 nullable,
                                 default,
-                            }
+                                primary_key: c.primary_key,
+                                precision: c.precision,
+                                length: c.length,
+                                padded: c.padded,
+                            })
                         })
-                        .collect(),
+                        .collect::<Result<_>>()?,
+                    indexes: Vec::new(),
+                },
+            },
+            ast::Statement::CreateIndex {
+                name,
+                table_name,
+                column,
+                unique,
+            } => Node::CreateIndex {
+                table_name,
+                index: schema::Index {
+                    name,
+                    column,
+                    unique,
                 },
             },
+            ast::Statement::DropIndex { name } => Node::DropIndex { name },
             ast::Statement::Insert {
                 table_name,
                 columns,
@@ -49,9 +198,926 @@ impl Planner {
             } => Node::Insert {
                 table_name,
                 columns: columns.unwrap_or_default(),
-                values,
+                values: values
+                    .into_iter()
+                    .map(|row| {
+                        row.into_iter()
+                            .map(|expr| Ok(self.build_expression(expr, &Scope::default())?.0))
+                            .collect()
+                    })
+                    .collect::<Result<_>>()?,
+            },
+            stmt @ (ast::Statement::Select { .. } | ast::Statement::SetOperation { .. }) => {
+                self.build_query(stmt, None)?.0
+            }
+            ast::Statement::Update {
+                table_name,
+                columns,
+                where_clause,
+            } => {
+                let table = self.txn.must_get_table(&table_name)?;
+                let scope = table_scope(&table);
+                let columns = columns
+                    .into_iter()
+                    .map(|(column, expr)| Ok((column, self.build_expression(expr, &scope)?.0)))
+                    .collect::<Result<_>>()?;
+                let filter = self.build_filter(where_clause, &scope)?;
+                Node::Update {
+                    source: Box::new(self.build_scan(&table, filter)?),
+                    table_name,
+                    columns,
+                }
+            }
+            ast::Statement::Delete {
+                table_name,
+                where_clause,
+            } => {
+                let table = self.txn.must_get_table(&table_name)?;
+                let filter = self.build_filter(where_clause, &table_scope(&table))?;
+                Node::Delete {
+                    source: Box::new(self.build_scan(&table, filter)?),
+                    table_name,
+                }
+            }
+            ast::Statement::Explain { statement } => Node::Explain {
+                source: Box::new(self.build_statment(*statement)?),
             },
-            ast::Statement::Select { table_name } => Node::Scan { table_name },
+            // handled by the session, they have no plan
+            ast::Statement::Begin | ast::Statement::Commit | ast::Statement::Rollback => {
+                return Err(Error::Internal(
+                    "[Planner] Unexpected transaction statement".to_string(),
+                ))
+            }
+        })
+    }
+
+    // the node of a query and the columns of its rows, outer is the query a
+    // subquery is part of
+    fn build_query(
+        &self,
+        stmt: ast::Statement,
+        outer: Option<&Outer>,
+    ) -> Result<(Node, Vec<Column>)> {
+        let ast::Statement::SetOperation {
+            operator,
+            all,
+            left,
+            right,
+            limit,
+            offset,
+        } = stmt
+        else {
+            return self.build_select(stmt, outer);
+        };
+        let (left, l_columns) = self.build_query(*left, outer)?;
+        let (right, r_columns) = self.build_query(*right, outer)?;
+        if l_columns.len() != r_columns.len() {
+            return Err(Error::Value(format!(
+                "[Planner] Each {} query must have the same number of columns, got {} and {}",
+                operator,
+                l_columns.len(),
+                r_columns.len()
+            )));
+        }
+        let (l_literals, r_literals) = (string_literals(&left), string_literals(&right));
+        let literal = |literals: &[bool], i: usize| literals.get(i).copied().unwrap_or(false);
+        let types = l_columns
+            .iter()
+            .zip(&r_columns)
+            .enumerate()
+            .map(|(i, (l, r))| {
+                common_type(
+                    operator,
+                    (l.datatype.clone(), literal(&l_literals, i)),
+                    (r.datatype.clone(), literal(&r_literals, i)),
+                )
+            })
+            .collect::<Result<Vec<_>>>()?;
+        // CHAR(n) values are matched without their padding, and so are the
+        // values of the other query, e.g. 'a  ' in CHAR(3) is a duplicate of 'a'
+        let unpad = l_columns
+            .iter()
+            .zip(&r_columns)
+            .map(|(l, r)| l.padded || r.padded)
+            .collect::<Vec<_>>();
+        let left = unpad_columns(left, &l_columns, &unpad);
+        let right = unpad_columns(right, &r_columns, &unpad);
+        // the columns are named after the left query
+        let columns = l_columns
+            .into_iter()
+            .zip(&types)
+            .map(|(column, datatype)| Column {
+                name: column.name,
+                datatype: datatype.clone(),
+                padded: false,
+            })
+            .collect();
+        let node = Node::SetOperation {
+            operator,
+            all,
+            left: Box::new(left),
+            right: Box::new(right),
+            types,
+        };
+        Ok((self.limit_offset(node, limit, offset)?, columns))
+    }
+
+    fn build_select(
+        &self,
+        stmt: ast::Statement,
+        outer: Option<&Outer>,
+    ) -> Result<(Node, Vec<Column>)> {
+        let ast::Statement::Select {
+            distinct,
+            select,
+            from,
+            where_clause,
+            limit,
+            offset,
+        } = stmt
+        else {
+            return Err(Error::Internal(
+                "[Planner] Expect a SELECT statement".to_string(),
+            ));
+        };
+        let (source, mut columns, scope) = match from {
+            ast::FromItem::Table(table_name, alias) => {
+                let table = self.txn.must_get_table(&table_name)?;
+                let scope = table_scope(&table);
+                let scope = Scope {
+                    qualifier: alias.or(scope.qualifier),
+                    outer,
+                    ..scope
+                };
+                (Source::Table(table), scope.columns.clone(), scope)
+            }
+            // the columns of a derived table have no known type
+            ast::FromItem::Subquery(query, alias) => {
+                let (node, columns) = self.build_query(*query, outer)?;
+                let scope = Scope {
+                    qualifier: Some(alias.clone()),
+                    columns: columns
+                        .iter()
+                        .map(|c| Column {
+                            name: c.name.clone(),
+                            datatype: None,
+                            padded: c.padded,
+                        })
+                        .collect(),
+                    outer,
+                };
+                (Source::Derived(node, alias), columns, scope)
+            }
+        };
+        let (mut exprs, mut types, mut padded) = (Vec::new(), Vec::new(), Vec::new());
+        for (expr, alias) in select {
+            // CHAR(n) values are returned with their padding
+            let (expr, datatype) = self.build_expression(expr, &scope)?;
+            let (expr, is_padded) = match expr {
+                Expression::Unpad(expr) => (*expr, true),
+                expr => (expr, false),
+            };
+            exprs.push((expr, alias));
+            types.push(datatype);
+            padded.push(is_padded);
+        }
+        let filter = self.build_filter(where_clause, &scope)?;
+        let mut node = match source {
+            Source::Table(table) => self.build_scan(&table, filter)?,
+            Source::Derived(source, alias) => {
+                let node = Node::SubqueryScan {
+                    alias,
+                    source: Box::new(source),
+                };
+                match filter {
+                    Some(predicate) => Node::Filter {
+                        source: Box::new(node),
+                        predicate,
+                    },
+                    None => node,
+                }
+            }
+        };
+        // the key of DISTINCT ON is evaluated on the rows before projection,
+        // DISTINCT compares the projected rows
+        let distinct = match distinct {
+            Some(on) if !on.is_empty() => {
+                let on = on
+                    .into_iter()
+                    .map(|expr| Ok(self.build_expression(expr, &scope)?.0))
+                    .collect::<Result<_>>()?;
+                node = Node::Distinct {
+                    source: Box::new(node),
+                    on,
+                };
+                false
+            }
+            distinct => distinct.is_some(),
+        };
+        if !exprs.is_empty() {
+            columns = exprs
+                .iter()
+                .zip(&types)
+                .zip(padded)
+                .map(|(((expr, alias), datatype), padded)| Column {
+                    name: super::column_name(expr, alias),
+                    datatype: datatype.clone(),
+                    padded,
+                })
+                .collect();
+            node = Node::Projection {
+                source: Box::new(node),
+                exprs,
+                types,
+            };
+        }
+        if distinct {
+            node = Node::Distinct {
+                source: Box::new(node),
+                on: Vec::new(),
+            };
+        }
+        Ok((self.limit_offset(node, limit, offset)?, columns))
+    }
+
+    // plan the query of a subquery, the columns of scope it refers to
+    // become its parameters, the value of a one column subquery is returned
+    // without padding if it is CHAR(n) or unpad is set, which is told along
+    fn build_subquery(
+        &self,
+        query: ast::Statement,
+        scope: &Scope,
+        one_column: bool,
+        unpad: bool,
+    ) -> Result<(SubqueryPlan, bool)> {
+        let outer = Outer {
+            scope,
+            params: RefCell::default(),
+        };
+        let (mut node, columns) = self.build_query(query.clone(), Some(&outer))?;
+        if !one_column {
+            return Ok((
+                SubqueryPlan::new(query, node, outer.params.into_inner()),
+                false,
+            ));
+        }
+        let [column] = columns.as_slice() else {
+            return Err(Error::Value(format!(
+                "[Planner] Subquery must return one column, got {}",
+                columns.len()
+            )));
+        };
+        let unpad = unpad || column.padded;
+        node = unpad_columns(node, &columns, &[unpad]);
+        Ok((
+            SubqueryPlan::new(query, node, outer.params.into_inner()),
+            unpad,
+        ))
+    }
+
+    fn build_filter(
+        &self,
+        where_clause: Option<ast::Expression>,
+        scope: &Scope,
+    ) -> Result<Option<Expression>> {
+        match where_clause {
+            Some(expr) => Ok(Some(self.build_expression(expr, scope)?.0)),
+            None => Ok(None),
+        }
+    }
+
+    // choose how to access the table rows: by primary key, through an index,
+    // as a key range, or a full scan, the filter is always kept to check the
+    // fetched rows
+    fn build_scan(&self, table: &Table, filter: Option<Expression>) -> Result<Node> {
+        let table_name = table.name.clone();
+        let Some(filter) = filter else {
+            return Ok(Node::Scan {
+                table_name,
+                filter: None,
+            });
+        };
+
+        let exprs = split_conjunction(&filter);
+        for expr in &exprs {
+            let Some((column, values)) = lookup_values(expr) else {
+                continue;
+            };
+            // keys are stored by exact value, so the values are converted to
+            // the column type the way comparisons do, e.g. id = '1' finds the
+            // integer 1, a value that does not convert exactly is left to the filter
+            let col = &table.columns[column];
+            let Some(values) = coerce_keys(values, col) else {
+                continue;
+            };
+            if col.primary_key {
+                return Ok(Node::KeyLookup {
+                    table_name,
+                    keys: values,
+                    filter: Some(filter),
+                });
+            }
+            if let Some(index) = table.indexes.iter().find(|i| i.column == col.name) {
+                return Ok(Node::IndexLookup {
+                    table_name,
+                    index_name: index.name.clone(),
+                    values,
+                    filter: Some(filter),
+                });
+            }
+        }
+
+        // CHAR(n) keys are ordered with their padding, unlike the values
+        // compared with them, so they are not scanned by range
+        if let Some(column) = table
+            .columns
+            .iter()
+            .position(|c| c.primary_key && !c.padded)
+        {
+            let datatype = &table.columns[column].datatype;
+            if let Some(range) = column_range(&exprs, column, datatype) {
+                return Ok(Node::RangeScan {
+                    table_name,
+                    range,
+                    filter: Some(filter),
+                });
+            }
+        }
+        for index in &table.indexes {
+            let column = table.get_col_index(&index.column)?;
+            if table.columns[column].padded {
+                continue;
+            }
+            let datatype = &table.columns[column].datatype;
+            if let Some(range) = column_range(&exprs, column, datatype) {
+                return Ok(Node::IndexRangeScan {
+                    table_name,
+                    index_name: index.name.clone(),
+                    range,
+                    filter: Some(filter),
+                });
+            }
+        }
+
+        Ok(Node::Scan {
+            table_name,
+            filter: Some(filter),
+        })
+    }
+
+    // resolve the columns, functions and subqueries of expr against scope,
+    // and infer the type of its result where it is known before evaluation
+    fn build_expression(
+        &self,
+        expr: ast::Expression,
+        scope: &Scope,
+    ) -> Result<(Expression, Option<DataType>)> {
+        let build = |expr: ast::Expression| self.build_expression(expr, scope);
+        let operand = |expr: Box<ast::Expression>| -> Result<_> { Ok(Box::new(build(*expr)?.0)) };
+        Ok(match expr {
+            ast::Expression::Consts(consts) => {
+                let value = Value::from(consts);
+                let datatype = value.datatype();
+                (Expression::Constant(value), datatype)
+            }
+            ast::Expression::Field(qualifier, name) => {
+                scope.resolve(qualifier.as_deref(), &name)?
+            }
+            ast::Expression::Current(current) => {
+                let datatype = match current {
+                    ast::Current::Date => DataType::Date,
+                    ast::Current::Time => DataType::Time,
+                    ast::Current::Timestamp => DataType::Timestamp,
+                };
+                (Expression::Current(current), Some(datatype))
+            }
+            // a cast to CHAR(n) pads the value
+            ast::Expression::Cast(expr, typename) => {
+                let (datatype, padded) = (typename.datatype.clone(), typename.padded);
+                let expr = Expression::Cast(operand(expr)?, typename);
+                (unpad_if(expr, padded), Some(datatype))
+            }
+            ast::Expression::Subquery(query) => {
+                let (plan, padded) = self.build_subquery(*query, scope, true, false)?;
+                let expr = Expression::Subquery(Box::new(plan));
+                (unpad_if(expr, padded), None)
+            }
+            ast::Expression::Exists(query) => {
+                let (plan, _) = self.build_subquery(*query, scope, false, false)?;
+                (Expression::Exists(Box::new(plan)), Some(DataType::Boolean))
+            }
+            // numbers in the branches give the type of their arithmetic,
+            // otherwise the first known type
+            ast::Expression::Case(operand_expr, branches, default) => {
+                let operand_expr = operand_expr.map(operand).transpose()?;
+                let padded = operand_expr.as_deref().is_some_and(is_unpadded);
+                let (mut cases, mut types) = (Vec::new(), Vec::new());
+                for (when, then) in branches {
+                    let when = unpad_if(build(when)?.0, padded);
+                    let (then, datatype) = build(then)?;
+                    cases.push((when, then));
+                    types.push(datatype);
+                }
+                let default = match default {
+                    Some(default) => {
+                        let (default, datatype) = build(*default)?;
+                        types.push(datatype);
+                        Some(Box::new(default))
+                    }
+                    None => None,
+                };
+                let datatype = functions::number_type(types.iter().map(|t| t.as_ref()))
+                    .or_else(|| types.into_iter().flatten().next());
+                (Expression::Case(operand_expr, cases, default), datatype)
+            }
+            ast::Expression::Function(name, args) => {
+                let (args, types): (Vec<_>, Vec<_>) = args
+                    .into_iter()
+                    .map(build)
+                    .collect::<Result<Vec<_>>>()?
+                    .into_iter()
+                    .unzip();
+                if let Some(function) = functions::get(&name) {
+                    if function.returns == functions::Returns::Common {
+                        return self.build_common_call(function, args, types);
+                    }
+                    let datatype = function.check(&types)?;
+                    return Ok((Expression::Function(function, args), datatype));
+                }
+                let function = self.txn.get_function(&name)?.ok_or_else(|| {
+                    Error::NotFound(format!("[Planner] Unknown function {}", name))
+                })?;
+                let datatype = function.check(&types)?;
+                (Expression::UserFunction(function, args), datatype)
+            }
+            ast::Expression::Operation(operation) => {
+                let (operation, datatype) = self.build_operation(operation, scope)?;
+                (operation.into(), datatype)
+            }
+        })
+    }
+
+    // a call of a function whose arguments and result convert to a common
+    // type: string literals take the type of the other arguments like
+    // untyped constants, e.g. coalesce(day, '2026-10-17') gives a DATE, and
+    // the result is converted where an argument has another type
+    fn build_common_call(
+        &self,
+        function: &'static functions::Function,
+        mut args: Vec<Expression>,
+        mut types: Vec<Option<DataType>>,
+    ) -> Result<(Expression, Option<DataType>)> {
+        let literal = |arg: &Expression| matches!(arg, Expression::Constant(Value::String(_)));
+        let known = args
+            .iter()
+            .zip(&types)
+            .map(|(arg, datatype)| datatype.clone().filter(|_| !literal(arg)))
+            .collect::<Vec<_>>();
+        let datatype = match function.check(&known)? {
+            Some(datatype) => Some(datatype),
+            None => function.check(&types)?,
+        };
+        let Some(datatype) = datatype else {
+            return Ok((Expression::Function(function, args), None));
+        };
+        for (arg, arg_type) in args.iter_mut().zip(&mut types) {
+            if let Expression::Constant(value @ Value::String(_)) = arg {
+                *value = value.clone().coerce(&datatype)?;
+                *arg_type = Some(datatype.clone());
+            }
+        }
+        let expr = Expression::Function(function, args);
+        if types.iter().flatten().all(|t| *t == datatype) {
+            return Ok((expr, Some(datatype)));
+        }
+        let typename = ast::TypeName {
+            datatype: datatype.clone(),
+            precision: None,
+            length: None,
+            padded: false,
+        };
+        Ok((Expression::Cast(Box::new(expr), typename), Some(datatype)))
+    }
+
+    fn build_operation(
+        &self,
+        operation: ast::Operation,
+        scope: &Scope,
+    ) -> Result<(Operation, Option<DataType>)> {
+        let build = |expr: Box<ast::Expression>| self.build_expression(*expr, scope);
+        let operand = |expr: Box<ast::Expression>| -> Result<_> { Ok(Box::new(build(expr)?.0)) };
+        let arithmetic =
+            |l, r, operation: fn(Box<Expression>, Box<Expression>) -> Operation| -> Result<_> {
+                let ((l, l_type), (r, r_type)) = (build(l)?, build(r)?);
+                let datatype =
+                    functions::number_type([l_type.as_ref(), r_type.as_ref()].into_iter());
+                Ok((operation(Box::new(l), Box::new(r)), datatype))
+            };
+        let boolean = Some(DataType::Boolean);
+        // CHAR(n) values compare without their padding, and so does what
+        // they are compared with, e.g. c = 'ab  ' where c is CHAR(4)
+        let comparison =
+            |l, r, operation: fn(Box<Expression>, Box<Expression>) -> Operation| -> Result<_> {
+                let (l, r) = (build(l)?.0, build(r)?.0);
+                let padded = is_unpadded(&l) || is_unpadded(&r);
+                let (l, r) = (unpad_if(l, padded), unpad_if(r, padded));
+                Ok((operation(Box::new(l), Box::new(r)), boolean.clone()))
+            };
+        Ok(match operation {
+            ast::Operation::And(l, r) => (Operation::And(operand(l)?, operand(r)?), boolean),
+            ast::Operation::Or(l, r) => (Operation::Or(operand(l)?, operand(r)?), boolean),
+            ast::Operation::Not(e) => (Operation::Not(operand(e)?), boolean),
+            ast::Operation::Equal(l, r) => comparison(l, r, Operation::Equal)?,
+            ast::Operation::NotEqual(l, r) => comparison(l, r, Operation::NotEqual)?,
+            ast::Operation::GreaterThan(l, r) => comparison(l, r, Operation::GreaterThan)?,
+            ast::Operation::GreaterThanOrEqual(l, r) => {
+                comparison(l, r, Operation::GreaterThanOrEqual)?
+            }
+            ast::Operation::LessThan(l, r) => comparison(l, r, Operation::LessThan)?,
+            ast::Operation::LessThanOrEqual(l, r) => comparison(l, r, Operation::LessThanOrEqual)?,
+            ast::Operation::In(e, list) => {
+                let e = build(e)?.0;
+                let list = list
+                    .into_iter()
+                    .map(|item| Ok(self.build_expression(item, scope)?.0))
+                    .collect::<Result<Vec<_>>>()?;
+                let padded = is_unpadded(&e) || list.iter().any(is_unpadded);
+                let list = list
+                    .into_iter()
+                    .map(|item| unpad_if(item, padded))
+                    .collect();
+                (Operation::In(Box::new(unpad_if(e, padded)), list), boolean)
+            }
+            ast::Operation::InSubquery(e, query) => {
+                let e = build(e)?.0;
+                let (plan, padded) = self.build_subquery(*query, scope, true, is_unpadded(&e))?;
+                let e = Box::new(unpad_if(e, padded));
+                (Operation::InSubquery(e, Box::new(plan)), boolean)
+            }
+            ast::Operation::Between(e, low, high) => {
+                let (e, low, high) = (build(e)?.0, build(low)?.0, build(high)?.0);
+                let padded = [&e, &low, &high].into_iter().any(is_unpadded);
+                let [e, low, high] = [e, low, high].map(|e| Box::new(unpad_if(e, padded)));
+                (Operation::Between(e, low, high), boolean)
+            }
+            ast::Operation::Add(l, r) => arithmetic(l, r, Operation::Add)?,
+            ast::Operation::Subtract(l, r) => arithmetic(l, r, Operation::Subtract)?,
+            ast::Operation::Multiply(l, r) => arithmetic(l, r, Operation::Multiply)?,
+            ast::Operation::Divide(l, r) => arithmetic(l, r, Operation::Divide)?,
+            ast::Operation::Negate(e) => {
+                let (e, datatype) = build(e)?;
+                let datatype = match datatype {
+                    Some(DataType::Interval) => Some(DataType::Interval),
+                    t => functions::number_type([t.as_ref()].into_iter()),
+                };
+                (Operation::Negate(Box::new(e)), datatype)
+            }
+            ast::Operation::Like(e, pattern, escape) => {
+                let (e, pattern) = (operand(e)?, operand(pattern)?);
+                let escape = escape.map(operand).transpose()?;
+                (Operation::Like(e, pattern, escape), boolean)
+            }
+            ast::Operation::ILike(e, pattern, escape) => {
+                let (e, pattern) = (operand(e)?, operand(pattern)?);
+                let escape = escape.map(operand).transpose()?;
+                (Operation::ILike(e, pattern, escape), boolean)
+            }
+            ast::Operation::JsonGet(l, r) => (
+                Operation::JsonGet(operand(l)?, operand(r)?),
+                Some(DataType::Json),
+            ),
+            ast::Operation::JsonGetText(l, r) => (
+                Operation::JsonGetText(operand(l)?, operand(r)?),
+                Some(DataType::String),
+            ),
+            // blobs concatenate to a blob, anything else to text
+            ast::Operation::Concat(l, r) => {
+                let ((l, l_type), (r, r_type)) = (build(l)?, build(r)?);
+                let datatype = match (l_type, r_type) {
+                    (Some(DataType::Blob), Some(DataType::Blob)) => Some(DataType::Blob),
+                    (Some(_), Some(_)) => Some(DataType::String),
+                    _ => None,
+                };
+                (Operation::Concat(Box::new(l), Box::new(r)), datatype)
+            }
+        })
+    }
+
+    // value of an expression without columns, e.g. a column default
+    fn evaluate_constant(&self, expr: ast::Expression) -> Result<Value> {
+        let (expr, _) = self.build_expression(expr, &Scope::default())?;
+        expr.evaluate(&Vec::new())
+    }
+
+    // the node limited by LIMIT and OFFSET, OFFSET is applied first
+    fn limit_offset(
+        &self,
+        mut node: Node,
+        limit: Option<ast::Expression>,
+        offset: Option<ast::Expression>,
+    ) -> Result<Node> {
+        if let Some(offset) = offset {
+            node = Node::Offset {
+                source: Box::new(node),
+                offset: self.row_count(offset)?,
+            };
+        }
+        if let Some(limit) = limit {
+            node = Node::Limit {
+                source: Box::new(node),
+                limit: self.row_count(limit)?,
+            };
+        }
+        Ok(node)
+    }
+
+    // value of a LIMIT or OFFSET clause
+    fn row_count(&self, expr: ast::Expression) -> Result<usize> {
+        match self.evaluate_constant(expr)? {
+            Value::Integer(n) if n >= 0 => Ok(n as usize),
+            v => Err(Error::Value(format!(
+                "[Planner] Expect a non-negative integer for LIMIT or OFFSET, got {}",
+                v
+            ))),
+        }
+    }
+}
+
+// the columns of the table rows
+fn table_scope(table: &Table) -> Scope<'static> {
+    Scope {
+        qualifier: Some(table.name.clone()),
+        columns: table
+            .columns
+            .iter()
+            .map(|c| Column {
+                name: c.name.clone(),
+                datatype: Some(c.datatype.clone()),
+                padded: c.padded,
+            })
+            .collect(),
+        outer: None,
+    }
+}
+
+// the rows of node with the columns where unpad is set without CHAR(n)
+// padding
+fn unpad_columns(node: Node, columns: &[Column], unpad: &[bool]) -> Node {
+    if !unpad.contains(&true) {
+        return node;
+    }
+    let exprs = columns
+        .iter()
+        .zip(unpad)
+        .enumerate()
+        .map(|(i, (c, unpad))| {
+            let field = Expression::Field(i, c.name.clone());
+            (unpad_if(field, *unpad), Some(c.name.clone()))
+        })
+        .collect();
+    Node::Projection {
+        source: Box::new(node),
+        exprs,
+        types: columns.iter().map(|c| c.datatype.clone()).collect(),
+    }
+}
+
+fn is_unpadded(expr: &Expression) -> bool {
+    matches!(expr, Expression::Unpad(_))
+}
+
+// the expression as stored, with CHAR(n) padding
+fn as_stored(expr: &Expression) -> &Expression {
+    match expr {
+        Expression::Unpad(expr) => expr,
+        expr => expr,
+    }
+}
+
+// the expression without CHAR(n) padding if padded is set
+fn unpad_if(expr: Expression, padded: bool) -> Expression {
+    match padded && !is_unpadded(&expr) {
+        true => Expression::Unpad(Box::new(expr)),
+        false => expr,
+    }
+}
+
+// the type both queries of a set operation convert a column to, numbers
+// to the type of their arithmetic, each side tells if the column is a string
+// literal, only those convert to the type of the other side like untyped
+// constants, a STRING column does not
+fn common_type(
+    operator: ast::SetOperator,
+    (l, l_literal): (Option<DataType>, bool),
+    (r, r_literal): (Option<DataType>, bool),
+) -> Result<Option<DataType>> {
+    let string = |datatype: &DataType| *datatype == DataType::String;
+    Ok(match (l, r) {
+        (Some(l), Some(r)) if l == r => Some(l),
+        (Some(l), Some(r)) => match functions::number_type([Some(&l), Some(&r)].into_iter()) {
+            Some(datatype) => Some(datatype),
+            None if string(&l) && l_literal => Some(r),
+            None if string(&r) && r_literal => Some(l),
+            None if !string(&r) && r.coerces_to(&l) => Some(l),
+            None if !string(&l) && l.coerces_to(&r) => Some(r),
+            None => {
+                return Err(Error::Value(format!(
+                    "[Planner] {} types {} and {} cannot be matched",
+                    operator, l, r
+                )))
+            }
+        },
+        (l, r) => l.or(r),
+    })
+}
+
+// the columns of a query that are string literals
+fn string_literals(node: &Node) -> Vec<bool> {
+    match node {
+        Node::Projection { exprs, .. } => exprs
+            .iter()
+            .map(|(expr, _)| matches!(expr, Expression::Constant(Value::String(_))))
+            .collect(),
+        Node::Distinct { source, .. }
+        | Node::Limit { source, .. }
+        | Node::Offset { source, .. } => string_literals(source),
+        _ => Vec::new(),
+    }
+}
+
+// a AND b AND c -> [a, b, c]
+fn split_conjunction(expr: &Expression) -> Vec<&Expression> {
+    match expr {
+        Expression::Operation(Operation::And(l, r)) => {
+            let mut exprs = split_conjunction(l);
+            exprs.extend(split_conjunction(r));
+            exprs
+        }
+        expr => vec![expr],
+    }
+}
+
+// values of the column matched by `column = const` or `column IN (const, ...)`,
+// NULLs are left out since they never compare equal
+fn lookup_values(expr: &Expression) -> Option<(usize, Vec<Value>)> {
+    let (column, consts) = match expr {
+        Expression::Operation(Operation::Equal(l, r)) => match (as_stored(l), as_stored(r)) {
+            (Expression::Field(column, _), c @ Expression::Constant(_))
+            | (c @ Expression::Constant(_), Expression::Field(column, _)) => (*column, vec![c]),
+            _ => return None,
+        },
+        Expression::Operation(Operation::In(e, list)) => match as_stored(e) {
+            Expression::Field(column, _)
+                if list
+                    .iter()
+                    .all(|c| matches!(as_stored(c), Expression::Constant(_))) =>
+            {
+                (*column, list.iter().map(as_stored).collect())
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    let mut values = Vec::new();
+    for c in consts {
+        if let Expression::Constant(value) = c {
+            if *value != Value::Null && !values.contains(value) {
+                values.push(value.clone());
+            }
+        }
+    }
+    Some((column, values))
+}
+
+// the values converted implicitly to the column type without duplicates,
+// CHAR(n) strings padded as they are stored, none if any does not convert
+fn coerce_keys(values: Vec<Value>, column: &schema::Column) -> Option<Vec<Value>> {
+    let mut keys = Vec::new();
+    for value in values {
+        let key = match value.coerce(&column.datatype).ok()? {
+            Value::String(s) if column.padded => Value::String(column.fit_length(s).ok()?),
+            key => key,
+        };
+        if !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    Some(keys)
+}
+
+// intersection of the ranges that the conjunctions put on the column at
+// the position, none if no conjunction bounds it with constants of its datatype
+fn column_range(
+    exprs: &[&Expression],
+    column: usize,
+    datatype: &DataType,
+) -> Option<(Bound<Value>, Bound<Value>)> {
+    let mut range = None;
+    for expr in exprs {
+        let Some((start, end)) = bounds(expr, column) else {
+            continue;
+        };
+        // keys of another type are ordered apart, the range would miss rows,
+        // so the bounds must convert to the column type without loss
+        let (Some(start), Some(end)) = (coerce_bound(start, datatype), coerce_bound(end, datatype))
+        else {
+            continue;
+        };
+        range = Some(match range {
+            None => (start, end),
+            Some((s, e)) => (
+                tighter(s, start, |o| o.is_gt()),
+                tighter(e, end, |o| o.is_lt()),
+            ),
+        });
+    }
+    range
+}
+
+fn coerce_bound(bound: Bound<Value>, datatype: &DataType) -> Option<Bound<Value>> {
+    Some(match bound {
+        Bound::Included(v) => Bound::Included(v.coerce(datatype).ok()?),
+        Bound::Excluded(v) => Bound::Excluded(v.coerce(datatype).ok()?),
+        Bound::Unbounded => Bound::Unbounded,
+    })
+}
+
+// bounds put on the column by `column op const`, `const op column`
+// or `column BETWEEN const AND const`
+fn bounds(expr: &Expression, column: usize) -> Option<(Bound<Value>, Bound<Value>)> {
+    let is_column = |e: &Expression| matches!(e, Expression::Field(c, _) if *c == column);
+    let constant = |e: &Expression| match e {
+        Expression::Constant(value) if *value != Value::Null => Some(value.clone()),
+        _ => None,
+    };
+
+    let Expression::Operation(op) = expr else {
+        return None;
+    };
+    if let Operation::Between(e, low, high) = op {
+        if !is_column(e) {
+            return None;
+        }
+        return Some((
+            Bound::Included(constant(low)?),
+            Bound::Included(constant(high)?),
+        ));
+    }
+
+    let (l, r) = match op {
+        Operation::Equal(l, r)
+        | Operation::GreaterThan(l, r)
+        | Operation::GreaterThanOrEqual(l, r)
+        | Operation::LessThan(l, r)
+        | Operation::LessThanOrEqual(l, r) => (l, r),
+        _ => return None,
+    };
+    // normalize to `column op value`, flipping the operator for `value op column`
+    let (v, flipped) = if is_column(l) {
+        (constant(r)?, false)
+    } else if is_column(r) {
+        (constant(l)?, true)
+    } else {
+        return None;
+    };
+    Some(match (op, flipped) {
+        (Operation::Equal(..), _) => (Bound::Included(v.clone()), Bound::Included(v)),
+        (Operation::GreaterThan(..), false) | (Operation::LessThan(..), true) => {
+            (Bound::Excluded(v), Bound::Unbounded)
+        }
+        (Operation::GreaterThanOrEqual(..), false) | (Operation::LessThanOrEqual(..), true) => {
+            (Bound::Included(v), Bound::Unbounded)
+        }
+        (Operation::LessThan(..), false) | (Operation::GreaterThan(..), true) => {
+            (Bound::Unbounded, Bound::Excluded(v))
+        }
+        (Operation::LessThanOrEqual(..), false) | (Operation::GreaterThanOrEqual(..), true) => {
+            (Bound::Unbounded, Bound::Included(v))
+        }
+        _ => return None,
+    })
+}
+
+// the more restrictive of two bounds, prefer(a, b) tells if a bound
+// at a is more restrictive than one at b
+fn tighter<F: Fn(std::cmp::Ordering) -> bool>(
+    a: Bound<Value>,
+    b: Bound<Value>,
+    prefer: F,
+) -> Bound<Value> {
+    match (&a, &b) {
+        (Bound::Unbounded, _) => b,
+        (_, Bound::Unbounded) => a,
+        (Bound::Included(x) | Bound::Excluded(x), Bound::Included(y) | Bound::Excluded(y)) => {
+            match x.partial_cmp(y) {
+                Some(o) if o.is_eq() => match a {
+                    Bound::Excluded(_) => a,
+                    _ => b,
+                },
+                Some(o) if prefer(o) => a,
+                _ => b,
+            }
         }
     }
 }
@@ -1,15 +1,204 @@
-use crate::sql::types::{DataType, Value};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, PartialEq)]
+use crate::{
+    error::{ConstraintKind, Error, Result},
+    sql::types::{DataType, Row, Value},
+};
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Table {
     pub name: String,
     pub columns: Vec<Column>,
+    pub indexes: Vec<Index>,
 }
 
-#[derive(Debug, PartialEq)]
+impl Table {
+    // check the table definition before it is created
+    pub fn validate(&self) -> Result<()> {
+        if self.columns.is_empty() {
+            return Err(Error::Value(format!(
+                "[Schema] Table {} has no columns",
+                self.name
+            )));
+        }
+
+        match self.columns.iter().filter(|c| c.primary_key).count() {
+            1 => {}
+            0 => {
+                return Err(Error::Value(format!(
+                    "[Schema] No primary key for table {}",
+                    self.name
+                )))
+            }
+            _ => {
+                return Err(Error::Value(format!(
+                    "[Schema] Multiple primary keys for table {}",
+                    self.name
+                )))
+            }
+        }
+
+        for (i, column) in self.columns.iter().enumerate() {
+            if self.columns[..i].iter().any(|c| c.name == column.name) {
+                return Err(Error::Value(format!(
+                    "[Schema] Duplicate column {} for table {}",
+                    column.name, self.name
+                )));
+            }
+            if column.primary_key && column.nullable {
+                return Err(Error::Value(format!(
+                    "[Schema] Primary key {} cannot be nullable",
+                    column.name
+                )));
+            }
+            if let Some(default) = &column.default {
+                column.coerce_value(&self.name, default.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    // check the values of a row against the columns, returns the row as
+    // stored, e.g. with decimals rounded to the scale of their column
+    pub fn validate_row(&self, row: Row) -> Result<Row> {
+        if row.len() != self.columns.len() {
+            return Err(Error::Value(format!(
+                "[Schema] Table {} expects {} values, got {}",
+                self.name,
+                self.columns.len(),
+                row.len()
+            )));
+        }
+        self.columns
+            .iter()
+            .zip(row)
+            .map(|(column, value)| column.coerce_value(&self.name, value))
+            .collect()
+    }
+
+    pub fn get_primary_key<'a>(&self, row: &'a Row) -> Result<&'a Value> {
+        let pos = self
+            .columns
+            .iter()
+            .position(|c| c.primary_key)
+            .ok_or_else(|| {
+                Error::Internal(format!("[Schema] No primary key for table {}", self.name))
+            })?;
+        Ok(&row[pos])
+    }
+
+    pub fn get_col_index(&self, col_name: &str) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|c| c.name == col_name)
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "[Schema] Column {} not found in table {}",
+                    col_name, self.name
+                ))
+            })
+    }
+
+    pub fn get_index(&self, index_name: &str) -> Option<&Index> {
+        self.indexes.iter().find(|i| i.name == index_name)
+    }
+
+    pub fn column_names(&self) -> Vec<String> {
+        self.columns.iter().map(|c| c.name.clone()).collect()
+    }
+
+    pub fn column_types(&self) -> Vec<Option<DataType>> {
+        self.columns
+            .iter()
+            .map(|c| Some(c.datatype.clone()))
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct Column {
     pub name: String,
     pub datatype: DataType,
     pub nullable: bool,
     pub default: Option<Value>,
-}
\ No newline at end of file
+    pub primary_key: bool,
+    // precision and scale of DECIMAL(p, s), any decimal fits if none
+    pub precision: Option<(u32, u32)>,
+    // maximum number of characters of VARCHAR(n) and CHAR(n)
+    pub length: Option<u32>,
+    // CHAR(n), shorter strings are padded with spaces to the length
+    pub padded: bool,
+}
+
+impl Column {
+    // the value as stored in the column, converted implicitly to the column
+    // type, decimals rounded to the scale of the column and strings checked
+    // against its length
+    fn coerce_value(&self, table_name: &str, value: Value) -> Result<Value> {
+        match value.datatype() {
+            None if self.nullable => return Ok(Value::Null),
+            None => {
+                return Err(self.null_violation(
+                    table_name,
+                    format!("[Schema] Column {} cannot be null", self.name),
+                ))
+            }
+            Some(datatype) if !datatype.coerces_to(&self.datatype) => {
+                return Err(Error::Value(format!(
+                    "[Schema] Column {} expects {}, got {}",
+                    self.name, self.datatype, datatype
+                )))
+            }
+            Some(_) => {}
+        }
+        Ok(match (value.coerce(&self.datatype)?, self.precision) {
+            (Value::Decimal(d), Some((precision, scale))) => {
+                Value::Decimal(d.with_precision(precision, scale)?)
+            }
+            (Value::String(s), _) => Value::String(self.fit_length(s)?),
+            (value, _) => value,
+        })
+    }
+
+    // enforce VARCHAR(n) and CHAR(n), trailing spaces beyond n are dropped
+    pub fn fit_length(&self, mut s: String) -> Result<String> {
+        let length = match self.length {
+            Some(length) => length as usize,
+            None => return Ok(s),
+        };
+        let count = s.chars().count();
+        if count > length {
+            let (end, _) = s.char_indices().nth(length).unwrap();
+            if s[end..].trim_start_matches(' ').is_empty() {
+                s.truncate(end);
+            } else {
+                return Err(Error::TooLong(format!(
+                    "[Schema] Value too long for column {} of type {}({})",
+                    self.name,
+                    if self.padded { "CHAR" } else { "VARCHAR" },
+                    length
+                )));
+            }
+        } else if self.padded {
+            s.push_str(&" ".repeat(length - count));
+        }
+        Ok(s)
+    }
+
+    // violation of the NOT NULL constraint of the column in table_name
+    pub fn null_violation(&self, table_name: &str, message: String) -> Error {
+        Error::Constraint {
+            kind: ConstraintKind::NotNull,
+            name: format!("{}_{}_not_null", table_name, self.name),
+            message,
+        }
+    }
+}
+
+// secondary index on a single column
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct Index {
+    pub name: String,
+    pub column: String,
+    pub unique: bool,
+}
@@ -0,0 +1,306 @@
+use crate::error::{Error, Result};
+
+use super::{datetime, parse_hex, DataType, Decimal, Value, MICROS_PER_DAY};
+
+// conversions between data types
+//
+// CAST(expr AS type) and expr::type convert explicitly, values written by
+// INSERT and UPDATE and the operands of comparisons convert implicitly
+//
+// from \ to | BOOL | INT | FLOAT | DECIMAL | STRING | DATE | TIME | TIMESTAMP | others
+// BOOLEAN   |  =   |  E  |       |         |   E    |      |      |           |
+// INTEGER   |  E   |  =  |  I*   |    I    |   E    |      |      |           |
+// FLOAT     |      | I*  |   =   |    I    |   E    |      |      |           |
+// DECIMAL   |      | I*  |  I*   |    =    |   E    |      |      |           |
+// STRING    |  I   |  I  |   I   |    I    |   =    |  I   |  I   |     I     | I
+// DATE      |      |     |       |         |   E    |  =   |      |     I     |
+// TIME      |      |     |       |         |   E    |      |  =   |           |
+// TIMESTAMP |      |     |       |         |   E    |  E   |  E   |     =     |
+// INTERVAL  |      |     |       |         |   E    |      |      |           | =
+// BLOB      |      |     |       |         |   E    |      |      |           | =
+// JSON      |  E   |  E  |   E   |    E    |   E    |      |      |           | =
+//
+// I: implicit and explicit, E: explicit only, *: implicitly only when no
+// digits are lost, e.g. 1.5 to INTEGER is an error while CAST rounds it to 2,
+// strings must parse as the target type, e.g. '12' to INTEGER or
+// '2026-10-17' to DATE, and JSON converts the scalar it holds
+impl DataType {
+    // whether values of the type convert implicitly to the other type
+    pub fn coerces_to(&self, other: &DataType) -> bool {
+        self == other
+            || matches!(
+                (self, other),
+                (DataType::Integer, DataType::Float | DataType::Decimal)
+                    | (DataType::Float, DataType::Integer | DataType::Decimal)
+                    | (DataType::Decimal, DataType::Integer | DataType::Float)
+                    | (DataType::Date, DataType::Timestamp)
+                    | (DataType::String, _)
+            )
+    }
+}
+
+impl Value {
+    // implicit conversion, an error if the types do not convert implicitly or
+    // the value would change, NULL stays NULL
+    pub fn coerce(self, datatype: &DataType) -> Result<Value> {
+        let from = match self.datatype() {
+            Some(from) if from == *datatype => return Ok(self),
+            Some(from) => from,
+            None => return Ok(self),
+        };
+        if !from.coerces_to(datatype) {
+            return Err(Error::Value(format!(
+                "[Cast] Cannot convert {} to {} implicitly, use CAST",
+                from, datatype
+            )));
+        }
+        Ok(match (self, datatype) {
+            (Value::Integer(i), DataType::Float) if i.unsigned_abs() <= 1 << 53 => {
+                Value::Float(i as f64)
+            }
+            (Value::Integer(i), DataType::Decimal) => Value::Decimal(Decimal::from(i)),
+            (Value::Float(f), DataType::Integer) if f.fract() == 0.0 => float_to_integer(f)?,
+            (Value::Float(f), DataType::Decimal) => Value::Decimal(Decimal::try_from(f)?),
+            (Value::Decimal(d), DataType::Integer) if d.round(0)? == d => {
+                Value::Integer(d.to_i64().ok_or_else(|| out_of_range(d, datatype))?)
+            }
+            (Value::Decimal(d), DataType::Float)
+                if Decimal::try_from(d.to_f64()).is_ok_and(|f| f == d) =>
+            {
+                Value::Float(d.to_f64())
+            }
+            (Value::Date(d), DataType::Timestamp) => Value::Timestamp(d as i64 * MICROS_PER_DAY),
+            (Value::String(s), datatype) => parse(&s, datatype)?,
+            (value, datatype) => {
+                return Err(Error::Value(format!(
+                    "[Cast] Cannot convert {} to {} without loss, use CAST",
+                    value, datatype
+                )))
+            }
+        })
+    }
+
+    // explicit conversion, numbers are rounded where needed
+    pub fn cast(self, datatype: &DataType) -> Result<Value> {
+        Ok(match (self, datatype) {
+            (Value::Null, _) => Value::Null,
+            (Value::String(s), DataType::String) => Value::String(s),
+            (value, DataType::String) => Value::String(value.to_string()),
+            (Value::Boolean(b), DataType::Integer) => Value::Integer(b as i64),
+            (Value::Integer(i), DataType::Boolean) => Value::Boolean(i != 0),
+            (Value::Integer(i), DataType::Float) => Value::Float(i as f64),
+            (Value::Float(f), DataType::Integer) => float_to_integer(f.round())?,
+            (Value::Decimal(d), DataType::Integer) => {
+                Value::Integer(d.to_i64().ok_or_else(|| out_of_range(d, datatype))?)
+            }
+            (Value::Decimal(d), DataType::Float) => Value::Float(d.to_f64()),
+            (Value::Timestamp(t), DataType::Date) => {
+                Value::Date(t.div_euclid(MICROS_PER_DAY) as i32)
+            }
+            (Value::Timestamp(t), DataType::Time) => Value::Time(t.rem_euclid(MICROS_PER_DAY)),
+            (Value::Json(j), datatype) if *datatype != DataType::Json => match j.as_scalar() {
+                Some(value) => value.cast(datatype)?,
+                None => {
+                    return Err(Error::Value(format!(
+                        "[Cast] Cannot cast JSON {} to {}",
+                        j, datatype
+                    )))
+                }
+            },
+            (value, datatype) => match value.datatype() {
+                Some(from) if !from.coerces_to(datatype) => {
+                    return Err(Error::Value(format!(
+                        "[Cast] Cannot cast {} to {}",
+                        from, datatype
+                    )))
+                }
+                _ => value.coerce(datatype)?,
+            },
+        })
+    }
+}
+
+// an integral float as an integer
+fn float_to_integer(f: f64) -> Result<Value> {
+    // i64::MAX is not a float, 2^63 is the first float out of range
+    if f.is_nan() || f < i64::MIN as f64 || f >= i64::MAX as f64 {
+        return Err(out_of_range(f, &DataType::Integer));
+    }
+    Ok(Value::Integer(f as i64))
+}
+
+fn out_of_range(value: impl std::fmt::Display, datatype: &DataType) -> Error {
+    Error::OutOfRange(format!("[Cast] {} is out of range for {}", value, datatype))
+}
+
+// the text of a value of the type, surrounding spaces are ignored except for
+// strings, blobs and JSON
+fn parse(text: &str, datatype: &DataType) -> Result<Value> {
+    let invalid = || Error::InvalidText(format!("[Cast] Invalid {} '{}'", datatype, text));
+    Ok(match datatype {
+        DataType::Boolean => match text.trim().to_lowercase().as_str() {
+            "true" | "t" | "yes" | "y" | "on" | "1" => Value::Boolean(true),
+            "false" | "f" | "no" | "n" | "off" | "0" => Value::Boolean(false),
+            _ => return Err(invalid()),
+        },
+        DataType::Integer => Value::Integer(text.trim().parse().map_err(|_| invalid())?),
+        DataType::Float => match text.trim().parse::<f64>() {
+            Ok(f) if f.is_finite() => Value::Float(f),
+            _ => return Err(invalid()),
+        },
+        DataType::Decimal => Value::Decimal(text.parse()?),
+        DataType::String => Value::String(text.to_string()),
+        DataType::Date => Value::Date(datetime::parse_date(text)?),
+        DataType::Time => Value::Time(datetime::parse_time(text)?),
+        DataType::Timestamp => Value::Timestamp(datetime::parse_timestamp(text)?),
+        DataType::Interval => Value::Interval(datetime::parse_interval(text)?),
+        // \x followed by hex digits as PostgreSQL prints bytea, otherwise
+        // the UTF-8 bytes of the text
+        DataType::Blob => Value::Blob(match text.strip_prefix("\\x") {
+            Some(hex) => parse_hex(hex)?,
+            None => text.as_bytes().to_vec(),
+        }),
+        DataType::Json => Value::Json(text.parse()?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sql::types::Json;
+
+    #[test]
+    fn test_coerce() -> Result<()> {
+        let ok = [
+            (Value::Integer(3), DataType::Float, Value::Float(3.0)),
+            (Value::Float(-2.0), DataType::Integer, Value::Integer(-2)),
+            (
+                Value::Integer(7),
+                DataType::Decimal,
+                Value::Decimal("7".parse()?),
+            ),
+            (
+                Value::Decimal("0.25".parse()?),
+                DataType::Float,
+                Value::Float(0.25),
+            ),
+            (
+                Value::Decimal("12.00".parse()?),
+                DataType::Integer,
+                Value::Integer(12),
+            ),
+            (
+                Value::String(" 42 ".to_string()),
+                DataType::Integer,
+                Value::Integer(42),
+            ),
+            (
+                Value::String("off".to_string()),
+                DataType::Boolean,
+                Value::Boolean(false),
+            ),
+            (
+                Value::String("2026-10-17".to_string()),
+                DataType::Date,
+                Value::Date(20743),
+            ),
+            (
+                Value::Date(1),
+                DataType::Timestamp,
+                Value::Timestamp(MICROS_PER_DAY),
+            ),
+            (
+                Value::String("\\xdead".to_string()),
+                DataType::Blob,
+                Value::Blob(vec![0xde, 0xad]),
+            ),
+            (Value::Null, DataType::Integer, Value::Null),
+        ];
+        for (value, datatype, expect) in ok {
+            assert_eq!(value.clone().coerce(&datatype)?, expect, "{}", value);
+        }
+
+        let err = [
+            (Value::Float(1.5), DataType::Integer),
+            (Value::Float(1e19), DataType::Integer),
+            (Value::Integer(i64::MAX), DataType::Float),
+            (Value::Decimal("0.1".parse()?), DataType::Integer),
+            (Value::String("1.5".to_string()), DataType::Integer),
+            (Value::String("abc".to_string()), DataType::Float),
+            (Value::Integer(1), DataType::String),
+            (Value::Integer(1), DataType::Boolean),
+            (Value::Timestamp(0), DataType::Date),
+            (Value::Json(Json::Number(1.0)), DataType::Integer),
+        ];
+        for (value, datatype) in err {
+            assert!(value.clone().coerce(&datatype).is_err(), "{}", value);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_cast() -> Result<()> {
+        let ok = [
+            (Value::Float(2.5), DataType::Integer, Value::Integer(3)),
+            (Value::Float(-2.5), DataType::Integer, Value::Integer(-3)),
+            (
+                Value::Decimal("-7.5".parse()?),
+                DataType::Integer,
+                Value::Integer(-8),
+            ),
+            (
+                Value::Decimal("0.1".parse()?),
+                DataType::Float,
+                Value::Float(0.1),
+            ),
+            (Value::Boolean(true), DataType::Integer, Value::Integer(1)),
+            (Value::Integer(0), DataType::Boolean, Value::Boolean(false)),
+            (
+                Value::Integer(12),
+                DataType::String,
+                Value::String("12".to_string()),
+            ),
+            (
+                Value::Blob(vec![1, 255]),
+                DataType::String,
+                Value::String("\\x01ff".to_string()),
+            ),
+            (
+                Value::Timestamp(MICROS_PER_DAY + 5),
+                DataType::Date,
+                Value::Date(1),
+            ),
+            (
+                Value::Timestamp(-1),
+                DataType::Time,
+                Value::Time(MICROS_PER_DAY - 1),
+            ),
+            (
+                Value::Json(Json::Number(4.0)),
+                DataType::Integer,
+                Value::Integer(4),
+            ),
+            (
+                Value::Json(Json::String("x".to_string())),
+                DataType::String,
+                Value::String("\"x\"".to_string()),
+            ),
+            (Value::Null, DataType::Date, Value::Null),
+        ];
+        for (value, datatype, expect) in ok {
+            assert_eq!(value.clone().cast(&datatype)?, expect, "{}", value);
+        }
+
+        let err = [
+            (Value::Float(f64::NAN), DataType::Integer),
+            (Value::Date(0), DataType::Integer),
+            (Value::Boolean(true), DataType::Float),
+            (Value::Json(Json::Array(Vec::new())), DataType::Integer),
+            (Value::String("maybe".to_string()), DataType::Boolean),
+        ];
+        for (value, datatype) in err {
+            assert!(value.clone().cast(&datatype).is_err(), "{}", value);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,529 @@
+use std::{
+    fmt::Display,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+// Dates are days since 1970-01-01, times are microseconds since midnight
+// and timestamps are microseconds since 1970-01-01 00:00:00, without time
+// zone, in the Gregorian calendar from year 1 to 9999
+
+pub const MICROS_PER_SECOND: i64 = 1_000_000;
+pub const MICROS_PER_MINUTE: i64 = 60 * MICROS_PER_SECOND;
+pub const MICROS_PER_HOUR: i64 = 60 * MICROS_PER_MINUTE;
+pub const MICROS_PER_DAY: i64 = 24 * MICROS_PER_HOUR;
+const MICROS_PER_MONTH: i128 = 30 * MICROS_PER_DAY as i128;
+
+// 0001-01-01 and 9999-12-31
+const MIN_DATE: i64 = -719_162;
+const MAX_DATE: i64 = 2_932_896;
+
+// months and days are kept apart from the time since their length varies,
+// e.g. a month after January 31 is the last day of February, but intervals
+// compare by their span with 30-day months and 24-hour days, as in PostgreSQL
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Interval {
+    pub months: i32,
+    pub days: i32,
+    pub micros: i64,
+}
+
+impl Interval {
+    fn span(&self) -> i128 {
+        self.months as i128 * MICROS_PER_MONTH
+            + self.days as i128 * MICROS_PER_DAY as i128
+            + self.micros as i128
+    }
+
+    // the equal interval with days below 30 and micros below a day, both
+    // non-negative, so that its fields order the same as spans
+    pub fn normalize(&self) -> Result<Interval> {
+        let span = self.span();
+        let rest = span.rem_euclid(MICROS_PER_MONTH) as i64;
+        Ok(Interval {
+            months: i32::try_from(span.div_euclid(MICROS_PER_MONTH))
+                .map_err(|_| interval_overflow())?,
+            days: (rest / MICROS_PER_DAY) as i32,
+            micros: rest % MICROS_PER_DAY,
+        })
+    }
+
+    pub fn checked_add(&self, other: &Interval) -> Result<Interval> {
+        Ok(Interval {
+            months: self
+                .months
+                .checked_add(other.months)
+                .ok_or_else(interval_overflow)?,
+            days: self
+                .days
+                .checked_add(other.days)
+                .ok_or_else(interval_overflow)?,
+            micros: self
+                .micros
+                .checked_add(other.micros)
+                .ok_or_else(interval_overflow)?,
+        })
+    }
+
+    pub fn checked_sub(&self, other: &Interval) -> Result<Interval> {
+        self.checked_add(&other.checked_mul(-1)?)
+    }
+
+    pub fn checked_mul(&self, n: i64) -> Result<Interval> {
+        let mul32 = |v: i32| {
+            (v as i64)
+                .checked_mul(n)
+                .and_then(|v| i32::try_from(v).ok())
+        };
+        Ok(Interval {
+            months: mul32(self.months).ok_or_else(interval_overflow)?,
+            days: mul32(self.days).ok_or_else(interval_overflow)?,
+            micros: self.micros.checked_mul(n).ok_or_else(interval_overflow)?,
+        })
+    }
+}
+
+impl PartialEq for Interval {
+    fn eq(&self, other: &Self) -> bool {
+        self.span() == other.span()
+    }
+}
+
+impl Eq for Interval {}
+
+impl PartialOrd for Interval {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Interval {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.span().cmp(&other.span())
+    }
+}
+
+impl std::hash::Hash for Interval {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.span().hash(state)
+    }
+}
+
+// e.g. 1 year 2 mons 3 days 04:05:06, as PostgreSQL prints intervals
+impl Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let plural = |n: i32, unit: &str| match n {
+            1 => format!("{} {}", n, unit),
+            _ => format!("{} {}s", n, unit),
+        };
+        let mut parts = Vec::new();
+        if self.months / 12 != 0 {
+            parts.push(plural(self.months / 12, "year"));
+        }
+        if self.months % 12 != 0 {
+            parts.push(plural(self.months % 12, "mon"));
+        }
+        if self.days != 0 {
+            parts.push(plural(self.days, "day"));
+        }
+        if self.micros != 0 || parts.is_empty() {
+            let sign = if self.micros < 0 { "-" } else { "" };
+            parts.push(format!(
+                "{}{}",
+                sign,
+                format_hms(self.micros.unsigned_abs())
+            ));
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+fn interval_overflow() -> Error {
+    Error::Value("[Datetime] Interval out of range".to_string())
+}
+
+fn invalid(kind: &str, text: &str) -> Error {
+    Error::InvalidText(format!("[Datetime] Invalid {} '{}'", kind, text))
+}
+
+// YYYY-MM-DD
+pub fn parse_date(text: &str) -> Result<i32> {
+    date_from_str(text.trim())
+        .map(|days| days as i32)
+        .ok_or_else(|| invalid("date", text))
+}
+
+// HH:MM[:SS[.ffffff]]
+pub fn parse_time(text: &str) -> Result<i64> {
+    time_from_str(text.trim()).ok_or_else(|| invalid("time", text))
+}
+
+// a date, optionally followed by a time after a space or a T
+pub fn parse_timestamp(text: &str) -> Result<i64> {
+    let text = text.trim();
+    let (date, time) = match text.split_once([' ', 'T']) {
+        Some((date, time)) => (date, time_from_str(time.trim_start())),
+        None => (text, Some(0)),
+    };
+    match (date_from_str(date), time) {
+        (Some(days), Some(time)) => Ok(days * MICROS_PER_DAY + time),
+        _ => Err(invalid("timestamp", text)),
+    }
+}
+
+// quantities with a unit, e.g. 1 year 2 months -3 days, and an optional
+// time such as 04:05:06, which may be negative and exceed 24 hours
+pub fn parse_interval(text: &str) -> Result<Interval> {
+    interval_from_str(text).ok_or_else(|| invalid("interval", text))
+}
+
+fn number(text: &str, max_len: usize) -> Option<i64> {
+    if text.is_empty() || text.len() > max_len || !text.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    text.parse().ok()
+}
+
+fn date_from_str(text: &str) -> Option<i64> {
+    let mut parts = text.split('-');
+    let year = number(parts.next()?, 4)?;
+    let month = number(parts.next()?, 2)? as u32;
+    let day = number(parts.next()?, 2)? as u32;
+    if parts.next().is_some()
+        || year < 1
+        || !(1..=12).contains(&month)
+        || day < 1
+        || day > days_in_month(year, month)
+    {
+        return None;
+    }
+    Some(days_from_civil(year, month, day))
+}
+
+fn time_from_str(text: &str) -> Option<i64> {
+    hms_from_str(text, 2).filter(|t| *t < MICROS_PER_DAY)
+}
+
+// microseconds of H:MM[:SS[.ffffff]], hours have at most max_len digits
+fn hms_from_str(text: &str, max_len: usize) -> Option<i64> {
+    let (hms, fraction) = match text.split_once('.') {
+        Some((hms, fraction)) => (hms, Some(fraction)),
+        None => (text, None),
+    };
+    let mut parts = hms.split(':');
+    let hours = number(parts.next()?, max_len)?;
+    let minutes = number(parts.next()?, 2)?;
+    let seconds = match parts.next() {
+        Some(seconds) => number(seconds, 2)?,
+        None if fraction.is_none() => 0,
+        None => return None,
+    };
+    if parts.next().is_some() || minutes > 59 || seconds > 59 {
+        return None;
+    }
+    let micros = match fraction {
+        Some(fraction) => number(fraction, 6)? * 10i64.pow(6 - fraction.len() as u32),
+        None => 0,
+    };
+    Some(
+        hours * MICROS_PER_HOUR
+            + minutes * MICROS_PER_MINUTE
+            + seconds * MICROS_PER_SECOND
+            + micros,
+    )
+}
+
+fn interval_from_str(text: &str) -> Option<Interval> {
+    let mut interval = Interval::default();
+    let mut words = text.split_whitespace().peekable();
+    words.peek()?;
+    while let Some(word) = words.next() {
+        if word.contains(':') {
+            let micros = match word.strip_prefix('-') {
+                Some(time) => -hms_from_str(time, 9)?,
+                None => hms_from_str(word.strip_prefix('+').unwrap_or(word), 9)?,
+            };
+            interval.micros = interval.micros.checked_add(micros)?;
+            continue;
+        }
+        let n: i64 = word.parse().ok()?;
+        let (months, days, micros) = match words.next()?.to_lowercase().as_str() {
+            "year" | "years" | "y" => (n.checked_mul(12)?, 0, 0),
+            "month" | "months" | "mon" | "mons" => (n, 0, 0),
+            "week" | "weeks" | "w" => (0, n.checked_mul(7)?, 0),
+            "day" | "days" | "d" => (0, n, 0),
+            "hour" | "hours" | "h" => (0, 0, n.checked_mul(MICROS_PER_HOUR)?),
+            "minute" | "minutes" | "min" | "mins" | "m" => {
+                (0, 0, n.checked_mul(MICROS_PER_MINUTE)?)
+            }
+            "second" | "seconds" | "sec" | "secs" | "s" => {
+                (0, 0, n.checked_mul(MICROS_PER_SECOND)?)
+            }
+            "millisecond" | "milliseconds" | "ms" => (0, 0, n.checked_mul(1000)?),
+            "microsecond" | "microseconds" | "us" => (0, 0, n),
+            _ => return None,
+        };
+        interval = interval
+            .checked_add(&Interval {
+                months: months.try_into().ok()?,
+                days: days.try_into().ok()?,
+                micros,
+            })
+            .ok()?;
+    }
+    Some(interval)
+}
+
+pub fn format_date(days: i32) -> String {
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+pub fn format_time(micros: i64) -> String {
+    format_hms(micros.unsigned_abs())
+}
+
+pub fn format_timestamp(micros: i64) -> String {
+    let days = micros.div_euclid(MICROS_PER_DAY);
+    format!(
+        "{} {}",
+        format_date(days as i32),
+        format_time(micros.rem_euclid(MICROS_PER_DAY))
+    )
+}
+
+// HH:MM:SS, followed by the fraction of the second if there is one
+fn format_hms(micros: u64) -> String {
+    let per_second = MICROS_PER_SECOND as u64;
+    let seconds = micros / per_second;
+    let mut text = format!(
+        "{:02}:{:02}:{:02}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    );
+    let fraction = micros % per_second;
+    if fraction != 0 {
+        text.push_str(format!(".{:06}", fraction).trim_end_matches('0'));
+    }
+    text
+}
+
+pub fn check_date(days: i64) -> Result<i32> {
+    match (MIN_DATE..=MAX_DATE).contains(&days) {
+        true => Ok(days as i32),
+        false => Err(Error::Value("[Datetime] Date out of range".to_string())),
+    }
+}
+
+pub fn check_timestamp(micros: i64) -> Result<i64> {
+    match (MIN_DATE * MICROS_PER_DAY..(MAX_DATE + 1) * MICROS_PER_DAY).contains(&micros) {
+        true => Ok(micros),
+        false => Err(Error::Value(
+            "[Datetime] Timestamp out of range".to_string(),
+        )),
+    }
+}
+
+pub fn add_days(date: i32, days: i64) -> Result<i32> {
+    check_date((date as i64).saturating_add(days))
+}
+
+// months are added first, keeping the day of the month where it exists,
+// then the days and the time
+pub fn add_interval(timestamp: i64, interval: &Interval) -> Result<i64> {
+    let days = timestamp.div_euclid(MICROS_PER_DAY);
+    let (year, month, day) = civil_from_days(days);
+    let months = year * 12 + month as i64 - 1 + interval.months as i64;
+    let (year, month) = (months.div_euclid(12), months.rem_euclid(12) as u32 + 1);
+    if !(1..=9999).contains(&year) {
+        return Err(Error::Value(
+            "[Datetime] Timestamp out of range".to_string(),
+        ));
+    }
+    let days = days_from_civil(year, month, day.min(days_in_month(year, month)));
+    check_timestamp(
+        (days + interval.days as i64)
+            .checked_mul(MICROS_PER_DAY)
+            .and_then(|t| t.checked_add(timestamp.rem_euclid(MICROS_PER_DAY)))
+            .and_then(|t| t.checked_add(interval.micros))
+            .unwrap_or(i64::MAX),
+    )
+}
+
+// the time of day wraps around midnight, months and days do not move it
+pub fn add_time(time: i64, interval: &Interval) -> i64 {
+    (time + interval.micros.rem_euclid(MICROS_PER_DAY)).rem_euclid(MICROS_PER_DAY)
+}
+
+// the difference of two timestamps in days and time
+pub fn timestamp_diff(a: i64, b: i64) -> Interval {
+    let micros = a - b;
+    Interval {
+        months: 0,
+        days: (micros / MICROS_PER_DAY) as i32,
+        micros: micros % MICROS_PER_DAY,
+    }
+}
+
+// microseconds since 1970-01-01 00:00:00 UTC
+pub fn now() -> i64 {
+    let elapsed = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    elapsed.as_micros() as i64
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since 1970-01-01 of a date, and the other way round, see
+// http://howardhinnant.github.io/date_algorithms.html
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = month as i64;
+    let day_of_year =
+        (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_datetime_parse_format() -> Result<()> {
+        assert_eq!(parse_date("1970-01-01")?, 0);
+        assert_eq!(parse_date("0001-01-01")? as i64, MIN_DATE);
+        assert_eq!(parse_date("9999-12-31")? as i64, MAX_DATE);
+        assert_eq!(format_date(parse_date("2024-02-29")?), "2024-02-29");
+        assert_eq!(format_date(-1), "1969-12-31");
+        for text in [
+            "2023-02-29",
+            "2026-13-01",
+            "0000-01-01",
+            "2026-1-1x",
+            "26-01",
+        ] {
+            assert!(parse_date(text).is_err(), "{}", text);
+        }
+
+        assert_eq!(
+            parse_time("01:02")?,
+            MICROS_PER_HOUR + 2 * MICROS_PER_MINUTE
+        );
+        assert_eq!(format_time(parse_time("23:59:59.25")?), "23:59:59.25");
+        assert!(parse_time("24:00:00").is_err());
+        assert!(parse_time("12:00.5").is_err());
+
+        let ts = parse_timestamp("2026-10-17 08:30:00.000001")?;
+        assert_eq!(format_timestamp(ts), "2026-10-17 08:30:00.000001");
+        assert_eq!(parse_timestamp("2026-10-17T08:30")?, ts - 1);
+        assert_eq!(
+            format_timestamp(parse_timestamp("1900-03-01")?),
+            "1900-03-01 00:00:00"
+        );
+
+        let interval = parse_interval("1 year 14 months -3 days 04:05:06.5")?;
+        assert_eq!(interval.to_string(), "2 years 2 mons -3 days 04:05:06.5");
+        assert_eq!(
+            parse_interval("2 weeks 1 hour")?.to_string(),
+            "14 days 01:00:00"
+        );
+        assert_eq!(parse_interval("-36:00")?.to_string(), "-36:00:00");
+        assert_eq!(parse_interval("0 days")?.to_string(), "00:00:00");
+        assert!(parse_interval("").is_err());
+        assert!(parse_interval("3 fortnights").is_err());
+        assert!(parse_interval("99999999999 days").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_datetime_arithmetic() -> Result<()> {
+        let ts = |text| parse_timestamp(text);
+        let month = Interval {
+            months: 1,
+            ..Default::default()
+        };
+        assert_eq!(
+            add_interval(ts("2024-01-31 10:00")?, &month)?,
+            ts("2024-02-29 10:00")?
+        );
+        assert_eq!(
+            add_interval(ts("2024-03-31")?, &month.checked_mul(-13)?)?,
+            ts("2023-02-28")?
+        );
+        assert_eq!(
+            add_interval(ts("2026-12-31 23:00")?, &parse_interval("1 day 2 hours")?)?,
+            ts("2027-01-02 01:00")?
+        );
+        assert!(add_interval(ts("9999-12-31")?, &parse_interval("1 day")?).is_err());
+        assert!(add_days(0, i64::MAX).is_err());
+
+        assert_eq!(
+            timestamp_diff(ts("2026-10-17 12:00")?, ts("2026-10-16 06:30")?).to_string(),
+            "1 day 05:30:00"
+        );
+        assert_eq!(
+            add_time(parse_time("23:00")?, &parse_interval("1 day 02:00")?),
+            parse_time("01:00")?
+        );
+        assert!(Interval {
+            months: i32::MAX,
+            ..Default::default()
+        }
+        .checked_add(&month)
+        .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_interval_compare() -> Result<()> {
+        let i = |text| parse_interval(text);
+        assert!(i("1 day")? < i("100 hours")?);
+        assert_eq!(i("1 day")?, i("24 hours")?);
+        assert_eq!(i("1 mon")?, i("30 days")?);
+        assert!(i("1 mon")? < i("31 days")?);
+        assert!(i("-1 hour")? < i("00:00")?);
+
+        // normalized fields order the same as the spans
+        let normalized = i("1 day -02:00")?.normalize()?;
+        assert_eq!(
+            (normalized.months, normalized.days, normalized.micros),
+            (0, 0, 22 * MICROS_PER_HOUR)
+        );
+        let normalized = i("-1 hour")?.normalize()?;
+        assert_eq!(
+            (normalized.months, normalized.days, normalized.micros),
+            (-1, 29, 23 * MICROS_PER_HOUR)
+        );
+        assert_eq!(i("100 hours")?.normalize()?.to_string(), "4 days 04:00:00");
+        Ok(())
+    }
+}
@@ -0,0 +1,610 @@
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+use serde::{
+    de::{self, SeqAccess, Visitor},
+    ser::SerializeTuple,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use crate::error::{Error, Result};
+
+// at most 38 digits, of which at most 38 after the point
+pub const MAX_PRECISION: u32 = 38;
+
+// fractional digits of a quotient, unless the operands have more
+const DIVISION_SCALE: u32 = 16;
+
+// exact fixed-point number, mantissa / 10^scale, so 1.50 is 150 with scale 2,
+// rounding is half away from zero
+#[derive(Debug, Clone, Copy)]
+pub struct Decimal {
+    mantissa: i128,
+    scale: u32,
+}
+
+fn pow10(n: u32) -> i128 {
+    10i128.pow(n)
+}
+
+fn overflow() -> Error {
+    Error::OutOfRange("[Decimal] Value out of range".to_string())
+}
+
+// number of digits of n, 0 has none
+fn digits(n: u128) -> u32 {
+    n.checked_ilog10().map_or(0, |d| d + 1)
+}
+
+impl Decimal {
+    pub fn new(mantissa: i128, scale: u32) -> Result<Decimal> {
+        if scale > MAX_PRECISION || digits(mantissa.unsigned_abs()) > MAX_PRECISION {
+            return Err(overflow());
+        }
+        Ok(Decimal { mantissa, scale })
+    }
+
+    pub fn mantissa(&self) -> i128 {
+        self.mantissa
+    }
+
+    pub fn scale(&self) -> u32 {
+        self.scale
+    }
+
+    // the value with scale digits after the point
+    pub fn round(&self, scale: u32) -> Result<Decimal> {
+        match scale.cmp(&self.scale) {
+            Ordering::Equal => Ok(*self),
+            Ordering::Greater if scale > MAX_PRECISION => Err(overflow()),
+            Ordering::Greater => Decimal::new(
+                self.mantissa
+                    .checked_mul(pow10(scale - self.scale))
+                    .ok_or_else(overflow)?,
+                scale,
+            ),
+            Ordering::Less => Ok(Decimal {
+                mantissa: round_div(self.mantissa, pow10(self.scale - scale)),
+                scale,
+            }),
+        }
+    }
+
+    // rounded to the scale of a DECIMAL(precision, scale) column, an error if
+    // it then has more than precision digits
+    pub fn with_precision(&self, precision: u32, scale: u32) -> Result<Decimal> {
+        let value = self.round(scale)?;
+        if digits(value.mantissa.unsigned_abs()) > precision {
+            return Err(Error::OutOfRange(format!(
+                "[Decimal] Value {} out of range for DECIMAL({}, {})",
+                self, precision, scale
+            )));
+        }
+        Ok(value)
+    }
+
+    // without trailing zeros after the point
+    pub fn normalize(&self) -> Decimal {
+        let mut value = *self;
+        while value.scale > 0 && value.mantissa % 10 == 0 {
+            value.mantissa /= 10;
+            value.scale -= 1;
+        }
+        value
+    }
+
+    // rounded to places digits after the point, or to a multiple of
+    // 10^-places if places is negative, e.g. 1250 to -2 places is 1300
+    pub fn round_places(&self, places: i64) -> Result<Decimal> {
+        if places >= 0 {
+            return self.round(places.min(MAX_PRECISION as i64 + 1) as u32);
+        }
+        let shift = places.unsigned_abs();
+        if self.scale as u64 + shift > MAX_PRECISION as u64 {
+            return Ok(Decimal::from(0));
+        }
+        let shift = shift as u32;
+        let mantissa = round_div(self.mantissa, pow10(self.scale + shift));
+        Decimal::new(mantissa.checked_mul(pow10(shift)).ok_or_else(overflow)?, 0)
+    }
+
+    // the largest integer not greater than the value
+    pub fn floor(&self) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa.div_euclid(pow10(self.scale)),
+            scale: 0,
+        }
+    }
+
+    // the smallest integer not less than the value
+    pub fn ceil(&self) -> Decimal {
+        Decimal {
+            mantissa: -(-self.mantissa).div_euclid(pow10(self.scale)),
+            scale: 0,
+        }
+    }
+
+    pub fn abs(&self) -> Decimal {
+        Decimal {
+            mantissa: self.mantissa.abs(),
+            scale: self.scale,
+        }
+    }
+
+    pub fn to_f64(&self) -> f64 {
+        self.to_string().parse().unwrap_or_default()
+    }
+
+    // rounded to an integer, none if out of range
+    pub fn to_i64(&self) -> Option<i64> {
+        i64::try_from(self.round(0).ok()?.mantissa).ok()
+    }
+
+    pub fn checked_add(&self, other: &Decimal) -> Result<Decimal> {
+        let (a, b, scale) = align(self, other)?;
+        fit(a.checked_add(b).ok_or_else(overflow)?, scale)
+    }
+
+    pub fn checked_sub(&self, other: &Decimal) -> Result<Decimal> {
+        let (a, b, scale) = align(self, other)?;
+        fit(a.checked_sub(b).ok_or_else(overflow)?, scale)
+    }
+
+    pub fn checked_mul(&self, other: &Decimal) -> Result<Decimal> {
+        let mantissa = self
+            .mantissa
+            .checked_mul(other.mantissa)
+            .ok_or_else(overflow)?;
+        fit(mantissa, self.scale + other.scale)
+    }
+
+    // the remainder has the sign of the dividend
+    pub fn checked_rem(&self, other: &Decimal) -> Result<Decimal> {
+        if other.mantissa == 0 {
+            return Err(Error::DivisionByZero(
+                "[Value] Division by zero".to_string(),
+            ));
+        }
+        let (a, b, scale) = align(self, other)?;
+        Decimal::new(a % b, scale)
+    }
+
+    // the quotient has at least 16 digits after the point, fewer only
+    // when the integer part leaves no room for them
+    pub fn checked_div(&self, other: &Decimal) -> Result<Decimal> {
+        if other.mantissa == 0 {
+            return Err(Error::DivisionByZero(
+                "[Value] Division by zero".to_string(),
+            ));
+        }
+        let (a, b, _) = align(self, other)?;
+        let (n, d) = (a.unsigned_abs(), b.unsigned_abs());
+        let limit = pow10(MAX_PRECISION) as u128;
+        let (mut quotient, mut remainder) = (n / d, n % d);
+        if quotient >= limit {
+            return Err(overflow());
+        }
+        // long division, one digit after the point at a time
+        let mut scale = 0;
+        let target = self.scale.max(other.scale).max(DIVISION_SCALE);
+        let next_digit = |remainder: u128| -> Result<(u128, u128)> {
+            let r = remainder.checked_mul(10).ok_or_else(overflow)?;
+            Ok((r / d, r % d))
+        };
+        while scale < target && quotient < limit / 10 {
+            let (digit, r) = next_digit(remainder)?;
+            quotient = quotient * 10 + digit;
+            remainder = r;
+            scale += 1;
+        }
+        if next_digit(remainder)?.0 >= 5 {
+            quotient += 1;
+        }
+        let mantissa = quotient as i128;
+        match (a < 0) != (b < 0) {
+            true => fit(-mantissa, scale),
+            false => fit(mantissa, scale),
+        }
+    }
+
+    // key bytes that sort in the order of the values, for the normalized
+    // value 0.d1d2...dn * 10^e: a sign byte, then for positives the
+    // exponent and the digits, and for negatives both inverted and followed
+    // by 0xff, so that -0.12 sorts after -0.123
+    fn ordered_bytes(&self) -> Vec<u8> {
+        let value = self.normalize();
+        let digits = value.mantissa.unsigned_abs().to_string();
+        let exponent = digits.len() as i32 - value.scale as i32;
+        let exponent = (exponent + 128) as u8;
+        match value.mantissa.cmp(&0) {
+            Ordering::Equal => vec![2],
+            Ordering::Greater => [3, exponent].into_iter().chain(digits.bytes()).collect(),
+            Ordering::Less => [1, !exponent]
+                .into_iter()
+                .chain(digits.bytes().map(|b| b'0' + b'9' - b))
+                .chain([0xff])
+                .collect(),
+        }
+    }
+
+    fn from_ordered_bytes(bytes: &[u8], scale: u32) -> Option<Decimal> {
+        let (&sign, rest) = bytes.split_first()?;
+        let (&exponent, digits) = match sign {
+            2 => return Decimal::new(0, scale).ok(),
+            1 => {
+                let (_, rest) = rest.split_last()?;
+                let (exponent, digits) = rest.split_first()?;
+                (exponent, digits)
+            }
+            _ => rest.split_first()?,
+        };
+        let (exponent, text): (i32, String) = match sign {
+            1 => (
+                (!exponent) as i32 - 128,
+                digits.iter().map(|b| (b'0' + b'9' - b) as char).collect(),
+            ),
+            _ => (
+                exponent as i32 - 128,
+                digits.iter().map(|b| *b as char).collect(),
+            ),
+        };
+        let normalized_scale = text.len() as i32 - exponent;
+        let mut value: Decimal = text.parse().ok()?;
+        if normalized_scale < 0 {
+            value = Decimal::new(value.mantissa * pow10((-normalized_scale) as u32), 0).ok()?;
+        } else {
+            value.scale = normalized_scale as u32;
+        }
+        if sign == 1 {
+            value.mantissa = -value.mantissa;
+        }
+        value.round(scale).ok()
+    }
+}
+
+// the mantissas of both at the larger scale
+fn align(a: &Decimal, b: &Decimal) -> Result<(i128, i128, u32)> {
+    let scale = a.scale.max(b.scale);
+    let rescale = |d: &Decimal| {
+        d.mantissa
+            .checked_mul(pow10(scale - d.scale))
+            .ok_or_else(overflow)
+    };
+    Ok((rescale(a)?, rescale(b)?, scale))
+}
+
+// n / d rounded half away from zero
+fn round_div(n: i128, d: i128) -> i128 {
+    let (quotient, remainder) = (n / d, n % d);
+    match remainder.unsigned_abs() * 2 >= d.unsigned_abs() {
+        true => quotient + n.signum() * d.signum(),
+        false => quotient,
+    }
+}
+
+// drops digits after the point until the value fits in 38 digits
+fn fit(mantissa: i128, scale: u32) -> Result<Decimal> {
+    let excess = (scale.saturating_sub(MAX_PRECISION))
+        .max(digits(mantissa.unsigned_abs()).saturating_sub(MAX_PRECISION));
+    if excess > scale {
+        return Err(overflow());
+    }
+    Decimal::new(
+        match excess {
+            0 => mantissa,
+            excess => round_div(mantissa, pow10(excess)),
+        },
+        scale - excess,
+    )
+}
+
+impl From<i64> for Decimal {
+    fn from(value: i64) -> Self {
+        Decimal {
+            mantissa: value as i128,
+            scale: 0,
+        }
+    }
+}
+
+// the shortest decimal that reads back as the same float, e.g. 0.1
+impl TryFrom<f64> for Decimal {
+    type Error = Error;
+
+    fn try_from(value: f64) -> Result<Self> {
+        if !value.is_finite() {
+            return Err(Error::Value(format!(
+                "[Decimal] Cannot convert {} to DECIMAL",
+                value
+            )));
+        }
+        format!("{:e}", value).parse()
+    }
+}
+
+// [+-]digits[.digits][e[+-]digits], digits beyond 38 are rounded off
+// after the point
+impl FromStr for Decimal {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let invalid = || Error::InvalidText(format!("[Decimal] Invalid decimal '{}'", text));
+        let s = text.trim();
+        let (negative, s) = match s.strip_prefix('-') {
+            Some(s) => (true, s),
+            None => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (s, exponent) = match s.split_once(['e', 'E']) {
+            Some((s, exponent)) => (s, exponent.parse::<i32>().map_err(|_| invalid())?),
+            None => (s, 0),
+        };
+        let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+        if (int.is_empty() && frac.is_empty())
+            || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit())
+        {
+            return Err(invalid());
+        }
+
+        let all = format!("{}{}", int, frac);
+        let digits = all.trim_start_matches('0');
+        let scale = frac.len() as i64 - exponent as i64;
+        let int_digits = digits.len() as i64 - scale;
+        if int_digits > MAX_PRECISION as i64 {
+            return Err(overflow());
+        }
+        let mantissa = if scale < 0 {
+            format!("{}{}", digits, "0".repeat((-scale) as usize))
+                .parse::<i128>()
+                .unwrap_or_default()
+        } else {
+            // keep what fits, the first dropped digit rounds
+            let keep = (scale)
+                .min(MAX_PRECISION as i64)
+                .min(MAX_PRECISION as i64 - int_digits.max(0));
+            let drop = (scale - keep) as usize;
+            let kept = &digits[..digits.len().saturating_sub(drop)];
+            let next = digits
+                .len()
+                .checked_sub(drop)
+                .and_then(|i| digits.as_bytes().get(i));
+            let mut mantissa = kept.parse::<i128>().unwrap_or_default();
+            if next.is_some_and(|d| *d >= b'5') {
+                mantissa += 1;
+            }
+            return Decimal::new(if negative { -mantissa } else { mantissa }, keep as u32);
+        };
+        Decimal::new(if negative { -mantissa } else { mantissa }, 0)
+    }
+}
+
+impl Display for Decimal {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let sign = if self.mantissa < 0 { "-" } else { "" };
+        let digits = format!(
+            "{:0>width$}",
+            self.mantissa.unsigned_abs(),
+            width = self.scale as usize + 1
+        );
+        let (int, frac) = digits.split_at(digits.len() - self.scale as usize);
+        match frac.is_empty() {
+            true => write!(f, "{}{}", sign, int),
+            false => write!(f, "{}{}.{}", sign, int, frac),
+        }
+    }
+}
+
+// numeric comparison, 1.5 equals 1.50
+impl Ord for Decimal {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // integer parts first, then the fractions at the larger scale
+        let scale = self.scale.max(other.scale);
+        let parts = |d: &Decimal| {
+            let unit = pow10(d.scale);
+            (
+                d.mantissa / unit,
+                d.mantissa % unit * pow10(scale - d.scale),
+            )
+        };
+        parts(self).cmp(&parts(other))
+    }
+}
+
+impl PartialOrd for Decimal {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Decimal {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other).is_eq()
+    }
+}
+
+impl Eq for Decimal {}
+
+// equal decimals hash the same, whatever their scale
+impl Hash for Decimal {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let value = self.normalize();
+        value.mantissa.hash(state);
+        value.scale.hash(state);
+    }
+}
+
+// the ordered bytes, so that decimals can be keys, and the scale, so that
+// 1.50 reads back with its trailing zero
+impl Serialize for Decimal {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(2)?;
+        tuple.serialize_element(serde_bytes::Bytes::new(&self.ordered_bytes()))?;
+        tuple.serialize_element(&(self.scale as i32))?;
+        tuple.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct DecimalVisitor;
+
+        impl<'de> Visitor<'de> for DecimalVisitor {
+            type Value = Decimal;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                f.write_str("a decimal")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> std::result::Result<Decimal, A::Error> {
+                let bytes: serde_bytes::ByteBuf = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(0, &self))?;
+                let scale: i32 = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                u32::try_from(scale)
+                    .ok()
+                    .and_then(|scale| Decimal::from_ordered_bytes(&bytes, scale))
+                    .ok_or_else(|| de::Error::custom("invalid decimal"))
+            }
+        }
+
+        deserializer.deserialize_tuple(2, DecimalVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decimal;
+    use crate::{error::Result, storage::keycode::serialize_key};
+
+    fn dec(text: &str) -> Decimal {
+        text.parse().unwrap()
+    }
+
+    #[test]
+    fn test_decimal_parse_format() -> Result<()> {
+        for (text, expect) in [
+            ("1.50", "1.50"),
+            ("-0.05", "-0.05"),
+            ("+007", "7"),
+            (".5", "0.5"),
+            ("2.", "2"),
+            ("1.5e3", "1500"),
+            ("-25e-4", "-0.0025"),
+            ("0.000", "0.000"),
+            (
+                "12345678901234567890.123456789012345678901",
+                "12345678901234567890.123456789012345679",
+            ),
+        ] {
+            assert_eq!(dec(text).to_string(), expect);
+        }
+        for text in ["", ".", "1.2.3", "1e", "abc", "1e99"] {
+            assert!(text.parse::<Decimal>().is_err(), "{}", text);
+        }
+        assert_eq!(Decimal::try_from(0.1)?.to_string(), "0.1");
+        assert_eq!(Decimal::try_from(-1e-7)?.to_string(), "-0.0000001");
+        assert!(Decimal::try_from(f64::NAN).is_err());
+        assert!(Decimal::try_from(1e300).is_err());
+        assert_eq!(Decimal::from(-42).to_string(), "-42");
+        assert_eq!(dec("2.5").to_i64(), Some(3));
+        assert_eq!(dec("-2.5").to_i64(), Some(-3));
+        assert_eq!(dec("0.1").to_f64(), 0.1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_round() -> Result<()> {
+        assert_eq!(dec("1.005").round(2)?.to_string(), "1.01");
+        assert_eq!(dec("-1.005").round(2)?.to_string(), "-1.01");
+        assert_eq!(dec("1.004").round(2)?.to_string(), "1.00");
+        assert_eq!(dec("1.5").round(3)?.to_string(), "1.500");
+        assert_eq!(
+            dec("999.995")
+                .with_precision(5, 2)
+                .map(|d| d.to_string())
+                .ok(),
+            None
+        );
+        assert_eq!(dec("999.994").with_precision(5, 2)?.to_string(), "999.99");
+        assert_eq!(dec("12.5").with_precision(2, 0)?.to_string(), "13");
+        assert_eq!(dec("1.2300").normalize().to_string(), "1.23");
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_arithmetic() -> Result<()> {
+        assert_eq!(dec("0.1").checked_add(&dec("0.2"))?, dec("0.3"));
+        assert_eq!(dec("0.1").checked_add(&dec("0.20"))?.to_string(), "0.30");
+        assert_eq!(dec("1").checked_sub(&dec("1.001"))?.to_string(), "-0.001");
+        assert_eq!(dec("1.5").checked_mul(&dec("-0.25"))?.to_string(), "-0.375");
+        assert_eq!(
+            dec("1").checked_div(&dec("3"))?.to_string(),
+            "0.3333333333333333"
+        );
+        assert_eq!(
+            dec("2").checked_div(&dec("3"))?.to_string(),
+            "0.6666666666666667"
+        );
+        assert_eq!(
+            dec("-7.50").checked_div(&dec("2"))?.to_string(),
+            "-3.7500000000000000"
+        );
+        assert_eq!(
+            dec("1e30").checked_div(&dec("3"))?.to_string(),
+            "333333333333333333333333333333.33333333"
+        );
+        assert!(dec("1").checked_div(&dec("0.00")).is_err());
+        assert!(dec("9e37").checked_add(&dec("9e37")).is_err());
+        assert!(dec("1e20").checked_mul(&dec("1e20")).is_err());
+        // the product is rounded to 38 digits
+        assert_eq!(
+            dec("0.11111111111111111111").checked_mul(&dec("0.11111111111111111111"))?,
+            dec("0.01234567901234567901209876543209876543")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decimal_order_and_encoding() -> Result<()> {
+        let ordered = [
+            "-1e30", "-100", "-12.3", "-12.25", "-1.23", "-1.2", "-0.5", "0", "0.001", "0.1",
+            "0.12", "1", "1.05", "1.5", "10", "12", "1e37",
+        ]
+        .map(dec);
+        for pair in ordered.windows(2) {
+            assert!(pair[0] < pair[1], "{} < {}", pair[0], pair[1]);
+        }
+        let encoded = ordered
+            .iter()
+            .map(|d| serialize_key(&(serde_bytes::Bytes::new(&d.ordered_bytes()))))
+            .collect::<Result<Vec<_>>>()?;
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+
+        assert_eq!(dec("1.5"), dec("1.500"));
+        assert_eq!(dec("1.5").ordered_bytes(), dec("1.500").ordered_bytes());
+        for text in [
+            "0.00",
+            "-1.50",
+            "1200",
+            "-0.0012",
+            "1e30",
+            "12345678901234567890123456789012345678",
+        ] {
+            let value = dec(text);
+            let bytes = bincode::serialize(&value)?;
+            let decoded: Decimal = bincode::deserialize(&bytes)?;
+            assert_eq!(decoded.to_string(), value.to_string());
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,521 @@
+use std::{cmp::Ordering, fmt::Display, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+use super::Value;
+
+// nesting deeper than this is rejected rather than overflowing the stack
+const MAX_DEPTH: usize = 256;
+
+// parsed JSON document, stored in this form rather than as text, object
+// keys are sorted and unique like PostgreSQL jsonb, the last duplicate wins,
+// variants are in the order values of different kinds compare
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Json {
+    Null,
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn rank(&self) -> u8 {
+        match self {
+            Json::Null => 0,
+            Json::String(_) => 1,
+            Json::Number(_) => 2,
+            Json::Boolean(_) => 3,
+            Json::Array(_) => 4,
+            Json::Object(_) => 5,
+        }
+    }
+
+    // member of an object
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members
+                .binary_search_by(|(k, _)| k.as_str().cmp(key))
+                .ok()
+                .map(|i| &members[i].1),
+            _ => None,
+        }
+    }
+
+    // element of an array, negative indexes count from the end
+    pub fn index(&self, index: i64) -> Option<&Json> {
+        match self {
+            Json::Array(elements) => {
+                let index = match index < 0 {
+                    true => elements.len() as i64 + index,
+                    false => index,
+                };
+                elements.get(usize::try_from(index).ok()?)
+            }
+            _ => None,
+        }
+    }
+
+    // the value at a path such as $.a.b[0] or $."a key"[-1], none if missing
+    pub fn path(&self, path: &str) -> Result<Option<&Json>> {
+        let invalid = || Error::Value(format!("[Json] Invalid path '{}'", path));
+        let mut rest = path.trim().strip_prefix('$').ok_or_else(invalid)?;
+        let mut json = self;
+        while !rest.is_empty() {
+            let next = if let Some(r) = rest.strip_prefix(".\"") {
+                let end = r.find('"').ok_or_else(invalid)?;
+                rest = &r[end + 1..];
+                json.get(&r[..end])
+            } else if let Some(r) = rest.strip_prefix('.') {
+                let end = r.find(['.', '[']).unwrap_or(r.len());
+                if end == 0 {
+                    return Err(invalid());
+                }
+                rest = &r[end..];
+                json.get(&r[..end])
+            } else if let Some(r) = rest.strip_prefix('[') {
+                let end = r.find(']').ok_or_else(invalid)?;
+                let index = r[..end].trim().parse().map_err(|_| invalid())?;
+                rest = &r[end + 1..];
+                json.index(index)
+            } else {
+                return Err(invalid());
+            };
+            match next {
+                Some(next) => json = next,
+                None => return Ok(None),
+            }
+        }
+        Ok(Some(json))
+    }
+
+    // scalars as SQL values, integral numbers become integers, JSON null is
+    // NULL, arrays and objects have no scalar value
+    pub fn as_scalar(&self) -> Option<Value> {
+        Some(match self {
+            Json::Null => Value::Null,
+            Json::String(s) => Value::String(s.clone()),
+            Json::Number(n) if n.fract() == 0.0 && n.abs() < 2f64.powi(53) => {
+                Value::Integer(*n as i64)
+            }
+            Json::Number(n) => Value::Float(*n),
+            Json::Boolean(b) => Value::Boolean(*b),
+            Json::Array(_) | Json::Object(_) => return None,
+        })
+    }
+
+    // the text of ->>, strings without quotes and null as NULL
+    pub fn to_text(&self) -> Value {
+        match self {
+            Json::Null => Value::Null,
+            Json::String(s) => Value::String(s.clone()),
+            json => Value::String(json.to_string()),
+        }
+    }
+}
+
+// values of different kinds order as null, string, number, boolean, array,
+// object, arrays and objects compare element by element
+impl PartialOrd for Json {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Json::Null, Json::Null) => Some(Ordering::Equal),
+            (Json::String(a), Json::String(b)) => a.partial_cmp(b),
+            (Json::Number(a), Json::Number(b)) => a.partial_cmp(b),
+            (Json::Boolean(a), Json::Boolean(b)) => a.partial_cmp(b),
+            (Json::Array(a), Json::Array(b)) => a.partial_cmp(b),
+            (Json::Object(a), Json::Object(b)) => a.partial_cmp(b),
+            (a, b) => a.rank().partial_cmp(&b.rank()),
+        }
+    }
+}
+
+impl FromStr for Json {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut parser = Parser {
+            text: text.as_bytes(),
+            pos: 0,
+        };
+        let json = parser.parse_value(0)?;
+        parser.skip_whitespace();
+        match parser.pos < parser.text.len() {
+            true => Err(parser.error("unexpected trailing characters")),
+            false => Ok(json),
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> Error {
+        Error::InvalidText(format!(
+            "[Json] Invalid JSON at position {}: {}",
+            self.pos, message
+        ))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self
+            .text
+            .get(self.pos)
+            .is_some_and(|c| matches!(c, b' ' | b'\t' | b'\n' | b'\r'))
+        {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.text.get(self.pos).copied()
+    }
+
+    fn expect(&mut self, c: u8) -> Result<()> {
+        match self.peek() == Some(c) {
+            true => {
+                self.pos += 1;
+                Ok(())
+            }
+            false => Err(self.error(&format!("expect '{}'", c as char))),
+        }
+    }
+
+    fn parse_value(&mut self, depth: usize) -> Result<Json> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("nested too deeply"));
+        }
+        match self.peek() {
+            Some(b'{') => self.parse_object(depth),
+            Some(b'[') => self.parse_array(depth),
+            Some(b'"') => Ok(Json::String(self.parse_string()?)),
+            Some(b'-' | b'0'..=b'9') => self.parse_number(),
+            Some(_) => {
+                for (word, json) in [
+                    ("null", Json::Null),
+                    ("true", Json::Boolean(true)),
+                    ("false", Json::Boolean(false)),
+                ] {
+                    if self.text[self.pos..].starts_with(word.as_bytes()) {
+                        self.pos += word.len();
+                        return Ok(json);
+                    }
+                }
+                Err(self.error("unexpected character"))
+            }
+            None => Err(self.error("unexpected end of input")),
+        }
+    }
+
+    fn parse_object(&mut self, depth: usize) -> Result<Json> {
+        self.expect(b'{')?;
+        let mut members: Vec<(String, Json)> = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.pos += 1;
+            return Ok(Json::Object(members));
+        }
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.error("expect string key"));
+            }
+            let key = self.parse_string()?;
+            self.expect(b':')?;
+            let value = self.parse_value(depth + 1)?;
+            match members.binary_search_by(|(k, _)| k.cmp(&key)) {
+                Ok(i) => members[i].1 = value,
+                Err(i) => members.insert(i, (key, value)),
+            }
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b'}') => {
+                    self.pos += 1;
+                    return Ok(Json::Object(members));
+                }
+                _ => return Err(self.error("expect ',' or '}'")),
+            }
+        }
+    }
+
+    fn parse_array(&mut self, depth: usize) -> Result<Json> {
+        self.expect(b'[')?;
+        let mut elements = Vec::new();
+        if self.peek() == Some(b']') {
+            self.pos += 1;
+            return Ok(Json::Array(elements));
+        }
+        loop {
+            elements.push(self.parse_value(depth + 1)?);
+            match self.peek() {
+                Some(b',') => self.pos += 1,
+                Some(b']') => {
+                    self.pos += 1;
+                    return Ok(Json::Array(elements));
+                }
+                _ => return Err(self.error("expect ',' or ']'")),
+            }
+        }
+    }
+
+    fn parse_string(&mut self) -> Result<String> {
+        self.expect(b'"')?;
+        let mut bytes = Vec::new();
+        loop {
+            let c = *self
+                .text
+                .get(self.pos)
+                .ok_or_else(|| self.error("unterminated string"))?;
+            self.pos += 1;
+            match c {
+                b'"' => break,
+                b'\\' => {
+                    let escape = self.text.get(self.pos).copied();
+                    self.pos += 1;
+                    let c = match escape {
+                        Some(b'"') => '"',
+                        Some(b'\\') => '\\',
+                        Some(b'/') => '/',
+                        Some(b'b') => '\u{8}',
+                        Some(b'f') => '\u{c}',
+                        Some(b'n') => '\n',
+                        Some(b'r') => '\r',
+                        Some(b't') => '\t',
+                        Some(b'u') => self.parse_unicode_escape()?,
+                        _ => return Err(self.error("invalid escape")),
+                    };
+                    bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+                }
+                0..=0x1f => return Err(self.error("control character in string")),
+                c => bytes.push(c),
+            }
+        }
+        // the input is a str and escapes are whole characters
+        String::from_utf8(bytes).map_err(|_| self.error("invalid UTF-8"))
+    }
+
+    // \uXXXX after the \u, characters outside the BMP are surrogate pairs
+    fn parse_unicode_escape(&mut self) -> Result<char> {
+        let high = self.parse_hex4()?;
+        let code = match high {
+            0xd800..=0xdbff => {
+                if !self.text[self.pos..].starts_with(b"\\u") {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                self.pos += 2;
+                let low = self.parse_hex4()?;
+                if !(0xdc00..=0xdfff).contains(&low) {
+                    return Err(self.error("unpaired surrogate"));
+                }
+                0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+            }
+            code => code,
+        };
+        char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))
+    }
+
+    fn parse_hex4(&mut self) -> Result<u32> {
+        let hex = self
+            .text
+            .get(self.pos..self.pos + 4)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .filter(|h| h.bytes().all(|b| b.is_ascii_hexdigit()))
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+        Ok(u32::from_str_radix(hex, 16).unwrap_or_default())
+    }
+
+    fn parse_number(&mut self) -> Result<Json> {
+        let start = self.pos;
+        let digits = |parser: &mut Self| {
+            let from = parser.pos;
+            while parser.text.get(parser.pos).is_some_and(u8::is_ascii_digit) {
+                parser.pos += 1;
+            }
+            parser.pos - from
+        };
+        if self.text[self.pos] == b'-' {
+            self.pos += 1;
+        }
+        let int_start = self.pos;
+        let int_digits = digits(self);
+        if int_digits == 0 || (int_digits > 1 && self.text[int_start] == b'0') {
+            return Err(self.error("invalid number"));
+        }
+        if self.text.get(self.pos) == Some(&b'.') {
+            self.pos += 1;
+            if digits(self) == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        if matches!(self.text.get(self.pos), Some(b'e' | b'E')) {
+            self.pos += 1;
+            if matches!(self.text.get(self.pos), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if digits(self) == 0 {
+                return Err(self.error("invalid number"));
+            }
+        }
+        let text = std::str::from_utf8(&self.text[start..self.pos]).unwrap_or_default();
+        match text.parse::<f64>() {
+            Ok(n) if n.is_finite() => Ok(Json::Number(n)),
+            _ => Err(self.error("number out of range")),
+        }
+    }
+}
+
+fn write_string(f: &mut std::fmt::Formatter<'_>, s: &str) -> std::fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+// the text form of PostgreSQL jsonb, e.g. {"a": 1, "b": [true, null]}
+impl Display for Json {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => f.write_str("null"),
+            Json::Boolean(b) => write!(f, "{}", b),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write_string(f, s),
+            Json::Array(elements) => {
+                f.write_str("[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", element)?;
+                }
+                f.write_str("]")
+            }
+            Json::Object(members) => {
+                f.write_str("{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_string(f, key)?;
+                    write!(f, ": {}", value)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+    use crate::{error::Result, sql::types::Value};
+
+    #[test]
+    fn test_json_parse_format() -> Result<()> {
+        for (text, expect) in [
+            ("null", "null"),
+            (" true ", "true"),
+            ("-1.5e2", "-150"),
+            ("0.25", "0.25"),
+            (r#""a\"b\\c\n\u00e9\ud83d\ude00""#, r#""a\"b\\c\né😀""#),
+            ("[1, [2, []], {}]", "[1, [2, []], {}]"),
+            (
+                r#"{"b": 1, "a": {"y": null, "x": "s"}, "b": 2}"#,
+                r#"{"a": {"x": "s", "y": null}, "b": 2}"#,
+            ),
+        ] {
+            let json: Json = text.parse()?;
+            assert_eq!(json.to_string(), expect);
+            assert_eq!(json.to_string().parse::<Json>()?, json);
+        }
+        for text in [
+            "",
+            "nul",
+            "01",
+            "1.",
+            "-",
+            "1e",
+            "[1,]",
+            "{\"a\" 1}",
+            "{a: 1}",
+            "\"\\x\"",
+            "\"\\ud800\"",
+            "[1] 2",
+            "\"a\nb\"",
+            "1e999",
+        ] {
+            assert!(text.parse::<Json>().is_err(), "{}", text);
+        }
+        assert!("[".repeat(300).parse::<Json>().is_err());
+
+        let bytes = bincode::serialize(&"[1, {\"a\": \"b\"}]".parse::<Json>()?)?;
+        assert!(bytes.len() < 64);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_path() -> Result<()> {
+        let json: Json = r#"{"a": {"b": [10, 20, {"c d": true}]}, "e": "x"}"#.parse()?;
+        let path = |p: &str| -> Result<Option<String>> { Ok(json.path(p)?.map(|j| j.to_string())) };
+        assert_eq!(path("$")?, Some(json.to_string()));
+        assert_eq!(path("$.e")?, Some("\"x\"".to_string()));
+        assert_eq!(path("$.a.b[1]")?, Some("20".to_string()));
+        assert_eq!(path("$.a.b[-1].\"c d\"")?, Some("true".to_string()));
+        assert_eq!(path("$.a.b[3]")?, None);
+        assert_eq!(path("$.e.f")?, None);
+        for p in ["a", "$.", "$[x]", "$.a[1", "$a"] {
+            assert!(json.path(p).is_err(), "{}", p);
+        }
+
+        assert_eq!(
+            json.get("e").map(|j| j.to_text()),
+            Some(Value::String("x".into()))
+        );
+        assert_eq!(
+            json.path("$.a.b[0]")?.and_then(|j| j.as_scalar()),
+            Some(Value::Integer(10))
+        );
+        assert_eq!(json.get("a").and_then(|j| j.as_scalar()), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_json_order() -> Result<()> {
+        let ordered = [
+            "null",
+            "\"\"",
+            "\"b\"",
+            "-1",
+            "2.5",
+            "false",
+            "true",
+            "[]",
+            "[1]",
+            "[1, 2]",
+            "[2]",
+            "{}",
+            "{\"a\": 1}",
+            "{\"b\": 0}",
+        ]
+        .map(|text| text.parse::<Json>());
+        for pair in ordered.windows(2) {
+            let (a, b) = (pair[0].as_ref().unwrap(), pair[1].as_ref().unwrap());
+            assert!(a < b, "{} < {}", a, b);
+        }
+        Ok(())
+    }
+}
@@ -1,32 +1,407 @@
-use crate::sql::parser::ast::{Consts, Expression};
+use std::{
+    cmp::Ordering,
+    fmt::Display,
+    hash::{Hash, Hasher},
+};
 
-#[derive(Debug, PartialEq, Clone)]
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Error, Result};
+
+pub mod cast;
+pub mod datetime;
+pub mod decimal;
+pub mod json;
+
+pub use datetime::Interval;
+use datetime::MICROS_PER_DAY;
+pub use decimal::Decimal;
+pub use json::Json;
+
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum DataType {
     Boolean,
     Float,
     Integer,
     String,
+    Date,
+    Time,
+    Timestamp,
+    Interval,
+    Decimal,
+    Blob,
+    Json,
 }
 
-#[derive(Debug, PartialEq)]
+impl Display for DataType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            DataType::Boolean => "BOOLEAN",
+            DataType::Float => "FLOAT",
+            DataType::Integer => "INTEGER",
+            DataType::String => "STRING",
+            DataType::Date => "DATE",
+            DataType::Time => "TIME",
+            DataType::Timestamp => "TIMESTAMP",
+            DataType::Interval => "INTERVAL",
+            DataType::Decimal => "DECIMAL",
+            DataType::Blob => "BLOB",
+            DataType::Json => "JSON",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Value {
     Null,
     Boolean(bool),
     Float(f64),
     Integer(i64),
     String(String),
+    // days since 1970-01-01
+    Date(i32),
+    // microseconds since midnight
+    Time(i64),
+    // microseconds since 1970-01-01 00:00:00
+    Timestamp(i64),
+    Interval(Interval),
+    Decimal(Decimal),
+    Blob(#[serde(with = "serde_bytes")] Vec<u8>),
+    Json(Json),
 }
 
 impl Value {
-    pub fn from_expression(expr: Expression) -> Self {
-        match expr {
-            Expression::Consts(Consts::Null) => Self::Null,
-            Expression::Consts(Consts::Boolean(b)) => Self::Boolean(b),
-            Expression::Consts(Consts::Float(f)) => Self::Float(f),
-            Expression::Consts(Consts::Integer(i)) => Self::Integer(i),
-            Expression::Consts(Consts::String(s)) => Self::String(s),
+    pub fn datatype(&self) -> Option<DataType> {
+        match self {
+            Self::Null => None,
+            Self::Boolean(_) => Some(DataType::Boolean),
+            Self::Float(_) => Some(DataType::Float),
+            Self::Integer(_) => Some(DataType::Integer),
+            Self::String(_) => Some(DataType::String),
+            Self::Date(_) => Some(DataType::Date),
+            Self::Time(_) => Some(DataType::Time),
+            Self::Timestamp(_) => Some(DataType::Timestamp),
+            Self::Interval(_) => Some(DataType::Interval),
+            Self::Decimal(_) => Some(DataType::Decimal),
+            Self::Blob(_) => Some(DataType::Blob),
+            Self::Json(_) => Some(DataType::Json),
+        }
+    }
+
+    // NULL if either side is NULL, integer overflow and division by zero
+    // are errors rather than wrapping or infinite results
+    pub fn checked_add(&self, other: &Value) -> Result<Value> {
+        Ok(match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (Value::Integer(a), Value::Integer(b)) => {
+                Value::Integer(a.checked_add(*b).ok_or_else(integer_overflow)?)
+            }
+            (Value::Date(d), Value::Integer(n)) | (Value::Integer(n), Value::Date(d)) => {
+                Value::Date(datetime::add_days(*d, *n)?)
+            }
+            (Value::Date(d), Value::Time(t)) | (Value::Time(t), Value::Date(d)) => {
+                Value::Timestamp(datetime::check_timestamp(*d as i64 * MICROS_PER_DAY + t)?)
+            }
+            (Value::Date(d), Value::Interval(i)) | (Value::Interval(i), Value::Date(d)) => {
+                Value::Timestamp(datetime::add_interval(*d as i64 * MICROS_PER_DAY, i)?)
+            }
+            (Value::Timestamp(t), Value::Interval(i))
+            | (Value::Interval(i), Value::Timestamp(t)) => {
+                Value::Timestamp(datetime::add_interval(*t, i)?)
+            }
+            (Value::Time(t), Value::Interval(i)) | (Value::Interval(i), Value::Time(t)) => {
+                Value::Time(datetime::add_time(*t, i))
+            }
+            (Value::Interval(a), Value::Interval(b)) => Value::Interval(a.checked_add(b)?),
+            (a, b) => match (a.as_decimal(), b.as_decimal()) {
+                (Some(a), Some(b)) => Value::Decimal(a.checked_add(&b)?),
+                _ => match (a.as_float(), b.as_float()) {
+                    (Some(a), Some(b)) => float(a + b)?,
+                    _ => return Err(cannot_evaluate(a, "+", b)),
+                },
+            },
+        })
+    }
+
+    pub fn checked_sub(&self, other: &Value) -> Result<Value> {
+        Ok(match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (Value::Integer(a), Value::Integer(b)) => {
+                Value::Integer(a.checked_sub(*b).ok_or_else(integer_overflow)?)
+            }
+            (Value::Date(d), Value::Integer(n)) => Value::Date(datetime::add_days(
+                *d,
+                n.checked_neg().ok_or_else(integer_overflow)?,
+            )?),
+            (Value::Date(a), Value::Date(b)) => Value::Integer(*a as i64 - *b as i64),
+            (Value::Date(_) | Value::Timestamp(_), Value::Interval(i)) => {
+                Value::Timestamp(datetime::add_interval(
+                    self.as_timestamp().unwrap_or_default(),
+                    &i.checked_mul(-1)?,
+                )?)
+            }
+            (Value::Date(_) | Value::Timestamp(_), Value::Date(_) | Value::Timestamp(_)) => {
+                Value::Interval(datetime::timestamp_diff(
+                    self.as_timestamp().unwrap_or_default(),
+                    other.as_timestamp().unwrap_or_default(),
+                ))
+            }
+            (Value::Time(t), Value::Interval(i)) => {
+                Value::Time(datetime::add_time(*t, &i.checked_mul(-1)?))
+            }
+            (Value::Time(a), Value::Time(b)) => Value::Interval(Interval {
+                micros: a - b,
+                ..Default::default()
+            }),
+            (Value::Interval(a), Value::Interval(b)) => Value::Interval(a.checked_sub(b)?),
+            (a, b) => match (a.as_decimal(), b.as_decimal()) {
+                (Some(a), Some(b)) => Value::Decimal(a.checked_sub(&b)?),
+                _ => match (a.as_float(), b.as_float()) {
+                    (Some(a), Some(b)) => float(a - b)?,
+                    _ => return Err(cannot_evaluate(a, "-", b)),
+                },
+            },
+        })
+    }
+
+    pub fn checked_mul(&self, other: &Value) -> Result<Value> {
+        Ok(match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (Value::Integer(a), Value::Integer(b)) => {
+                Value::Integer(a.checked_mul(*b).ok_or_else(integer_overflow)?)
+            }
+            (Value::Interval(i), Value::Integer(n)) | (Value::Integer(n), Value::Interval(i)) => {
+                Value::Interval(i.checked_mul(*n)?)
+            }
+            (a, b) => match (a.as_decimal(), b.as_decimal()) {
+                (Some(a), Some(b)) => Value::Decimal(a.checked_mul(&b)?),
+                _ => match (a.as_float(), b.as_float()) {
+                    (Some(a), Some(b)) => float(a * b)?,
+                    _ => return Err(cannot_evaluate(a, "*", b)),
+                },
+            },
+        })
+    }
+
+    // integer division truncates towards zero
+    pub fn checked_div(&self, other: &Value) -> Result<Value> {
+        Ok(match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (_, Value::Integer(0)) | (_, Value::Float(0.0)) => {
+                return Err(Error::DivisionByZero(
+                    "[Value] Division by zero".to_string(),
+                ))
+            }
+            (Value::Integer(a), Value::Integer(b)) => {
+                Value::Integer(a.checked_div(*b).ok_or_else(integer_overflow)?)
+            }
+            (a, b) => match (a.as_decimal(), b.as_decimal()) {
+                (Some(a), Some(b)) => Value::Decimal(a.checked_div(&b)?),
+                _ => match (a.as_float(), b.as_float()) {
+                    (Some(a), Some(b)) => float(a / b)?,
+                    _ => return Err(cannot_evaluate(a, "/", b)),
+                },
+            },
+        })
+    }
+
+    // the remainder of integer division, it has the sign of the dividend
+    pub fn checked_rem(&self, other: &Value) -> Result<Value> {
+        Ok(match (self, other) {
+            (Value::Null, _) | (_, Value::Null) => Value::Null,
+            (_, Value::Integer(0)) | (_, Value::Float(0.0)) => {
+                return Err(Error::DivisionByZero(
+                    "[Value] Division by zero".to_string(),
+                ))
+            }
+            // i64::MIN % -1 is 0, only the quotient overflows
+            (Value::Integer(a), Value::Integer(b)) => Value::Integer(a.wrapping_rem(*b)),
+            (a, b) => match (a.as_decimal(), b.as_decimal()) {
+                (Some(a), Some(b)) => Value::Decimal(a.checked_rem(&b)?),
+                _ => match (a.as_float(), b.as_float()) {
+                    (Some(a), Some(b)) => float(a % b)?,
+                    _ => return Err(cannot_evaluate(a, "%", b)),
+                },
+            },
+        })
+    }
+
+    pub fn checked_neg(&self) -> Result<Value> {
+        Ok(match self {
+            Value::Null => Value::Null,
+            Value::Integer(i) => Value::Integer(i.checked_neg().ok_or_else(integer_overflow)?),
+            Value::Float(f) => Value::Float(-f),
+            Value::Decimal(d) => Value::Decimal(Decimal::new(-d.mantissa(), d.scale())?),
+            Value::Interval(i) => Value::Interval(i.checked_mul(-1)?),
+            v => return Err(Error::Value(format!("[Value] Cannot evaluate -{}", v))),
+        })
+    }
+
+    fn as_float(&self) -> Option<f64> {
+        match self {
+            Value::Integer(i) => Some(*i as f64),
+            Value::Float(f) => Some(*f),
+            Value::Decimal(d) => Some(d.to_f64()),
+            _ => None,
+        }
+    }
+
+    // decimals mixed with integers stay exact, with floats they do not
+    fn as_decimal(&self) -> Option<Decimal> {
+        match self {
+            Value::Integer(i) => Some(Decimal::from(*i)),
+            Value::Decimal(d) => Some(*d),
+            _ => None,
+        }
+    }
+
+    // a date is the timestamp of its midnight
+    fn as_timestamp(&self) -> Option<i64> {
+        match self {
+            Value::Date(d) => Some(*d as i64 * MICROS_PER_DAY),
+            Value::Timestamp(t) => Some(*t),
+            _ => None,
+        }
+    }
+}
+
+fn integer_overflow() -> Error {
+    Error::OutOfRange("[Value] Integer out of range".to_string())
+}
+
+fn float(f: f64) -> Result<Value> {
+    match f.is_infinite() {
+        true => Err(Error::OutOfRange("[Value] Float out of range".to_string())),
+        false => Ok(Value::Float(f)),
+    }
+}
+
+fn cannot_evaluate(l: &Value, op: &str, r: &Value) -> Error {
+    Error::Value(format!("[Value] Cannot evaluate {} {} {}", l, op, r))
+}
+
+// rows are hashed to find duplicates, e.g. by UNION, where NULLs are equal
+// unlike in comparisons, values of different types are never equal, and so
+// are NaNs, 0.0 equals -0.0
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Value::Integer(a), Value::Integer(b)) => a == b,
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Date(a), Value::Date(b)) => a == b,
+            (Value::Time(a), Value::Time(b)) | (Value::Timestamp(a), Value::Timestamp(b)) => {
+                a == b
+            }
+            (Value::Interval(a), Value::Interval(b)) => a == b,
+            (Value::Decimal(a), Value::Decimal(b)) => a == b,
+            (Value::Blob(a), Value::Blob(b)) => a == b,
+            (Value::Json(a), Value::Json(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Null => {}
+            Value::Boolean(b) => b.hash(state),
+            // 0.0 equals -0.0, and all NaNs are equal
+            Value::Float(f) if *f == 0.0 => 0.0f64.to_bits().hash(state),
+            Value::Float(f) if f.is_nan() => f64::NAN.to_bits().hash(state),
+            Value::Float(f) => f.to_bits().hash(state),
+            Value::Integer(i) => i.hash(state),
+            Value::String(s) => s.hash(state),
+            Value::Date(d) => d.hash(state),
+            Value::Time(t) | Value::Timestamp(t) => t.hash(state),
+            Value::Interval(i) => i.hash(state),
+            Value::Decimal(d) => d.hash(state),
+            Value::Blob(b) => b.hash(state),
+            Value::Json(j) => j.to_string().hash(state),
         }
     }
 }
 
-pub type Row = Vec<Value>;
\ No newline at end of file
+// NULL and values of different types are not comparable, except integers,
+// floats and decimals which compare numerically, dates and timestamps, and
+// JSON scalars with the SQL values they hold
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Boolean(a), Value::Boolean(b)) => a.partial_cmp(b),
+            (Value::Integer(a), Value::Integer(b)) => a.partial_cmp(b),
+            (Value::Integer(a), Value::Float(b)) => (*a as f64).partial_cmp(b),
+            (Value::Float(a), Value::Integer(b)) => a.partial_cmp(&(*b as f64)),
+            (Value::Float(a), Value::Float(b)) => a.partial_cmp(b),
+            (Value::Decimal(a), Value::Decimal(b)) => a.partial_cmp(b),
+            (Value::Decimal(a), Value::Integer(b)) => a.partial_cmp(&Decimal::from(*b)),
+            (Value::Integer(a), Value::Decimal(b)) => Decimal::from(*a).partial_cmp(b),
+            (Value::Decimal(a), Value::Float(b)) => a.to_f64().partial_cmp(b),
+            (Value::Float(a), Value::Decimal(b)) => a.partial_cmp(&b.to_f64()),
+            (Value::String(a), Value::String(b)) => a.partial_cmp(b),
+            (Value::Blob(a), Value::Blob(b)) => a.partial_cmp(b),
+            (Value::Json(a), Value::Json(b)) => a.partial_cmp(b),
+            // extracted JSON scalars compare with SQL values, e.g. data->'n' > 1
+            (Value::Json(a), b) => a.as_scalar()?.partial_cmp(b),
+            (a, Value::Json(b)) => a.partial_cmp(&b.as_scalar()?),
+            (Value::Time(a), Value::Time(b)) => a.partial_cmp(b),
+            (Value::Interval(a), Value::Interval(b)) => a.partial_cmp(b),
+            (Value::Date(_) | Value::Timestamp(_), Value::Date(_) | Value::Timestamp(_)) => {
+                self.as_timestamp().partial_cmp(&other.as_timestamp())
+            }
+            (_, _) => None,
+        }
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Null => write!(f, "NULL"),
+            Value::Boolean(b) if *b => write!(f, "TRUE"),
+            Value::Boolean(_) => write!(f, "FALSE"),
+            Value::Float(v) => write!(f, "{}", v),
+            Value::Integer(v) => write!(f, "{}", v),
+            Value::String(v) => write!(f, "{}", v),
+            Value::Date(d) => write!(f, "{}", datetime::format_date(*d)),
+            Value::Time(t) => write!(f, "{}", datetime::format_time(*t)),
+            Value::Timestamp(t) => write!(f, "{}", datetime::format_timestamp(*t)),
+            Value::Interval(i) => write!(f, "{}", i),
+            Value::Decimal(d) => write!(f, "{}", d),
+            // the hex output format of PostgreSQL bytea
+            Value::Blob(b) => write!(f, "\\x{}", format_hex(b)),
+            Value::Json(j) => write!(f, "{}", j),
+        }
+    }
+}
+
+// bytes as lowercase hex digits, two per byte
+pub fn format_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub fn parse_hex(hex: &str) -> Result<Vec<u8>> {
+    if hex.len() % 2 == 1 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return Err(Error::InvalidText(format!(
+            "[Value] Invalid hex string '{}'",
+            hex
+        )));
+    }
+    let digit = |b: u8| (b as char).to_digit(16).unwrap_or_default() as u8;
+    Ok(hex
+        .as_bytes()
+        .chunks(2)
+        .map(|pair| (digit(pair[0]) << 4) | digit(pair[1]))
+        .collect())
+}
+
+pub type Row = Vec<Value>;
+
+// rows produced one at a time, so that they need not all be held in memory
+pub type Rows = Box<dyn Iterator<Item = Result<Row>>>;
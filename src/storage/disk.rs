@@ -0,0 +1,291 @@
+use std::{
+    collections::{btree_map, BTreeMap},
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use fs4::FileExt;
+
+use super::engine::{Engine, EngineIterator};
+use crate::error::{Error, Result};
+
+// key -> (offset of the value in the log, value size)
+pub type KeyDir = BTreeMap<Vec<u8>, (u64, u32)>;
+
+// key size (u32) and value size (i32, -1 for a deletion)
+const LOG_HEADER_SIZE: u32 = 8;
+
+// Log-structured storage engine, every write is appended to a log file and
+// an in-memory keydir points each live key at its latest value in the log
+pub struct DiskEngine {
+    keydir: KeyDir,
+    log: Log,
+}
+
+impl DiskEngine {
+    pub fn new(file_path: PathBuf) -> Result<Self> {
+        let mut log = Log::new(file_path)?;
+        let keydir = log.build_keydir()?;
+        Ok(Self { keydir, log })
+    }
+
+    // open and rewrite the log without the overwritten and deleted entries
+    pub fn new_compact(file_path: PathBuf) -> Result<Self> {
+        let mut eng = Self::new(file_path)?;
+        eng.compact()?;
+        Ok(eng)
+    }
+
+    fn compact(&mut self) -> Result<()> {
+        let mut compact_path = self.log.file_path.clone();
+        compact_path.set_extension("compact");
+        let mut new_log = Log::new(compact_path)?;
+        new_log.file.set_len(0)?;
+
+        let mut new_keydir = KeyDir::new();
+        for (key, (offset, val_size)) in &self.keydir {
+            let value = self.log.read_value(*offset, *val_size)?;
+            let (new_offset, new_size) = new_log.write_entry(key, Some(&value))?;
+            new_keydir.insert(
+                key.clone(),
+                (new_offset + new_size as u64 - *val_size as u64, *val_size),
+            );
+        }
+
+        // the new log must be on disk before it replaces the old one, and
+        // the rename must be on disk before the old entries are given up
+        new_log.file.sync_all()?;
+        std::fs::rename(&new_log.file_path, &self.log.file_path)?;
+        let dir = match self.log.file_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        File::open(dir)?.sync_all()?;
+        new_log.file_path = self.log.file_path.clone();
+        self.keydir = new_keydir;
+        self.log = new_log;
+        Ok(())
+    }
+}
+
+impl Engine for DiskEngine {
+    type EngineIterator<'a> = DiskEngineIterator<'a>;
+
+    fn set(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        let (offset, size) = self.log.write_entry(&key, Some(&value))?;
+        let val_size = value.len() as u32;
+        self.keydir
+            .insert(key, (offset + size as u64 - val_size as u64, val_size));
+        Ok(())
+    }
+
+    fn get(&mut self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        match self.keydir.get(&key) {
+            Some((offset, val_size)) => Ok(Some(self.log.read_value(*offset, *val_size)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn delete(&mut self, key: Vec<u8>) -> Result<()> {
+        self.log.write_entry(&key, None)?;
+        self.keydir.remove(&key);
+        Ok(())
+    }
+
+    fn scan(&mut self, range: impl std::ops::RangeBounds<Vec<u8>>) -> Self::EngineIterator<'_> {
+        DiskEngineIterator {
+            inner: self.keydir.range(range),
+            log: &mut self.log,
+        }
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        Ok(self.log.file.sync_data()?)
+    }
+}
+
+pub struct DiskEngineIterator<'a> {
+    inner: btree_map::Range<'a, Vec<u8>, (u64, u32)>,
+    log: &'a mut Log,
+}
+
+impl DiskEngineIterator<'_> {
+    fn map(&mut self, item: (&Vec<u8>, &(u64, u32))) -> <Self as Iterator>::Item {
+        let (k, (offset, val_size)) = item;
+        let value = self.log.read_value(*offset, *val_size)?;
+        Ok((k.clone(), value))
+    }
+}
+
+impl EngineIterator for DiskEngineIterator<'_> {}
+
+impl Iterator for DiskEngineIterator<'_> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(|item| self.map(item))
+    }
+}
+
+impl DoubleEndedIterator for DiskEngineIterator<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(|item| self.map(item))
+    }
+}
+
+// append-only log file, each entry is
+// | key size (u32) | value size (i32) | key | value |
+struct Log {
+    file_path: PathBuf,
+    file: File,
+}
+
+impl Log {
+    fn new(file_path: PathBuf) -> Result<Self> {
+        if let Some(dir) = file_path.parent() {
+            if !dir.as_os_str().is_empty() && !dir.exists() {
+                std::fs::create_dir_all(dir)?;
+            }
+        }
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .read(true)
+            .write(true)
+            .open(&file_path)?;
+        // one process at a time may use the file
+        FileExt::try_lock_exclusive(&file).map_err(|_| {
+            Error::Io(format!(
+                "[Disk] Database file {} is locked by another process",
+                file_path.display()
+            ))
+        })?;
+        Ok(Self { file_path, file })
+    }
+
+    // replay the log, an incomplete entry left by a crash is cut off
+    fn build_keydir(&mut self) -> Result<KeyDir> {
+        let mut keydir = KeyDir::new();
+        let file_size = self.file.metadata()?.len();
+        let mut reader = BufReader::new(&self.file);
+        reader.seek(SeekFrom::Start(0))?;
+        let mut offset = 0;
+        while offset < file_size {
+            let entry = || -> std::io::Result<(Vec<u8>, i32)> {
+                let mut len_buf = [0u8; 4];
+                reader.read_exact(&mut len_buf)?;
+                let key_size = u32::from_be_bytes(len_buf);
+                reader.read_exact(&mut len_buf)?;
+                let val_size = i32::from_be_bytes(len_buf);
+                let mut key = vec![0; key_size as usize];
+                reader.read_exact(&mut key)?;
+                if val_size > 0 {
+                    reader.seek_relative(val_size as i64)?;
+                }
+                Ok((key, val_size))
+            }();
+            let (key, val_size) = match entry {
+                Ok(entry) => entry,
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                    self.file.set_len(offset)?;
+                    break;
+                }
+                Err(err) => return Err(err.into()),
+            };
+            let val_offset = offset + LOG_HEADER_SIZE as u64 + key.len() as u64;
+            if val_offset + val_size.max(0) as u64 > file_size {
+                self.file.set_len(offset)?;
+                break;
+            }
+            if val_size < 0 {
+                keydir.remove(&key);
+                offset = val_offset;
+            } else {
+                keydir.insert(key, (val_offset, val_size as u32));
+                offset = val_offset + val_size as u64;
+            }
+        }
+        Ok(keydir)
+    }
+
+    fn read_value(&mut self, offset: u64, val_size: u32) -> Result<Vec<u8>> {
+        let mut value = vec![0; val_size as usize];
+        self.file.seek(SeekFrom::Start(offset))?;
+        self.file.read_exact(&mut value)?;
+        Ok(value)
+    }
+
+    // returns the offset and size of the written entry, none value marks a deletion
+    fn write_entry(&mut self, key: &[u8], value: Option<&[u8]>) -> Result<(u64, u32)> {
+        let offset = self.file.seek(SeekFrom::End(0))?;
+        let val_size = value.map_or(0, |v| v.len() as u32);
+        let total_size = LOG_HEADER_SIZE + key.len() as u32 + val_size;
+
+        let mut writer = BufWriter::with_capacity(total_size as usize, &self.file);
+        writer.write_all(&(key.len() as u32).to_be_bytes())?;
+        writer.write_all(&value.map_or(-1, |v| v.len() as i32).to_be_bytes())?;
+        writer.write_all(key)?;
+        if let Some(value) = value {
+            writer.write_all(value)?;
+        }
+        writer.flush()?;
+        Ok((offset, total_size))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::DiskEngine;
+    use crate::{error::Result, storage::engine::Engine};
+
+    #[test]
+    fn test_disk_engine() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("sqldb-log");
+        {
+            let mut eng = DiskEngine::new(path.clone())?;
+            eng.set(b"a".to_vec(), b"1".to_vec())?;
+            eng.set(b"ab".to_vec(), b"2".to_vec())?;
+            eng.set(b"b".to_vec(), b"3".to_vec())?;
+            eng.set(b"a".to_vec(), b"4".to_vec())?;
+            eng.delete(b"b".to_vec())?;
+            eng.set(b"c".to_vec(), vec![])?;
+            // the file is locked while it is open
+            assert!(DiskEngine::new(path.clone()).is_err());
+        }
+
+        // the data is rebuilt from the log, also after compaction
+        for _ in 0..2 {
+            let mut eng = DiskEngine::new_compact(path.clone())?;
+            assert_eq!(eng.get(b"a".to_vec())?, Some(b"4".to_vec()));
+            assert_eq!(eng.get(b"b".to_vec())?, None);
+            let items = eng.scan(..).collect::<Result<Vec<_>>>()?;
+            assert_eq!(
+                items,
+                vec![
+                    (b"a".to_vec(), b"4".to_vec()),
+                    (b"ab".to_vec(), b"2".to_vec()),
+                    (b"c".to_vec(), vec![]),
+                ]
+            );
+        }
+        assert_eq!(
+            std::fs::metadata(&path)?.len(),
+            8 * 3 + (1 + 1) + (2 + 1) + 1
+        );
+
+        // a torn write at the end of the log is dropped
+        let len = std::fs::metadata(&path)?.len();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(&path)?
+            .write_all(&[0, 0, 0, 5, 0])?;
+        let mut eng = DiskEngine::new(path.clone())?;
+        assert_eq!(std::fs::metadata(&path)?.len(), len);
+        assert_eq!(eng.scan_prefix(b"a".to_vec()).count(), 2);
+        Ok(())
+    }
+}
@@ -0,0 +1,60 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::error::Result;
+
+// Abstract key/value storage engine, keys and values are raw bytes
+pub trait Engine {
+    type EngineIterator<'a>: EngineIterator
+    where
+        Self: 'a;
+
+    fn set(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()>;
+
+    fn get(&mut self, key: Vec<u8>) -> Result<Option<Vec<u8>>>;
+
+    fn delete(&mut self, key: Vec<u8>) -> Result<()>;
+
+    fn scan(&mut self, range: impl RangeBounds<Vec<u8>>) -> Self::EngineIterator<'_>;
+
+    // make the writes so far durable, nothing to do for volatile storage
+    fn flush(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    // scan all keys starting with prefix
+    fn scan_prefix(&mut self, prefix: Vec<u8>) -> Self::EngineIterator<'_> {
+        let start = Bound::Included(prefix.clone());
+        let end = match prefix_end(prefix) {
+            Some(end) => Bound::Excluded(end),
+            None => Bound::Unbounded,
+        };
+        self.scan((start, end))
+    }
+}
+
+pub trait EngineIterator: DoubleEndedIterator<Item = Result<(Vec<u8>, Vec<u8>)>> {}
+
+// smallest key greater than every key starting with prefix,
+// none if prefix is empty or made of 0xff only
+pub fn prefix_end(mut prefix: Vec<u8>) -> Option<Vec<u8>> {
+    while let Some(last) = prefix.pop() {
+        if last < u8::MAX {
+            prefix.push(last + 1);
+            return Some(prefix);
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::prefix_end;
+
+    #[test]
+    fn test_prefix_end() {
+        assert_eq!(prefix_end(b"abc".to_vec()), Some(b"abd".to_vec()));
+        assert_eq!(prefix_end(vec![1, 0xff, 0xff]), Some(vec![2]));
+        assert_eq!(prefix_end(vec![0xff]), None);
+        assert_eq!(prefix_end(vec![]), None);
+    }
+}
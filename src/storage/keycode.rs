@@ -0,0 +1,668 @@
+use serde::{
+    de::{self, IntoDeserializer},
+    ser, Deserialize, Serialize,
+};
+
+use crate::error::{Error, Result};
+
+// Key encoding used by the storage layer, the encoded bytes keep the
+// ordering of the original values, so keys can be range scanned:
+// - bool: 0x00 for false, 0x01 for true
+// - u64: big-endian
+// - i32 and i64: big-endian with the sign bit flipped, so negatives sort first
+// - f64: big-endian, sign bit flipped for positives, all bits flipped for
+//   negatives, so that -inf < negatives < -0.0 < 0.0 < positives < inf
+// - bytes and strings: 0x00 is escaped as 0x00 0xff, terminated by 0x00 0x00
+// - enum variant: variant index as a single byte
+// - tuples, structs and tuple variants: fields concatenated in order
+pub fn serialize_key<T: Serialize>(key: &T) -> Result<Vec<u8>> {
+    let mut ser = Serializer { output: Vec::new() };
+    key.serialize(&mut ser)?;
+    Ok(ser.output)
+}
+
+pub fn deserialize_key<'a, T: Deserialize<'a>>(input: &'a [u8]) -> Result<T> {
+    let mut der = Deserializer { input };
+    T::deserialize(&mut der)
+}
+
+fn unsupported<T>(what: &str) -> Result<T> {
+    Err(Error::Internal(format!(
+        "[Keycode] {} is not supported",
+        what
+    )))
+}
+
+pub struct Serializer {
+    output: Vec<u8>,
+}
+
+impl ser::Serializer for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    type SerializeSeq = Self;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = ser::Impossible<(), Error>;
+    type SerializeTupleVariant = Self;
+    type SerializeMap = ser::Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<()> {
+        self.output.push(v as u8);
+        Ok(())
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<()> {
+        unsupported("i8")
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<()> {
+        unsupported("i16")
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<()> {
+        let mut bytes = v.to_be_bytes();
+        bytes[0] ^= 1 << 7;
+        self.output.extend(bytes);
+        Ok(())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<()> {
+        let mut bytes = v.to_be_bytes();
+        bytes[0] ^= 1 << 7;
+        self.output.extend(bytes);
+        Ok(())
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<()> {
+        unsupported("u8")
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<()> {
+        unsupported("u16")
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<()> {
+        unsupported("u32")
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<()> {
+        self.output.extend(v.to_be_bytes());
+        Ok(())
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<()> {
+        unsupported("f32")
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<()> {
+        let mut bytes = v.to_be_bytes();
+        if v.is_sign_negative() {
+            bytes.iter_mut().for_each(|b| *b = !*b);
+        } else {
+            bytes[0] ^= 1 << 7;
+        }
+        self.output.extend(bytes);
+        Ok(())
+    }
+
+    fn serialize_char(self, _v: char) -> Result<()> {
+        unsupported("char")
+    }
+
+    fn serialize_str(self, v: &str) -> Result<()> {
+        self.serialize_bytes(v.as_bytes())
+    }
+
+    // 0x00 -> 0x00 0xff, end with 0x00 0x00
+    fn serialize_bytes(self, v: &[u8]) -> Result<()> {
+        let mut res = Vec::with_capacity(v.len() + 2);
+        for b in v {
+            match b {
+                0 => res.extend([0, 255]),
+                b => res.push(*b),
+            }
+        }
+        res.extend([0, 0]);
+        self.output.extend(res);
+        Ok(())
+    }
+
+    fn serialize_none(self) -> Result<()> {
+        unsupported("option")
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<()> {
+        unsupported("option")
+    }
+
+    fn serialize_unit(self) -> Result<()> {
+        unsupported("unit")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
+        unsupported("unit struct")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<()> {
+        self.output.push(u8::try_from(variant_index).map_err(|_| {
+            Error::Internal(format!(
+                "[Keycode] Variant index {} overflow",
+                variant_index
+            ))
+        })?);
+        Ok(())
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        self.serialize_unit_variant(name, variant_index, variant)?;
+        value.serialize(self)
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
+        Ok(self)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct> {
+        unsupported("tuple struct")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant> {
+        self.serialize_unit_variant(name, variant_index, variant)?;
+        Ok(self)
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
+        unsupported("map")
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
+        Ok(self)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant> {
+        unsupported("struct variant")
+    }
+}
+
+impl ser::SerializeSeq for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTuple for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeStruct for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+impl ser::SerializeTupleVariant for &mut Serializer {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<()> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<()> {
+        Ok(())
+    }
+}
+
+pub struct Deserializer<'de> {
+    input: &'de [u8],
+}
+
+impl<'de> Deserializer<'de> {
+    fn take_bytes(&mut self, len: usize) -> Result<&'de [u8]> {
+        if self.input.len() < len {
+            return Err(Error::Internal(format!(
+                "[Keycode] Insufficient bytes, expected {} bytes for {:?}",
+                len, self.input
+            )));
+        }
+        let bytes = &self.input[..len];
+        self.input = &self.input[len..];
+        Ok(bytes)
+    }
+
+    // read escaped bytes until the 0x00 0x00 terminator
+    fn next_bytes(&mut self) -> Result<Vec<u8>> {
+        let mut res = Vec::new();
+        let mut iter = self.input.iter().enumerate();
+        let index = loop {
+            match iter.next() {
+                Some((_, 0)) => match iter.next() {
+                    Some((i, 0)) => break i + 1,
+                    Some((_, 255)) => res.push(0),
+                    _ => return Err(Error::Internal("[Keycode] Unexpected escape".to_string())),
+                },
+                Some((_, b)) => res.push(*b),
+                None => {
+                    return Err(Error::Internal(
+                        "[Keycode] Unexpected end of input".to_string(),
+                    ))
+                }
+            }
+        };
+        self.input = &self.input[index..];
+        Ok(res)
+    }
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn deserialize_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("deserialize_any")
+    }
+
+    fn deserialize_bool<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        match self.take_bytes(1)?[0] {
+            0 => visitor.visit_bool(false),
+            1 => visitor.visit_bool(true),
+            v => Err(Error::Internal(format!("[Keycode] Invalid boolean {}", v))),
+        }
+    }
+
+    fn deserialize_i8<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("i8")
+    }
+
+    fn deserialize_i16<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("i16")
+    }
+
+    fn deserialize_i32<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut bytes: [u8; 4] = self.take_bytes(4)?.try_into()?;
+        bytes[0] ^= 1 << 7;
+        visitor.visit_i32(i32::from_be_bytes(bytes))
+    }
+
+    fn deserialize_i64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut bytes: [u8; 8] = self.take_bytes(8)?.try_into()?;
+        bytes[0] ^= 1 << 7;
+        visitor.visit_i64(i64::from_be_bytes(bytes))
+    }
+
+    fn deserialize_u8<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("u8")
+    }
+
+    fn deserialize_u16<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("u16")
+    }
+
+    fn deserialize_u32<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("u32")
+    }
+
+    fn deserialize_u64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let bytes = self.take_bytes(8)?;
+        let v = u64::from_be_bytes(bytes.try_into()?);
+        visitor.visit_u64(v)
+    }
+
+    fn deserialize_f32<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("f32")
+    }
+
+    fn deserialize_f64<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let mut bytes: [u8; 8] = self.take_bytes(8)?.try_into()?;
+        // a set top bit means the value was positive
+        if bytes[0] >> 7 == 1 {
+            bytes[0] ^= 1 << 7;
+        } else {
+            bytes.iter_mut().for_each(|b| *b = !*b);
+        }
+        visitor.visit_f64(f64::from_be_bytes(bytes))
+    }
+
+    fn deserialize_char<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("char")
+    }
+
+    fn deserialize_str<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        let bytes = self.next_bytes()?;
+        visitor.visit_string(
+            String::from_utf8(bytes)
+                .map_err(|e| Error::Internal(format!("[Keycode] Invalid utf8 string: {}", e)))?,
+        )
+    }
+
+    fn deserialize_bytes<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_bytes(&self.next_bytes()?)
+    }
+
+    fn deserialize_byte_buf<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_byte_buf(self.next_bytes()?)
+    }
+
+    fn deserialize_option<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("option")
+    }
+
+    fn deserialize_unit<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("unit")
+    }
+
+    fn deserialize_unit_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _visitor: V,
+    ) -> Result<V::Value> {
+        unsupported("unit struct")
+    }
+
+    fn deserialize_newtype_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_seq<V: de::Visitor<'de>>(self, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self)
+    }
+
+    fn deserialize_tuple<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self)
+    }
+
+    fn deserialize_tuple_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        _visitor: V,
+    ) -> Result<V::Value> {
+        unsupported("tuple struct")
+    }
+
+    fn deserialize_map<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("map")
+    }
+
+    fn deserialize_struct<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_seq(self)
+    }
+
+    fn deserialize_enum<V: de::Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value> {
+        visitor.visit_enum(self)
+    }
+
+    fn deserialize_identifier<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("identifier")
+    }
+
+    fn deserialize_ignored_any<V: de::Visitor<'de>>(self, _visitor: V) -> Result<V::Value> {
+        unsupported("ignored any")
+    }
+}
+
+impl<'de> de::SeqAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn next_element_seed<T: de::DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>> {
+        seed.deserialize(&mut **self).map(Some)
+    }
+}
+
+impl<'de> de::EnumAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+    type Variant = Self;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(self, seed: V) -> Result<(V::Value, Self)> {
+        let index = self.take_bytes(1)?[0] as u32;
+        let value: Result<_> = seed.deserialize(index.into_deserializer());
+        Ok((value?, self))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for &mut Deserializer<'de> {
+    type Error = Error;
+
+    fn unit_variant(self) -> Result<()> {
+        Ok(())
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(self, seed: T) -> Result<T::Value> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: de::Visitor<'de>>(self, _len: usize, visitor: V) -> Result<V::Value> {
+        visitor.visit_seq(self)
+    }
+
+    fn struct_variant<V: de::Visitor<'de>>(
+        self,
+        _fields: &'static [&'static str],
+        _visitor: V,
+    ) -> Result<V::Value> {
+        unsupported("struct variant")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    use super::{deserialize_key, serialize_key};
+    use crate::{
+        error::Result,
+        sql::types::{Interval, Value},
+    };
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    enum Key {
+        Unit,
+        Version(u64),
+        Data(#[serde(with = "serde_bytes")] Vec<u8>, u64),
+    }
+
+    #[test]
+    fn test_keycode_roundtrip() -> Result<()> {
+        let keys = vec![
+            Key::Unit,
+            Key::Version(7),
+            Key::Data(vec![1, 0, 2], 3),
+            Key::Data(vec![], u64::MAX),
+        ];
+        for key in keys {
+            let bytes = serialize_key(&key)?;
+            assert_eq!(deserialize_key::<Key>(&bytes)?, key);
+        }
+        assert_eq!(
+            serialize_key(&Key::Data(vec![1, 0], 2))?,
+            vec![2, 1, 0, 255, 0, 0, 0, 0, 0, 0, 0, 0, 0, 2]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_keycode_order() -> Result<()> {
+        let ordered = [
+            Key::Unit,
+            Key::Version(1),
+            Key::Version(256),
+            Key::Data(vec![], 9),
+            Key::Data(vec![0], 1),
+            Key::Data(vec![0, 1], 0),
+            Key::Data(vec![1], 0),
+            Key::Data(vec![1], 1),
+        ];
+        let encoded = ordered
+            .iter()
+            .map(serialize_key)
+            .collect::<Result<Vec<_>>>()?;
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+        Ok(())
+    }
+
+    #[test]
+    fn test_keycode_value_order() -> Result<()> {
+        let ordered = [
+            Value::Null,
+            Value::Boolean(false),
+            Value::Boolean(true),
+            Value::Float(f64::NEG_INFINITY),
+            Value::Float(-1e10),
+            Value::Float(-1.5),
+            Value::Float(-0.0),
+            Value::Float(0.0),
+            Value::Float(0.25),
+            Value::Float(f64::INFINITY),
+            Value::Integer(i64::MIN),
+            Value::Integer(-300),
+            Value::Integer(-1),
+            Value::Integer(0),
+            Value::Integer(1),
+            Value::Integer(i64::MAX),
+            Value::String("".to_string()),
+            Value::String("a".to_string()),
+            Value::String("a\0".to_string()),
+            Value::String("a\0b".to_string()),
+            Value::String("ab".to_string()),
+            Value::String("b".to_string()),
+            Value::Date(-719_162),
+            Value::Date(-1),
+            Value::Date(0),
+            Value::Date(20_000),
+            Value::Time(0),
+            Value::Time(86_399_999_999),
+            Value::Timestamp(-1),
+            Value::Timestamp(0),
+            Value::Timestamp(1_760_000_000_000_000),
+            Value::Interval(Interval {
+                months: -1,
+                days: 40,
+                micros: 0,
+            }),
+            Value::Interval(Interval {
+                months: 0,
+                days: -1,
+                micros: 5,
+            }),
+            Value::Interval(Interval {
+                months: 0,
+                days: 0,
+                micros: -1,
+            }),
+            Value::Decimal("-12.5".parse()?),
+            Value::Decimal("-0.001".parse()?),
+            Value::Decimal("0".parse()?),
+            Value::Decimal("0.10".parse()?),
+            Value::Decimal("2".parse()?),
+            Value::Decimal("10.000".parse()?),
+            Value::Blob(vec![]),
+            Value::Blob(vec![0]),
+            Value::Blob(vec![0, 0]),
+            Value::Blob(vec![0, 1]),
+            Value::Blob(vec![0xff]),
+        ];
+        let encoded = ordered
+            .iter()
+            .map(serialize_key)
+            .collect::<Result<Vec<_>>>()?;
+        let mut sorted = encoded.clone();
+        sorted.sort();
+        assert_eq!(encoded, sorted);
+
+        for (value, bytes) in ordered.iter().zip(&encoded) {
+            assert_eq!(&deserialize_key::<Value>(bytes)?, value);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::{btree_map, BTreeMap};
+
+use super::engine::{Engine, EngineIterator};
+use crate::error::Result;
+
+// In-memory storage engine backed by a BTreeMap
+pub struct MemoryEngine {
+    data: BTreeMap<Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryEngine {
+    pub fn new() -> Self {
+        Self {
+            data: BTreeMap::new(),
+        }
+    }
+}
+
+impl Default for MemoryEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine for MemoryEngine {
+    type EngineIterator<'a> = MemoryEngineIterator<'a>;
+
+    fn set(&mut self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.data.insert(key, value);
+        Ok(())
+    }
+
+    fn get(&mut self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        Ok(self.data.get(&key).cloned())
+    }
+
+    fn delete(&mut self, key: Vec<u8>) -> Result<()> {
+        self.data.remove(&key);
+        Ok(())
+    }
+
+    fn scan(&mut self, range: impl std::ops::RangeBounds<Vec<u8>>) -> Self::EngineIterator<'_> {
+        MemoryEngineIterator {
+            inner: self.data.range(range),
+        }
+    }
+}
+
+pub struct MemoryEngineIterator<'a> {
+    inner: btree_map::Range<'a, Vec<u8>, Vec<u8>>,
+}
+
+impl MemoryEngineIterator<'_> {
+    fn map(item: (&Vec<u8>, &Vec<u8>)) -> <Self as Iterator>::Item {
+        let (k, v) = item;
+        Ok((k.clone(), v.clone()))
+    }
+}
+
+impl EngineIterator for MemoryEngineIterator<'_> {}
+
+impl Iterator for MemoryEngineIterator<'_> {
+    type Item = Result<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(Self::map)
+    }
+}
+
+impl DoubleEndedIterator for MemoryEngineIterator<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(Self::map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MemoryEngine;
+    use crate::{error::Result, storage::engine::Engine};
+
+    #[test]
+    fn test_memory_engine() -> Result<()> {
+        let mut eng = MemoryEngine::new();
+        eng.set(b"a".to_vec(), b"1".to_vec())?;
+        eng.set(b"ab".to_vec(), b"2".to_vec())?;
+        eng.set(b"b".to_vec(), b"3".to_vec())?;
+        assert_eq!(eng.get(b"ab".to_vec())?, Some(b"2".to_vec()));
+
+        eng.delete(b"b".to_vec())?;
+        assert_eq!(eng.get(b"b".to_vec())?, None);
+
+        let keys = eng
+            .scan_prefix(b"a".to_vec())
+            .map(|r| r.map(|(k, _)| k))
+            .collect::<Result<Vec<_>>>()?;
+        assert_eq!(keys, vec![b"a".to_vec(), b"ab".to_vec()]);
+
+        let last = eng.scan(..).next_back().transpose()?;
+        assert_eq!(last, Some((b"ab".to_vec(), b"2".to_vec())));
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod disk;
+pub mod engine;
+pub mod keycode;
+pub mod memory;
+pub mod mvcc;
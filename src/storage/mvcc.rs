@@ -0,0 +1,624 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    ops::{Bound, RangeBounds},
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::{
+    engine::{prefix_end, Engine},
+    keycode::{deserialize_key, serialize_key},
+};
+use crate::error::{Error, Result};
+
+pub type Version = u64;
+
+// Multi-version concurrency control over a storage engine,
+// every transaction reads a snapshot of the data at its version
+pub struct Mvcc<E: Engine> {
+    engine: Arc<Mutex<E>>,
+}
+
+impl<E: Engine> Clone for Mvcc<E> {
+    fn clone(&self) -> Self {
+        Self {
+            engine: self.engine.clone(),
+        }
+    }
+}
+
+impl<E: Engine> Mvcc<E> {
+    // transactions still marked active were interrupted by a crash,
+    // they can never commit so their writes are rolled back
+    pub fn new(mut eng: E) -> Result<Self> {
+        for version in MvccTransaction::scan_active(&mut eng)? {
+            rollback_writes(&mut eng, version)?;
+        }
+        eng.flush()?;
+        Ok(Self {
+            engine: Arc::new(Mutex::new(eng)),
+        })
+    }
+
+    pub fn begin(&self) -> Result<MvccTransaction<E>> {
+        MvccTransaction::begin(self.engine.clone())
+    }
+}
+
+pub struct MvccTransaction<E: Engine> {
+    engine: Arc<Mutex<E>>,
+    state: TransactionState,
+}
+
+// a handle on the same transaction, e.g. for reading rows while a scan is consumed
+impl<E: Engine> Clone for MvccTransaction<E> {
+    fn clone(&self) -> Self {
+        Self {
+            engine: self.engine.clone(),
+            state: self.state.clone(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct TransactionState {
+    // version of current transaction
+    pub version: Version,
+    // versions of transactions still active when current one begins
+    pub active_versions: HashSet<Version>,
+}
+
+impl TransactionState {
+    fn is_visible(&self, version: Version) -> bool {
+        if self.active_versions.contains(&version) {
+            false
+        } else {
+            version <= self.version
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum MvccKey {
+    NextVersion,
+    TxnActive(Version),
+    TxnWrite(Version, #[serde(with = "serde_bytes")] Vec<u8>),
+    Version(#[serde(with = "serde_bytes")] Vec<u8>, Version),
+}
+
+impl MvccKey {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        serialize_key(self)
+    }
+
+    pub fn decode(data: Vec<u8>) -> Result<Self> {
+        deserialize_key(&data)
+    }
+}
+
+// prefix of MvccKey, variants must keep the same order as MvccKey
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+pub enum MvccKeyPrefix {
+    NextVersion,
+    TxnActive,
+    TxnWrite(Version),
+    Version(#[serde(with = "serde_bytes")] Vec<u8>),
+}
+
+impl MvccKeyPrefix {
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        serialize_key(self)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ScanResult {
+    pub key: Vec<u8>,
+    pub value: Vec<u8>,
+}
+
+impl<E: Engine> MvccTransaction<E> {
+    pub fn begin(eng: Arc<Mutex<E>>) -> Result<Self> {
+        let mut engine = eng.lock()?;
+        // allocate a new version
+        let next_version = match engine.get(MvccKey::NextVersion.encode()?)? {
+            Some(value) => bincode::deserialize(&value)?,
+            None => 1,
+        };
+        engine.set(
+            MvccKey::NextVersion.encode()?,
+            bincode::serialize(&(next_version + 1))?,
+        )?;
+
+        // collect active transactions before marking this one active
+        let active_versions = Self::scan_active(&mut *engine)?;
+        engine.set(MvccKey::TxnActive(next_version).encode()?, vec![])?;
+        drop(engine);
+
+        Ok(Self {
+            engine: eng,
+            state: TransactionState {
+                version: next_version,
+                active_versions,
+            },
+        })
+    }
+
+    pub fn version(&self) -> Version {
+        self.state.version
+    }
+
+    pub fn commit(&self) -> Result<()> {
+        let mut engine = self.engine.lock()?;
+        // written keys become visible once the transaction is no longer active
+        let mut delete_keys = Vec::new();
+        let mut iter = engine.scan_prefix(MvccKeyPrefix::TxnWrite(self.state.version).encode()?);
+        while let Some((key, _)) = iter.next().transpose()? {
+            delete_keys.push(key);
+        }
+        drop(iter);
+
+        for key in delete_keys {
+            engine.delete(key)?;
+        }
+        engine.delete(MvccKey::TxnActive(self.state.version).encode()?)?;
+        // the commit is durable once the storage is synced
+        engine.flush()
+    }
+
+    pub fn rollback(&self) -> Result<()> {
+        let mut engine = self.engine.lock()?;
+        rollback_writes(&mut *engine, self.state.version)
+    }
+
+    pub fn set(&self, key: Vec<u8>, value: Vec<u8>) -> Result<()> {
+        self.write_inner(key, Some(value))
+    }
+
+    pub fn delete(&self, key: Vec<u8>) -> Result<()> {
+        self.write_inner(key, None)
+    }
+
+    pub fn get(&self, key: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        let mut engine = self.engine.lock()?;
+        // newest version visible to this transaction wins
+        let from = MvccKey::Version(key.clone(), 0).encode()?;
+        let to = MvccKey::Version(key.clone(), self.state.version).encode()?;
+        let mut iter = engine.scan(from..=to).rev();
+        while let Some((key, value)) = iter.next().transpose()? {
+            match MvccKey::decode(key.clone())? {
+                MvccKey::Version(_, version) => {
+                    if self.state.is_visible(version) {
+                        return Ok(bincode::deserialize(&value)?);
+                    }
+                }
+                _ => {
+                    return Err(Error::Internal(format!(
+                        "[Mvcc] Unexpected key: {:?}",
+                        String::from_utf8(key)
+                    )))
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    pub fn scan_prefix(&self, prefix: Vec<u8>) -> Result<MvccScanIterator<E>> {
+        let mut enc_prefix = MvccKeyPrefix::Version(prefix).encode()?;
+        // drop the bytes terminator so that longer keys still match the prefix
+        enc_prefix.truncate(enc_prefix.len() - 2);
+        let end = match prefix_end(enc_prefix.clone()) {
+            Some(end) => Bound::Excluded(end),
+            None => Bound::Unbounded,
+        };
+        Ok(MvccScanIterator::new(
+            self.engine.clone(),
+            self.state.clone(),
+            (Bound::Included(enc_prefix), end),
+        ))
+    }
+
+    // scan raw keys within range, the key encoding keeps their order
+    pub fn scan(&self, range: impl RangeBounds<Vec<u8>>) -> Result<MvccScanIterator<E>> {
+        let mut versions = MvccKeyPrefix::Version(vec![]).encode()?;
+        versions.truncate(versions.len() - 2);
+
+        let start = match range.start_bound() {
+            Bound::Included(k) => Bound::Included(MvccKey::Version(k.clone(), 0).encode()?),
+            Bound::Excluded(k) => Bound::Excluded(MvccKey::Version(k.clone(), u64::MAX).encode()?),
+            Bound::Unbounded => Bound::Included(versions.clone()),
+        };
+        let end = match range.end_bound() {
+            Bound::Included(k) => Bound::Included(MvccKey::Version(k.clone(), u64::MAX).encode()?),
+            Bound::Excluded(k) => Bound::Excluded(MvccKey::Version(k.clone(), 0).encode()?),
+            Bound::Unbounded => match prefix_end(versions) {
+                Some(end) => Bound::Excluded(end),
+                None => Bound::Unbounded,
+            },
+        };
+
+        // the storage engine rejects inverted ranges, e.g. k > 10 AND k < 5
+        if let (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e)) =
+            (&start, &end)
+        {
+            if s > e || (s == e && matches!(start, Bound::Excluded(_))) {
+                let mut iter =
+                    MvccScanIterator::new(self.engine.clone(), self.state.clone(), (start, end));
+                iter.done = true;
+                return Ok(iter);
+            }
+        }
+
+        Ok(MvccScanIterator::new(
+            self.engine.clone(),
+            self.state.clone(),
+            (start, end),
+        ))
+    }
+
+    fn write_inner(&self, key: Vec<u8>, value: Option<Vec<u8>>) -> Result<()> {
+        let mut engine = self.engine.lock()?;
+
+        // conflict if the latest version of key is invisible to this transaction,
+        // either written by an active transaction or by a newer one
+        let from = MvccKey::Version(
+            key.clone(),
+            self.state
+                .active_versions
+                .iter()
+                .min()
+                .copied()
+                .unwrap_or(self.state.version + 1),
+        )
+        .encode()?;
+        let to = MvccKey::Version(key.clone(), u64::MAX).encode()?;
+        if let Some((k, _)) = engine.scan(from..=to).last().transpose()? {
+            match MvccKey::decode(k.clone())? {
+                MvccKey::Version(_, version) => {
+                    if !self.state.is_visible(version) {
+                        return Err(Error::WriteConflict);
+                    }
+                }
+                _ => {
+                    return Err(Error::Internal(format!(
+                        "[Mvcc] Unexpected key: {:?}",
+                        String::from_utf8(k)
+                    )))
+                }
+            }
+        }
+
+        // record the write so it can be rolled back
+        engine.set(
+            MvccKey::TxnWrite(self.state.version, key.clone()).encode()?,
+            vec![],
+        )?;
+        engine.set(
+            MvccKey::Version(key, self.state.version).encode()?,
+            bincode::serialize(&value)?,
+        )
+    }
+
+    fn scan_active(engine: &mut E) -> Result<HashSet<Version>> {
+        let mut active_versions = HashSet::new();
+        let mut iter = engine.scan_prefix(MvccKeyPrefix::TxnActive.encode()?);
+        while let Some((key, _)) = iter.next().transpose()? {
+            match MvccKey::decode(key.clone())? {
+                MvccKey::TxnActive(version) => {
+                    active_versions.insert(version);
+                }
+                _ => {
+                    return Err(Error::Internal(format!(
+                        "[Mvcc] Unexpected key: {:?}",
+                        String::from_utf8(key)
+                    )))
+                }
+            }
+        }
+        Ok(active_versions)
+    }
+}
+
+// remove every version written by the transaction and its active marker
+fn rollback_writes<E: Engine>(engine: &mut E, version: Version) -> Result<()> {
+    let mut delete_keys = Vec::new();
+    let mut iter = engine.scan_prefix(MvccKeyPrefix::TxnWrite(version).encode()?);
+    while let Some((key, _)) = iter.next().transpose()? {
+        match MvccKey::decode(key.clone())? {
+            MvccKey::TxnWrite(_, raw_key) => {
+                delete_keys.push(MvccKey::Version(raw_key, version).encode()?);
+            }
+            _ => {
+                return Err(Error::Internal(format!(
+                    "[Mvcc] Unexpected key: {:?}",
+                    String::from_utf8(key)
+                )))
+            }
+        }
+        delete_keys.push(key);
+    }
+    drop(iter);
+
+    for key in delete_keys {
+        engine.delete(key)?;
+    }
+    engine.delete(MvccKey::TxnActive(version).encode()?)
+}
+
+// number of keys read from the storage engine at a time by a scan
+const SCAN_BATCH_SIZE: usize = 128;
+
+// latest visible value of every key in the range, deleted keys are skipped,
+// the storage engine is read in batches and only locked while reading one
+pub struct MvccScanIterator<E: Engine> {
+    engine: Arc<Mutex<E>>,
+    state: TransactionState,
+    // encoded version keys not read yet
+    range: (Bound<Vec<u8>>, Bound<Vec<u8>>),
+    buffer: VecDeque<ScanResult>,
+    done: bool,
+}
+
+impl<E: Engine> MvccScanIterator<E> {
+    fn new(
+        engine: Arc<Mutex<E>>,
+        state: TransactionState,
+        range: (Bound<Vec<u8>>, Bound<Vec<u8>>),
+    ) -> Self {
+        Self {
+            engine,
+            state,
+            range,
+            buffer: VecDeque::new(),
+            done: false,
+        }
+    }
+
+    fn fill_buffer(&mut self) -> Result<()> {
+        let mut engine = self.engine.lock()?;
+        let mut iter = engine.scan(self.range.clone());
+        // versions of a key are adjacent and ascending, the last visible one wins
+        let mut current: Option<(Vec<u8>, Option<Vec<u8>>)> = None;
+        let mut count = 0;
+        loop {
+            let Some((key, value)) = iter.next().transpose()? else {
+                self.done = true;
+                break;
+            };
+            let (raw_key, version) = match MvccKey::decode(key.clone())? {
+                MvccKey::Version(raw_key, version) => (raw_key, version),
+                _ => {
+                    return Err(Error::Internal(format!(
+                        "[Mvcc] Unexpected key: {:?}",
+                        String::from_utf8(key)
+                    )))
+                }
+            };
+            if current.as_ref().map(|(k, _)| k) != Some(&raw_key) {
+                if let Some((k, Some(v))) = current.take() {
+                    self.buffer.push_back(ScanResult { key: k, value: v });
+                }
+                // stop between keys, the next batch starts at this version
+                if count == SCAN_BATCH_SIZE {
+                    self.range.0 = Bound::Included(key);
+                    return Ok(());
+                }
+                count += 1;
+                current = Some((raw_key, None));
+            }
+            if self.state.is_visible(version) {
+                if let Some((_, v)) = current.as_mut() {
+                    *v = bincode::deserialize(&value)?;
+                }
+            }
+        }
+        if let Some((k, Some(v))) = current {
+            self.buffer.push_back(ScanResult { key: k, value: v });
+        }
+        Ok(())
+    }
+}
+
+impl<E: Engine> Iterator for MvccScanIterator<E> {
+    type Item = Result<ScanResult>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.buffer.is_empty() && !self.done {
+            if let Err(err) = self.fill_buffer() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.buffer.pop_front().map(Ok)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ops::Bound;
+
+    use super::{Mvcc, MvccScanIterator, ScanResult, SCAN_BATCH_SIZE};
+    use crate::{
+        error::{Error, Result},
+        storage::{disk::DiskEngine, memory::MemoryEngine},
+    };
+
+    #[test]
+    fn test_mvcc_snapshot_isolation() -> Result<()> {
+        let mvcc = Mvcc::new(MemoryEngine::new())?;
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"val1".to_vec())?;
+        tx.set(b"key2".to_vec(), b"val2".to_vec())?;
+        tx.commit()?;
+
+        let tx1 = mvcc.begin()?;
+        let tx2 = mvcc.begin()?;
+        tx2.set(b"key1".to_vec(), b"val3".to_vec())?;
+        tx2.delete(b"key2".to_vec())?;
+        tx2.set(b"key3".to_vec(), b"val4".to_vec())?;
+        tx2.commit()?;
+
+        // tx1 still sees the data when it began
+        assert_eq!(tx1.get(b"key1".to_vec())?, Some(b"val1".to_vec()));
+        assert_eq!(tx1.get(b"key2".to_vec())?, Some(b"val2".to_vec()));
+        assert_eq!(tx1.get(b"key3".to_vec())?, None);
+
+        let tx3 = mvcc.begin()?;
+        assert_eq!(
+            tx3.scan_prefix(b"key".to_vec())?
+                .collect::<Result<Vec<_>>>()?,
+            vec![
+                ScanResult {
+                    key: b"key1".to_vec(),
+                    value: b"val3".to_vec()
+                },
+                ScanResult {
+                    key: b"key3".to_vec(),
+                    value: b"val4".to_vec()
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mvcc_scan_range() -> Result<()> {
+        let mvcc = Mvcc::new(MemoryEngine::new())?;
+        let tx = mvcc.begin()?;
+        for key in [b"a".to_vec(), b"b".to_vec(), b"b\0".to_vec(), b"c".to_vec()] {
+            tx.set(key.clone(), key)?;
+        }
+        tx.commit()?;
+
+        let tx1 = mvcc.begin()?;
+        tx1.delete(b"c".to_vec())?;
+        let keys = |results: MvccScanIterator<MemoryEngine>| {
+            results
+                .map(|r| r.map(|r| r.key))
+                .collect::<Result<Vec<_>>>()
+        };
+        assert_eq!(
+            keys(tx1.scan(b"a".to_vec()..b"c".to_vec())?)?,
+            vec![b"a".to_vec(), b"b".to_vec(), b"b\0".to_vec()]
+        );
+        assert_eq!(
+            keys(tx1.scan((Bound::Excluded(b"b".to_vec()), Bound::Unbounded))?)?,
+            vec![b"b\0".to_vec()]
+        );
+        assert_eq!(keys(tx1.scan(..=b"a".to_vec())?)?, vec![b"a".to_vec()]);
+        assert_eq!(
+            keys(tx1.scan(b"c".to_vec()..b"a".to_vec())?)?,
+            Vec::<Vec<u8>>::new()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_mvcc_scan_batches() -> Result<()> {
+        let mvcc = Mvcc::new(MemoryEngine::new())?;
+        let n = SCAN_BATCH_SIZE as u64 * 3 + 1;
+        let key = |i: u64| format!("key{:04}", i).into_bytes();
+        let tx = mvcc.begin()?;
+        for i in 0..n {
+            tx.set(key(i), vec![0])?;
+        }
+        tx.commit()?;
+
+        // several versions per key, some of them deleted or not yet visible
+        let tx1 = mvcc.begin()?;
+        for i in (0..n).step_by(2) {
+            tx1.set(key(i), vec![1])?;
+        }
+        for i in (0..n).step_by(3) {
+            tx1.delete(key(i))?;
+        }
+        tx1.commit()?;
+        let tx2 = mvcc.begin()?;
+        let tx3 = mvcc.begin()?;
+        tx3.set(key(1), vec![3])?;
+
+        let mut iter = tx2.scan_prefix(b"key".to_vec())?;
+        let first = iter.next().transpose()?;
+        // writes between batches of an open scan do not block it
+        tx3.set(key(n - 1), vec![3])?;
+        tx3.commit()?;
+        let results = first
+            .into_iter()
+            .map(Ok)
+            .chain(iter)
+            .collect::<Result<Vec<_>>>()?;
+
+        let expect = (0..n)
+            .filter(|i| i % 3 != 0)
+            .map(|i| ScanResult {
+                key: key(i),
+                value: vec![if i % 2 == 0 { 1 } else { 0 }],
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(results, expect);
+        Ok(())
+    }
+
+    #[test]
+    fn test_mvcc_write_conflict() -> Result<()> {
+        let mvcc = Mvcc::new(MemoryEngine::new())?;
+        let tx1 = mvcc.begin()?;
+        let tx2 = mvcc.begin()?;
+        tx1.set(b"key1".to_vec(), b"val1".to_vec())?;
+        assert_eq!(
+            tx2.set(b"key1".to_vec(), b"val2".to_vec()),
+            Err(Error::WriteConflict)
+        );
+        tx1.commit()?;
+        // a transaction started before tx1 committed still conflicts
+        assert_eq!(tx2.delete(b"key1".to_vec()), Err(Error::WriteConflict));
+        Ok(())
+    }
+
+    #[test]
+    fn test_mvcc_rollback() -> Result<()> {
+        let mvcc = Mvcc::new(MemoryEngine::new())?;
+        let tx = mvcc.begin()?;
+        tx.set(b"key1".to_vec(), b"val1".to_vec())?;
+        tx.commit()?;
+
+        let tx1 = mvcc.begin()?;
+        tx1.set(b"key1".to_vec(), b"val2".to_vec())?;
+        tx1.set(b"key2".to_vec(), b"val3".to_vec())?;
+        tx1.rollback()?;
+
+        let tx2 = mvcc.begin()?;
+        assert_eq!(tx2.get(b"key1".to_vec())?, Some(b"val1".to_vec()));
+        assert_eq!(tx2.get(b"key2".to_vec())?, None);
+        // rolled back versions no longer block writers
+        tx2.set(b"key2".to_vec(), b"val4".to_vec())?;
+        tx2.commit()?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_mvcc_recover() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("sqldb-log");
+        {
+            let mvcc = Mvcc::new(DiskEngine::new(path.clone())?)?;
+            let tx = mvcc.begin()?;
+            tx.set(b"key1".to_vec(), b"val1".to_vec())?;
+            tx.commit()?;
+            // a crash leaves the transaction active with its writes
+            let tx1 = mvcc.begin()?;
+            tx1.set(b"key1".to_vec(), b"val2".to_vec())?;
+            tx1.set(b"key2".to_vec(), b"val3".to_vec())?;
+        }
+
+        let mvcc = Mvcc::new(DiskEngine::new(path)?)?;
+        let tx = mvcc.begin()?;
+        assert_eq!(tx.get(b"key1".to_vec())?, Some(b"val1".to_vec()));
+        assert_eq!(tx.get(b"key2".to_vec())?, None);
+        tx.set(b"key1".to_vec(), b"val4".to_vec())?;
+        tx.commit()?;
+        assert_eq!(mvcc.begin()?.get(b"key1".to_vec())?, Some(b"val4".to_vec()));
+        Ok(())
+    }
+}